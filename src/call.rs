@@ -1,17 +1,71 @@
-use std::env;
-use std::path::PathBuf;
+use std::{ env, fs };
+use std::fs::File;
+use std::path::{ Path, PathBuf };
+use std::process::{ self, Child };
 use std::os::fd::AsRawFd;
 use std::os::unix::net::UnixStream;
-use std::io::{ self, Read, Write };
+use std::net::TcpStream;
+use std::io::{ self, Read, Write, IsTerminal };
+use std::sync::mpsc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::time::{ Duration, Instant };
+use std::thread;
 use anyhow::Context as AnyhowContext;
 use serde::{ Serialize, Deserialize };
+use serde::de::DeserializeOwned;
 use directories::ProjectDirs;
 use passfd::FdPassingExt;
-use crate::util::hashpath;
+use crate::util::peer_uid;
 use crate::Options;
 
 
 pub const SESSION_ENVNAME: &str = "FI_SESSION";
+pub const TOKEN_ENVNAME: &str = "FI_TOKEN";
+
+/// Tags of frames sent back to tcp clients
+pub const FRAME_STDOUT: u8 = 1;
+pub const FRAME_STDERR: u8 = 2;
+pub const FRAME_EXIT: u8 = 3;
+/// Largest frame body, checked before allocating for a length read from the peer
+pub const MAX_FRAME: usize = 16 << 20;
+
+/// Zero-length message sent by the liveness probe of `fi sessions` before closing,
+/// the session does not count it as a client
+pub const PROBE: &[u8] = &[0, 0];
+
+/// Version of the messages between client and session, bumped on incompatible changes
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Output written to fds passed over the unix socket
+pub const FEATURE_FDS: &str = "fds";
+/// Output streamed back as frames, for tcp clients
+pub const FEATURE_FRAMES: &str = "frames";
+/// `Control` messages sent after `Start`
+pub const FEATURE_HEARTBEAT: &str = "heartbeat";
+/// Optional features known to this build
+pub const FEATURES: &[&str] = &[FEATURE_FDS, FEATURE_FRAMES, FEATURE_HEARTBEAT];
+
+/// How often a client sends `Control::Heartbeat`,
+/// the session cancels a request after missing a few
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// First message of a client, before `Start`
+#[derive(Serialize, Deserialize)]
+pub struct Hello {
+    pub version: u32,
+    pub features: Vec<String>,
+    /// token of the session, required over tcp
+    #[serde(default)]
+    pub token: Option<String>
+}
+
+/// Answer of the session to `Hello`
+#[derive(Serialize, Deserialize)]
+pub enum Welcome {
+    /// features supported by both sides
+    Accept { features: Vec<String> },
+    Reject { version: u32, reason: String }
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct Start {
@@ -20,85 +74,379 @@ pub struct Start {
     pub options: Box<Options>
 }
 
+/// Messages of a client while its command runs
+#[derive(Serialize, Deserialize)]
+pub enum Control {
+    Heartbeat,
+    /// stop the command, the session answers with `ExitCode::Cancelled`
+    Cancel
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Exit {
     pub code: ExitCode
 }
 
 #[derive(Serialize, Deserialize)]
+#[derive(Clone, Copy)]
 pub enum ExitCode {
     Ok,
-    Failure
+    Failure,
+    Cancelled
 }
 
-pub fn call(dir: &ProjectDirs, options: Box<Options>) -> anyhow::Result<()> {
-    let ipc_path = if let Some(ipc_path) = env::var_os(SESSION_ENVNAME) {
+pub fn call(dir: &ProjectDirs, mut options: Box<Options>) -> anyhow::Result<()> {
+    if let Some(addr) = options.connect.clone() {
+        return exec_tcp(&addr, options);
+    }
+
+    let ipc_path = if let Some(id) = options.session.as_deref() {
+        crate::sessions::select(crate::sessions::dir(dir), id)?
+    } else if let Some(ipc_path) = env::var_os(SESSION_ENVNAME) {
         PathBuf::from(ipc_path)
+    } else if let Some(path) = options.file.clone() {
+        // the session may serve several binaries
+        if options.bin.is_none() {
+            let path = fs::canonicalize(&path)
+                .with_context(|| format!("not found file: {}", path.display()))?;
+            options.bin = Some(path.to_string_lossy().into_owned());
+        }
+
+        match crate::sessions::find_serving(crate::sessions::dir(dir), &path)? {
+            Some(ipc_path) => ipc_path,
+            None => crate::sessions::spawn(dir, &path)?
+        }
     } else {
-        use std::os::unix::fs::FileTypeExt;
+        crate::sessions::select_default(crate::sessions::dir(dir), options.bin.as_deref())?
+    };
 
-        let dir = dir.runtime_dir()
-            .unwrap_or_else(|| dir.cache_dir());
-        let mut found = Vec::new();
+    exec(ipc_path, options)
+}
 
-        for entry in std::fs::read_dir(dir)? {
-            let entry = entry?;
+/// Temporary output file, renamed into place on success
+struct Output {
+    path: PathBuf,
+    tmp_path: PathBuf,
+    file: File,
+    persisted: bool
+}
 
-            if entry.file_type()?.is_socket() {
-                found.push(entry.path());
-            }
+impl Output {
+    fn create(path: &Path) -> anyhow::Result<Output> {
+        let name = path.file_name().context("output path is not a file")?;
+        let tmp_path = path.with_file_name(format!(
+            ".{}.{}.tmp",
+            name.display(),
+            std::process::id()
+        ));
+        let file = File::create(&tmp_path).context("create output file failed")?;
+
+        Ok(Output {
+            path: path.to_owned(),
+            tmp_path, file,
+            persisted: false
+        })
+    }
+
+    fn persist(mut self) -> io::Result<()> {
+        fs::rename(&self.tmp_path, &self.path)?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = fs::remove_file(&self.tmp_path);
         }
+    }
+}
 
-        let prefix = hashpath(&env::current_dir()?);
+/// Spawn `$FI_PAGER`/`$PAGER` (default `less`) reading from a pipe
+fn spawn_pager() -> Option<Child> {
+    let pager = env::var("FI_PAGER")
+        .or_else(|_| env::var("PAGER"))
+        .unwrap_or_else(|_| "less".into());
 
-        found.sort_by_key(|path| path.file_name()
-            .and_then(|name| name.to_str())
-            .filter(|name| name.starts_with(&prefix))
-            .is_none()
-        );
+    if pager.is_empty() || pager == "cat" {
+        return None;
+    }
 
-        found
-            .into_iter()
-            .next()
-            .context("not found any ipc path")?
-    };
+    let mut cmd = process::Command::new("sh");
+    cmd.arg("-c").arg(&pager).stdin(process::Stdio::piped());
 
-    exec(ipc_path, options)
+    // quit if one screen, keep colors
+    if env::var_os("LESS").is_none() {
+        cmd.env("LESS", "FRX");
+    }
+
+    cmd.spawn().ok()
 }
 
 fn exec(ipc_path: PathBuf, options: Box<Options>) -> anyhow::Result<()> {
-    let mut stream = UnixStream::connect(ipc_path).context("session connect failed")?;
-
-    {
-        let colored = supports_color::on(supports_color::Stream::Stdout).is_some();
-        let options = Start {
-            colored,
-            hyperlink: colored && supports_hyperlinks::supports_hyperlinks(),
-            options
-        };
-        let buf = cbor4ii::serde::to_vec(Vec::new(), &options)?;
-        let len: u16 = buf.len().try_into().context("command too long")?;
-
-        stream.write_all(&len.to_le_bytes())?;
-        stream.write_all(&buf)?;
-        stream.flush()?;
+    let mut stream = UnixStream::connect(&ipc_path).context("session connect failed")?;
 
-        stream.send_fd(io::stdin().as_raw_fd())?;
-        stream.send_fd(io::stdout().as_raw_fd())?;
-        stream.send_fd(io::stderr().as_raw_fd())?;
-        stream.flush()?;
+    // fds are only passed to a session of the same user
+    let uid = peer_uid(&stream).context("session credentials failed")?;
+    let current = unsafe { libc::getuid() };
+    anyhow::ensure!(
+        uid == current,
+        "session {} belongs to uid {}, not the current uid {}",
+        ipc_path.display(), uid, current
+    );
+
+    exec_on(options, |options, output, pager| exec_with(&mut stream, options, output, pager))
+}
+
+fn exec_tcp(addr: &str, options: Box<Options>) -> anyhow::Result<()> {
+    let mut stream = TcpStream::connect(addr).context("session connect failed")?;
+    exec_on(options, |options, output, pager| exec_tcp_with(&mut stream, options, output, pager))
+}
+
+/// Set up the output file or pager around a command sent by `f`
+fn exec_on<F>(options: Box<Options>, f: F) -> anyhow::Result<()>
+where
+    F: FnOnce(Box<Options>, Option<&Output>, Option<&mut Child>) -> anyhow::Result<ExitCode>
+{
+    let output = options.output.as_deref()
+        .map(Output::create)
+        .transpose()?;
+    let mut pager = if output.is_none() && !options.no_pager && io::stdout().is_terminal() {
+        spawn_pager()
+    } else {
+        None
+    };
+
+    let ret = f(options, output.as_ref(), pager.as_mut());
+
+    if let Some(mut pager) = pager {
+        drop(pager.stdin.take());
+        pager.wait()?;
+    }
+
+    match ret? {
+        ExitCode::Ok => {
+            if let Some(output) = output {
+                output.persist().context("rename output file failed")?;
+            }
+
+            Ok(())
+        },
+        ExitCode::Failure => anyhow::bail!("exec failed"),
+        ExitCode::Cancelled => anyhow::bail!("cancelled")
     }
+}
+
+/// Set by the first ctrl-c while a command runs
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+
+    // a second ctrl-c kills the client as usual
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+    }
+}
+
+/// Send heartbeats until `done` is dropped, and `Cancel` on the first ctrl-c
+///
+/// The client keeps waiting for `Exit`, so the pager and the output file
+/// are cleaned up as on any other failure.
+fn heartbeat<W: Write>(mut stream: W, done: mpsc::Receiver<()>) -> anyhow::Result<()> {
+    const POLL: Duration = Duration::from_millis(100);
+
+    let mut last = Instant::now();
+
+    loop {
+        match done.recv_timeout(POLL) {
+            Err(mpsc::RecvTimeoutError::Timeout) => (),
+            _ => return Ok(())
+        }
+
+        if INTERRUPTED.load(Ordering::Relaxed) {
+            return write_message(&mut stream, &Control::Cancel);
+        }
+
+        if last.elapsed() >= HEARTBEAT_INTERVAL {
+            write_message(&mut stream, &Control::Heartbeat)?;
+            last = Instant::now();
+        }
+    }
+}
+
+/// Run `f` waiting for the command, with `heartbeat` sending on `writer`,
+/// a clone of the stream if the session supports it
+fn with_heartbeat<W, F>(writer: Option<W>, f: F) -> anyhow::Result<ExitCode>
+where
+    W: Write + Send,
+    F: FnOnce() -> anyhow::Result<ExitCode>
+{
+    let Some(writer) = writer
+        else { return f() };
+    let (tx, rx) = mpsc::channel();
+
+    unsafe {
+        libc::signal(libc::SIGINT, on_interrupt as *const () as libc::sighandler_t);
+    }
+
+    let ret = thread::scope(|scope| {
+        scope.spawn(move || heartbeat(writer, rx));
+        let ret = f();
+        drop(tx);
+        ret
+    });
+
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+    }
+
+    ret
+}
+
+fn has_heartbeat(features: &[String]) -> bool {
+    features.iter().any(|feature| feature == FEATURE_HEARTBEAT)
+}
+
+/// Write a message as u16 le length and cbor
+fn write_message<W: Write, T: Serialize>(stream: &mut W, message: &T) -> anyhow::Result<()> {
+    let buf = cbor4ii::serde::to_vec(Vec::new(), message)?;
+    let len: u16 = buf.len().try_into().context("message too long")?;
 
+    stream.write_all(&len.to_le_bytes())?;
+    stream.write_all(&buf)?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+fn read_message<R: Read, T: DeserializeOwned>(stream: &mut R) -> anyhow::Result<T> {
     let mut buf = [0; 2];
     stream.read_exact(&mut buf)?;
     let len = u16::from_le_bytes(buf);
     let mut buf = vec![0; len.into()];
     stream.read_exact(&mut buf)?;
 
-    let exit: Exit = cbor4ii::serde::from_slice(&buf)?;
+    Ok(cbor4ii::serde::from_slice(&buf)?)
+}
 
-    match exit.code {
-        ExitCode::Ok => Ok(()),
-        ExitCode::Failure => anyhow::bail!("exec failed")
+/// Check the session speaks this protocol and supports the `required` transport
+fn handshake<S: Read + Write>(stream: &mut S, required: &str, token: Option<String>)
+    -> anyhow::Result<Vec<String>>
+{
+    write_message(stream, &Hello {
+        version: PROTOCOL_VERSION,
+        features: FEATURES.iter().map(|&feature| feature.into()).collect(),
+        token
+    })?;
+
+    match read_message(stream).context("session handshake failed, is it an older fi?")? {
+        Welcome::Accept { features } => {
+            anyhow::ensure!(
+                features.iter().any(|feature| feature == required),
+                "session does not support {}", required
+            );
+            Ok(features)
+        },
+        Welcome::Reject { version, reason } if version == PROTOCOL_VERSION => anyhow::bail!(
+            "session rejected: {}", reason
+        ),
+        Welcome::Reject { version, reason } => anyhow::bail!(
+            "session rejected protocol version {} (session has {}): {}",
+            PROTOCOL_VERSION, version, reason
+        )
     }
 }
+
+fn write_start<W: Write>(stream: &mut W, options: Box<Options>, output: Option<&Output>)
+    -> anyhow::Result<()>
+{
+    let colored = output.is_none()
+        && supports_color::on(supports_color::Stream::Stdout).is_some();
+    let options = Start {
+        colored,
+        hyperlink: colored && supports_hyperlinks::supports_hyperlinks(),
+        options
+    };
+
+    write_message(stream, &options).context("command too long")
+}
+
+fn exec_with(
+    stream: &mut UnixStream,
+    options: Box<Options>,
+    output: Option<&Output>,
+    pager: Option<&mut Child>
+) -> anyhow::Result<ExitCode> {
+
+    let features = {
+        let features = handshake(stream, FEATURE_FDS, None)?;
+        write_start(stream, options, output)?;
+
+        stream.send_fd(io::stdin().as_raw_fd())?;
+        match (output, pager) {
+            (Some(output), _) => stream.send_fd(output.file.as_raw_fd())?,
+            (None, Some(pager)) => {
+                let stdin = pager.stdin.take().context("pager stdin not piped")?;
+                stream.send_fd(stdin.as_raw_fd())?;
+            },
+            (None, None) => stream.send_fd(io::stdout().as_raw_fd())?
+        }
+        stream.send_fd(io::stderr().as_raw_fd())?;
+        stream.flush()?;
+        features
+    };
+
+    let writer = has_heartbeat(&features).then(|| stream.try_clone()).transpose()?;
+
+    with_heartbeat(writer, || {
+        let exit: Exit = read_message(stream)?;
+        Ok(exit.code)
+    })
+}
+
+/// Write frames of stdout and stderr until the exit frame
+fn exec_tcp_with(
+    stream: &mut TcpStream,
+    options: Box<Options>,
+    output: Option<&Output>,
+    pager: Option<&mut Child>
+) -> anyhow::Result<ExitCode> {
+    let token = options.token.clone().or_else(|| env::var(TOKEN_ENVNAME).ok());
+    let features = handshake(stream, FEATURE_FRAMES, token)?;
+    write_start(stream, options, output)?;
+    let writer = has_heartbeat(&features).then(|| stream.try_clone()).transpose()?;
+
+    let stdout = io::stdout();
+    let mut stdout: Box<dyn Write> = match (output, pager) {
+        (Some(output), _) => Box::new(&output.file),
+        (None, Some(pager)) => Box::new(pager.stdin.take().context("pager stdin not piped")?),
+        (None, None) => Box::new(stdout.lock())
+    };
+    let mut buf = Vec::new();
+
+    with_heartbeat(writer, || loop {
+        let mut header = [0; 5];
+        stream.read_exact(&mut header)?;
+        let len: usize = u32::from_le_bytes(header[1..].try_into()?).try_into()?;
+        anyhow::ensure!(len <= MAX_FRAME, "frame too long: {} bytes", len);
+        buf.resize(len, 0);
+        stream.read_exact(&mut buf)?;
+
+        match header[0] {
+            FRAME_STDOUT => match stdout.write_all(&buf) {
+                // pager quit early, the server cancels on disconnect
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return Ok(ExitCode::Ok),
+                ret => ret?
+            },
+            FRAME_STDERR => io::stderr().write_all(&buf)?,
+            FRAME_EXIT => {
+                stdout.flush()?;
+                let exit: Exit = cbor4ii::serde::from_slice(&buf)?;
+                return Ok(exit.code);
+            },
+            tag => anyhow::bail!("unknown frame: {}", tag)
+        }
+    })
+}
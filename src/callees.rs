@@ -0,0 +1,194 @@
+use std::io::Write;
+use anyhow::Context;
+use indexmap::IndexMap;
+use object::{ Object, ObjectSection, ObjectSymbol, SectionKind };
+use owo_colors::OwoColorize;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, IfSupported };
+use crate::disasm::{ Disassembler, InstKind };
+use crate::show::{ query_plt, query_symbol_by_addr };
+
+
+/// list the functions called by a function, with call counts
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// function name or address
+    pub target: String,
+
+    /// demangle symbol name
+    #[arg(short, long)]
+    pub demangle: bool,
+
+    /// omit unresolved indirect calls
+    #[arg(long)]
+    pub no_indirect: bool,
+}
+
+/// How a call reaches its target
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Via {
+    Direct,
+    Plt,
+    Got
+}
+
+impl Command {
+    /// Calls through a register or memory that is not a GOT slot are unresolved,
+    /// they are listed one per call site.
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            address: Option<u64>,
+            name: Option<&'a str>,
+            via: Option<Via>,
+            count: usize,
+            /// call site of an unresolved indirect call
+            site: Option<u64>,
+            operands: Option<&'a str>
+        }
+
+        let symlist = explorer.cache.symlist(&explorer.obj).await;
+        let idx = explorer.lookup_symbol(symlist, &self.target)?
+            .with_context(|| format!("not found symbol: {}", self.target))?;
+        let Calls { mut callees, indirect } = resolve(explorer, idx).await?;
+
+        // most called first
+        callees.sort_by(|_, (addr0, count0), _, (addr1, count1)| count1.cmp(count0).then(addr0.cmp(addr1)));
+
+        for (&(name, via), &(addr, count)) in &callees {
+            let name = if self.demangle {
+                explorer.cache.demangle(name)
+            } else {
+                name.into()
+            };
+
+            if !stdio.format.is_text() {
+                stdio.record(&Record {
+                    address: Some(addr).filter(|&addr| addr != 0),
+                    name: Some(&name),
+                    via: Some(via),
+                    count,
+                    site: None,
+                    operands: None
+                })?;
+                continue
+            }
+
+            let suffix = match via {
+                Via::Direct => "",
+                Via::Plt => "@plt",
+                Via::Got => "@got"
+            };
+
+            writeln!(stdio.stdout, "{:018p} {:6} {}{}", addr as *const (), count, name, suffix)?;
+        }
+
+        if !self.no_indirect {
+            for (site, operands) in &indirect {
+                if !stdio.format.is_text() {
+                    stdio.record(&Record {
+                        address: None,
+                        name: None,
+                        via: None,
+                        count: 1,
+                        site: Some(*site),
+                        operands: Some(operands)
+                    })?;
+                    continue
+                }
+
+                writeln!(
+                    stdio.stdout,
+                    "{}",
+                    format_args!("{:018p} {:6} ? indirect: {}", *site as *const (), 1, operands)
+                        .if_supported(stdio.colored, |a| a.yellow())
+                )?;
+            }
+        }
+
+        if stdio.format.is_text() {
+            writeln!(
+                stdio.stdout,
+                "total: {} callees, {} calls, {} unresolved",
+                callees.len(),
+                callees.values().map(|(_, count)| count).sum::<usize>(),
+                indirect.len()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Calls of a function
+pub(crate) struct Calls {
+    /// by target name and how it is reached, with its address, zero for imports, and call count,
+    /// in order of the first call
+    pub callees: IndexMap<(&'static str, Via), (u64, usize)>,
+    /// call site and operands of unresolved indirect calls
+    pub indirect: Vec<(u64, String)>
+}
+
+pub(crate) async fn resolve(explorer: &Explorer, idx: object::SymbolIndex) -> anyhow::Result<Calls> {
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let addr2sym = explorer.cache.addr2sym(&explorer.obj).await;
+    let dyn_rela = explorer.cache.dyn_rela(&explorer.obj).await;
+    let plt = explorer.cache.plt(&explorer.obj).await;
+
+    let sym = explorer.sym_obj().symbol_by_index(idx)?;
+    let size = explorer.symbol_size(symlist, idx)?;
+    let section = sym.section_index()
+        .map(|section_idx| explorer.obj.section_by_index(section_idx))
+        .transpose()?
+        .filter(|section| section.kind() == SectionKind::Text)
+        .with_context(|| format!("not a function: {}", sym.name().unwrap_or_default()))?;
+    let data = explorer.cache.data(&explorer.obj, section.index()).await?;
+    let data = data.get((sym.address() - section.address()) as usize..)
+        .and_then(|data| data.get(..size as usize))
+        .context("symbol out of section")?;
+    let disasm = Disassembler::new(&explorer.obj)?;
+
+    let mut callees: IndexMap<(&'static str, Via), (u64, usize)> = IndexMap::new();
+    let mut indirect = Vec::new();
+    let mut insts = disasm.disasm_iter(data, sym.address())?;
+
+    while let Some(inst) = insts.next() {
+        let inst = inst?;
+
+        if disasm.inst_kind(&inst) != InstKind::Call {
+            continue
+        }
+
+        let Some(addr) = disasm.operand2addr(&inst)?
+            else {
+                indirect.push((inst.address(), inst.op_str().unwrap_or_default().to_owned()));
+                continue
+            };
+
+        let found = if let Some(entry) = query_plt(plt, addr) {
+            Some((entry.addr, Via::Plt, entry.name))
+        } else if let Some((name, target)) = query_symbol_by_addr(explorer, addr2sym, dyn_rela, addr) {
+            // an exact symbol is the callee itself, otherwise the slot of the GOT holding it
+            let via = if target == addr { Via::Direct } else { Via::Got };
+            Some((target, via, name))
+        } else {
+            None
+        };
+
+        match found {
+            Some((target, via, name)) => callees.entry((name, via)).or_insert((target, 0)).1 += 1,
+            None => indirect.push((inst.address(), inst.op_str().unwrap_or_default().to_owned()))
+        }
+    }
+
+    Ok(Calls { callees, indirect })
+}
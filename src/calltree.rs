@@ -0,0 +1,237 @@
+use std::io::Write;
+use std::collections::{ HashMap, HashSet };
+use anyhow::Context;
+use object::{ Object, ObjectSymbol, SymbolIndex };
+use owo_colors::OwoColorize;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, YieldPoint, IfSupported };
+use crate::callees::{ self, Via };
+
+
+/// print the tree of functions called from a function, with the code size each one drags in
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// function name or address
+    pub target: String,
+
+    /// levels of calls below the root
+    #[arg(long, default_value_t = 3)]
+    pub depth: usize,
+
+    /// demangle symbol name
+    #[arg(short, long)]
+    pub demangle: bool,
+}
+
+/// Callee in the tree, imports are leaves
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Node {
+    Symbol(SymbolIndex),
+    Import(&'static str, Via)
+}
+
+struct Tree<'a> {
+    explorer: &'a Explorer,
+    sizes: HashMap<SymbolIndex, u64>,
+    /// direct callees of each function up to the depth limit, in order of the first call
+    edges: HashMap<SymbolIndex, Vec<Node>>
+}
+
+impl Command {
+    /// A function already expanded elsewhere in the tree is printed with `...` and
+    /// not expanded again, a call back to a function on the current path with `(recursive)`.
+    ///
+    /// The cumulative size of a node counts each distinct function of its subtree once,
+    /// down to the depth limit.
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        let symlist = explorer.cache.symlist(&explorer.obj).await;
+        let root = explorer.lookup_symbol(symlist, &self.target)?
+            .with_context(|| format!("not found symbol: {}", self.target))?;
+        let mut point = YieldPoint::default();
+
+        let mut tree = Tree { explorer, sizes: HashMap::new(), edges: HashMap::new() };
+        tree.sizes.insert(root, explorer.symbol_size(symlist, root)?);
+        let mut queue = vec![root];
+
+        for _ in 0..self.depth {
+            let mut next = Vec::new();
+
+            for idx in queue {
+                point.yield_now().await;
+                stdio.cancel.check()?;
+
+                let calls = callees::resolve(explorer, idx).await?;
+                let mut nodes = Vec::new();
+
+                for (&(name, via), &(addr, _)) in &calls.callees {
+                    let node = match via {
+                        Via::Direct => {
+                            let Ok(pos) = symlist.binary_search_by_key(
+                                &addr,
+                                |&symidx| explorer.sym_obj().symbol_by_index(symidx).unwrap().address()
+                            )
+                                else { continue };
+                            Node::Symbol(symlist[pos])
+                        },
+                        Via::Plt | Via::Got => Node::Import(name, via)
+                    };
+
+                    if let Node::Symbol(callee) = node
+                        && !tree.sizes.contains_key(&callee)
+                    {
+                        tree.sizes.insert(callee, explorer.symbol_size(symlist, callee)?);
+                        next.push(callee);
+                    }
+
+                    if !nodes.contains(&node) {
+                        nodes.push(node);
+                    }
+                }
+
+                tree.edges.insert(idx, nodes);
+            }
+
+            queue = next;
+        }
+
+        let mut path = Vec::new();
+        let mut expanded = HashSet::new();
+        tree.print(&self, Node::Symbol(root), 0, &mut path, &mut expanded, stdio)
+    }
+}
+
+impl Tree<'_> {
+    fn print(
+        &self,
+        cmd: &Command,
+        node: Node,
+        depth: usize,
+        path: &mut Vec<SymbolIndex>,
+        expanded: &mut HashSet<SymbolIndex>,
+        stdio: &mut Stdio
+    )
+        -> anyhow::Result<()>
+    {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            depth: usize,
+            address: Option<u64>,
+            name: &'a str,
+            via: Via,
+            size: u64,
+            cumulative: u64,
+            recursive: bool,
+            repeated: bool
+        }
+
+        let (idx, mangled_name, via) = match node {
+            Node::Symbol(idx) => (Some(idx), self.explorer.sym_obj().symbol_by_index(idx)?.name()?, Via::Direct),
+            Node::Import(name, via) => (None, name, via)
+        };
+        let name = if cmd.demangle {
+            self.explorer.cache.demangle(mangled_name)
+        } else {
+            mangled_name.into()
+        };
+        let address = idx
+            .map(|idx| self.explorer.sym_obj().symbol_by_index(idx))
+            .transpose()?
+            .map(|sym| sym.address());
+        let size = idx.and_then(|idx| self.sizes.get(&idx).copied()).unwrap_or_default();
+        let cumulative = idx.map(|idx| self.cumulative(idx, cmd.depth - depth)).unwrap_or_default();
+        let recursive = idx.is_some_and(|idx| path.contains(&idx));
+        let repeated = !recursive && idx.is_some_and(|idx| expanded.contains(&idx));
+
+        if !stdio.format.is_text() {
+            stdio.record(&Record {
+                depth,
+                address,
+                name: &name,
+                via,
+                size,
+                cumulative,
+                recursive,
+                repeated
+            })?;
+        } else {
+            let suffix = match via {
+                Via::Direct => "",
+                Via::Plt => "@plt",
+                Via::Got => "@got"
+            };
+            let mark = if recursive {
+                " (recursive)"
+            } else if repeated {
+                " ..."
+            } else {
+                ""
+            };
+
+            writeln!(
+                stdio.stdout,
+                "{:8} {:8} {:indent$}{}{}{}",
+                size,
+                cumulative,
+                "",
+                name.if_supported(stdio.colored && idx.is_none(), |name| name.dimmed()),
+                suffix,
+                mark,
+                indent = depth * 2
+            )?;
+        }
+
+        let Some(idx) = idx
+            else { return Ok(()) };
+
+        if recursive || repeated {
+            return Ok(());
+        }
+
+        expanded.insert(idx);
+
+        if let Some(nodes) = self.edges.get(&idx)
+            && depth < cmd.depth
+        {
+            path.push(idx);
+            for &node in nodes {
+                self.print(cmd, node, depth + 1, path, expanded, stdio)?;
+            }
+            path.pop();
+        }
+
+        Ok(())
+    }
+
+    /// Size of the distinct functions reachable from `root` within `depth` calls
+    fn cumulative(&self, root: SymbolIndex, depth: usize) -> u64 {
+        let mut seen = HashSet::from([root]);
+        let mut queue = vec![root];
+
+        for _ in 0..depth {
+            let mut next = Vec::new();
+
+            for idx in queue {
+                for node in self.edges.get(&idx).into_iter().flatten() {
+                    if let Node::Symbol(callee) = *node
+                        && seen.insert(callee)
+                    {
+                        next.push(callee);
+                    }
+                }
+            }
+
+            queue = next;
+        }
+
+        seen.iter()
+            .filter_map(|idx| self.sizes.get(idx))
+            .sum()
+    }
+}
@@ -0,0 +1,112 @@
+use std::process;
+use std::path::Path;
+use std::collections::HashMap;
+use anyhow::Context;
+use serde::Deserialize;
+
+
+/// Label of symbols that belong to no crate of the workspace
+pub const EXTERNAL: &str = "external/std";
+
+/// Crates of a cargo workspace and its dependencies, from `cargo metadata`
+pub struct Crates {
+    /// crate name as it appears in symbol paths -> `name version`
+    labels: HashMap<String, String>
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+    version: String,
+    targets: Vec<Target>
+}
+
+#[derive(Deserialize)]
+struct Target {
+    name: String,
+    kind: Vec<String>
+}
+
+impl Crates {
+    pub fn open(manifest_path: &Path) -> anyhow::Result<Crates> {
+        let output = process::Command::new(std::env::var_os("CARGO").unwrap_or("cargo".into()))
+            .args(["metadata", "--format-version", "1", "--manifest-path"])
+            .arg(manifest_path)
+            .stderr(process::Stdio::inherit())
+            .output()
+            .context("run cargo metadata failed")?;
+
+        if !output.status.success() {
+            anyhow::bail!("cargo metadata failed: {}", output.status);
+        }
+
+        let metadata: Metadata = serde_json::from_slice(&output.stdout)
+            .context("parse cargo metadata failed")?;
+        let mut labels: HashMap<String, String> = HashMap::new();
+
+        for package in &metadata.packages {
+            // tests, examples and build scripts are not linked into the binary
+            let targets = package.targets.iter()
+                .filter(|target| target.kind.iter().any(|kind| matches!(
+                    kind.as_str(),
+                    "lib" | "rlib" | "dylib" | "cdylib" | "staticlib" | "proc-macro" | "bin"
+                )));
+
+            for target in targets {
+                let name = target.name.replace('-', "_");
+
+                // same crate in several versions can not be told apart by path
+                match labels.get_mut(&name) {
+                    Some(label) if !label.split([' ', ',']).any(|version| version == package.version) => {
+                        label.push_str(", ");
+                        label.push_str(&package.version);
+                    },
+                    Some(_) => (),
+                    None => {
+                        labels.insert(name, format!("{} {}", package.name, package.version));
+                    }
+                }
+            }
+        }
+
+        Ok(Crates { labels })
+    }
+
+    /// `name version` of the crate defining a demangled symbol, or [`EXTERNAL`]
+    pub fn label_of(&self, demangled: &str) -> &str {
+        crate_of(demangled)
+            .and_then(|name| self.labels.get(name))
+            .map(String::as_str)
+            .unwrap_or(EXTERNAL)
+    }
+}
+
+/// First path segment of a demangled rust symbol
+///
+/// For `<T as Trait>::f` this is the crate of `T`.
+pub fn crate_of(demangled: &str) -> Option<&str> {
+    let mut name = demangled;
+
+    loop {
+        let rest = name.trim_start_matches(['<', '&', '*', '(', '['])
+            .trim_start_matches("mut ")
+            .trim_start_matches("const ")
+            .trim_start_matches("dyn ");
+
+        if rest.len() == name.len() {
+            break
+        }
+
+        name = rest;
+    }
+
+    let end = name.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))?;
+    let (krate, rest) = name.split_at(end);
+
+    (!krate.is_empty() && rest.starts_with("::")).then_some(krate)
+}
@@ -0,0 +1,241 @@
+use std::io::Write;
+use object::{ Object, ObjectSymbol, elf };
+use object::read::elf::{ ElfFile, FileHeader, ProgramHeader, Dyn };
+use owo_colors::OwoColorize;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, IfSupported, YieldPoint };
+use crate::disasm::Disassembler;
+use crate::callees::{ self, Via };
+
+
+/// report missing hardening of an ELF: W|X segments, executable stack, RELRO, PIE, stack canary
+/// and calls to unbounded libc string functions
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// also report the checks that passed
+    #[arg(long)]
+    pub all: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+    Error,
+    Warning,
+    /// check passed, only with `--all`, `none` as SARIF names it
+    #[serde(rename = "none")]
+    Pass
+}
+
+/// One result, `rule` and `level` become the SARIF `ruleId` and `level`
+#[derive(Serialize)]
+struct Finding {
+    rule: &'static str,
+    level: Level,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<u64>
+}
+
+impl Command {
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        let mut findings = match &explorer.obj {
+            object::File::Elf32(elf) => check_elf(elf)?,
+            object::File::Elf64(elf) => check_elf(elf)?,
+            _ => anyhow::bail!("checksec supports ELF only")
+        };
+
+        let canary = explorer.obj.symbols()
+            .chain(explorer.obj.dynamic_symbols())
+            .any(|sym| sym.name().is_ok_and(|name| name == "__stack_chk_fail"));
+        findings.push(if canary {
+            pass("checksec/canary", "stack protector used")
+        } else {
+            Finding {
+                rule: "checksec/canary",
+                level: Level::Warning,
+                message: "no stack protector, `__stack_chk_fail` is not referenced".into(),
+                address: None
+            }
+        });
+
+        if Disassembler::new(&explorer.obj).is_ok() {
+            let calls = check_calls(explorer).await?;
+
+            if calls.is_empty() {
+                findings.push(pass("checksec/unsafe-call", "no call to an unbounded string function"));
+            }
+
+            findings.extend(calls);
+        }
+
+        // stable, calls stay in address order
+        findings.sort_by_key(|finding| finding.level);
+
+        for finding in findings.iter().filter(|finding| self.all || finding.level != Level::Pass) {
+            if !stdio.format.is_text() {
+                stdio.record(finding)?;
+                continue
+            }
+
+            let level = match finding.level {
+                Level::Error => "error".if_supported(stdio.colored, |a| a.red()).to_string(),
+                Level::Warning => "warning".if_supported(stdio.colored, |a| a.yellow()).to_string(),
+                Level::Pass => "pass".if_supported(stdio.colored, |a| a.green()).to_string()
+            };
+
+            writeln!(stdio.stdout, "{}\t{}\t{}", level, finding.rule, finding.message)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn pass(rule: &'static str, message: &str) -> Finding {
+    Finding { rule, level: Level::Pass, message: message.into(), address: None }
+}
+
+/// libc functions writing without a bound, `gets` can never be used safely
+const UNSAFE_CALLS: &[(&str, Level)] = &[
+    ("gets", Level::Error),
+    ("strcpy", Level::Warning),
+    ("strcat", Level::Warning),
+    ("stpcpy", Level::Warning),
+    ("sprintf", Level::Warning),
+    ("vsprintf", Level::Warning),
+    ("wcscpy", Level::Warning),
+    ("wcscat", Level::Warning)
+];
+
+/// Functions calling one of `UNSAFE_CALLS`, one finding per caller and callee
+async fn check_calls(explorer: &Explorer) -> anyhow::Result<Vec<Finding>> {
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let mut point = YieldPoint::default();
+    let mut findings = Vec::new();
+
+    for &idx in symlist {
+        point.yield_now().await;
+
+        let sym = explorer.sym_obj().symbol_by_index(idx)?;
+
+        if sym.kind() != object::SymbolKind::Text || sym.is_undefined() {
+            continue
+        }
+
+        // not a function, such as a symbol outside of the text sections
+        let Ok(calls) = callees::resolve(explorer, idx).await
+            else { continue };
+
+        for (&(name, via), &(_, count)) in &calls.callees {
+            // versioned imports, such as `gets@GLIBC_2.2.5`
+            let name = name.split('@').next().unwrap_or(name);
+            let Some(&(_, level)) = UNSAFE_CALLS.iter().find(|(unsafe_name, _)| *unsafe_name == name)
+                else { continue };
+            let suffix = if via == Via::Plt { "@plt" } else { "" };
+
+            findings.push(Finding {
+                rule: "checksec/unsafe-call",
+                level,
+                message: format!(
+                    "`{}` calls `{}{}`{}",
+                    explorer.cache.demangle(sym.name()?),
+                    name, suffix,
+                    if count > 1 { format!(" {} times", count) } else { String::new() }
+                ),
+                address: Some(sym.address())
+            });
+        }
+    }
+
+    Ok(findings)
+}
+
+fn check_elf<Elf: FileHeader<Endian = object::Endianness>>(file: &ElfFile<'_, Elf>)
+    -> anyhow::Result<Vec<Finding>>
+{
+    let endian = file.endian();
+    let header = file.elf_header();
+    let mut findings = Vec::new();
+    let mut gnu_stack = None;
+    let mut relro = false;
+    let mut bind_now = false;
+
+    for phdr in file.elf_program_headers() {
+        let flags = phdr.p_flags(endian);
+
+        match phdr.p_type(endian) {
+            elf::PT_LOAD if flags & elf::PF_W != 0 && flags & elf::PF_X != 0 => findings.push(Finding {
+                rule: "checksec/wx-segment",
+                level: Level::Error,
+                message: format!("segment at {:#x} is writable and executable", phdr.p_vaddr(endian).into()),
+                address: Some(phdr.p_vaddr(endian).into())
+            }),
+            elf::PT_GNU_STACK => gnu_stack = Some(flags & elf::PF_X != 0),
+            elf::PT_GNU_RELRO => relro = true,
+            elf::PT_DYNAMIC => if let Some(dynamic) = phdr.dynamic(endian, file.data())? {
+                bind_now |= dynamic.iter().any(|entry| match entry.d_tag(endian).into() as u32 {
+                    elf::DT_BIND_NOW => true,
+                    elf::DT_FLAGS => entry.d_val(endian).into() & u64::from(elf::DF_BIND_NOW) != 0,
+                    elf::DT_FLAGS_1 => entry.d_val(endian).into() & u64::from(elf::DF_1_NOW) != 0,
+                    _ => false
+                });
+            },
+            _ => ()
+        }
+    }
+
+    if !findings.iter().any(|finding| finding.rule == "checksec/wx-segment") {
+        findings.push(pass("checksec/wx-segment", "no segment is writable and executable"));
+    }
+
+    findings.push(match gnu_stack {
+        Some(false) => pass("checksec/exec-stack", "stack is not executable"),
+        Some(true) => Finding {
+            rule: "checksec/exec-stack",
+            level: Level::Error,
+            message: "`PT_GNU_STACK` makes the stack executable".into(),
+            address: None
+        },
+        None => Finding {
+            rule: "checksec/exec-stack",
+            level: Level::Warning,
+            message: "no `PT_GNU_STACK`, the stack may be executable".into(),
+            address: None
+        }
+    });
+
+    findings.push(match (relro, bind_now) {
+        (true, true) => pass("checksec/relro", "full RELRO"),
+        (true, false) => Finding {
+            rule: "checksec/relro",
+            level: Level::Warning,
+            message: "partial RELRO, symbols are bound lazily so the GOT stays writable".into(),
+            address: None
+        },
+        (false, _) => Finding {
+            rule: "checksec/relro",
+            level: Level::Error,
+            message: "no RELRO".into(),
+            address: None
+        }
+    });
+
+    findings.push(match header.e_type(endian) {
+        elf::ET_EXEC => Finding {
+            rule: "checksec/pie",
+            level: Level::Warning,
+            message: "not a position independent executable".into(),
+            address: None
+        },
+        _ => pass("checksec/pie", "position independent")
+    });
+
+    Ok(findings)
+}
@@ -1,21 +1,24 @@
+use std::path::PathBuf;
 use clap::Args;
 use serde::{ Serialize, Deserialize };
 use super::Options;
 
 
-/// print shell complete rule
+/// print shell complete rule or write man pages
 #[derive(Serialize, Deserialize)]
 #[derive(Debug, Args)]
 #[command(args_conflicts_with_subcommands = true)]
 #[command(flatten_help = true)]
 pub struct Command {
     /// shell type
-    #[serde(skip, default = "default_shell")]
-    shell: clap_complete::Shell
-}
+    #[arg(required_unless_present = "man")]
+    #[serde(skip)]
+    shell: Option<clap_complete::Shell>,
 
-fn default_shell() -> clap_complete::Shell {
-    clap_complete::Shell::Bash
+    /// write man pages of fi and each subcommand into dir
+    #[arg(long, value_name = "DIR", conflicts_with = "shell")]
+    #[serde(skip)]
+    man: Option<PathBuf>
 }
 
 impl Command {
@@ -24,10 +27,18 @@ impl Command {
         use clap::CommandFactory;
     
         let mut cmd = Options::command();
+
+        if let Some(dir) = self.man {
+            std::fs::create_dir_all(&dir)?;
+            clap_mangen::generate_to(cmd, &dir)?;
+            return Ok(());
+        }
+
+        let shell = self.shell.unwrap_or(clap_complete::Shell::Bash);
         let stdout = io::stdout();
         let mut stdout = stdout.lock();
     
-        clap_complete::generate(self.shell, &mut cmd, "fi", &mut stdout);
+        clap_complete::generate(shell, &mut cmd, "fi", &mut stdout);
 
         Ok(())
     }
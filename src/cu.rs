@@ -0,0 +1,147 @@
+use std::io::Write;
+use addr2line::gimli;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, YieldPoint, MaybePrinter };
+use crate::dwarf;
+
+
+/// list DWARF compilation units with their text size
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// filter unit name by regex
+    pub keyword: Option<String>,
+
+    /// sort by text size, biggest first
+    #[arg(long)]
+    pub sort_size: bool,
+
+    /// sort by name
+    #[arg(long, conflicts_with = "sort_size")]
+    pub sort_name: bool,
+
+    /// only print the first N units
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+struct CompUnit {
+    name: Option<String>,
+    producer: Option<String>,
+    language: Option<&'static str>,
+    low: Option<u64>,
+    high: Option<u64>,
+    size: u64
+}
+
+impl Command {
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            name: Option<&'a str>,
+            producer: Option<&'a str>,
+            language: Option<&'a str>,
+            low_pc: Option<u64>,
+            high_pc: Option<u64>,
+            size: u64
+        }
+
+        let re = self.keyword.as_deref()
+            .map(regex::Regex::new)
+            .transpose()?;
+        let dwarf = explorer.cache.dwarf(explorer.dwarf_obj(), explorer.dwarf_path.as_deref()).await?;
+        let mut point = YieldPoint::default();
+        let mut units = dwarf.units();
+        let mut list = Vec::new();
+
+        while let Some(header) = units.next()? {
+            point.yield_now().await;
+
+            let unit = dwarf.unit(header)?;
+            let mut entries = unit.entries();
+            let Some((_, root)) = entries.next_dfs()?
+                else { continue };
+
+            let name = dwarf::attr_string(dwarf, &unit, root, gimli::DW_AT_name)
+                .map(|name| name.into_owned());
+
+            if let Some(re) = re.as_ref()
+                && !name.as_deref().is_some_and(|name| re.is_match(name))
+            {
+                continue
+            }
+
+            let producer = dwarf::attr_string(dwarf, &unit, root, gimli::DW_AT_producer)
+                .map(|producer| producer.into_owned());
+            let language = match root.attr_value(gimli::DW_AT_language)? {
+                Some(gimli::AttributeValue::Language(lang)) => lang.static_string()
+                    .map(|lang| lang.trim_start_matches("DW_LANG_")),
+                _ => None
+            };
+
+            let mut cu = CompUnit { name, producer, language, low: None, high: None, size: 0 };
+            let mut ranges = dwarf.unit_ranges(&unit)?;
+
+            while let Some(range) = ranges.next()? {
+                // code discarded by the linker
+                if range.begin == 0 || range.end <= range.begin {
+                    continue
+                }
+
+                cu.low = Some(cu.low.map_or(range.begin, |low| low.min(range.begin)));
+                cu.high = Some(cu.high.map_or(range.end, |high| high.max(range.end)));
+                cu.size += range.end - range.begin;
+            }
+
+            list.push(cu);
+        }
+
+        if self.sort_size {
+            list.sort_by_key(|cu| std::cmp::Reverse(cu.size));
+        } else if self.sort_name {
+            list.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+
+        list.truncate(self.limit.unwrap_or(usize::MAX));
+        let mut sum = 0;
+
+        for cu in &list {
+            sum += cu.size;
+
+            if !stdio.format.is_text() {
+                stdio.record(&Record {
+                    name: cu.name.as_deref(),
+                    producer: cu.producer.as_deref(),
+                    language: cu.language,
+                    low_pc: cu.low,
+                    high_pc: cu.high,
+                    size: cu.size
+                })?;
+                continue
+            }
+
+            writeln!(
+                stdio.stdout,
+                "{:018p}..{:018p}\t{:>8}\t{}\t{}\t{}",
+                cu.low.unwrap_or_default() as *const (),
+                cu.high.unwrap_or_default() as *const (),
+                cu.size,
+                MaybePrinter(cu.language, Some('?')),
+                MaybePrinter(cu.name.as_deref(), Some('?')),
+                MaybePrinter(cu.producer.as_deref(), Some('?'))
+            )?;
+        }
+
+        if stdio.format.is_text() {
+            writeln!(stdio.stdout, "total: {} units, {} bytes", list.len(), sum)?;
+        }
+
+        Ok(())
+    }
+}
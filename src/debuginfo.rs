@@ -0,0 +1,274 @@
+use std::{ env, fs, process };
+use std::path::{ Path, PathBuf, Component };
+use std::process::Command;
+use std::sync::Mutex;
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::collections::HashSet;
+use memmap2::MmapOptions;
+use object::{ Object, ObjectSection };
+use crate::util::{ Arena, hashpath };
+
+
+/// Separate file holding the debug info of the binary
+pub struct DebugFile {
+    pub path: PathBuf,
+    pub obj: object::File<'static>
+}
+
+impl DebugFile {
+    /// Map the file into `arena`, the one of the explorer holding it
+    pub fn open(path: PathBuf, arena: &Arena) -> anyhow::Result<DebugFile> {
+        let fd = fs::File::open(&path)?;
+        let mmap = unsafe {
+            MmapOptions::new().map_copy_read_only(&fd)?
+        };
+        let obj = object::File::parse(arena.keep(mmap))?;
+
+        Ok(DebugFile { path, obj })
+    }
+}
+
+/// Whether `obj` carries its own DWARF
+pub fn has_dwarf(obj: &object::File<'static>) -> bool {
+    obj.section_by_name(".debug_info").is_some_and(|section| section.size() > 0)
+}
+
+/// Hex encoded GNU build-id
+pub fn build_id(obj: &object::File<'static>) -> Option<String> {
+    let id = obj.build_id().ok()??;
+    Some(data_encoding::HEXLOWER.encode(id))
+}
+
+/// Local debug file of the binary at `path`
+pub fn locate(path: &Path, obj: &object::File<'static>) -> Option<PathBuf> {
+    match obj.format() {
+        object::BinaryFormat::MachO => find_dsym(path, obj),
+        object::BinaryFormat::Elf => find_by_build_id(obj)
+            .or_else(|| find_by_debuglink(path, obj)),
+        _ => None
+    }
+}
+
+const DEBUG_DIR: &str = "/usr/lib/debug";
+
+/// `/usr/lib/debug/.build-id/xx/yyyy.debug`
+fn find_by_build_id(obj: &object::File<'static>) -> Option<PathBuf> {
+    build_id_path(&build_id(obj)?)
+}
+
+fn build_id_path(id: &str) -> Option<PathBuf> {
+    if id.len() < 3 {
+        return None;
+    }
+
+    let path = Path::new(DEBUG_DIR)
+        .join(".build-id")
+        .join(&id[..2])
+        .join(format!("{}.debug", &id[2..]));
+    path.is_file().then_some(path)
+}
+
+/// File named by `.gnu_debuglink` with a matching CRC, next to the binary,
+/// in its `.debug` directory or under `/usr/lib/debug`
+fn find_by_debuglink(path: &Path, obj: &object::File<'static>) -> Option<PathBuf> {
+    let (name, crc) = obj.gnu_debuglink().ok()??;
+    let name = Path::new(std::str::from_utf8(name).ok()?);
+    let dir = fs::canonicalize(path).ok()?.parent()?.to_owned();
+    let candidates = [
+        dir.join(name),
+        dir.join(".debug").join(name),
+        Path::new(DEBUG_DIR).join(dir.strip_prefix("/").unwrap_or(&dir)).join(name)
+    ];
+
+    candidates.into_iter()
+        .find(|candidate| crc32_of(candidate) == Some(crc))
+}
+
+fn crc32_of(path: &Path) -> Option<u32> {
+    let fd = fs::File::open(path).ok()?;
+    let mmap = unsafe {
+        MmapOptions::new().map(&fd).ok()?
+    };
+    Some(crc32fast::hash(&mmap))
+}
+
+/// DWARF file of the `.dSYM` bundle with the same UUID,
+/// next to the binary or indexed by Spotlight
+fn find_dsym(path: &Path, obj: &object::File<'static>) -> Option<PathBuf> {
+    let uuid = obj.mach_uuid().ok()??;
+    let mut bundles = Vec::new();
+
+    if let Some(name) = path.file_name() {
+        let mut bundle = name.to_owned();
+        bundle.push(".dSYM");
+        bundles.push(path.with_file_name(bundle));
+    }
+
+    if let Some(dir) = path.parent()
+        && let Ok(entries) = fs::read_dir(if dir.as_os_str().is_empty() { Path::new(".") } else { dir })
+    {
+        bundles.extend(entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "dSYM"))
+        );
+    }
+
+    // spotlight keys the uuid as uppercase with dashes
+    let hex = data_encoding::HEXUPPER.encode(&uuid);
+    let query = format!(
+        "com_apple_xcode_dsym_uuids == {}-{}-{}-{}-{}",
+        &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]
+    );
+
+    if let Ok(output) = Command::new("mdfind").arg(&query).output()
+        && output.status.success()
+    {
+        bundles.extend(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(PathBuf::from)
+        );
+    }
+
+    bundles.iter()
+        .filter_map(|bundle| fs::read_dir(bundle.join("Contents/Resources/DWARF")).ok())
+        .flat_map(|entries| entries.filter_map(|entry| entry.ok()))
+        .map(|entry| entry.path())
+        .find(|path| mach_uuid_of(path) == Some(uuid))
+}
+
+fn mach_uuid_of(path: &Path) -> Option<[u8; 16]> {
+    let fd = fs::File::open(path).ok()?;
+    let mmap = unsafe {
+        MmapOptions::new().map(&fd).ok()?
+    };
+    object::File::parse(&*mmap).ok()?.mach_uuid().ok()?
+}
+
+/// Supplementary file named by `.gnu_debugaltlink` of the debug file at `path`,
+/// as the recorded path relative to the debug file or by its build-id,
+/// with the build-id hex encoded
+pub fn find_altlink(path: &Path, obj: &object::File<'static>) -> Option<(Option<PathBuf>, String)> {
+    let (name, id) = obj.gnu_debugaltlink().ok()??;
+    let id = data_encoding::HEXLOWER.encode(id);
+    let name = Path::new(std::str::from_utf8(name).ok()?);
+    let dir = fs::canonicalize(path).ok()?.parent()?.to_owned();
+    let found = Some(dir.join(name))
+        .filter(|path| path.is_file())
+        .or_else(|| build_id_path(&id));
+
+    Some((found, id))
+}
+
+/// Client of the servers listed in `$DEBUGINFOD_URLS`
+///
+/// Requests are made by `curl`, downloads are kept under `cache_dir/<build-id>/`.
+pub struct Debuginfod {
+    urls: Vec<String>,
+    cache_dir: PathBuf,
+    build_id: String,
+    /// sources not found on any server
+    missing: Mutex<HashSet<String>>
+}
+
+impl Debuginfod {
+    /// None if no server is configured or the binary has no build-id
+    pub fn new(obj: &object::File<'static>, cache_dir: &Path) -> Option<Debuginfod> {
+        let urls = env::var("DEBUGINFOD_URLS").ok()?
+            .split_whitespace()
+            .map(|url| url.trim_end_matches('/').to_owned())
+            .collect::<Vec<_>>();
+
+        if urls.is_empty() {
+            return None;
+        }
+
+        Some(Debuginfod {
+            urls,
+            cache_dir: cache_dir.to_owned(),
+            build_id: build_id(obj)?,
+            missing: Mutex::default()
+        })
+    }
+
+    pub fn debuginfo(&self) -> anyhow::Result<PathBuf> {
+        self.fetch(&self.build_id, "debuginfo", Path::new("debuginfo"))
+    }
+
+    /// Supplementary file of `.gnu_debugaltlink`, by its own build-id
+    pub fn altlink(&self, build_id: &str) -> anyhow::Result<PathBuf> {
+        self.fetch(build_id, "debuginfo", Path::new("debuginfo"))
+    }
+
+    /// Source file at the absolute `path` recorded in DWARF
+    pub fn source(&self, path: &str) -> Option<PathBuf> {
+        // the path comes from the binary, `..` must not lead a fetch out of the cache
+        if !path.starts_with('/')
+            || Path::new(path).components().any(|c| c == Component::ParentDir)
+            || self.missing.lock().unwrap().contains(path)
+        {
+            return None;
+        }
+
+        let local = Path::new("source").join(hashpath(Path::new(path)));
+
+        match self.fetch(&self.build_id, &format!("source{}", escape_path(path)), &local) {
+            Ok(path) => Some(path),
+            Err(_) => {
+                self.missing.lock().unwrap().insert(path.to_owned());
+                None
+            }
+        }
+    }
+
+    fn fetch(&self, build_id: &str, kind: &str, local: &Path) -> anyhow::Result<PathBuf> {
+        let path = self.cache_dir.join(build_id).join(local);
+
+        if path.is_file() {
+            return Ok(path);
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // own name per fetch, concurrent fetches of one file must not write into each other
+        static FETCHES: AtomicU64 = AtomicU64::new(0);
+        let mut partial = path.clone().into_os_string();
+        partial.push(format!(".{}.{}.part", process::id(), FETCHES.fetch_add(1, Ordering::Relaxed)));
+
+        for url in &self.urls {
+            let url = format!("{}/buildid/{}/{}", url, build_id, kind);
+            let status = Command::new("curl")
+                .args(["--fail", "--silent", "--location", "--output"])
+                .arg(&partial)
+                .arg(&url)
+                .status();
+
+            if status.is_ok_and(|status| status.success()) {
+                fs::rename(&partial, &path)?;
+                return Ok(path);
+            }
+        }
+
+        let _ = fs::remove_file(&partial);
+        anyhow::bail!("debuginfod: not found {} of {}", kind, build_id)
+    }
+}
+
+/// Percent-encode `path` for a URL, keeping `/` and unreserved characters
+fn escape_path(path: &str) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::with_capacity(path.len());
+
+    for &b in path.as_bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'/' | b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            let _ = write!(out, "%{:02X}", b);
+        }
+    }
+
+    out
+}
@@ -0,0 +1,303 @@
+use std::io::Write;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use anyhow::Context;
+use object::{ Object, ObjectSection, ObjectSymbol, SectionKind, SymbolIndex };
+use owo_colors::OwoColorize;
+use similar::{ ChangeTag, TextDiff };
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, IfSupported };
+use crate::disasm::{ self, Disassembler, Inst };
+use crate::show::{ query_plt, query_symbol_by_addr, read_literal };
+use crate::search::dedup_name;
+
+
+/// diff the disassembly of a function with its version in another binary of the session
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// function name or address
+    pub target: String,
+
+    /// binary of the session to compare with, by file name or path as `--bin`
+    #[arg(long, value_name = "BIN")]
+    pub to: String,
+
+    /// function name or address in the other binary, the same name by default
+    #[arg(long, value_name = "TARGET")]
+    pub to_target: Option<String>,
+
+    /// lines of context around changes
+    #[arg(short = 'U', long, default_value_t = 3)]
+    pub context: usize,
+
+    /// demangle symbol name
+    #[arg(short, long)]
+    pub demangle: bool,
+}
+
+impl Command {
+    pub async fn exec(self, explorer: &Explorer, other: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            op: char,
+            old: Option<usize>,
+            new: Option<usize>,
+            text: &'a str
+        }
+
+        let symlist = explorer.cache.symlist(&explorer.obj).await;
+        let idx = explorer.lookup_symbol(symlist, &self.target)?
+            .with_context(|| format!("not found symbol: {}", self.target))?;
+        let name = explorer.sym_obj().symbol_by_index(idx)?.name()?;
+
+        let other_symlist = other.cache.symlist(&other.obj).await;
+        let other_idx = match self.to_target.as_deref() {
+            Some(target) => other.lookup_symbol(other_symlist, target)?,
+            None => match other.lookup_symbol(other_symlist, name)? {
+                Some(idx) => Some(idx),
+                // rust legacy hash differs between builds
+                None => {
+                    let key = dedup_name(&explorer.cache.demangle(name)).to_owned();
+                    other_symlist.iter()
+                        .copied()
+                        .find(|&idx| other.sym_obj().symbol_by_index(idx)
+                            .ok()
+                            .filter(|sym| sym.kind() == object::SymbolKind::Text && !sym.is_undefined())
+                            .and_then(|sym| sym.name().ok())
+                            .is_some_and(|name| dedup_name(&other.cache.demangle(name)) == key))
+                }
+            }
+        };
+        let other_idx = other_idx
+            .with_context(|| format!("not found symbol in {}: {}", other.path.display(), self.to_target.as_deref().unwrap_or(name)))?;
+        let other_name = other.sym_obj().symbol_by_index(other_idx)?.name()?;
+
+        let old = listing(explorer, idx).await?;
+        let new = listing(other, other_idx).await?;
+        let old = old.iter().map(String::as_str).collect::<Vec<_>>();
+        let new = new.iter().map(String::as_str).collect::<Vec<_>>();
+        let diff = TextDiff::from_slices(&old, &new);
+        let groups = diff.grouped_ops(self.context);
+
+        if groups.is_empty() {
+            return Ok(());
+        }
+
+        let display_name = |explorer: &Explorer, name: &str| if self.demangle {
+            explorer.cache.demangle(name).into_owned()
+        } else {
+            name.to_owned()
+        };
+
+        if stdio.format.is_text() {
+            writeln!(
+                stdio.stdout,
+                "{}",
+                format_args!("--- {}: {}", explorer.path.display(), display_name(explorer, name))
+                    .if_supported(stdio.colored, |a| a.bold())
+            )?;
+            writeln!(
+                stdio.stdout,
+                "{}",
+                format_args!("+++ {}: {}", other.path.display(), display_name(other, other_name))
+                    .if_supported(stdio.colored, |a| a.bold())
+            )?;
+        }
+
+        for group in &groups {
+            if let (Some(first), Some(last)) = (group.first(), group.last())
+                && stdio.format.is_text()
+            {
+                let old_range = first.old_range().start..last.old_range().end;
+                let new_range = first.new_range().start..last.new_range().end;
+
+                writeln!(
+                    stdio.stdout,
+                    "{}",
+                    format_args!(
+                        "@@ -{},{} +{},{} @@",
+                        old_range.start + 1, old_range.len(),
+                        new_range.start + 1, new_range.len()
+                    ).if_supported(stdio.colored, |a| a.cyan())
+                )?;
+            }
+
+            for change in group.iter().flat_map(|op| diff.iter_changes(op)) {
+                let op = match change.tag() {
+                    ChangeTag::Equal => ' ',
+                    ChangeTag::Delete => '-',
+                    ChangeTag::Insert => '+'
+                };
+
+                if !stdio.format.is_text() {
+                    stdio.record(&Record {
+                        op,
+                        old: change.old_index(),
+                        new: change.new_index(),
+                        text: change.value()
+                    })?;
+                    continue
+                }
+
+                let line = format_args!("{}{}", op, change.value());
+
+                match change.tag() {
+                    ChangeTag::Equal => writeln!(stdio.stdout, "{}", line)?,
+                    ChangeTag::Delete => writeln!(stdio.stdout, "{}", line.if_supported(stdio.colored, |a| a.red()))?,
+                    ChangeTag::Insert => writeln!(stdio.stdout, "{}", line.if_supported(stdio.colored, |a| a.green()))?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Instructions of a function without addresses, one per line
+///
+/// Branch targets within the function become local labels numbered in address order,
+/// other targets and data references become `<name+offset>` with the rust hash stripped,
+/// or the string literal they point to, so code that only moved compares equal.
+async fn listing(explorer: &Explorer, idx: SymbolIndex) -> anyhow::Result<Vec<String>> {
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let addr2sym = explorer.cache.addr2sym(&explorer.obj).await;
+    let dyn_rela = explorer.cache.dyn_rela(&explorer.obj).await;
+    let plt = explorer.cache.plt(&explorer.obj).await;
+
+    let sym = explorer.sym_obj().symbol_by_index(idx)?;
+    let size = explorer.symbol_size(symlist, idx)?;
+    let section = sym.section_index()
+        .map(|section_idx| explorer.obj.section_by_index(section_idx))
+        .transpose()?
+        .filter(|section| section.kind() == SectionKind::Text)
+        .with_context(|| format!("not a function: {}", sym.name().unwrap_or_default()))?;
+    let data = explorer.cache.data(&explorer.obj, section.index()).await?;
+    let data = data.get((sym.address() - section.address()) as usize..)
+        .and_then(|data| data.get(..size as usize))
+        .context("symbol out of section")?;
+    let range = sym.address()..sym.address() + size;
+    // capstone is not `Send`, so find every address to name before awaiting
+    let (mut labels, mut refs) = {
+        let disasm = Disassembler::new(&explorer.obj)?;
+        let mut labels = Vec::new();
+        let mut refs = Vec::new();
+        let mut data_refs = disasm::DataRefs::default();
+        let mut insts = disasm.disasm_iter(data, sym.address())?;
+
+        while let Some(inst) = insts.next() {
+            let inst = inst?;
+
+            if let Ok(Some(target)) = disasm.operand2addr(&inst) {
+                if range.contains(&target) {
+                    labels.push(target);
+                } else {
+                    refs.push(target);
+                }
+            } else if let Some(addr) = disasm.operand2data(&inst, &mut data_refs) {
+                refs.push(addr);
+            }
+        }
+
+        (labels, refs)
+    };
+
+    labels.sort_unstable();
+    labels.dedup();
+    refs.sort_unstable();
+    refs.dedup();
+
+    let mut names = HashMap::with_capacity(refs.len());
+
+    for addr in refs {
+        let found = query_symbol_by_addr(explorer, addr2sym, dyn_rela, addr)
+            .map(|(name, base)| (Cow::Borrowed(name), addr - base))
+            .or_else(|| query_plt(plt, addr).map(|entry| (format!("{}@plt", entry.name).into(), 0)))
+            .or_else(|| addr2sym.get(addr).map(|sym| (Cow::Borrowed(sym.name()), addr - sym.address())));
+
+        let name = match found {
+            Some((name, 0)) => format!("<{}>", dedup_name(&explorer.cache.demangle(&name))),
+            Some((name, offset)) => format!("<{}+{:#x}>", dedup_name(&explorer.cache.demangle(&name)), offset),
+            None => read_literal(explorer, addr, None).await.unwrap_or_else(|| "<?>".into())
+        };
+        names.insert(addr, name);
+    }
+
+    let name_of = |addr: u64| names.get(&addr).map(String::as_str).unwrap_or("<?>");
+
+    let disasm = Disassembler::new(&explorer.obj)?;
+    let mut lines = Vec::new();
+    let mut data_refs = disasm::DataRefs::default();
+    let mut insts = disasm.disasm_iter(data, sym.address())?;
+
+    while let Some(inst) = insts.next() {
+        let inst = inst?;
+
+        if let Ok(id) = labels.binary_search(&inst.address()) {
+            lines.push(format!(".LBB{}:", id));
+        }
+
+        if matches!(inst, Inst::Wasm { .. }) {
+            lines.push(format!("\t{}", inst));
+            continue
+        }
+
+        let mnemonic = inst.mnemonic().unwrap_or("???");
+        let operands = inst.op_str().unwrap_or_default();
+
+        let operands = if let Ok(Some(target)) = disasm.operand2addr(&inst) {
+            match labels.binary_search(&target) {
+                Ok(id) if range.contains(&target) => replace_address(operands, target, &format!(".LBB{}", id)),
+                _ => replace_address(operands, target, name_of(target))
+            }
+        } else if let Some(addr) = disasm.operand2data(&inst, &mut data_refs) {
+            replace_address(operands, addr, name_of(addr))
+        } else if mnemonic == "adrp" {
+            // the page moves with the data, the `add` or `ldr` after it names the data
+            replace_address(operands, u64::MAX, "<page>")
+        } else {
+            operands.into()
+        };
+
+        if operands.is_empty() {
+            lines.push(format!("\t{}", mnemonic));
+        } else {
+            lines.push(format!("\t{} {}", mnemonic, operands));
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Replace the operand giving `addr` with `name`: the address itself, a rip-relative
+/// memory operand, or else the last immediate, the page offset of aarch64
+fn replace_address<'a>(operands: &'a str, addr: u64, name: &str) -> Cow<'a, str> {
+    let hex = format!("{:#x}", addr);
+
+    if let Some(pos) = operands.rfind(&hex)
+        && !operands[pos + hex.len()..].starts_with(|c: char| c.is_ascii_hexdigit())
+    {
+        let before = operands[..pos].strip_suffix('#').unwrap_or(&operands[..pos]);
+        return format!("{}{}{}", before, name, &operands[pos + hex.len()..]).into();
+    }
+
+    if let Some(pos) = operands.find("rip ") {
+        let rest = &operands[pos..];
+        let end = rest.find(']').unwrap_or(rest.len());
+        return format!("{}{}{}", &operands[..pos], name, &rest[end..]).into();
+    }
+
+    if let Some(pos) = operands.rfind('#') {
+        let rest = &operands[pos + 1..];
+        let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '-')).unwrap_or(rest.len());
+        return format!("{}{}{}", &operands[..pos], name, &rest[end..]).into();
+    }
+
+    operands.into()
+}
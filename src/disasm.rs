@@ -1,6 +1,12 @@
-use std::{ fmt, slice };
+pub mod jumptable;
+pub mod syscall;
+pub mod stack;
+
+use std::fmt;
 use std::iter::Peekable;
+use std::collections::HashMap;
 use capstone::Capstone;
+use crate::util::IfSupported;
 
 pub enum Disassembler {
     X86_64(Capstone),
@@ -8,15 +14,10 @@ pub enum Disassembler {
     Wasm,
 }
 
-pub enum InstList<'a> {
-    X86_64(capstone::Instructions<'a>),
-    Aarch64(capstone::Instructions<'a>),
-    Wasm(wasmparser::FunctionBody<'a>)
-}
-
+/// Instructions decoded one at a time, see `InstIter::next`
 pub enum InstIter<'a> {
-    X86_64(slice::Iter<'a, capstone::Insn<'a>>),
-    Aarch64(slice::Iter<'a, capstone::Insn<'a>>),
+    X86_64(Chunks<'a>),
+    Aarch64(Chunks<'a>),
     Wasm {
         base: usize,
         data: &'a [u8],
@@ -24,6 +25,15 @@ pub enum InstIter<'a> {
     },
 }
 
+/// Capstone instructions decoded `CHUNK_SIZE` at a time
+pub struct Chunks<'a> {
+    cs: &'a Capstone,
+    code: &'a [u8],
+    addr: u64,
+    chunk: capstone::Instructions<'a>,
+    pos: usize
+}
+
 pub enum Inst<'a> {
     X86_64(&'a capstone::Insn<'a>),
     Aarch64(&'a capstone::Insn<'a>),
@@ -34,6 +44,25 @@ pub enum Inst<'a> {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstKind {
+    Call,
+    Jump,
+    Return,
+    Other
+}
+
+/// Track `adrp` pages to resolve aarch64 data references
+#[derive(Default)]
+pub struct DataRefs(HashMap<capstone::RegId, u64>);
+
+pub struct Highlight<'a> {
+    disasm: &'a Disassembler,
+    inst: &'a Inst<'a>,
+    colored: bool,
+    label: Option<(usize, u64)>
+}
+
 impl Disassembler {
     pub fn new(obj: &object::File) -> anyhow::Result<Disassembler> {
         use object::Object;
@@ -61,18 +90,39 @@ impl Disassembler {
         Ok(disasm)
     }
 
-    pub fn disasm_all<'a>(&'a self, code: &'a [u8], addr: u64) -> anyhow::Result<InstList<'a>> {
-        let list = match self {
-            Disassembler::X86_64(disasm) => disasm.disasm_all(code, addr).map(InstList::X86_64)?,
-            Disassembler::Aarch64(disasm) => disasm.disasm_all(code, addr).map(InstList::Aarch64)?,
+    /// x86-64 disassembler without an object, for the unit tests
+    #[cfg(test)]
+    pub(crate) fn x86_64() -> Disassembler {
+        use capstone::Capstone;
+        use capstone::arch::BuildsCapstone;
+
+        let cs = Capstone::new()
+            .x86()
+            .mode(capstone::arch::x86::ArchMode::Mode64)
+            .detail(true)
+            .build()
+            .unwrap();
+        Disassembler::X86_64(cs)
+    }
+
+    /// Decode `code` as it is iterated, without holding all its instructions
+    pub fn disasm_iter<'a>(&'a self, code: &'a [u8], addr: u64) -> anyhow::Result<InstIter<'a>> {
+        let iter = match self {
+            Disassembler::X86_64(disasm) => InstIter::X86_64(Chunks::new(disasm, code, addr)?),
+            Disassembler::Aarch64(disasm) => InstIter::Aarch64(Chunks::new(disasm, code, addr)?),
             Disassembler::Wasm => {
                 let reader = wasmparser::BinaryReader::new(code, addr.try_into()?);
                 let func = wasmparser::FunctionBody::new(reader);
-                InstList::Wasm(func)
+                let base = func.range().start;
+                let data = func.as_bytes();
+                let iter = func.get_operators_reader()?
+                    .into_iter_with_offsets()
+                    .peekable();
+                InstIter::Wasm { base, data, iter }
             }
         };
 
-        Ok(list)
+        Ok(iter)
     }
 
     pub fn operand2addr(&self, inst: &Inst<'_>) -> anyhow::Result<Option<u64>> {
@@ -151,34 +201,333 @@ impl Disassembler {
             _ => anyhow::bail!("unsupported arch")
         }
     }
-}
 
-impl<'a> InstList<'a> {
-    pub fn iter(&self) -> anyhow::Result<InstIter<'_>> {
-        let iter = match self {
-            InstList::X86_64(list) => InstIter::X86_64(list.iter()),
-            InstList::Aarch64(list) => InstIter::Aarch64(list.iter()),
-            InstList::Wasm(func) => {
-                let base = func.range().start;
-                let data = func.as_bytes();
-                let iter = func.get_operators_reader()?
-                    .into_iter_with_offsets()
-                    .peekable();
-                InstIter::Wasm { base, data, iter }
-            },
+    /// Target of a branch by immediate, None for one through memory or a register
+    pub fn direct_target(&self, inst: &Inst<'_>) -> Option<u64> {
+        use capstone::arch::{ ArchDetail, DetailsArchInsn };
+        use capstone::arch::x86::X86OperandType;
+        use capstone::arch::arm64::Arm64OperandType;
+
+        if !matches!(self.inst_kind(inst), InstKind::Call | InstKind::Jump) {
+            return None
+        }
+
+        let imm = match (self, inst) {
+            (Disassembler::X86_64(disasm), Inst::X86_64(insn))
+                | (Disassembler::Aarch64(disasm), Inst::Aarch64(insn)) =>
+                match disasm.insn_detail(insn).ok()?.arch_detail() {
+                    ArchDetail::X86Detail(detail) => match detail.operands().next()?.op_type {
+                        X86OperandType::Imm(imm) => imm,
+                        _ => return None
+                    },
+                    ArchDetail::Arm64Detail(detail) => match detail.operands().next()?.op_type {
+                        Arm64OperandType::Imm(imm) => imm,
+                        _ => return None
+                    },
+                    _ => return None
+                },
+            _ => return None
         };
 
-        Ok(iter)
+        imm.try_into().ok()
+    }
+
+    /// Resolve the data address referenced by an instruction,
+    /// such as `lea` with rip-relative operand or `adrp` + `add`/`ldr`/`str`.
+    pub fn operand2data(&self, inst: &Inst<'_>, refs: &mut DataRefs) -> Option<u64> {
+        use capstone::arch::{ ArchDetail, DetailsArchInsn };
+        use capstone::InsnGroupType::{ Type as InsnGroupType, CS_GRP_CALL, CS_GRP_JUMP };
+
+        match (self, inst) {
+            (Disassembler::X86_64(disasm), Inst::X86_64(insn)) => {
+                let detail = disasm.insn_detail(insn).ok()?;
+
+                if detail.groups()
+                    .iter()
+                    .any(|id| matches!(InsnGroupType::from(id.0), CS_GRP_CALL | CS_GRP_JUMP))
+                {
+                    return None;
+                }
+
+                self.rip_relative(inst)
+            },
+            (Disassembler::Aarch64(disasm), Inst::Aarch64(inst)) => {
+                use capstone::arch::arm64::Arm64OperandType;
+
+                let detail = disasm.insn_detail(inst).ok()?;
+                let ArchDetail::Arm64Detail(inst_detail) = detail.arch_detail()
+                    else {
+                        return None
+                    };
+                let operands = inst_detail.operands()
+                    .map(|operand| operand.op_type)
+                    .collect::<Vec<_>>();
+
+                match (inst.mnemonic()?, operands.as_slice()) {
+                    ("adrp", [Arm64OperandType::Reg(dst), Arm64OperandType::Imm(page)]) => {
+                        refs.0.insert(*dst, u64::try_from(*page).ok()?);
+                        None
+                    },
+                    ("adr", [Arm64OperandType::Reg(_), Arm64OperandType::Imm(addr)]) =>
+                        u64::try_from(*addr).ok(),
+                    ("add", [Arm64OperandType::Reg(_), Arm64OperandType::Reg(src), Arm64OperandType::Imm(offset)]) =>
+                        refs.0.get(src)?.checked_add_signed(*offset),
+                    (mnemonic, [Arm64OperandType::Reg(_), Arm64OperandType::Mem(mem)])
+                        if mnemonic.starts_with("ldr") || mnemonic.starts_with("str") =>
+                    {
+                        refs.0.get(&mem.base())?.checked_add_signed(mem.disp().into())
+                    },
+                    _ => None
+                }
+            },
+            _ => None
+        }
+    }
+
+    /// Index of the argument register written by an instruction, such as 1 for
+    /// `mov esi, 0x11` or `add x1, x1, #0x20`
+    pub fn arg_reg(&self, inst: &Inst<'_>) -> Option<usize> {
+        use capstone::arch::{ ArchDetail, DetailsArchInsn };
+        use capstone::arch::x86::X86OperandType;
+        use capstone::arch::arm64::Arm64OperandType;
+
+        const X86_ARGS: [[&str; 2]; 6] = [
+            ["rdi", "edi"], ["rsi", "esi"], ["rdx", "edx"],
+            ["rcx", "ecx"], ["r8", "r8d"], ["r9", "r9d"]
+        ];
+
+        match (self, inst) {
+            (Disassembler::X86_64(disasm), Inst::X86_64(insn))
+                | (Disassembler::Aarch64(disasm), Inst::Aarch64(insn)) =>
+            {
+                let detail = disasm.insn_detail(insn).ok()?;
+                let reg = match detail.arch_detail() {
+                    ArchDetail::X86Detail(detail) => match detail.operands().next()?.op_type {
+                        X86OperandType::Reg(reg) => reg,
+                        _ => return None
+                    },
+                    ArchDetail::Arm64Detail(detail) => match detail.operands().next()?.op_type {
+                        Arm64OperandType::Reg(reg) => reg,
+                        _ => return None
+                    },
+                    _ => return None
+                };
+                let name = disasm.reg_name(reg)?;
+
+                match self {
+                    Disassembler::X86_64(_) => X86_ARGS.iter().position(|names| names.contains(&name.as_str())),
+                    _ => name.strip_prefix(['x', 'w'])
+                        .and_then(|n| n.parse::<usize>().ok())
+                        .filter(|&n| n < 8)
+                }
+            },
+            _ => None
+        }
+    }
+
+    /// Resolve the GOT slot used by an instruction of a PLT stub
+    pub fn plt_slot(&self, inst: &Inst<'_>, refs: &mut DataRefs) -> Option<u64> {
+        match (self, inst) {
+            (Disassembler::X86_64(_), Inst::X86_64(_)) if self.inst_kind(inst) == InstKind::Jump =>
+                self.rip_relative(inst),
+            (Disassembler::Aarch64(_), Inst::Aarch64(insn)) => {
+                let addr = self.operand2data(inst, refs)?;
+                insn.mnemonic()?.starts_with("ldr").then_some(addr)
+            },
+            _ => None
+        }
+    }
+
+    fn rip_relative(&self, inst: &Inst<'_>) -> Option<u64> {
+        use capstone::arch::{ ArchDetail, DetailsArchInsn };
+        use capstone::arch::x86::X86OperandType;
+        use capstone::arch::x86::X86Reg::{ Type as X86RegType, X86_REG_RIP };
+
+        let (Disassembler::X86_64(disasm), Inst::X86_64(inst)) = (self, inst)
+            else {
+                return None
+            };
+        let detail = disasm.insn_detail(inst).ok()?;
+        let ArchDetail::X86Detail(inst_detail) = detail.arch_detail()
+            else {
+                return None
+            };
+        let next_ip = inst.address() + inst.bytes().len() as u64;
+
+        inst_detail.operands().find_map(|operand| match operand.op_type {
+            X86OperandType::Mem(mem) if X86RegType::from(mem.base().0) == X86_REG_RIP =>
+                next_ip.checked_add_signed(mem.disp()),
+            _ => None
+        })
+    }
+
+    /// Displacement of a memory operand based on a register other than the pc,
+    /// such as the slot offset of `call [rax + 0x18]` or `ldr x8, [x9, #0x18]`
+    pub fn mem_displacement(&self, inst: &Inst<'_>) -> Option<i64> {
+        use capstone::arch::{ ArchDetail, DetailsArchInsn };
+
+        match (self, inst) {
+            (Disassembler::X86_64(disasm), Inst::X86_64(inst)) => {
+                use capstone::arch::x86::X86OperandType;
+                use capstone::arch::x86::X86Reg::{ Type as X86RegType, X86_REG_RIP, X86_REG_INVALID };
+
+                let detail = disasm.insn_detail(inst).ok()?;
+                let ArchDetail::X86Detail(inst_detail) = detail.arch_detail()
+                    else {
+                        return None
+                    };
+
+                inst_detail.operands().find_map(|operand| match operand.op_type {
+                    X86OperandType::Mem(mem)
+                        if !matches!(X86RegType::from(mem.base().0), X86_REG_RIP | X86_REG_INVALID) =>
+                        Some(mem.disp()),
+                    _ => None
+                })
+            },
+            (Disassembler::Aarch64(disasm), Inst::Aarch64(inst)) => {
+                use capstone::arch::arm64::Arm64OperandType;
+
+                let detail = disasm.insn_detail(inst).ok()?;
+                let ArchDetail::Arm64Detail(inst_detail) = detail.arch_detail()
+                    else {
+                        return None
+                    };
+
+                inst_detail.operands().find_map(|operand| match operand.op_type {
+                    Arm64OperandType::Mem(mem) if mem.base().0 != 0 => Some(mem.disp().into()),
+                    _ => None
+                })
+            },
+            _ => None
+        }
+    }
+
+    pub fn inst_kind(&self, inst: &Inst<'_>) -> InstKind {
+        use capstone::InsnGroupType::{ Type as InsnGroupType, CS_GRP_CALL, CS_GRP_JUMP, CS_GRP_RET };
+        use wasmparser::Operator;
+
+        match (self, inst) {
+            (Disassembler::X86_64(disasm), Inst::X86_64(inst))
+                | (Disassembler::Aarch64(disasm), Inst::Aarch64(inst)) =>
+            {
+                let Ok(detail) = disasm.insn_detail(inst)
+                    else {
+                        return InstKind::Other
+                    };
+
+                detail.groups()
+                    .iter()
+                    .find_map(|id| match InsnGroupType::from(id.0) {
+                        CS_GRP_CALL => Some(InstKind::Call),
+                        CS_GRP_JUMP => Some(InstKind::Jump),
+                        CS_GRP_RET => Some(InstKind::Return),
+                        _ => None
+                    })
+                    .unwrap_or(InstKind::Other)
+            },
+            (Disassembler::Wasm, Inst::Wasm { operator, .. }) => match operator {
+                Operator::Call { .. } | Operator::CallIndirect { .. } => InstKind::Call,
+                Operator::Br { .. } | Operator::BrIf { .. } | Operator::BrTable { .. }
+                    | Operator::If { .. } | Operator::Else => InstKind::Jump,
+                Operator::Return => InstKind::Return,
+                _ => InstKind::Other
+            },
+            _ => InstKind::Other
+        }
+    }
+
+    /// Immediate operands of an instruction, branch targets and `adrp`/`adr` pages excluded
+    pub fn immediates(&self, inst: &Inst<'_>) -> Vec<i64> {
+        use capstone::arch::{ ArchDetail, DetailsArchInsn };
+        use capstone::arch::x86::X86OperandType;
+        use capstone::arch::arm64::Arm64OperandType;
+        use wasmparser::Operator;
+
+        if self.inst_kind(inst) != InstKind::Other
+            || matches!(inst.mnemonic(), Some("adrp" | "adr"))
+        {
+            return Vec::new();
+        }
+
+        match (self, inst) {
+            (Disassembler::X86_64(disasm), Inst::X86_64(insn))
+                | (Disassembler::Aarch64(disasm), Inst::Aarch64(insn)) =>
+            {
+                let Ok(detail) = disasm.insn_detail(insn)
+                    else {
+                        return Vec::new()
+                    };
+
+                match detail.arch_detail() {
+                    ArchDetail::X86Detail(detail) => detail.operands()
+                        .filter_map(|operand| match operand.op_type {
+                            X86OperandType::Imm(imm) => Some(imm),
+                            _ => None
+                        })
+                        .collect(),
+                    ArchDetail::Arm64Detail(detail) => detail.operands()
+                        .filter_map(|operand| match operand.op_type {
+                            Arm64OperandType::Imm(imm) | Arm64OperandType::Cimm(imm) => Some(imm),
+                            _ => None
+                        })
+                        .collect(),
+                    _ => Vec::new()
+                }
+            },
+            (Disassembler::Wasm, Inst::Wasm { operator, .. }) => match operator {
+                Operator::I32Const { value } => vec![(*value).into()],
+                Operator::I64Const { value } => vec![*value],
+                _ => Vec::new()
+            },
+            _ => Vec::new()
+        }
     }
 }
 
-impl<'a> Iterator for InstIter<'a> {
-    type Item = anyhow::Result<Inst<'a>>;
+impl<'a> Chunks<'a> {
+    const CHUNK_SIZE: usize = 256;
 
-    fn next(&mut self) -> Option<Self::Item> {
+    fn new(cs: &'a Capstone, code: &'a [u8], addr: u64) -> anyhow::Result<Chunks<'a>> {
+        let chunk = cs.disasm_count(code, addr, Self::CHUNK_SIZE)?;
+        Ok(Chunks { cs, code, addr, chunk, pos: 0 })
+    }
+
+    fn next(&mut self) -> Option<anyhow::Result<&capstone::Insn<'a>>> {
+        if self.pos == self.chunk.len() {
+            // capstone stops at the first invalid instruction, so a short chunk is the end
+            let last = self.chunk.last()?;
+            if self.chunk.len() < Self::CHUNK_SIZE {
+                return None;
+            }
+
+            let next = last.address() + last.len() as u64;
+            let offset = (next - self.addr) as usize;
+            let code = self.code.get(offset..).filter(|code| !code.is_empty())?;
+            match self.cs.disasm_count(code, next, Self::CHUNK_SIZE) {
+                Ok(chunk) => self.chunk = chunk,
+                Err(err) => return Some(Err(err.into()))
+            }
+            self.code = code;
+            self.addr = next;
+            self.pos = 0;
+        }
+
+        let inst = self.chunk.get(self.pos)?;
+        self.pos += 1;
+        Some(Ok(inst))
+    }
+}
+
+impl InstIter<'_> {
+    /// Decode the next instruction
+    ///
+    /// Not an `Iterator`, an instruction borrows the decoded chunk
+    /// and must be dropped before the next is decoded.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<anyhow::Result<Inst<'_>>> {
         match self {
-            InstIter::X86_64(iter) => iter.next().map(Inst::X86_64).map(Ok),
-            InstIter::Aarch64(iter) => iter.next().map(Inst::Aarch64).map(Ok),
+            InstIter::X86_64(iter) => iter.next().map(|inst| inst.map(Inst::X86_64)),
+            InstIter::Aarch64(iter) => iter.next().map(|inst| inst.map(Inst::Aarch64)),
             InstIter::Wasm { base, data, iter } => {
                 let next = iter.next();
                 let peek = iter.peek()
@@ -217,6 +566,20 @@ impl Inst<'_> {
             Inst::Wasm { data, .. } => data
         }
     }
+
+    pub fn mnemonic(&self) -> Option<&str> {
+        match self {
+            Inst::X86_64(inst) | Inst::Aarch64(inst) => inst.mnemonic(),
+            Inst::Wasm { .. } => None
+        }
+    }
+
+    pub fn op_str(&self) -> Option<&str> {
+        match self {
+            Inst::X86_64(inst) | Inst::Aarch64(inst) => inst.op_str(),
+            Inst::Wasm { .. } => None
+        }
+    }
 }
 
 impl fmt::Display for Inst<'_> {
@@ -235,3 +598,150 @@ impl fmt::Display for Inst<'_> {
         }
     }
 }
+
+impl<'a> Highlight<'a> {
+    pub fn new(disasm: &'a Disassembler, inst: &'a Inst<'a>, colored: bool) -> Self {
+        Highlight { disasm, inst, colored, label: None }
+    }
+
+    /// Replace branch target `addr` in operands with local label `.LBB{id}`
+    pub fn label(mut self, label: Option<(usize, u64)>) -> Self {
+        self.label = label;
+        self
+    }
+}
+
+impl fmt::Display for Highlight<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use owo_colors::{ OwoColorize, Style };
+
+        let colored = self.colored;
+        let (mnemonic, operands) = match self.inst {
+            Inst::X86_64(inst) | Inst::Aarch64(inst) => (
+                inst.mnemonic().unwrap_or("???").to_owned(),
+                inst.op_str().map(|op_str| op_str.to_owned())
+            ),
+            Inst::Wasm { .. } => {
+                let text = self.inst.to_string();
+                match text.split_once(' ') {
+                    Some((name, rest)) => (name.to_owned(), Some(rest.to_owned())),
+                    None => (text, None)
+                }
+            }
+        };
+
+        match self.disasm.inst_kind(self.inst) {
+            InstKind::Call | InstKind::Jump | InstKind::Return =>
+                write!(f, "{}", mnemonic.if_supported(colored, |a| a.style(Style::new().bright_red().bold())))?,
+            InstKind::Other => write!(f, "{}", mnemonic.if_supported(colored, |a| a.yellow()))?
+        }
+
+        let Some(operands) = operands
+            else {
+                return Ok(())
+            };
+
+        f.write_str(" ")?;
+
+        if matches!(self.inst, Inst::Wasm { .. }) {
+            return f.write_str(&operands);
+        }
+
+        if let Some((id, addr)) = self.label
+            && let Some(pos) = operands.rfind(&format!("{:#x}", addr))
+        {
+            let before = operands[..pos].strip_suffix('#').unwrap_or(&operands[..pos]);
+            let after = operands[pos..].trim_start_matches(|c: char| c.is_ascii_alphanumeric());
+
+            highlight_operands(before, colored, f)?;
+            write!(
+                f,
+                "{} {}",
+                format_args!(".LBB{}", id).if_supported(colored, |a| a.magenta()),
+                format_args!("({:#x})", addr).if_supported(colored, |a| a.dimmed())
+            )?;
+            highlight_operands(after, colored, f)
+        } else {
+            highlight_operands(&operands, colored, f)
+        }
+    }
+}
+
+/// Immediate operand as capstone prints it, such as `0x1a8` or `#16`
+fn parse_imm(s: &str) -> Option<u64> {
+    let s = s.strip_prefix('#').unwrap_or(s);
+
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok()
+    }
+}
+
+/// Colorize a capstone operand string: registers, immediates and memory operands
+fn highlight_operands(op_str: &str, colored: bool, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    use owo_colors::OwoColorize;
+
+    const MEMORY_KEYWORDS: &[&str] = &[
+        "ptr", "byte", "word", "dword", "fword", "qword", "tbyte",
+        "oword", "xmmword", "ymmword", "zmmword"
+    ];
+    const SHIFT_KEYWORDS: &[&str] = &[
+        "lsl", "lsr", "asr", "ror", "msl",
+        "uxtb", "uxth", "uxtw", "uxtx", "sxtb", "sxth", "sxtw", "sxtx"
+    ];
+
+    if !colored {
+        return f.write_str(op_str);
+    }
+
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.';
+    let mut depth = 0usize;
+    let mut rest = op_str;
+
+    while let Some(c) = rest.chars().next() {
+        let imm = c == '#'
+            || c.is_ascii_digit()
+            || (c == '-' && rest[1..].starts_with(|c: char| c.is_ascii_digit()));
+
+        if imm {
+            let prefix = rest.len() - rest.trim_start_matches(['#', '-']).len();
+            let end = rest[prefix..].find(|c: char| !is_word(c))
+                .map_or(rest.len(), |n| n + prefix);
+            let (token, next) = rest.split_at(end);
+            write!(f, "{}", token.cyan())?;
+            rest = next;
+        } else if is_word(c) {
+            let end = rest.find(|c: char| !is_word(c)).unwrap_or(rest.len());
+            let (token, next) = rest.split_at(end);
+
+            if MEMORY_KEYWORDS.contains(&token) {
+                write!(f, "{}", token.blue())?;
+            } else if SHIFT_KEYWORDS.contains(&token) {
+                f.write_str(token)?;
+            } else {
+                write!(f, "{}", token.green())?;
+            }
+
+            rest = next;
+        } else {
+            let (token, next) = rest.split_at(c.len_utf8());
+
+            match c {
+                '[' => {
+                    depth += 1;
+                    write!(f, "{}", token.blue())?;
+                },
+                ']' => {
+                    depth = depth.saturating_sub(1);
+                    write!(f, "{}", token.blue())?;
+                },
+                _ if depth > 0 && !c.is_whitespace() => write!(f, "{}", token.blue())?,
+                _ => f.write_str(token)?
+            }
+
+            rest = next;
+        }
+    }
+
+    Ok(())
+}
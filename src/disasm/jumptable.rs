@@ -0,0 +1,349 @@
+use std::ops::Range;
+use std::collections::HashMap;
+use capstone::{ Capstone, Insn };
+use super::{ Disassembler, Inst };
+
+
+/// Upper bound on entries when no `cmp` bound was found
+const MAX_ENTRIES: usize = 1024;
+
+/// Indirect jump through a table, found by `JumpTableFinder`
+#[derive(Debug, Clone)]
+pub struct JumpTableRef {
+    /// address of the indirect jump instruction
+    pub jump: u64,
+    /// address of the table
+    pub table: u64,
+    /// entries are relative to this address, absolute if none
+    pub base: Option<u64>,
+    pub size: u8,
+    pub signed: bool,
+    pub shift: u8,
+    pub count: Option<usize>
+}
+
+/// Decoded jump table
+#[derive(Debug, Clone)]
+pub struct JumpTable {
+    pub jump: u64,
+    pub table: u64,
+    pub size: u8,
+    pub targets: Vec<u64>
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Value {
+    Const(u64),
+    Entry { table: u64, size: u8, signed: bool },
+    Target { table: u64, base: u64, size: u8, signed: bool, shift: u8 },
+}
+
+enum Operand {
+    Reg(String),
+    Imm(i64),
+    Mem {
+        base: Option<String>,
+        index: Option<String>,
+        scale: i32,
+        disp: i64
+    }
+}
+
+/// Find indirect jumps through tables in a function, fed one instruction at a time
+///
+/// This is a light data-flow analysis over known compiler patterns,
+/// `lea + movsxd + add + jmp` on x86-64 and `adrp/adr + ldr + add + br` on aarch64.
+#[derive(Default)]
+pub struct JumpTableFinder {
+    regs: HashMap<String, Value>,
+    cmp: Option<u64>,
+    bound: Option<usize>,
+    output: Vec<JumpTableRef>
+}
+
+impl JumpTableFinder {
+    /// Feed the next instruction of the function
+    pub fn step(&mut self, disasm: &Disassembler, inst: &Inst<'_>) {
+        let JumpTableFinder { regs, cmp, bound, output } = self;
+        let (cs, insn) = match (disasm, inst) {
+            (Disassembler::X86_64(cs), Inst::X86_64(insn))
+                | (Disassembler::Aarch64(cs), Inst::Aarch64(insn)) => (cs, insn),
+            _ => return
+        };
+        let Some(mnemonic) = insn.mnemonic()
+            else {
+                return
+            };
+        let Some(ops) = operands(cs, insn)
+            else {
+                return
+            };
+        let next_ip = insn.address() + insn.bytes().len() as u64;
+
+        let value = match (mnemonic, ops.as_slice()) {
+            // bound check
+            ("cmp", [_, Operand::Imm(imm)]) => {
+                *cmp = u64::try_from(*imm).ok();
+                return
+            },
+            ("ja" | "b.hi", _) => {
+                *bound = cmp.map(|n| n as usize + 1);
+                return
+            },
+            ("jae" | "b.hs" | "b.cs", _) => {
+                *bound = cmp.map(|n| n as usize);
+                return
+            },
+
+            // x86-64
+            ("lea", [Operand::Reg(dst), Operand::Mem { base: Some(base), index: None, disp, .. }])
+                if base == "rip" =>
+            {
+                next_ip.checked_add_signed(*disp).map(|addr| (dst, Value::Const(addr)))
+            },
+            ("movsxd", [Operand::Reg(dst), Operand::Mem { base: Some(base), index: Some(_), scale: 4, disp: 0 }]) =>
+                match regs.get(base) {
+                    Some(&Value::Const(table)) => Some((dst, Value::Entry { table, size: 4, signed: true })),
+                    _ => None
+                },
+            ("mov", [Operand::Reg(dst), Operand::Mem { base: None, index: Some(_), scale: 8, disp }]) =>
+                u64::try_from(*disp).ok()
+                    .map(|table| (dst, Value::Entry { table, size: 8, signed: false })),
+            ("add", [Operand::Reg(dst), Operand::Reg(src)]) => match (regs.get(dst), regs.get(src)) {
+                (Some(&Value::Entry { table, size, signed }), Some(&Value::Const(base)))
+                    | (Some(&Value::Const(base)), Some(&Value::Entry { table, size, signed })) =>
+                    Some((dst, Value::Target { table, base, size, signed, shift: 0 })),
+                _ => None
+            },
+            ("jmp", [Operand::Mem { base: None, index: Some(_), scale: 8, disp }]) => {
+                if let Ok(table) = u64::try_from(*disp) {
+                    output.push(JumpTableRef {
+                        jump: insn.address(), table,
+                        base: None, size: 8, signed: false, shift: 0,
+                        count: bound.take()
+                    });
+                }
+                return
+            },
+            ("jmp" | "br", [Operand::Reg(reg)]) => {
+                match regs.get(reg) {
+                    Some(&Value::Target { table, base, size, signed, shift }) => output.push(JumpTableRef {
+                        jump: insn.address(), table,
+                        base: Some(base), size, signed, shift,
+                        count: bound.take()
+                    }),
+                    Some(&Value::Entry { table, size: 8, .. }) => output.push(JumpTableRef {
+                        jump: insn.address(), table,
+                        base: None, size: 8, signed: false, shift: 0,
+                        count: bound.take()
+                    }),
+                    _ => ()
+                }
+                return
+            },
+
+            // aarch64
+            ("adrp" | "adr", [Operand::Reg(dst), Operand::Imm(imm)]) =>
+                u64::try_from(*imm).ok().map(|addr| (dst, Value::Const(addr))),
+            ("add", [Operand::Reg(dst), Operand::Reg(src), Operand::Imm(imm)]) => match regs.get(src) {
+                Some(&Value::Const(addr)) => addr.checked_add_signed(*imm)
+                    .map(|addr| (dst, Value::Const(addr))),
+                _ => None
+            },
+            ("ldrb" | "ldrh" | "ldrsw" | "ldr", [Operand::Reg(dst), Operand::Mem { base: Some(base), index: Some(_), disp: 0, .. }]) =>
+                match (regs.get(base), mnemonic) {
+                    (Some(&Value::Const(table)), "ldrb") => Some((dst, Value::Entry { table, size: 1, signed: false })),
+                    (Some(&Value::Const(table)), "ldrh") => Some((dst, Value::Entry { table, size: 2, signed: false })),
+                    (Some(&Value::Const(table)), "ldrsw") => Some((dst, Value::Entry { table, size: 4, signed: true })),
+                    _ => None
+                },
+            ("add", [Operand::Reg(dst), Operand::Reg(lhs), Operand::Reg(rhs)]) => match (regs.get(lhs), regs.get(rhs)) {
+                (Some(&Value::Const(base)), Some(&Value::Entry { table, size, signed })) => {
+                    let shift = insn.op_str()
+                        .and_then(|op_str| op_str.rsplit_once('#'))
+                        .and_then(|(_, shift)| shift.trim().parse().ok())
+                        .unwrap_or(0);
+                    Some((dst, Value::Target { table, base, size, signed, shift }))
+                },
+                _ => None
+            },
+
+            _ => None
+        };
+
+        // any other write clobbers the destination register
+        match (value, ops.first()) {
+            (Some((dst, value)), _) => {
+                let dst = dst.clone();
+                regs.insert(dst, value);
+            },
+            (None, Some(Operand::Reg(dst))) => {
+                regs.remove(dst);
+            },
+            _ => ()
+        }
+    }
+
+    /// Jump tables found in the instructions fed so far
+    pub fn finish(self) -> Vec<JumpTableRef> {
+        self.output
+    }
+}
+
+impl JumpTableRef {
+    /// Decode table entries from `data` (starting at `data_addr`),
+    /// stopping at the first target outside `range`.
+    pub fn decode(&self, data: &[u8], data_addr: u64, range: Range<u64>) -> JumpTable {
+        let size = self.size as usize;
+        let count = self.count.unwrap_or(MAX_ENTRIES);
+        let mut targets = Vec::new();
+
+        if let Some(offset) = self.table.checked_sub(data_addr) {
+            let entries = data.get(offset as usize..).unwrap_or_default().chunks_exact(size);
+
+            for entry in entries.take(count) {
+                let mut buf = [0; 8];
+                buf[..size].copy_from_slice(entry);
+                let value = u64::from_le_bytes(buf);
+                let value = if self.signed {
+                    let shift = 64 - size * 8;
+                    ((value << shift) as i64) >> shift
+                } else {
+                    value as i64
+                };
+
+                let target = match self.base {
+                    Some(base) => base.wrapping_add_signed(value << self.shift),
+                    None => value as u64
+                };
+
+                if !range.contains(&target) {
+                    break
+                }
+
+                targets.push(target);
+            }
+        }
+
+        JumpTable {
+            jump: self.jump,
+            table: self.table,
+            size: self.size,
+            targets
+        }
+    }
+}
+
+fn operands(cs: &Capstone, insn: &Insn<'_>) -> Option<Vec<Operand>> {
+    use capstone::arch::{ ArchDetail, DetailsArchInsn };
+    use capstone::arch::x86::X86OperandType;
+    use capstone::arch::arm64::Arm64OperandType;
+
+    let reg_name = |reg: capstone::RegId| cs.reg_name(reg).map(|name| {
+        // treat aarch64 `wN` as the lower half of `xN`
+        match name.strip_prefix('w') {
+            Some(n) if n == "zr" || n.parse::<u8>().is_ok() => format!("x{}", n),
+            _ => name
+        }
+    });
+
+    let detail = cs.insn_detail(insn).ok()?;
+    let ops = match detail.arch_detail() {
+        ArchDetail::X86Detail(detail) => detail.operands()
+            .map(|op| match op.op_type {
+                X86OperandType::Reg(reg) => Operand::Reg(reg_name(reg).unwrap_or_default()),
+                X86OperandType::Imm(imm) => Operand::Imm(imm),
+                X86OperandType::Mem(mem) => Operand::Mem {
+                    base: reg_name(mem.base()),
+                    index: reg_name(mem.index()),
+                    scale: mem.scale(),
+                    disp: mem.disp()
+                },
+                _ => Operand::Imm(0)
+            })
+            .collect(),
+        ArchDetail::Arm64Detail(detail) => detail.operands()
+            .map(|op| match op.op_type {
+                Arm64OperandType::Reg(reg) => Operand::Reg(reg_name(reg).unwrap_or_default()),
+                Arm64OperandType::Imm(imm) => Operand::Imm(imm),
+                Arm64OperandType::Mem(mem) => Operand::Mem {
+                    base: reg_name(mem.base()),
+                    index: reg_name(mem.index()),
+                    scale: 1,
+                    disp: mem.disp().into()
+                },
+                _ => Operand::Imm(0)
+            })
+            .collect(),
+        _ => return None
+    };
+
+    Some(ops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(disasm: &Disassembler, code: &[u8], addr: u64) -> Vec<JumpTableRef> {
+        let mut finder = JumpTableFinder::default();
+        let mut insts = disasm.disasm_iter(code, addr).unwrap();
+
+        while let Some(inst) = insts.next() {
+            finder.step(disasm, &inst.unwrap());
+        }
+
+        finder.finish()
+    }
+
+    #[test]
+    fn relative_table() {
+        let disasm = Disassembler::x86_64();
+        let code = [
+            0x83, 0xff, 0x03,                           // cmp edi, 3
+            0x77, 0x1b,                                 // ja 0x1020
+            0x48, 0x8d, 0x15, 0xf4, 0x0f, 0x00, 0x00,   // lea rdx, [rip + 0xff4]
+            0x48, 0x63, 0x04, 0xba,                     // movsxd rax, dword ptr [rdx + rdi*4]
+            0x48, 0x01, 0xd0,                           // add rax, rdx
+            0xff, 0xe0                                  // jmp rax
+        ];
+
+        let refs = find(&disasm, &code, 0x1000);
+        assert_eq!(refs.len(), 1);
+
+        let table = &refs[0];
+        assert_eq!((table.jump, table.table, table.base), (0x1013, 0x2000, Some(0x2000)));
+        assert_eq!((table.size, table.signed, table.shift), (4, true, 0));
+        assert_eq!(table.count, Some(4));
+
+        // the bound stops before the fifth entry, though it is in range
+        let data = [0x1015i32, 0x1018, 0x101b, 0x101e, 0x1000]
+            .iter()
+            .flat_map(|target| (target - 0x2000).to_le_bytes())
+            .collect::<Vec<_>>();
+        let decoded = table.decode(&data, 0x2000, 0x1000..0x1020);
+        assert_eq!(decoded.targets, vec![0x1015, 0x1018, 0x101b, 0x101e]);
+    }
+
+    #[test]
+    fn absolute_table() {
+        let disasm = Disassembler::x86_64();
+        // jmp qword ptr [rax*8 + 0x2000]
+        let code = [0xff, 0x24, 0xc5, 0x00, 0x20, 0x00, 0x00];
+
+        let refs = find(&disasm, &code, 0x1000);
+        assert_eq!(refs.len(), 1);
+
+        let table = &refs[0];
+        assert_eq!((table.jump, table.table, table.base), (0x1000, 0x2000, None));
+        assert_eq!((table.size, table.count), (8, None));
+
+        // without a bound, entries run up to the first target out of the function
+        let data = [0x1007u64, 0x1010, 0x3000, 0x1008]
+            .iter()
+            .flat_map(|target| target.to_le_bytes())
+            .collect::<Vec<_>>();
+        let decoded = table.decode(&data, 0x2000, 0x1000..0x1020);
+        assert_eq!(decoded.targets, vec![0x1007, 0x1010]);
+    }
+}
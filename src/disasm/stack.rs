@@ -0,0 +1,64 @@
+use super::{ Disassembler, InstIter, InstKind, parse_imm };
+
+
+/// Instructions read at most looking for the end of a prologue
+const MAX_PROLOGUE: usize = 64;
+
+/// Stack reserved by the prologue of a function, including the return address on x86-64
+///
+/// Reads up to the first branch, adding pushes and stack pointer subtractions
+/// by a constant, also one loaded into a register for a stack probe call.
+/// Stack grown by a runtime amount, such as `alloca`, is not counted.
+pub fn prologue_size(disasm: &Disassembler, insts: &mut InstIter<'_>) -> anyhow::Result<u64> {
+    let (mut size, sp) = match disasm {
+        Disassembler::X86_64(_) => (8, "rsp"),
+        Disassembler::Aarch64(_) => (0, "sp"),
+        Disassembler::Wasm => anyhow::bail!("unsupported arch: wasm")
+    };
+    // constant moved into a register, eg `mov eax, 0x11000` before `call __rust_probestack`
+    let mut loaded: Option<(String, u64)> = None;
+
+    for _ in 0..MAX_PROLOGUE {
+        let Some(inst) = insts.next()
+            else { break };
+        let inst = inst?;
+        let Some(mnemonic) = inst.mnemonic()
+            else { break };
+        let op_str = inst.op_str().unwrap_or_default();
+        let ops = op_str.split(", ").collect::<Vec<_>>();
+
+        match (mnemonic, ops.as_slice()) {
+            ("push", [_]) => size += 8,
+            ("sub", [dst, src]) | ("sub", [dst, _, src]) if *dst == sp => {
+                size += parse_imm(src)
+                    .or_else(|| loaded.take().filter(|(reg, _)| same_reg(reg, src)).map(|(_, n)| n))
+                    .unwrap_or(0);
+            },
+            ("sub", [dst, src, imm, "lsl #12"]) if *dst == sp && *src == sp =>
+                size += parse_imm(imm).unwrap_or(0) << 12,
+            // pre-indexed store, eg `stp x29, x30, [sp, #-0x20]!`
+            ("stp" | "str", _) if op_str.ends_with("]!") => {
+                size += op_str.rsplit_once("[sp, #-")
+                    .and_then(|(_, offset)| parse_imm(offset.strip_suffix("]!")?))
+                    .unwrap_or(0);
+            },
+            ("mov" | "movz", [dst, src]) => if let Some(n) = parse_imm(src) {
+                loaded = Some((dst.to_string(), n));
+            },
+            _ => match disasm.inst_kind(&inst) {
+                InstKind::Other => (),
+                // a stack probe, which takes the frame size in `rax`
+                InstKind::Call if loaded.as_ref().is_some_and(|(reg, _)| same_reg(reg, "rax")) => (),
+                _ => break
+            }
+        }
+    }
+
+    Ok(size)
+}
+
+/// Same x86-64 register, `eax` and `rax` alike
+fn same_reg(a: &str, b: &str) -> bool {
+    a == b || (a.len() == 3 && b.len() == 3 && a[1..] == b[1..]
+        && matches!((&a[..1], &b[..1]), ("e", "r") | ("r", "e")))
+}
@@ -0,0 +1,319 @@
+pub mod value;
+
+use std::fs;
+use std::path::Path;
+use std::borrow::Cow;
+use memmap2::MmapOptions;
+use object::{ Object, ObjectSection };
+use addr2line::gimli;
+use crate::util::Arena;
+
+
+pub type Reader = gimli::EndianSlice<'static, gimli::RunTimeEndian>;
+pub type Dwarf = gimli::Dwarf<Reader>;
+pub type Unit = gimli::Unit<Reader>;
+pub type Entry<'abbrev, 'unit> = gimli::DebuggingInformationEntry<'abbrev, 'unit, Reader>;
+
+/// Load DWARF sections of `obj`, with the supplementary file at `sup_path`
+///
+/// Compressed sections are decompressed once into the arena of the explorer,
+/// the result is kept in the explorer cache for the whole session.
+pub fn load(obj: &object::File<'static>, sup_path: Option<&Path>, arena: &Arena) -> anyhow::Result<Dwarf> {
+    let mut dwarf = gimli::Dwarf::load(|id| load_section(obj, id, arena))?;
+
+    if let Some(sup_path) = sup_path {
+        let fd = fs::File::open(sup_path)?;
+        let mmap = unsafe {
+            MmapOptions::new().map_copy_read_only(&fd)?
+        };
+        let sup = object::File::parse(arena.keep(mmap))?;
+
+        dwarf.load_sup(|id| load_section(&sup, id, arena))?;
+    }
+
+    Ok(dwarf)
+}
+
+/// Call frame information of `.eh_frame` and `.debug_frame`
+pub struct Frames {
+    pub eh_frame: gimli::EhFrame<Reader>,
+    pub debug_frame: gimli::DebugFrame<Reader>,
+    pub bases: gimli::BaseAddresses
+}
+
+pub fn load_frames(obj: &object::File<'static>, arena: &Arena) -> anyhow::Result<Frames> {
+    use gimli::Section;
+
+    let address_size = if obj.is_64() { 8 } else { 4 };
+    let section_address = |name| obj.section_by_name(name).map(|section| section.address());
+
+    let mut eh_frame = gimli::EhFrame::load(|id| load_section(obj, id, arena))?;
+    let mut debug_frame = gimli::DebugFrame::load(|id| load_section(obj, id, arena))?;
+    eh_frame.set_address_size(address_size);
+    debug_frame.set_address_size(address_size);
+
+    let mut bases = gimli::BaseAddresses::default();
+
+    if let Some(address) = section_address(".eh_frame") {
+        bases = bases.set_eh_frame(address);
+    }
+
+    if let Some(address) = section_address(".text") {
+        bases = bases.set_text(address);
+    }
+
+    if let Some(address) = section_address(".got") {
+        bases = bases.set_got(address);
+    }
+
+    Ok(Frames { eh_frame, debug_frame, bases })
+}
+
+fn load_section(obj: &object::File<'static>, id: gimli::SectionId, arena: &Arena) -> anyhow::Result<Reader> {
+    let endian = if obj.is_little_endian() {
+        gimli::RunTimeEndian::Little
+    } else {
+        gimli::RunTimeEndian::Big
+    };
+
+    let data = match obj.section_by_name(id.name()) {
+        Some(section) => match section.uncompressed_data()? {
+            Cow::Borrowed(data) => data,
+            Cow::Owned(data) => arena.keep_heap(data)
+        },
+        None => &[]
+    };
+
+    Ok(gimli::EndianSlice::new(data, endian))
+}
+
+/// String attribute of an entry, such as `DW_AT_name`
+pub fn attr_string(dwarf: &Dwarf, unit: &Unit, entry: &Entry<'_, '_>, name: gimli::DwAt)
+    -> Option<Cow<'static, str>>
+{
+    let value = entry.attr_value(name).ok()??;
+    let s = dwarf.attr_string(unit, value).ok()?;
+    Some(s.to_string_lossy())
+}
+
+/// `DW_AT_decl_file` and `DW_AT_decl_line` of an entry
+pub fn decl_location(dwarf: &Dwarf, unit: &Unit, entry: &Entry<'_, '_>)
+    -> Option<(String, Option<u64>)>
+{
+    let file = match entry.attr_value(gimli::DW_AT_decl_file).ok()?? {
+        gimli::AttributeValue::FileIndex(index) => index,
+        value => value.udata_value()?
+    };
+    let line = entry.attr_value(gimli::DW_AT_decl_line).ok()
+        .flatten()
+        .and_then(|value| value.udata_value());
+
+    Some((file_path(dwarf, unit, file)?, line))
+}
+
+/// Path of file `index` in the line program of a unit
+pub fn file_path(dwarf: &Dwarf, unit: &Unit, index: u64) -> Option<String> {
+    let header = unit.line_program.as_ref()?.header();
+    let file = header.file(index)?;
+    let name = dwarf.attr_string(unit, file.path_name()).ok()?.to_string_lossy();
+    let mut path = std::path::PathBuf::new();
+
+    if let Some(comp_dir) = unit.comp_dir.as_ref() {
+        path.push(&*comp_dir.to_string_lossy());
+    }
+
+    if let Some(dir) = file.directory(header)
+        && let Ok(dir) = dwarf.attr_string(unit, dir)
+    {
+        path.push(&*dir.to_string_lossy());
+    }
+
+    path.push(&*name);
+    Some(path.to_string_lossy().into_owned())
+}
+
+/// Static address of a variable from its `DW_AT_location`
+pub fn location_address(dwarf: &Dwarf, unit: &Unit, entry: &Entry<'_, '_>) -> Option<u64> {
+    let gimli::AttributeValue::Exprloc(expr) = entry.attr_value(gimli::DW_AT_location).ok()??
+        else {
+            return None
+        };
+    let mut ops = expr.operations(unit.encoding());
+
+    match ops.next().ok()?? {
+        gimli::Operation::Address { address } => Some(address),
+        gimli::Operation::AddressIndex { index } => dwarf.address(unit, index).ok(),
+        _ => None
+    }
+}
+
+/// Whether a flag attribute such as `DW_AT_declaration` is set
+pub fn attr_flag(entry: &Entry<'_, '_>, name: gimli::DwAt) -> bool {
+    matches!(entry.attr_value(name), Ok(Some(gimli::AttributeValue::Flag(true))))
+}
+
+/// Entry referenced by `DW_AT_type`, in the same unit
+pub fn type_offset(entry: &Entry<'_, '_>) -> Option<gimli::UnitOffset> {
+    match entry.attr_value(gimli::DW_AT_type).ok()?? {
+        gimli::AttributeValue::UnitRef(offset) => Some(offset),
+        _ => None
+    }
+}
+
+/// Entry in the same unit referenced by an attribute such as `DW_AT_abstract_origin`
+pub fn attr_ref(unit: &Unit, entry: &Entry<'_, '_>, name: gimli::DwAt) -> Option<gimli::UnitOffset> {
+    match entry.attr_value(name).ok()?? {
+        gimli::AttributeValue::UnitRef(offset) => Some(offset),
+        gimli::AttributeValue::DebugInfoRef(offset) => offset.to_unit_offset(&unit.header),
+        _ => None
+    }
+}
+
+/// Enclosing namespaces and types of the entries of a depth first walk
+#[derive(Default)]
+pub struct Scope {
+    /// (depth, tag, name)
+    stack: Vec<(isize, gimli::DwTag, Option<Cow<'static, str>>)>
+}
+
+impl Scope {
+    /// Enter an entry at `depth`, leaving the entries that are not its parents
+    pub fn enter(&mut self, depth: isize, tag: gimli::DwTag, name: Option<Cow<'static, str>>) {
+        while self.stack.last().is_some_and(|(d, ..)| *d >= depth) {
+            self.stack.pop();
+        }
+
+        self.stack.push((depth, tag, name));
+    }
+
+    /// `name` qualified by the parents of the last entered entry, such as `foo::Bar::name`
+    pub fn qualify(&self, name: &str) -> String {
+        let parents = self.stack.len().saturating_sub(1);
+
+        self.stack[..parents].iter()
+            .filter(|(_, tag, _)| matches!(
+                *tag,
+                gimli::DW_TAG_namespace
+                    | gimli::DW_TAG_structure_type
+                    | gimli::DW_TAG_class_type
+                    | gimli::DW_TAG_union_type
+                    | gimli::DW_TAG_enumeration_type
+            ))
+            .filter_map(|(_, _, name)| name.as_deref())
+            .chain(Some(name))
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+}
+
+/// Readable name of the type at `offset`
+pub fn type_name(dwarf: &Dwarf, unit: &Unit, offset: gimli::UnitOffset) -> String {
+    fn inner(dwarf: &Dwarf, unit: &Unit, offset: Option<gimli::UnitOffset>, depth: usize) -> String {
+        let Some(entry) = offset.and_then(|offset| unit.entry(offset).ok())
+            else { return "void".into() };
+
+        if let Some(name) = attr_string(dwarf, unit, &entry, gimli::DW_AT_name) {
+            return name.into_owned();
+        }
+
+        // self referencing types
+        if depth > 16 {
+            return "?".into();
+        }
+
+        let next = || inner(dwarf, unit, type_offset(&entry), depth + 1);
+
+        match entry.tag() {
+            gimli::DW_TAG_pointer_type => format!("*{}", next()),
+            gimli::DW_TAG_reference_type | gimli::DW_TAG_rvalue_reference_type => format!("&{}", next()),
+            gimli::DW_TAG_const_type => format!("const {}", next()),
+            gimli::DW_TAG_volatile_type => format!("volatile {}", next()),
+            gimli::DW_TAG_array_type => match array_count(unit, &entry) {
+                Some(count) => format!("[{}; {}]", next(), count),
+                None => format!("[{}]", next())
+            },
+            gimli::DW_TAG_subroutine_type => "fn".into(),
+            gimli::DW_TAG_structure_type => "struct ?".into(),
+            gimli::DW_TAG_union_type => "union ?".into(),
+            gimli::DW_TAG_enumeration_type => "enum ?".into(),
+            _ => next()
+        }
+    }
+
+    inner(dwarf, unit, Some(offset), 0)
+}
+
+/// Size in bytes of the type at `offset`
+pub fn type_size(unit: &Unit, offset: gimli::UnitOffset) -> Option<u64> {
+    let mut offset = offset;
+
+    // typedef and qualifier chains
+    for _ in 0..16 {
+        let entry = unit.entry(offset).ok()?;
+
+        if let Some(size) = entry.attr_value(gimli::DW_AT_byte_size).ok()?
+            .and_then(|value| value.udata_value())
+        {
+            return Some(size);
+        }
+
+        match entry.tag() {
+            gimli::DW_TAG_pointer_type
+                | gimli::DW_TAG_reference_type
+                | gimli::DW_TAG_rvalue_reference_type
+                => return Some(unit.encoding().address_size.into()),
+            gimli::DW_TAG_array_type => {
+                let count = array_count(unit, &entry)?;
+                let size = type_size(unit, type_offset(&entry)?)?;
+                return count.checked_mul(size);
+            },
+            _ => offset = type_offset(&entry)?
+        }
+    }
+
+    None
+}
+
+/// Number of elements of a one dimensional array type
+fn array_count(unit: &Unit, entry: &Entry<'_, '_>) -> Option<u64> {
+    let mut tree = unit.entries_tree(Some(entry.offset())).ok()?;
+    let root = tree.root().ok()?;
+    let mut children = root.children();
+
+    while let Some(child) = children.next().ok()? {
+        let child = child.entry();
+
+        if child.tag() != gimli::DW_TAG_subrange_type {
+            continue
+        }
+
+        if let Some(count) = child.attr_value(gimli::DW_AT_count).ok()?
+            .and_then(|value| value.udata_value())
+        {
+            return Some(count);
+        }
+
+        let lower = child.attr_value(gimli::DW_AT_lower_bound).ok()?
+            .and_then(|value| value.udata_value())
+            .unwrap_or(0);
+        let upper = child.attr_value(gimli::DW_AT_upper_bound).ok()??.udata_value()?;
+        return upper.checked_add(1)?.checked_sub(lower);
+    }
+
+    None
+}
+
+/// `DW_AT_data_member_location` of a member, as a constant or `DW_OP_plus_uconst`
+pub fn member_offset(unit: &Unit, entry: &Entry<'_, '_>) -> Option<u64> {
+    match entry.attr_value(gimli::DW_AT_data_member_location).ok()?? {
+        gimli::AttributeValue::Exprloc(expr) => {
+            let mut ops = expr.operations(unit.encoding());
+
+            match ops.next().ok()?? {
+                gimli::Operation::PlusConstant { value } => Some(value),
+                _ => None
+            }
+        },
+        value => value.udata_value()
+    }
+}
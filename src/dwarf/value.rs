@@ -0,0 +1,352 @@
+use std::borrow::Cow;
+use addr2line::gimli;
+
+use super::{ Dwarf, Unit, Entry };
+
+
+/// Scalar of a value decoded with its DWARF type
+pub struct Item {
+    pub offset: u64,
+    /// field and index path, such as `.name[2]`
+    pub path: String,
+    pub type_name: String,
+    pub value: String,
+    /// raw value of pointers
+    pub pointer: Option<u64>
+}
+
+/// Decode bytes of a variable into scalars by walking its type
+pub struct Decoder<'a> {
+    dwarf: &'a Dwarf,
+    unit: &'a Unit,
+    little_endian: bool,
+    pub items: Vec<Item>
+}
+
+struct Member {
+    name: String,
+    bit_offset: u64,
+    bit_size: Option<u64>,
+    type_offset: gimli::UnitOffset
+}
+
+const MAX_ITEMS: usize = 256;
+const MAX_ELEMENTS: u64 = 16;
+const MAX_DEPTH: usize = 8;
+
+impl<'a> Decoder<'a> {
+    pub fn new(dwarf: &'a Dwarf, unit: &'a Unit, little_endian: bool) -> Decoder<'a> {
+        Decoder { dwarf, unit, little_endian, items: Vec::new() }
+    }
+
+    /// Decode `data[offset..]` as the type at `type_offset`
+    pub fn decode(&mut self, type_offset: gimli::UnitOffset, data: &[u8], offset: u64, path: String) {
+        self.decode_inner(type_offset, data, offset, path, 0)
+    }
+
+    fn decode_inner(
+        &mut self,
+        type_offset: gimli::UnitOffset,
+        data: &[u8],
+        offset: u64,
+        path: String,
+        depth: usize
+    ) {
+        if self.items.len() >= MAX_ITEMS {
+            return
+        }
+
+        let type_name = super::type_name(self.dwarf, self.unit, type_offset);
+        let size = super::type_size(self.unit, type_offset);
+        let Some(entry) = strip(self.unit, type_offset)
+            else {
+                return self.push(offset, path, type_name, "?".into())
+            };
+        let bytes = size.and_then(|size| data.get(offset as usize..)?.get(..size as usize));
+
+        let value = match (entry.tag(), bytes) {
+            (_, None) => "?".into(),
+            (gimli::DW_TAG_base_type, Some(bytes)) => {
+                let encoding = match entry.attr_value(gimli::DW_AT_encoding) {
+                    Ok(Some(gimli::AttributeValue::Encoding(encoding))) => encoding,
+                    _ => gimli::DW_ATE_unsigned
+                };
+                self.scalar(encoding, bytes)
+            },
+            (gimli::DW_TAG_pointer_type
+                | gimli::DW_TAG_reference_type
+                | gimli::DW_TAG_rvalue_reference_type, Some(bytes)) => match self.read_uint(bytes) {
+                Some(value) => {
+                    let text = format!("{:#x}", value);
+                    self.items.push(Item { offset, path, type_name, value: text, pointer: Some(value) });
+                    return
+                },
+                None => hex(bytes)
+            },
+            (gimli::DW_TAG_enumeration_type, Some(bytes)) => match self.read_uint(bytes) {
+                Some(value) => {
+                    let enumerators = self.enumerators(&entry);
+
+                    match enumerator_name(&enumerators, bytes.len() as u64, value) {
+                        Some(name) => format!("{} ({})", value, name),
+                        None => value.to_string()
+                    }
+                },
+                None => hex(bytes)
+            },
+            (gimli::DW_TAG_structure_type | gimli::DW_TAG_class_type | gimli::DW_TAG_union_type, Some(bytes))
+                if depth < MAX_DEPTH =>
+            {
+                match self.members(&entry) {
+                    Some(members) => {
+                        for member in members {
+                            let path = format!("{}.{}", path, member.name);
+
+                            match member.bit_size {
+                                Some(bit_size) if member.bit_offset % 8 != 0 || bit_size % 8 != 0 => {
+                                    let value = self.bitfield(bytes, member.bit_offset, bit_size);
+                                    let type_name = super::type_name(self.dwarf, self.unit, member.type_offset);
+                                    self.push(offset + member.bit_offset / 8, path, type_name, value);
+                                },
+                                _ => {
+                                    let offset = offset + member.bit_offset / 8;
+                                    self.decode_inner(member.type_offset, data, offset, path, depth + 1);
+                                }
+                            }
+                        }
+
+                        return
+                    },
+                    // rust enums, layout depends on the discriminant
+                    None => hex(bytes)
+                }
+            },
+            (gimli::DW_TAG_array_type, Some(bytes)) if depth < MAX_DEPTH => {
+                let Some(element) = super::type_offset(&entry)
+                    else {
+                        return self.push(offset, path, type_name, hex(bytes))
+                    };
+                let element_size = super::type_size(self.unit, element).unwrap_or(0);
+
+                // char arrays as strings
+                if element_size == 1 && self.is_char(element) {
+                    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+                    let value = format!("{:?}", String::from_utf8_lossy(&bytes[..len]));
+                    return self.push(offset, path, type_name, value);
+                }
+
+                let count = (bytes.len() as u64).checked_div(element_size).unwrap_or(0);
+
+                for i in 0..count.min(MAX_ELEMENTS) {
+                    let path = format!("{}[{}]", path, i);
+                    self.decode_inner(element, data, offset + i * element_size, path, depth + 1);
+                }
+
+                if count > MAX_ELEMENTS {
+                    let rest = format!("... {} more", count - MAX_ELEMENTS);
+                    self.push(offset + MAX_ELEMENTS * element_size, format!("{}[..]", path), type_name, rest);
+                }
+
+                return
+            },
+            (_, Some(bytes)) => hex(bytes)
+        };
+
+        self.push(offset, path, type_name, value)
+    }
+
+    fn push(&mut self, offset: u64, path: String, type_name: String, value: String) {
+        self.items.push(Item { offset, path, type_name, value, pointer: None });
+    }
+
+    fn is_char(&self, type_offset: gimli::UnitOffset) -> bool {
+        strip(self.unit, type_offset).is_some_and(|entry| entry.tag() == gimli::DW_TAG_base_type
+            && matches!(
+                entry.attr_value(gimli::DW_AT_encoding),
+                Ok(Some(gimli::AttributeValue::Encoding(gimli::DW_ATE_signed_char | gimli::DW_ATE_unsigned_char)))
+            )
+        )
+    }
+
+    /// Data members of a struct or union, none if the type has a variant part
+    fn members(&self, entry: &Entry<'_, '_>) -> Option<Vec<Member>> {
+        let mut tree = self.unit.entries_tree(Some(entry.offset())).ok()?;
+        let root = tree.root().ok()?;
+        let mut children = root.children();
+        let mut members = Vec::new();
+
+        while let Ok(Some(child)) = children.next() {
+            let child = child.entry();
+
+            match child.tag() {
+                gimli::DW_TAG_variant_part => return None,
+                gimli::DW_TAG_member if !super::attr_flag(child, gimli::DW_AT_external)
+                    && !super::attr_flag(child, gimli::DW_AT_declaration) =>
+                {
+                    let Some(type_offset) = super::type_offset(child)
+                        else { continue };
+                    let name = super::attr_string(self.dwarf, self.unit, child, gimli::DW_AT_name)
+                        .map(|name| name.into_owned())
+                        .unwrap_or_else(|| "?".into());
+                    let bit_size = child.attr_value(gimli::DW_AT_bit_size).ok()?
+                        .and_then(|value| value.udata_value());
+                    let bit_offset = match child.attr_value(gimli::DW_AT_data_bit_offset).ok()?
+                        .and_then(|value| value.udata_value())
+                    {
+                        Some(bit_offset) => bit_offset,
+                        None => super::member_offset(self.unit, child).unwrap_or(0) * 8
+                    };
+                    members.push(Member { name, bit_offset, bit_size, type_offset });
+                },
+                _ => ()
+            }
+        }
+
+        Some(members)
+    }
+
+    /// (value, name) of enumerators of an enumeration type
+    fn enumerators(&self, entry: &Entry<'_, '_>) -> Vec<(i64, String)> {
+        let mut enumerators = Vec::new();
+        let Ok(mut tree) = self.unit.entries_tree(Some(entry.offset()))
+            else { return enumerators };
+        let Ok(root) = tree.root()
+            else { return enumerators };
+        let mut children = root.children();
+
+        while let Ok(Some(child)) = children.next() {
+            let child = child.entry();
+
+            if child.tag() != gimli::DW_TAG_enumerator {
+                continue
+            }
+
+            let name = super::attr_string(self.dwarf, self.unit, child, gimli::DW_AT_name);
+            let value = child.attr_value(gimli::DW_AT_const_value).ok()
+                .flatten()
+                .and_then(|value| value.sdata_value().or_else(|| value.udata_value().map(|n| n as i64)));
+
+            if let (Some(name), Some(value)) = (name, value) {
+                enumerators.push((value, name.into_owned()));
+            }
+        }
+
+        enumerators
+    }
+
+    /// Unsigned value of `bit_size` bits at `bit_offset` of a struct
+    fn bitfield(&self, bytes: &[u8], bit_offset: u64, bit_size: u64) -> String {
+        let start = (bit_offset / 8) as usize;
+        let end = (bit_offset + bit_size).div_ceil(8) as usize;
+
+        // big endian bit numbering is not handled
+        match bytes.get(start..end).filter(|_| self.little_endian && bit_size < 64)
+            .and_then(|bytes| self.read_uint(bytes))
+        {
+            Some(value) => ((value >> (bit_offset % 8)) & ((1 << bit_size) - 1)).to_string(),
+            None => "?".into()
+        }
+    }
+
+    fn read_uint(&self, bytes: &[u8]) -> Option<u64> {
+        if bytes.len() > 8 {
+            return None;
+        }
+
+        let mut buf = [0; 8];
+
+        if self.little_endian {
+            buf[..bytes.len()].copy_from_slice(bytes);
+            Some(u64::from_le_bytes(buf))
+        } else {
+            buf[8 - bytes.len()..].copy_from_slice(bytes);
+            Some(u64::from_be_bytes(buf))
+        }
+    }
+
+    fn scalar(&self, encoding: gimli::DwAte, bytes: &[u8]) -> String {
+        let Some(value) = self.read_uint(bytes)
+            else { return hex(bytes) };
+        let bits = bytes.len() as u32 * 8;
+        // sign extend
+        let signed = if bits == 0 || bits >= 64 {
+            value as i64
+        } else {
+            ((value << (64 - bits)) as i64) >> (64 - bits)
+        };
+
+        match encoding {
+            gimli::DW_ATE_boolean => (value != 0).to_string(),
+            gimli::DW_ATE_float if bytes.len() == 4 => f32::from_bits(value as u32).to_string(),
+            gimli::DW_ATE_float if bytes.len() == 8 => f64::from_bits(value).to_string(),
+            gimli::DW_ATE_signed => signed.to_string(),
+            gimli::DW_ATE_signed_char | gimli::DW_ATE_unsigned_char => {
+                let value = if encoding == gimli::DW_ATE_signed_char { signed } else { value as i64 };
+
+                match u8::try_from(value) {
+                    Ok(c) if c.is_ascii_graphic() || c == b' ' => format!("{} {:?}", value, c as char),
+                    _ => value.to_string()
+                }
+            },
+            gimli::DW_ATE_UTF => match char::from_u32(value as u32) {
+                Some(c) => format!("{:?}", c),
+                None => value.to_string()
+            },
+            _ => value.to_string()
+        }
+    }
+}
+
+/// Name of the enumerator equal to the low `size` bytes of `value`,
+/// or the enumerators of its set bits, such as `READ | WRITE`
+pub fn enumerator_name(enumerators: &[(i64, String)], size: u64, value: u64) -> Option<Cow<'_, str>> {
+    let mask = match size {
+        1..8 => (1 << (size * 8)) - 1,
+        _ => u64::MAX
+    };
+    let value = value & mask;
+
+    if let Some((_, name)) = enumerators.iter().find(|(n, _)| *n as u64 & mask == value) {
+        return Some(Cow::Borrowed(name));
+    }
+
+    // bit flags
+    let flags = enumerators.iter()
+        .filter(|(n, _)| (*n as u64 & mask).is_power_of_two() && *n as u64 & value != 0)
+        .collect::<Vec<_>>();
+    let covered = flags.iter().fold(0, |acc, (n, _)| acc | *n as u64);
+
+    if value == 0 || covered != value {
+        return None;
+    }
+
+    let names = flags.iter()
+        .map(|(_, name)| name.as_str())
+        .collect::<Vec<_>>();
+    Some(Cow::Owned(names.join(" | ")))
+}
+
+/// Type at `offset` without typedefs and qualifiers
+fn strip(unit: &Unit, offset: gimli::UnitOffset) -> Option<Entry<'_, '_>> {
+    let mut offset = offset;
+
+    for _ in 0..16 {
+        let entry = unit.entry(offset).ok()?;
+
+        match entry.tag() {
+            gimli::DW_TAG_typedef
+                | gimli::DW_TAG_const_type
+                | gimli::DW_TAG_volatile_type
+                | gimli::DW_TAG_restrict_type
+                | gimli::DW_TAG_atomic_type
+                => offset = super::type_offset(&entry)?,
+            _ => return Some(entry)
+        }
+    }
+
+    None
+}
+
+fn hex(bytes: &[u8]) -> String {
+    data_encoding::HEXLOWER.encode(bytes)
+}
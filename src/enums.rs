@@ -0,0 +1,111 @@
+use std::io::Write;
+use std::borrow::Cow;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ u64ptr, Stdio };
+use crate::dwarf::value::enumerator_name;
+use crate::types;
+
+
+/// decode values into enum variant names from DWARF
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// enum name, full path or last component, generic parameters optional
+    pub name: String,
+
+    /// values to decode, list all variants if empty
+    #[arg(allow_negative_numbers = true)]
+    pub values: Vec<String>,
+}
+
+impl Command {
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            #[serde(rename = "type")]
+            type_name: &'a str,
+            value: i64,
+            name: Option<&'a str>
+        }
+
+        let values = self.values.iter()
+            .map(|value| match value.strip_prefix('-') {
+                Some(n) => u64ptr(n).map(|n| (n as i64).wrapping_neg()),
+                None => u64ptr(value).map(|n| n as i64)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let dwarf = explorer.cache.dwarf(explorer.dwarf_obj(), explorer.dwarf_path.as_deref()).await?;
+        // (layout, variant without discriminant value)
+        let mut enums = Vec::new();
+
+        types::walk(dwarf, |mut layout| {
+            // generic enums match without parameters, such as `Option`
+            let base = layout.name.split('<').next().unwrap_or(&layout.name);
+            let last = base.rsplit("::").next().unwrap_or(base);
+
+            if layout.kind != "enum"
+                || (layout.name != self.name && base != self.name && last != self.name)
+            {
+                return false
+            }
+
+            // rust enums with fields, named by the member of each variant
+            if layout.enumerators.is_empty() {
+                layout.enumerators = layout.fields.iter()
+                    .filter_map(|field| Some((field.variant.as_deref()?.parse::<u64>().ok()? as i64, field.name.clone())))
+                    .collect();
+            }
+
+            let default = layout.fields.iter()
+                .find(|field| field.variant.as_deref() == Some("_"))
+                .map(|field| field.name.clone());
+            enums.push((layout, default));
+            false
+        }).await?;
+
+        if enums.is_empty() {
+            anyhow::bail!("not found enum: {}", self.name);
+        }
+
+        for (layout, default) in &enums {
+            if stdio.format.is_text() {
+                writeln!(stdio.stdout, "enum {}\t{} bytes", layout.name, layout.size)?;
+            }
+
+            let list = if values.is_empty() {
+                layout.enumerators.iter()
+                    .map(|(value, name)| (*value, Some(name.into())))
+                    .collect::<Vec<(_, Option<Cow<'_, str>>)>>()
+            } else {
+                values.iter()
+                    .map(|&value| {
+                        let name = enumerator_name(&layout.enumerators, layout.size, value as u64)
+                            .or_else(|| default.as_deref().map(Into::into));
+                        (value, name)
+                    })
+                    .collect()
+            };
+
+            for (value, name) in &list {
+                if !stdio.format.is_text() {
+                    stdio.record(&Record {
+                        type_name: &layout.name,
+                        value: *value,
+                        name: name.as_deref()
+                    })?;
+                    continue
+                }
+
+                writeln!(stdio.stdout, "  {}\t{:#x}\t{}", value, value, name.as_deref().unwrap_or("?"))?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,58 +1,387 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{ Path, PathBuf };
 use std::borrow::Cow;
 use std::sync::{ Arc, OnceLock };
+use std::sync::atomic::{ AtomicU64, Ordering };
 use std::collections::HashMap;
-use tokio::sync::{ OnceCell, RwLock, Mutex };
-use memmap2::{ MmapOptions, Mmap };
+use std::collections::hash_map::Entry;
+use tokio::sync::{ OnceCell, RwLock, Mutex, OwnedMutexGuard };
+use memmap2::MmapOptions;
 use object::{ Object, ObjectSection, ObjectSymbol, ObjectSymbolTable };
 use object::read::{ SectionIndex, SymbolIndex };
 use addr2line::Loader;
+use crate::disasm::{ Disassembler, DataRefs };
+use crate::disasm::jumptable::JumpTable;
+use crate::linkmap::LinkMap;
+use crate::cargo::Crates;
+use crate::debuginfo::{ DebugFile, Debuginfod };
+use crate::names::NameIndex;
+use crate::util::Arena;
 
 
 pub struct Explorer {
     pub path: PathBuf,
     pub dwarf_path: Option<PathBuf>,
+    pub link_map: Option<LinkMap>,
+    pub crates: Option<Crates>,
+    /// (from, to) source path prefixes
+    pub source_map: Vec<(PathBuf, PathBuf)>,
+    /// separate debug file, used instead of the binary for DWARF
+    pub debug_file: Option<DebugFile>,
+    pub debuginfod: Option<Arc<Debuginfod>>,
     pub obj: object::File<'static>,
     pub cache: Cache,
 }
 
 #[derive(Default)]
 pub struct Cache {
+    /// PDB of a PE binary, loaded into `pdb` on first use
+    pub pdb_path: Option<PathBuf>,
+    pub pdb: OnceCell<Option<crate::pdb::Pdb>>,
     pub addr2sym: OnceCell<object::read::SymbolMap<object::read::SymbolMapName<'static>>>,
     pub symlist: OnceCell<Box<[SymbolIndex]>>,
+    pub demangled: OnceCell<Demangled>,
+    /// index of the mangled and demangled names of symlist
+    pub names: OnceCell<NameIndex>,
+    pub demangled_names: OnceCell<NameIndex>,
     pub dyn_rela: OnceCell<Box<[(u64, object::read::Relocation)]>>,
-    pub addr2line: OnceCell<Mutex<Loader>>,
-    pub data: DataCache
+    pub plt: OnceCell<Box<[PltEntry]>>,
+    pub addr2line: OnceCell<Addr2Line>,
+    pub dwarf: OnceCell<crate::dwarf::Dwarf>,
+    pub frames: OnceCell<crate::dwarf::Frames>,
+    /// calls as (callee, caller, kind), sorted by callee, see `search::call_index`
+    pub calls: OnceCell<Box<[(u64, SymbolIndex, crate::search::CallKind)]>>,
+    /// jump tables of all functions, sorted by table address, see `show::jump_tables`
+    pub jump_tables: OnceCell<Box<[JumpTable]>>,
+    /// outermost inlined function of `--dwarf-top` line ranges, by range start
+    pub top_frames: RwLock<HashMap<u64, Option<Arc<str>>>>,
+    pub data: DataCache,
+    /// log of the session serving this binary, None outside `fi listen`
+    pub log: Option<Arc<crate::listen::logger::Logger>>,
+    /// backing memory of the binary and debug files, dropped last
+    pub arena: Arena
+}
+
+/// Whether a cache is built and its approximate size
+pub struct Usage {
+    pub name: &'static str,
+    pub built: bool,
+    /// heap bytes, None if unknown
+    pub bytes: Option<usize>
+}
+
+/// Demangled names of the symbol table, by mangled name
+///
+/// None if the name does not demangle to anything else.
+pub struct Demangled(HashMap<&'static str, Option<Box<str>>>);
+
+impl Demangled {
+    /// Demangled `name`, demangled again if it is not in the symbol table
+    pub fn get<'a>(&'a self, name: &'a str) -> Cow<'a, str> {
+        match self.0.get(name) {
+            Some(Some(demangled)) => Cow::Borrowed(demangled),
+            Some(None) => Cow::Borrowed(name),
+            None => symbolic_demangle::demangle(name)
+        }
+    }
+
+    fn size(&self) -> usize {
+        use std::mem::size_of;
+
+        self.0.capacity() * size_of::<(&str, Option<Box<str>>)>()
+            + self.0.values().flatten().map(|name| name.len()).sum::<usize>()
+    }
+}
+
+impl Addr2Line {
+    async fn new(path: &Path, sup_path: Option<&Path>) -> anyhow::Result<Addr2Line> {
+        let loader = Addr2Line::open(path.into(), sup_path.map(Into::into)).await?;
+        let limit = std::thread::available_parallelism().map_or(1, |n| n.get()).min(MAX_LOADERS);
+        let pool = LoaderPool {
+            loaders: vec![Arc::new(Mutex::new(loader))],
+            ..Default::default()
+        };
+
+        Ok(Addr2Line {
+            path: path.into(),
+            sup_path: sup_path.map(Into::into),
+            limit,
+            pool: std::sync::Mutex::new(pool)
+        })
+    }
+
+    /// Open a loader on the blocking pool, parsing the debug info would stall the runtime
+    async fn open(path: PathBuf, sup_path: Option<PathBuf>) -> anyhow::Result<Loader> {
+        tokio::task::spawn_blocking(move || {
+            if let Some(sup_path) = sup_path {
+                Loader::new_with_sup(&path, Some(&sup_path))
+            } else {
+                Loader::new(&path)
+            }
+                .map_err(|err| anyhow::format_err!("addr2line: {:?}", err))
+        })
+            .await?
+    }
+
+    /// A loader for the lookups of one request, held until the guard drops
+    pub async fn get(&self) -> anyhow::Result<OwnedMutexGuard<Loader>> {
+        if let Some(guard) = self.try_get().await? {
+            return Ok(guard);
+        }
+
+        let loader = {
+            let mut pool = self.pool.lock().unwrap();
+            pool.next = (pool.next + 1) % pool.loaders.len();
+            pool.loaders[pool.next].clone()
+        };
+
+        Ok(loader.lock_owned().await)
+    }
+
+    /// An idle loader, or a new one while under the limit, None if all are busy
+    pub async fn try_get(&self) -> anyhow::Result<Option<OwnedMutexGuard<Loader>>> {
+        {
+            let mut pool = self.pool.lock().unwrap();
+
+            if let Some(guard) = pool.loaders.iter()
+                .find_map(|loader| loader.clone().try_lock_owned().ok())
+            {
+                return Ok(Some(guard));
+            }
+
+            if pool.loaders.len() + pool.opening >= self.limit {
+                return Ok(None);
+            }
+
+            pool.opening += 1;
+        }
+
+        // also released if the request is cancelled while opening
+        scopeguard::defer!{
+            self.pool.lock().unwrap().opening -= 1;
+        }
+
+        let loader = Addr2Line::open(self.path.clone(), self.sup_path.clone()).await?;
+        let loader = Arc::new(Mutex::new(loader));
+        // locked before it is published, so no other request takes it first
+        let guard = loader.clone().try_lock_owned().expect("unshared");
+        self.pool.lock().unwrap().loaders.push(loader);
+
+        Ok(Some(guard))
+    }
+}
+
+/// PLT stub resolved to its relocation target
+pub struct PltEntry {
+    pub addr: u64,
+    pub name: &'static str,
+    /// target symbol address, zero if imported
+    pub target: u64
+}
+
+/// Most loaders of a binary, each one holds the debug info it has parsed
+const MAX_LOADERS: usize = 4;
+
+/// Loaders of the debug info, so that concurrent lookups do not wait on one another
+///
+/// A `Loader` parses units as they are looked up and is not `Sync`, each one
+/// serves one request at a time. A request finding them all busy opens another,
+/// up to the available parallelism or `MAX_LOADERS`, and past that waits for one in turn.
+pub struct Addr2Line {
+    path: PathBuf,
+    sup_path: Option<PathBuf>,
+    limit: usize,
+    pool: std::sync::Mutex<LoaderPool>,
 }
 
+#[derive(Default)]
+struct LoaderPool {
+    loaders: Vec<Arc<Mutex<Loader>>>,
+    /// loaders being opened, counted against the limit
+    opening: usize,
+    /// next loader to wait on when all are busy
+    next: usize
+}
+
+/// Decompressed section data, evicted least recently used first
 #[derive(Default)]
 pub struct DataCache {
-    data: RwLock<Vec<Arc<Cow<'static, [u8]>>>>,
-    map: RwLock<HashMap<SectionIndex, usize>>,
+    map: RwLock<HashMap<SectionIndex, DataEntry>>,
+    /// `--max-cache-memory`, bytes kept while a command runs, unbounded if not set
+    limit: Option<usize>
+}
+
+struct DataEntry {
+    data: Arc<Vec<u8>>,
+    /// `DATA_TICK` of the last use
+    used: AtomicU64
+}
+
+/// Data of a section, borrowed from the mapping unless it was compressed
+#[derive(Clone)]
+pub enum SectionData {
+    Mapped(&'static [u8]),
+    Decompressed(Arc<Vec<u8>>)
 }
 
-static TARGET: OnceLock<(fs::File, Mmap)> = OnceLock::new();
+impl std::ops::Deref for SectionData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            SectionData::Mapped(data) => data,
+            SectionData::Decompressed(data) => data
+        }
+    }
+}
+
+/// Use order of section data across all binaries
+static DATA_TICK: AtomicU64 = AtomicU64::new(0);
+
+impl DataEntry {
+    fn touch(&self) -> SectionData {
+        self.used.store(DATA_TICK.fetch_add(1, Ordering::Relaxed), Ordering::Relaxed);
+        SectionData::Decompressed(self.data.clone())
+    }
+}
+
+impl DataCache {
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = Some(limit);
+    }
+
+    /// Evict least recently used data down to the limit, except the section `keep` just used
+    fn shrink(&self, map: &mut HashMap<SectionIndex, DataEntry>, keep: SectionIndex) {
+        let Some(limit) = self.limit
+            else { return };
+        let mut total: usize = map.values().map(|entry| entry.data.len()).sum();
+
+        while total > limit {
+            let Some((&idx, entry)) = map.iter()
+                .filter(|&(&idx, _)| idx != keep)
+                .min_by_key(|(_, entry)| entry.used.load(Ordering::Relaxed))
+                else { break };
+
+            total -= entry.data.len();
+            map.remove(&idx);
+        }
+    }
+
+    /// Decompressed sections as (last use, section, bytes)
+    pub async fn owned(&self) -> Vec<(u64, SectionIndex, usize)> {
+        self.map.read().await
+            .iter()
+            .map(|(&idx, entry)| (entry.used.load(Ordering::Relaxed), idx, entry.data.len()))
+            .collect()
+    }
+
+    /// Drop the data of a section, commands using it keep it until done
+    pub async fn evict(&self, idx: SectionIndex) {
+        self.map.write().await.remove(&idx);
+    }
+}
+
+/// `lib/rustlib/src/rust` of the sysroot, if `rust-src` is installed
+fn rust_src() -> Option<&'static Path> {
+    static RUST_SRC: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+    RUST_SRC.get_or_init(|| {
+        let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        let output = std::process::Command::new(rustc)
+            .args(["--print", "sysroot"])
+            .output()
+            .ok()?;
+        let sysroot = String::from_utf8(output.stdout).ok()?;
+        let src = Path::new(sysroot.trim()).join("lib/rustlib/src/rust");
+
+        (output.status.success() && src.is_dir()).then_some(src)
+    })
+        .as_deref()
+}
 
 impl Explorer {
-    pub fn open(path: PathBuf, dwarf_path: Option<PathBuf>) -> anyhow::Result<Explorer> {
+    pub fn open(
+        path: PathBuf,
+        dwarf_path: Option<PathBuf>,
+        map_path: Option<PathBuf>,
+        manifest_path: Option<PathBuf>,
+        source_map: Vec<(PathBuf, PathBuf)>
+    ) -> anyhow::Result<Explorer>
+    {
         let fd = fs::File::open(&path)?;
         let mmap = unsafe {
             MmapOptions::new().map_copy_read_only(&fd)?
         };
-        let (_, mmap) = TARGET.get_or_init(move || (fd, mmap));
-        let obj = object::File::parse(mmap.as_ref())?;
+        let mut cache = Cache::default();
+        let obj = object::File::parse(cache.arena.keep(mmap))?;
+        let link_map = map_path.as_deref()
+            .map(LinkMap::open)
+            .transpose()?;
+        let crates = manifest_path.as_deref()
+            .map(Crates::open)
+            .transpose()?;
+
+        // PE symbols live in the PDB, loaded with symlist
+        if obj.format() == object::BinaryFormat::Pe {
+            cache.pdb_path = crate::pdb::locate(&path, &obj);
+        }
 
         Ok(Explorer {
-            path, dwarf_path, obj,
-            cache: Cache::default(),
+            path, dwarf_path, link_map, crates, source_map, obj,
+            debug_file: None,
+            debuginfod: None,
+            cache
         })
     }
 
+    /// Object of the symbols of symlist, the PDB symbols of a PE once symlist is built
+    ///
+    /// Its section indices are those of `obj`.
+    pub fn sym_obj(&self) -> &object::File<'static> {
+        self.cache.pdb.get()
+            .and_then(Option::as_ref)
+            .map_or(&self.obj, |pdb| &pdb.obj)
+    }
+
+    /// Object holding the DWARF sections
+    pub fn dwarf_obj(&self) -> &object::File<'static> {
+        self.debug_file.as_ref().map_or(&self.obj, |file| &file.obj)
+    }
+
+    /// Path of the object holding the DWARF sections
+    pub fn dwarf_file(&self) -> &Path {
+        self.debug_file.as_ref().map_or(&self.path, |file| &file.path)
+    }
+
+    /// Local path of a source file recorded in debug info, by the first matching `--source-map`
+    ///
+    /// Paths of the rust standard library, `/rustc/<commit>/library/..`,
+    /// fall back to the `rust-src` component of the installed toolchain.
+    pub fn source_path<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        for (from, to) in &self.source_map {
+            if let Ok(rest) = Path::new(path).strip_prefix(from) {
+                return Cow::Owned(to.join(rest).to_string_lossy().into_owned());
+            }
+        }
+
+        if let Some(rest) = path.strip_prefix("/rustc/")
+            && let Some((_commit, rest)) = rest.split_once('/')
+            && let Some(src) = rust_src()
+        {
+            return Cow::Owned(src.join(rest).to_string_lossy().into_owned());
+        }
+
+        Cow::Borrowed(path)
+    }
+
     pub fn symbol_kind(&self, idx: SymbolIndex) -> char {
-        use object::{ SymbolSection, SectionKind };
+        let sym = self.sym_obj().symbol_by_index(idx).unwrap();
+        self.symbol_kind_of(&sym)
+    }
 
-        let sym = self.obj.symbol_by_index(idx).unwrap();
+    /// Like `symbol_kind`, for symbols outside the regular symbol table
+    pub fn symbol_kind_of(&self, sym: &object::Symbol<'static, '_>) -> char {
+        use object::{ SymbolSection, SectionKind };
 
         let mut kind = match sym.section() {
             SymbolSection::Undefined => 'U',
@@ -79,13 +408,13 @@ impl Explorer {
     }
 
     pub fn symbol_size(&self, symlist: &[SymbolIndex], idx: SymbolIndex) -> anyhow::Result<u64> {
-        let sym = self.obj.symbol_by_index(idx)?;
+        let sym = self.sym_obj().symbol_by_index(idx)?;
 
         let size = if self.obj.format() != object::BinaryFormat::MachO {
             sym.size()
         } else {
             let idx = match symlist.binary_search_by(|&idx0| {
-                let sym0 = self.obj.symbol_by_index(idx0).unwrap();
+                let sym0 = self.sym_obj().symbol_by_index(idx0).unwrap();
                 sym0.address().cmp(&sym.address())
             }) {
                 Ok(idx) => idx,
@@ -93,7 +422,7 @@ impl Explorer {
             };
 
             if let Some(&sym1) = symlist.get(idx + 1)
-                && let sym1 = self.obj.symbol_by_index(sym1).unwrap()
+                && let sym1 = self.sym_obj().symbol_by_index(sym1).unwrap()
                 && sym.section_index() == sym1.section_index()
             {
                 sym1.address() - sym.address()
@@ -107,18 +436,172 @@ impl Explorer {
 
         Ok(size)        
     }
+
+    /// Symbol containing the address `target`, or named `target`, mangled or demangled
+    pub fn lookup_symbol(&self, symlist: &[SymbolIndex], target: &str)
+        -> anyhow::Result<Option<SymbolIndex>>
+    {
+        if target.starts_with("0x") || target.bytes().all(|b| b.is_ascii_digit()) {
+            let addr = crate::util::u64ptr(target)?;
+            let idx = symlist.partition_point(|&idx| self.sym_obj().symbol_by_index(idx)
+                .is_ok_and(|sym| sym.address() <= addr)
+            );
+
+            // symbols at the same address, any sized one
+            for &idx in symlist[..idx].iter().rev() {
+                let sym = self.sym_obj().symbol_by_index(idx)?;
+                let size = self.symbol_size(symlist, idx)?;
+
+                if (sym.address()..sym.address() + size).contains(&addr) {
+                    return Ok(Some(idx));
+                } else if sym.address() + size <= addr && size != 0 {
+                    break
+                }
+            }
+
+            return Ok(None);
+        }
+
+        let found = symlist.iter()
+            .copied()
+            .find(|&idx| self.sym_obj().symbol_by_index(idx)
+                .ok()
+                .filter(|sym| sym.kind() != object::SymbolKind::File && !sym.is_undefined())
+                .and_then(|sym| sym.name().ok())
+                .is_some_and(|name| name == target || self.cache.demangle(name) == target)
+            );
+
+        Ok(found)
+    }
 }
 
 impl Cache {
+    /// Report a failure that falls back to less, as `event` into the session log or to stderr
+    fn warn(&self, event: &str, msg: &str, err: &anyhow::Error) {
+        match &self.log {
+            Some(log) => log.warn(event, &[("message", &msg), ("error", &format_args!("{:#}", err))]),
+            None => eprintln!("{}: {:?}", msg, err)
+        }
+    }
+
+    /// Usage of each cache and the arena, without waiting for caches being written
+    pub fn usage(&self) -> Vec<Usage> {
+        let mut usage = self.caches();
+        usage.push(Usage { name: "arena", built: true, bytes: Some(self.arena.size()) });
+        usage
+    }
+
+    /// Known heap bytes of the caches and of the arena, such as decompressed DWARF sections
+    ///
+    /// The parsed DWARF of `addr2line`, `dwarf` and `frames` is not known and left out,
+    /// as are the mappings of the arena.
+    pub fn memory(&self) -> usize {
+        self.caches()
+            .iter()
+            .filter_map(|usage| usage.bytes)
+            .sum::<usize>()
+            + self.arena.heap_size()
+    }
+
+    fn caches(&self) -> Vec<Usage> {
+        use std::mem::{ size_of, size_of_val };
+
+        fn slice<T>(name: &'static str, cell: &OnceCell<Box<[T]>>) -> Usage {
+            Usage {
+                name,
+                built: cell.initialized(),
+                bytes: cell.get().map(|list| size_of_val(&**list))
+            }
+        }
+
+        fn index(name: &'static str, cell: &OnceCell<NameIndex>) -> Usage {
+            Usage { name, built: cell.initialized(), bytes: cell.get().map(NameIndex::size) }
+        }
+
+        fn opaque<T>(name: &'static str, cell: &OnceCell<T>) -> Usage {
+            Usage { name, built: cell.initialized(), bytes: None }
+        }
+
+        let top_frames = self.top_frames.try_read().ok();
+        let data = self.data.map.try_read().ok();
+
+        vec![
+            Usage {
+                name: "addr2sym",
+                built: self.addr2sym.initialized(),
+                bytes: self.addr2sym.get().map(|map| size_of_val(map.symbols()))
+            },
+            slice("symlist", &self.symlist),
+            opaque("pdb", &self.pdb),
+            Usage {
+                name: "demangled",
+                built: self.demangled.initialized(),
+                bytes: self.demangled.get().map(Demangled::size)
+            },
+            index("names", &self.names),
+            index("demangled_names", &self.demangled_names),
+            slice("dyn_rela", &self.dyn_rela),
+            slice("plt", &self.plt),
+            opaque("addr2line", &self.addr2line),
+            opaque("dwarf", &self.dwarf),
+            opaque("frames", &self.frames),
+            slice("calls", &self.calls),
+            Usage {
+                name: "jump_tables",
+                built: self.jump_tables.initialized(),
+                bytes: self.jump_tables.get().map(|tables| tables.iter()
+                    .map(|table| size_of::<JumpTable>() + table.targets.len() * size_of::<u64>())
+                    .sum())
+            },
+            Usage {
+                name: "top_frames",
+                built: top_frames.as_ref().is_some_and(|frames| !frames.is_empty()),
+                bytes: top_frames.as_ref().map(|frames| {
+                    frames.capacity() * size_of::<(u64, Option<Arc<str>>)>()
+                        + frames.values().flatten().map(|name| name.len()).sum::<usize>()
+                })
+            },
+            Usage {
+                name: "data",
+                built: data.as_ref().is_some_and(|map| !map.is_empty()),
+                bytes: data.as_ref().map(|map| map.values().map(|entry| entry.data.len()).sum())
+            }
+        ]
+    }
+
     pub async fn addr2sym<'a>(&'a self, obj: &object::File<'static>)
         -> &'a object::read::SymbolMap<object::read::SymbolMapName<'static>>
     {
+        let obj = self.sym_obj(obj).await;
         self.addr2sym.get_or_init(async || obj.symbol_map()).await
     }
 
+    /// PDB of a PE binary, None if there is none or it failed to load
+    pub async fn pdb<'a>(&'a self, obj: &object::File<'static>) -> Option<&'a crate::pdb::Pdb> {
+        self.pdb.get_or_init(async || {
+            let path = self.pdb_path.as_deref()?;
+
+            match crate::pdb::load(path, obj, &self.arena) {
+                Ok(pdb) => Some(pdb),
+                Err(err) => {
+                    self.warn("pdb", "pdb load failed, use the PE symbols", &err);
+                    None
+                }
+            }
+        })
+            .await
+            .as_ref()
+    }
+
+    /// Object of the symbols of symlist, see `Explorer::sym_obj`
+    pub async fn sym_obj<'a>(&'a self, obj: &'a object::File<'static>) -> &'a object::File<'static> {
+        self.pdb(obj).await.map_or(obj, |pdb| &pdb.obj)
+    }
+
     pub async fn symlist<'a>(&'a self, obj: &object::File<'static>)
         -> &'a [SymbolIndex]
     {
+        let obj = self.sym_obj(obj).await;
         self.symlist.get_or_init(async || {
             let mut list = obj.symbol_table()
                 .into_iter()
@@ -130,6 +613,54 @@ impl Cache {
         }).await
     }
 
+    /// Demangled names of the symbol table, built in parallel on first use
+    pub async fn demangled<'a>(&'a self, obj: &object::File<'static>) -> &'a Demangled {
+        use rayon::prelude::*;
+
+        let symlist = self.symlist(obj).await;
+        let obj = self.sym_obj(obj).await;
+
+        self.demangled.get_or_init(async || {
+            let map = symlist.par_iter()
+                .filter_map(|&idx| obj.symbol_by_index(idx).ok()?.name().ok())
+                .map(|name| match symbolic_demangle::demangle(name) {
+                    Cow::Owned(demangled) if demangled != name => (name, Some(demangled.into_boxed_str())),
+                    _ => (name, None)
+                })
+                .collect();
+            Demangled(map)
+        }).await
+    }
+
+    /// Index of the names of symlist, demangled or not, built on first use
+    pub async fn names<'a>(&'a self, obj: &object::File<'static>, demangled: bool) -> &'a NameIndex {
+        let symlist = self.symlist(obj).await;
+        let sym_obj = self.sym_obj(obj).await;
+        let name = |idx| sym_obj.symbol_by_index(idx)
+            .ok()
+            .and_then(|sym| sym.name().ok())
+            .unwrap_or_default();
+
+        if demangled {
+            let demangled = self.demangled(obj).await;
+            self.demangled_names.get_or_init(async || NameIndex::new(
+                symlist.iter().map(|&idx| demangled.get(name(idx)))
+            )).await
+        } else {
+            self.names.get_or_init(async || NameIndex::new(
+                symlist.iter().map(|&idx| name(idx))
+            )).await
+        }
+    }
+
+    /// Demangled `name`, looked up in `demangled` if it was built
+    pub fn demangle<'a>(&'a self, name: &'a str) -> Cow<'a, str> {
+        match self.demangled.get() {
+            Some(demangled) => demangled.get(name),
+            None => symbolic_demangle::demangle(name)
+        }
+    }
+
     pub async fn dyn_rela<'a>(&'a self, obj: &object::File<'static>)
         -> &'a [(u64, object::read::Relocation)]
     {
@@ -144,46 +675,199 @@ impl Cache {
             .await
     }
 
-    pub async fn data(&self, obj: &object::File<'static>, idx: SectionIndex)
-        -> anyhow::Result<Arc<Cow<'static, [u8]>>>
+    pub async fn plt<'a>(&'a self, obj: &object::File<'static>)
+        -> &'a [PltEntry]
     {
-        // fast check
-        {
-            let map = self.data.map.read().await;
-            if let Some(id) = map.get(&idx).copied() {
-                let list = self.data.data.read().await;
-                return Ok(list[id].clone());
+        let dyn_rela = self.dyn_rela(obj).await;
+        self.plt.get_or_init(async || {
+            match build_plt(obj, dyn_rela) {
+                Ok(mut list) => {
+                    list.sort_by_key(|entry| entry.addr);
+                    list.into_boxed_slice()
+                },
+                Err(err) => {
+                    self.warn("plt", "plt parse failed", &err);
+                    Box::new([])
+                }
             }
-        }
+        })
+            .await
+    }
 
-        // insert
-        let id = {
-            let mut map = self.data.map.write().await;
+    /// A loader of `path` not used by other requests, see `Addr2Line`
+    pub async fn addr2line(&self, path: &Path, dwarf_path: Option<&Path>)
+        -> anyhow::Result<OwnedMutexGuard<Loader>>
+    {
+        self.addr2line.get_or_try_init(async || Addr2Line::new(path, dwarf_path).await)
+            .await?
+            .get()
+            .await
+    }
 
-            // double check
-            if map.get(&idx).is_none() {
-                let section = obj.section_by_index(idx)?;
-                let data = section.uncompressed_data()?;
-                
-                let mut list = self.data.data.write().await;
-                let id = list.len();
-                list.push(Arc::new(data));
-                map.insert(idx, id);
+    pub async fn dwarf<'a>(&'a self, obj: &object::File<'static>, sup_path: Option<&Path>)
+        -> anyhow::Result<&'a crate::dwarf::Dwarf>
+    {
+        self.dwarf.get_or_try_init(async || crate::dwarf::load(obj, sup_path, &self.arena)).await
+    }
 
-                Some(id)
-            } else {
-                None
+    pub async fn frames<'a>(&'a self, obj: &object::File<'static>)
+        -> anyhow::Result<&'a crate::dwarf::Frames>
+    {
+        self.frames.get_or_try_init(async || crate::dwarf::load_frames(obj, &self.arena)).await
+    }
+
+    /// Data of a section, only compressed sections are decompressed and cached
+    pub async fn data(&self, obj: &object::File<'static>, idx: SectionIndex)
+        -> anyhow::Result<SectionData>
+    {
+        let section = obj.section_by_index(idx)?;
+
+        if section.compressed_file_range()?.format == object::CompressionFormat::None {
+            return Ok(SectionData::Mapped(section.data()?));
+        }
+
+        // fast check
+        if let Some(entry) = self.data.map.read().await.get(&idx) {
+            return Ok(entry.touch());
+        }
+
+        // insert, double checked
+        let mut map = self.data.map.write().await;
+        let entry = match map.entry(idx) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(DataEntry {
+                    data: Arc::new(section.uncompressed_data()?.into_owned()),
+                    used: AtomicU64::new(0)
+                })
             }
         };
+        let data = entry.touch();
 
-        let id = if let Some(id) = id {
-            id
-        } else {
-            let map = self.data.map.read().await;
-            map.get(&idx).copied().unwrap()
+        self.data.shrink(&mut map, idx);
+
+        Ok(data)
+    }
+}
+
+fn build_plt(obj: &object::File<'static>, dyn_rela: &[(u64, object::read::Relocation)])
+    -> anyhow::Result<Vec<PltEntry>>
+{
+    use object::read::RelocationTarget;
+
+    let mut list = Vec::new();
+    let Some(dynsyms) = obj.dynamic_symbol_table()
+        else {
+            return Ok(list)
         };
+    let disasm = Disassembler::new(obj)?;
+
+    for section in obj.sections()
+        .filter(|section| section.name().is_ok_and(|name| name.starts_with(".plt")))
+    {
+        let data = section.data()?;
+        let mut insts = disasm.disasm_iter(data, section.address())?;
+        let mut refs = DataRefs::default();
+        let mut prev_endbr = None;
+        let mut last_adrp = None;
+
+        while let Some(inst) = insts.next() {
+            let inst = inst?;
+
+            if let Some(slot) = disasm.plt_slot(&inst, &mut refs)
+                && let Ok(idx) = dyn_rela.binary_search_by_key(&slot, |(addr, _)| *addr)
+                && let RelocationTarget::Symbol(symidx) = dyn_rela[idx].1.target()
+                && let Ok(sym) = dynsyms.symbol_by_index(symidx)
+                && let Ok(name) = sym.name()
+            {
+                // stub starts at `endbr64` (x86-64) or `adrp` (aarch64)
+                let addr = match disasm {
+                    Disassembler::Aarch64(_) => last_adrp.unwrap_or(inst.address()),
+                    _ => prev_endbr.unwrap_or(inst.address())
+                };
+                list.push(PltEntry { addr, name, target: sym.address() });
+            }
+
+            prev_endbr = (inst.mnemonic() == Some("endbr64")).then(|| inst.address());
+
+            if inst.mnemonic() == Some("adrp") {
+                last_adrp = Some(inst.address());
+            }
+        }
+    }
+
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object::elf;
 
-        let list = self.data.data.read().await;
-        Ok(list[id].clone())
+    /// ELF object with a `.debug_str` section of `SHF_COMPRESSED` data
+    fn compressed_elf(ch_type: u32, size: usize, compressed: &[u8]) -> Vec<u8> {
+        use crate::testing::{ write_elf, Section };
+
+        // Elf64_Chdr
+        let mut data = Vec::new();
+        data.extend_from_slice(&ch_type.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&(size as u64).to_le_bytes());
+        data.extend_from_slice(&1u64.to_le_bytes());
+        data.extend_from_slice(compressed);
+
+        write_elf(elf::ET_REL, &[Section {
+            name: b".debug_str",
+            flags: elf::SHF_COMPRESSED,
+            addr: 0,
+            data: &data,
+            align: 8,
+            entsize: 1
+        }])
+    }
+
+    /// zlib stream of one stored block
+    fn zlib_stored(data: &[u8]) -> Vec<u8> {
+        let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+            let a = (a + u32::from(byte)) % 65521;
+            (a, (b + a) % 65521)
+        });
+        let len = data.len() as u16;
+
+        let mut buf = vec![0x78, 0x01, 0x01];
+        buf.extend_from_slice(&len.to_le_bytes());
+        buf.extend_from_slice(&(!len).to_le_bytes());
+        buf.extend_from_slice(data);
+        buf.extend_from_slice(&((b << 16) | a).to_be_bytes());
+        buf
+    }
+
+    /// zstd frame of one raw block
+    fn zstd_raw(data: &[u8]) -> Vec<u8> {
+        let header = (data.len() as u32) << 3 | 1;
+
+        let mut buf = vec![0x28, 0xb5, 0x2f, 0xfd, 0x20, data.len() as u8];
+        buf.extend_from_slice(&header.to_le_bytes()[..3]);
+        buf.extend_from_slice(data);
+        buf
+    }
+
+    #[tokio::test]
+    async fn decompress_debug_sections() {
+        let text = b"compressed debug strings\0";
+
+        for (ch_type, compressed) in [
+            (elf::ELFCOMPRESS_ZLIB, zlib_stored(text)),
+            (elf::ELFCOMPRESS_ZSTD, zstd_raw(text))
+        ] {
+            let cache = Cache::default();
+            let obj = object::File::parse(cache.arena.keep(compressed_elf(ch_type, text.len(), &compressed)))
+                .unwrap();
+            let section = obj.section_by_name(".debug_str").unwrap();
+            let data = cache.data(&obj, section.index()).await.unwrap();
+
+            assert!(matches!(data, SectionData::Decompressed(_)));
+            assert_eq!(&*data, text);
+        }
     }
 }
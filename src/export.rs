@@ -0,0 +1,356 @@
+use std::io::Write;
+use std::ops::Range;
+use std::borrow::Cow;
+use object::{ Object, ObjectSection, ObjectSegment, ObjectSymbol, SectionKind, SymbolKind };
+
+use clap::{ Args, ValueEnum };
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::Stdio;
+use crate::disasm::{ DataRefs, InstKind };
+use crate::disasm::jumptable::{ JumpTableFinder, JumpTableRef };
+
+
+/// export recovered names and annotations for other tools
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// output kind
+    #[arg(value_enum)]
+    pub target: Target,
+
+    /// skip string references, discovered functions and jump tables, which need to disassemble everything
+    #[arg(long)]
+    pub no_disasm: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Target {
+    /// rizin commands, load with `. script.rz`
+    Rizin,
+    /// ghidra python script
+    Ghidra,
+    /// linker map with publics by value, as read by ida and x64dbg
+    Map,
+    /// `address size name` per line
+    Names,
+}
+
+/// Everything fi knows about the binary
+struct Knowledge {
+    /// (address, size, name, is function)
+    symbols: Vec<(u64, u64, &'static str, bool)>,
+    /// (stub address, imported name)
+    plt: Vec<(u64, &'static str)>,
+    /// call targets without symbol
+    functions: Vec<u64>,
+    /// (instruction address, string literal)
+    strings: Vec<(u64, String)>,
+    /// (jump address, table address, entry size, targets)
+    jump_tables: Vec<(u64, u64, u8, Vec<u64>)>,
+}
+
+impl Command {
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        let knowledge = collect(&self, explorer, stdio).await?;
+
+        match self.target {
+            Target::Rizin => write_rizin(&knowledge, stdio),
+            Target::Ghidra => write_ghidra(explorer, &knowledge, stdio),
+            Target::Map => write_map(explorer, &knowledge, stdio),
+            Target::Names => write_names(&knowledge, stdio),
+        }
+    }
+}
+
+async fn collect(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<Knowledge> {
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let plt = explorer.cache.plt(&explorer.obj).await;
+    let mut symbols = Vec::new();
+
+    for &idx in symlist {
+        let sym = explorer.sym_obj().symbol_by_index(idx)?;
+
+        let Ok(name) = sym.name()
+            else { continue };
+
+        if name.is_empty() || sym.address() == 0 || sym.is_undefined()
+            || !matches!(sym.kind(), SymbolKind::Text | SymbolKind::Data | SymbolKind::Tls)
+        {
+            continue
+        }
+
+        let size = explorer.symbol_size(symlist, idx)?;
+        symbols.push((sym.address(), size, name, sym.kind() == SymbolKind::Text));
+    }
+
+    let mut strings = Vec::new();
+    let mut functions = Vec::new();
+    let mut jump_tables = Vec::new();
+
+    if !cmd.no_disasm {
+        enum Hit {
+            Data(u64, u64),
+            Call(u64),
+            /// table and the function jumping through it
+            JumpTable(JumpTableRef, Range<u64>)
+        }
+
+        let hits = crate::search::par_disasm(explorer, None, stdio, |disasm, symidx, size, mut insts| {
+            let start = explorer.sym_obj().symbol_by_index(symidx)?.address();
+            let mut refs = DataRefs::default();
+            let mut finder = JumpTableFinder::default();
+            let mut hits = Vec::new();
+
+            while let Some(Ok(inst)) = insts.next() {
+                finder.step(disasm, &inst);
+
+                // a call through memory loads its target, such as from the GOT
+                if disasm.inst_kind(&inst) == InstKind::Call
+                    && let Some(addr) = disasm.direct_target(&inst)
+                {
+                    hits.push(Hit::Call(addr));
+                } else if let Some(addr) = disasm.operand2data(&inst, &mut refs) {
+                    hits.push(Hit::Data(inst.address(), addr));
+                }
+            }
+
+            hits.extend(finder.finish()
+                .into_iter()
+                .map(|table| Hit::JumpTable(table, start..start + size)));
+
+            Ok(hits)
+        }).await?;
+
+        let text = explorer.obj.sections()
+            .filter(|section| section.kind() == SectionKind::Text && section.address() != 0)
+            .map(|section| section.address()..section.address() + section.size())
+            .collect::<Vec<_>>();
+        let mut named = symbols.iter().map(|&(addr, ..)| addr)
+            .chain(plt.iter().map(|entry| entry.addr))
+            .collect::<Vec<_>>();
+        named.sort_unstable();
+
+        for hit in hits {
+            match hit {
+                Hit::Data(addr, target) => if let Some(literal) = crate::show::read_literal(explorer, target, None).await {
+                    strings.push((addr, literal));
+                },
+                Hit::Call(addr) => if named.binary_search(&addr).is_err()
+                    && text.iter().any(|range| range.contains(&addr))
+                {
+                    functions.push(addr);
+                },
+                Hit::JumpTable(table, range) => if let Some(table) = crate::show::decode_jump_table(explorer, &table, range).await {
+                    jump_tables.push((table.jump, table.table, table.size, table.targets));
+                }
+            }
+        }
+
+        functions.sort_unstable();
+        functions.dedup();
+        jump_tables.sort_unstable_by_key(|&(jump, table, ..)| (table, jump));
+        jump_tables.dedup_by_key(|&mut (_, table, ..)| table);
+    }
+
+    let plt = plt.iter()
+        .map(|entry| (entry.addr, entry.name))
+        .collect();
+
+    Ok(Knowledge { symbols, plt, functions, strings, jump_tables })
+}
+
+/// Flag name accepted by rizin
+fn rizin_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '_' | '.') { c } else { '_' })
+        .collect()
+}
+
+fn write_rizin(knowledge: &Knowledge, stdio: &mut Stdio) -> anyhow::Result<()> {
+    let out = &mut stdio.stdout;
+
+    writeln!(out, "# generated by fi")?;
+
+    for &(addr, size, name, is_func) in &knowledge.symbols {
+        let name = rizin_name(name);
+        writeln!(out, "f fi.{} {} @ {:#x}", name, size.max(1), addr)?;
+
+        if is_func {
+            writeln!(out, "af fi.{} @ {:#x}", name, addr)?;
+        }
+    }
+
+    for &(addr, name) in &knowledge.plt {
+        writeln!(out, "f fi.plt.{} 1 @ {:#x}", rizin_name(name), addr)?;
+    }
+
+    for &addr in &knowledge.functions {
+        writeln!(out, "af fi.sub_{:x} @ {:#x}", addr, addr)?;
+    }
+
+    for (addr, literal) in &knowledge.strings {
+        writeln!(out, "CCu base64:{} @ {:#x}", data_encoding::BASE64.encode(literal.as_bytes()), addr)?;
+    }
+
+    for (jump, table, size, targets) in &knowledge.jump_tables {
+        writeln!(out, "f fi.jmptbl.{:x} {} @ {:#x}", table, *size as usize * targets.len(), table)?;
+        writeln!(out, "CCu jump table {:#x}, {} cases @ {:#x}", table, targets.len(), jump)?;
+
+        for (case, target) in targets.iter().enumerate() {
+            writeln!(out, "CC case {} @ {:#x}", case, target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Address ghidra takes as image base
+///
+/// ELF has none, ghidra uses the lowest loaded address.
+fn image_base(explorer: &Explorer) -> u64 {
+    if explorer.obj.format() == object::BinaryFormat::Elf {
+        explorer.obj.segments()
+            .map(|segment| segment.address())
+            .min()
+            .unwrap_or_default()
+    } else {
+        explorer.obj.relative_address_base()
+    }
+}
+
+fn write_ghidra(explorer: &Explorer, knowledge: &Knowledge, stdio: &mut Stdio) -> anyhow::Result<()> {
+    let out = &mut stdio.stdout;
+    // json strings are python literals too
+    let quote = |s: &str| serde_json::to_string(s);
+
+    writeln!(out, "# generated by fi, run in the ghidra script manager")?;
+    writeln!(out, "from ghidra.program.model.symbol import SourceType")?;
+    writeln!(out)?;
+    writeln!(out, "BASE = {:#x}", image_base(explorer))?;
+    writeln!(out)?;
+    writeln!(out, "def at(addr):")?;
+    writeln!(out, "    return currentProgram.getImageBase().add(addr - BASE)")?;
+    writeln!(out)?;
+    writeln!(out, "def label(addr, name):")?;
+    writeln!(out, "    createLabel(at(addr), name, True, SourceType.IMPORTED)")?;
+    writeln!(out)?;
+    writeln!(out, "def function(addr, name):")?;
+    writeln!(out, "    if getFunctionAt(at(addr)) is None:")?;
+    writeln!(out, "        createFunction(at(addr), name)")?;
+    writeln!(out, "    label(addr, name)")?;
+    writeln!(out)?;
+
+    for &(addr, _size, name, is_func) in &knowledge.symbols {
+        let kind = if is_func { "function" } else { "label" };
+        writeln!(out, "{}({:#x}, {})", kind, addr, quote(name)?)?;
+    }
+
+    for &(addr, name) in &knowledge.plt {
+        writeln!(out, "function({:#x}, {})", addr, quote(&format!("{}@plt", name))?)?;
+    }
+
+    for &addr in &knowledge.functions {
+        writeln!(out, "function({:#x}, \"sub_{:x}\")", addr, addr)?;
+    }
+
+    for (addr, literal) in &knowledge.strings {
+        writeln!(out, "setEOLComment(at({:#x}), {})", addr, quote(literal)?)?;
+    }
+
+    for (jump, table, _size, targets) in &knowledge.jump_tables {
+        let comment = format!("jump table {:#x}, {} cases", table, targets.len());
+        writeln!(out, "setEOLComment(at({:#x}), {})", jump, quote(&comment)?)?;
+        writeln!(out, "label({:#x}, {})", table, quote(&format!("jmptbl_{:x}", table))?)?;
+
+        for (case, target) in targets.iter().enumerate() {
+            writeln!(out, "setPreComment(at({:#x}), {})", target, quote(&format!("case {}", case))?)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Every named address as (address, size, name, is function), sorted
+fn publics(knowledge: &Knowledge) -> Vec<(u64, u64, Cow<'static, str>, bool)> {
+    let mut list = knowledge.symbols.iter()
+        .map(|&(addr, size, name, is_func)| (addr, size, Cow::Borrowed(name), is_func))
+        .chain(knowledge.plt.iter().map(|&(addr, name)| (addr, 0, format!("{}@plt", name).into(), true)))
+        .chain(knowledge.functions.iter().map(|&addr| (addr, 0, format!("sub_{:x}", addr).into(), true)))
+        .collect::<Vec<_>>();
+    list.sort_by_key(|&(addr, ..)| addr);
+    list
+}
+
+fn write_map(explorer: &Explorer, knowledge: &Knowledge, stdio: &mut Stdio) -> anyhow::Result<()> {
+    let out = &mut stdio.stdout;
+    let base = image_base(explorer);
+    let sections = explorer.obj.sections()
+        .filter(|section| section.address() != 0 && section.size() != 0)
+        .collect::<Vec<_>>();
+    let module = explorer.path.file_stem()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+
+    writeln!(out, " {}", module)?;
+    writeln!(out)?;
+    writeln!(out, " Preferred load address is {:016x}", base)?;
+    writeln!(out)?;
+    writeln!(out, " Start         Length     Name                   Class")?;
+
+    for (i, section) in sections.iter().enumerate() {
+        let class = if section.kind() == SectionKind::Text { "CODE" } else { "DATA" };
+        writeln!(
+            out,
+            " {:04x}:{:08x} {:08x}H {:<23} {}",
+            i + 1,
+            0,
+            section.size(),
+            section.name().unwrap_or_default(),
+            class
+        )?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "  Address         Publics by Value              Rva+Base               Lib:Object")?;
+    writeln!(out)?;
+
+    // section number and offset
+    let locate = |addr: u64| sections.iter()
+        .enumerate()
+        .find(|(_, section)| (section.address()..section.address() + section.size()).contains(&addr))
+        .map(|(i, section)| format!("{:04x}:{:08x}", i + 1, addr - section.address()));
+
+    for (addr, _size, name, is_func) in publics(knowledge) {
+        let Some(location) = locate(addr)
+            else { continue };
+
+        writeln!(
+            out,
+            " {}       {:<29} {:016x}{}",
+            location,
+            name,
+            addr,
+            if is_func { " f" } else { "" }
+        )?;
+    }
+
+    if let Some(location) = locate(explorer.obj.entry()) {
+        writeln!(out)?;
+        writeln!(out, " entry point at        {}", location)?;
+    }
+
+    Ok(())
+}
+
+fn write_names(knowledge: &Knowledge, stdio: &mut Stdio) -> anyhow::Result<()> {
+    for (addr, size, name, _) in publics(knowledge) {
+        writeln!(stdio.stdout, "{:016x} {:08x} {}", addr, size, name)?;
+    }
+
+    Ok(())
+}
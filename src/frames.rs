@@ -0,0 +1,244 @@
+use std::io::Write;
+use std::borrow::Cow;
+use object::{ Object, ObjectSymbol };
+use addr2line::gimli::{ self, UnwindSection };
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ u64ptr, Stdio };
+use crate::dwarf::{ Frames, Reader };
+
+
+/// show CFI unwind rules of a function
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// function name or address
+    pub target: String,
+
+    /// demangle symbol name
+    #[arg(short, long)]
+    pub demangle: bool,
+}
+
+/// Unwind rules from `start` to `end`
+struct Row {
+    start: u64,
+    end: u64,
+    cfa: String,
+    registers: String
+}
+
+impl Command {
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            start: u64,
+            end: u64,
+            symbol: Option<&'a str>,
+            cfa: &'a str,
+            registers: &'a str
+        }
+
+        let symlist = explorer.cache.symlist(&explorer.obj).await;
+        let frames = explorer.cache.frames(&explorer.obj).await?;
+        let arch = explorer.obj.architecture();
+
+        let (symbol, start, end) = match explorer.lookup_symbol(symlist, &self.target)? {
+            Some(idx) => {
+                let sym = explorer.sym_obj().symbol_by_index(idx)?;
+                let size = explorer.symbol_size(symlist, idx)?;
+                let name = sym.name()?;
+                let name = if self.demangle {
+                    explorer.cache.demangle(name)
+                } else {
+                    Cow::Borrowed(name)
+                };
+                (Some(name), sym.address(), sym.address() + size.max(1))
+            },
+            None => {
+                let addr = u64ptr(&self.target)
+                    .map_err(|_| anyhow::format_err!("not found symbol: {}", self.target))?;
+                (None, addr, addr + 1)
+            }
+        };
+
+        let mut addr = start;
+
+        while addr < end {
+            let rows = if let Ok(fde) = frames.eh_frame.fde_for_address(&frames.bases, addr, gimli::EhFrame::cie_from_offset) {
+                fde_rows(&frames.eh_frame, &frames.bases, &fde, arch)?
+            } else if let Ok(fde) = frames.debug_frame.fde_for_address(&frames.bases, addr, gimli::DebugFrame::cie_from_offset) {
+                fde_rows(&frames.debug_frame, &frames.bases, &fde, arch)?
+            } else if addr == start {
+                anyhow::bail!("no unwind info for {:#x}", addr);
+            } else {
+                break
+            };
+
+            let Some(fde_end) = rows.last().map(|row| row.end)
+                else { break };
+
+            if stdio.format.is_text() {
+                writeln!(
+                    stdio.stdout,
+                    "fde: {:018p}..{:018p}\t{}",
+                    rows[0].start as *const (),
+                    fde_end as *const (),
+                    symbol.as_deref().unwrap_or("?")
+                )?;
+            }
+
+            for row in rows.iter().filter(|row| row.start < end && start < row.end) {
+                if !stdio.format.is_text() {
+                    stdio.record(&Record {
+                        start: row.start,
+                        end: row.end,
+                        symbol: symbol.as_deref(),
+                        cfa: &row.cfa,
+                        registers: &row.registers
+                    })?;
+                    continue
+                }
+
+                writeln!(
+                    stdio.stdout,
+                    "{:018p}\tCFA={}\t{}",
+                    row.start as *const (),
+                    row.cfa,
+                    row.registers
+                )?;
+            }
+
+            addr = fde_end;
+        }
+
+        Ok(())
+    }
+}
+
+fn fde_rows<S: UnwindSection<Reader>>(
+    section: &S,
+    bases: &gimli::BaseAddresses,
+    fde: &gimli::FrameDescriptionEntry<Reader>,
+    arch: object::Architecture
+) -> anyhow::Result<Vec<Row>> {
+    let mut ctx = gimli::UnwindContext::new();
+    let mut table = fde.rows(section, bases, &mut ctx)?;
+    let mut rows = Vec::new();
+
+    while let Some(row) = table.next_row()? {
+        let cfa = match row.cfa() {
+            gimli::CfaRule::RegisterAndOffset { register, offset } => {
+                format!("{}{:+}", register_name(arch, *register), offset)
+            },
+            gimli::CfaRule::Expression(_) => "exp".into()
+        };
+        let registers = row.registers()
+            .map(|(register, rule)| {
+                let rule = match rule {
+                    gimli::RegisterRule::Undefined => "undefined".into(),
+                    gimli::RegisterRule::SameValue => "same".into(),
+                    gimli::RegisterRule::Offset(offset) => format!("[CFA{:+}]", offset),
+                    gimli::RegisterRule::ValOffset(offset) => format!("CFA{:+}", offset),
+                    gimli::RegisterRule::Register(register) => register_name(arch, *register).into_owned(),
+                    gimli::RegisterRule::Expression(_) => "exp".into(),
+                    gimli::RegisterRule::ValExpression(_) => "val_exp".into(),
+                    gimli::RegisterRule::Architectural => "arch".into(),
+                    gimli::RegisterRule::Constant(value) => format!("{:#x}", value),
+                    _ => "?".into()
+                };
+                format!("{}={}", register_name(arch, *register), rule)
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        rows.push(Row {
+            start: row.start_address(),
+            end: row.end_address(),
+            cfa, registers
+        });
+    }
+
+    Ok(rows)
+}
+
+/// Stack frame size of each FDE as (start, end, size), by start address
+///
+/// The size is the largest CFA offset from the stack pointer, which on x86-64
+/// includes the return address. None once the CFA moves to another register,
+/// such as the frame pointer, as the stack grown after that no longer shows in it.
+pub(crate) fn frame_sizes(frames: &Frames, arch: object::Architecture)
+    -> anyhow::Result<Vec<(u64, u64, Option<u64>)>>
+{
+    use object::Architecture;
+
+    let sp = match arch {
+        Architecture::X86_64 => gimli::X86_64::RSP,
+        Architecture::Aarch64 => gimli::AArch64::SP,
+        arch => anyhow::bail!("unsupported arch: {:?}", arch)
+    };
+    let mut sizes = Vec::new();
+
+    section_frame_sizes(&frames.eh_frame, &frames.bases, sp, &mut sizes)?;
+    section_frame_sizes(&frames.debug_frame, &frames.bases, sp, &mut sizes)?;
+    sizes.sort_unstable_by_key(|&(start, ..)| start);
+
+    Ok(sizes)
+}
+
+fn section_frame_sizes<S: UnwindSection<Reader>>(
+    section: &S,
+    bases: &gimli::BaseAddresses,
+    sp: gimli::Register,
+    sizes: &mut Vec<(u64, u64, Option<u64>)>
+) -> anyhow::Result<()> {
+    let mut ctx = gimli::UnwindContext::new();
+    let mut entries = section.entries(bases);
+
+    while let Some(entry) = entries.next()? {
+        let gimli::CieOrFde::Fde(partial) = entry
+            else { continue };
+        // skip entries gimli cannot evaluate, the prologue is read instead
+        let Ok(fde) = partial.parse(S::cie_from_offset)
+            else { continue };
+        let Ok(mut table) = fde.rows(section, bases, &mut ctx)
+            else { continue };
+        let mut size = Some(0);
+
+        while let Ok(Some(row)) = table.next_row() {
+            size = match row.cfa() {
+                gimli::CfaRule::RegisterAndOffset { register, offset } if *register == sp =>
+                    size.map(|size| size.max(u64::try_from(*offset).unwrap_or(0))),
+                _ => None
+            };
+        }
+
+        let start = fde.initial_address();
+        sizes.push((start, start + fde.len(), size));
+    }
+
+    Ok(())
+}
+
+fn register_name(arch: object::Architecture, register: gimli::Register) -> Cow<'static, str> {
+    use object::Architecture;
+
+    let name = match arch {
+        Architecture::X86_64 | Architecture::X86_64_X32 => gimli::X86_64::register_name(register),
+        Architecture::I386 => gimli::X86::register_name(register),
+        Architecture::Aarch64 | Architecture::Aarch64_Ilp32 => gimli::AArch64::register_name(register),
+        Architecture::Arm => gimli::Arm::register_name(register),
+        Architecture::Riscv32 | Architecture::Riscv64 => gimli::RiscV::register_name(register),
+        _ => None
+    };
+
+    match name {
+        Some(name) => Cow::Borrowed(name),
+        None => Cow::Owned(format!("r{}", register.0))
+    }
+}
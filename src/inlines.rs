@@ -0,0 +1,222 @@
+use std::io::Write;
+use std::collections::HashMap;
+use addr2line::gimli;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, YieldPoint, MaybePrinter };
+use crate::dwarf::{ self, Dwarf, Unit };
+
+
+/// list inlined instances of a function from DWARF
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// function name, full path, last component or linkage name
+    pub name: String,
+
+    /// sort by size contribution, biggest first
+    #[arg(long)]
+    pub sort_size: bool,
+
+    /// only print the first N instances
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+struct Instance {
+    address: u64,
+    size: u64,
+    ranges: usize,
+    caller: String,
+    file: Option<String>,
+    line: Option<u64>
+}
+
+impl Command {
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            address: u64,
+            size: u64,
+            ranges: usize,
+            caller: &'a str,
+            file: Option<&'a str>,
+            line: Option<u64>
+        }
+
+        let dwarf = explorer.cache.dwarf(explorer.dwarf_obj(), explorer.dwarf_path.as_deref()).await?;
+        let mut point = YieldPoint::default();
+        let mut units = dwarf.units();
+        let mut instances = Vec::new();
+
+        while let Some(header) = units.next()? {
+            point.yield_now().await;
+
+            let unit = dwarf.unit(header)?;
+            let names = function_names(dwarf, &unit)?;
+            let mut entries = unit.entries();
+            let mut depth = 0;
+            // enclosing functions as (depth, name)
+            let mut callers: Vec<(isize, &str)> = Vec::new();
+
+            while let Some((delta, entry)) = entries.next_dfs()? {
+                depth += delta;
+                while callers.last().is_some_and(|(d, _)| *d >= depth) {
+                    callers.pop();
+                }
+
+                let name = match entry.tag() {
+                    gimli::DW_TAG_subprogram => names.get(&entry.offset()),
+                    gimli::DW_TAG_inlined_subroutine => dwarf::attr_ref(&unit, entry, gimli::DW_AT_abstract_origin)
+                        .and_then(|origin| names.get(&origin)),
+                    _ => continue
+                };
+                let caller = callers.last().map(|(_, caller)| *caller);
+                callers.push((depth, name.map(|(name, _)| name.as_str()).unwrap_or("?")));
+
+                if entry.tag() != gimli::DW_TAG_inlined_subroutine
+                    || !name.is_some_and(|(qualified, linkage)| self.matches(qualified, linkage.as_deref()))
+                {
+                    continue
+                }
+
+                let mut ranges = dwarf.die_ranges(&unit, entry)?;
+                let mut instance = Instance {
+                    address: u64::MAX,
+                    size: 0,
+                    ranges: 0,
+                    caller: caller.unwrap_or("?").to_owned(),
+                    file: None,
+                    line: None
+                };
+
+                while let Some(range) = ranges.next()? {
+                    // code discarded by the linker
+                    if range.begin == 0 || range.end <= range.begin {
+                        continue
+                    }
+
+                    instance.address = instance.address.min(range.begin);
+                    instance.size += range.end - range.begin;
+                    instance.ranges += 1;
+                }
+
+                if instance.ranges == 0 {
+                    continue
+                }
+
+                if let Some(file) = entry.attr_value(gimli::DW_AT_call_file)?
+                    .and_then(|value| match value {
+                        gimli::AttributeValue::FileIndex(index) => Some(index),
+                        value => value.udata_value()
+                    })
+                {
+                    instance.file = dwarf::file_path(dwarf, &unit, file);
+                }
+
+                instance.line = entry.attr_value(gimli::DW_AT_call_line)?
+                    .and_then(|value| value.udata_value());
+                instances.push(instance);
+            }
+        }
+
+        if self.sort_size {
+            instances.sort_by(|a, b| b.size.cmp(&a.size).then(a.address.cmp(&b.address)));
+        } else {
+            instances.sort_by_key(|instance| instance.address);
+        }
+
+        instances.truncate(self.limit.unwrap_or(usize::MAX));
+        let mut sum = 0;
+
+        for instance in &instances {
+            sum += instance.size;
+
+            if !stdio.format.is_text() {
+                stdio.record(&Record {
+                    address: instance.address,
+                    size: instance.size,
+                    ranges: instance.ranges,
+                    caller: &instance.caller,
+                    file: instance.file.as_deref(),
+                    line: instance.line
+                })?;
+                continue
+            }
+
+            let location = instance.file.as_ref()
+                .map(|file| format!("{}:{}", file, MaybePrinter(instance.line, Some('?'))));
+
+            writeln!(
+                stdio.stdout,
+                "{:018p}\t{:>6}\t{}\t{}",
+                instance.address as *const (),
+                instance.size,
+                instance.caller,
+                MaybePrinter(location, Some('?'))
+            )?;
+        }
+
+        if stdio.format.is_text() {
+            writeln!(stdio.stdout, "total: {} instances, {} bytes", instances.len(), sum)?;
+        }
+
+        Ok(())
+    }
+
+    fn matches(&self, qualified: &str, linkage: Option<&str>) -> bool {
+        let last = qualified.rsplit("::").next().unwrap_or(qualified);
+
+        qualified == self.name
+            || last == self.name
+            || linkage.is_some_and(|linkage| linkage == self.name)
+    }
+}
+
+/// Qualified and linkage name of every subprogram of a unit
+fn function_names(dwarf: &Dwarf, unit: &Unit)
+    -> anyhow::Result<HashMap<gimli::UnitOffset, (String, Option<String>)>>
+{
+    let mut names = HashMap::new();
+    // out-of-line definitions and concrete instances, resolved after all declarations
+    let mut refs = Vec::new();
+    let mut entries = unit.entries();
+    let mut depth = 0;
+    let mut scope = dwarf::Scope::default();
+
+    while let Some((delta, entry)) = entries.next_dfs()? {
+        depth += delta;
+
+        let name = dwarf::attr_string(dwarf, unit, entry, gimli::DW_AT_name);
+        scope.enter(depth, entry.tag(), name.clone());
+
+        if entry.tag() != gimli::DW_TAG_subprogram {
+            continue
+        }
+
+        let linkage = dwarf::attr_string(dwarf, unit, entry, gimli::DW_AT_linkage_name)
+            .or_else(|| dwarf::attr_string(dwarf, unit, entry, gimli::DW_AT_MIPS_linkage_name))
+            .map(|name| name.into_owned());
+
+        if let Some(name) = name {
+            names.insert(entry.offset(), (scope.qualify(&name), linkage));
+        } else if let Some(target) = dwarf::attr_ref(unit, entry, gimli::DW_AT_specification)
+            .or_else(|| dwarf::attr_ref(unit, entry, gimli::DW_AT_abstract_origin))
+        {
+            refs.push((entry.offset(), target));
+        }
+    }
+
+    for (offset, target) in refs {
+        if let Some(name) = names.get(&target).cloned() {
+            names.insert(offset, name);
+        }
+    }
+
+    Ok(names)
+}
@@ -0,0 +1,138 @@
+use std::io::Write;
+use std::path::Path;
+use std::collections::HashMap;
+use object::{ Object, ObjectSymbol };
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ u64ptr, Stdio, YieldPoint };
+
+
+/// dump DWARF line table rows
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// function name or address, address range `start..end`, or source file
+    pub target: String,
+
+    /// only print the first N rows
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+enum Filter<'a> {
+    Range(u64, u64),
+    File(&'a Path)
+}
+
+impl Command {
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            address: u64,
+            file: Option<&'a str>,
+            line: u64,
+            column: u64,
+            is_stmt: bool,
+            end_sequence: bool
+        }
+
+        let symlist = explorer.cache.symlist(&explorer.obj).await;
+        let filter = if let Some((start, end)) = self.target.split_once("..") {
+            Filter::Range(u64ptr(start)?, u64ptr(end)?)
+        } else if let Some(idx) = explorer.lookup_symbol(symlist, &self.target)? {
+            let sym = explorer.sym_obj().symbol_by_index(idx)?;
+            let size = explorer.symbol_size(symlist, idx)?;
+            Filter::Range(sym.address(), sym.address() + size)
+        } else {
+            Filter::File(Path::new(&self.target))
+        };
+
+        let dwarf = explorer.cache.dwarf(explorer.dwarf_obj(), explorer.dwarf_path.as_deref()).await?;
+        let mut point = YieldPoint::default();
+        let mut units = dwarf.units();
+        let mut count = 0;
+
+        while let Some(header) = units.next()? {
+            point.yield_now().await;
+
+            let unit = dwarf.unit(header)?;
+            let Some(program) = unit.line_program.clone()
+                else { continue };
+            let mut files: HashMap<u64, Option<String>> = HashMap::new();
+            let mut rows = program.rows();
+
+            while let Some((_, row)) = rows.next_row()? {
+                let file = files.entry(row.file_index())
+                    .or_insert_with(|| crate::dwarf::file_path(dwarf, &unit, row.file_index()))
+                    .as_deref();
+
+                let matched = match filter {
+                    // end of sequence is the address after the last instruction
+                    Filter::Range(start, end) if row.end_sequence() => (start + 1..=end).contains(&row.address()),
+                    Filter::Range(start, end) => (start..end).contains(&row.address()),
+                    // skip code discarded by the linker
+                    Filter::File(path) => row.address() != 0
+                        && file.is_some_and(|file| Path::new(file).ends_with(path))
+                };
+
+                if !matched {
+                    continue
+                }
+
+                if self.limit.is_some_and(|limit| count >= limit) {
+                    return Ok(())
+                }
+
+                count += 1;
+
+                let line = row.line().map(|line| line.get()).unwrap_or(0);
+                let column = match row.column() {
+                    addr2line::gimli::ColumnType::LeftEdge => 0,
+                    addr2line::gimli::ColumnType::Column(column) => column.get()
+                };
+
+                if !stdio.format.is_text() {
+                    stdio.record(&Record {
+                        address: row.address(),
+                        file,
+                        line,
+                        column,
+                        is_stmt: row.is_stmt(),
+                        end_sequence: row.end_sequence()
+                    })?;
+                    continue
+                }
+
+                let flags = [(row.is_stmt(), "is_stmt"), (row.end_sequence(), "end_sequence")]
+                    .iter()
+                    .filter(|(set, _)| *set)
+                    .map(|(_, flag)| *flag)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                writeln!(
+                    stdio.stdout,
+                    "{:018p}\t{}:{}:{}\t{}",
+                    row.address() as *const (),
+                    file.unwrap_or("?"),
+                    line,
+                    column,
+                    flags
+                )?;
+            }
+        }
+
+        if let Filter::File(path) = filter
+            && count == 0
+        {
+            anyhow::bail!("not found symbol or source file: {}", path.display());
+        }
+
+        Ok(())
+    }
+}
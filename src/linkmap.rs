@@ -0,0 +1,122 @@
+use std::fs;
+use std::path::Path;
+use anyhow::Context;
+
+
+/// Input sections of a GNU ld, lld or mold map file
+///
+/// Used to attribute addresses to the object file or archive member they come from.
+pub struct LinkMap {
+    /// (start, end, object), sorted by start
+    ranges: Vec<(u64, u64, Box<str>)>
+}
+
+impl LinkMap {
+    pub fn open(path: &Path) -> anyhow::Result<LinkMap> {
+        let text = fs::read_to_string(path).context("read map file failed")?;
+        let mut ranges = if let Some(columns) = text.lines().find_map(llvm_columns) {
+            parse_llvm(&text, columns)
+        } else {
+            parse_gnu(&text)
+        };
+
+        if ranges.is_empty() {
+            anyhow::bail!("no input section found in map file");
+        }
+
+        ranges.sort_by_key(|(start, ..)| *start);
+
+        Ok(LinkMap { ranges })
+    }
+
+    /// Object file or `archive(member)` containing `addr`
+    pub fn object_of(&self, addr: u64) -> Option<&str> {
+        let idx = self.ranges.partition_point(|(start, ..)| *start <= addr).checked_sub(1)?;
+        let (start, end, object) = &self.ranges[idx];
+        (*start..*end).contains(&addr).then_some(&**object)
+    }
+}
+
+/// Number of numeric columns, from the header of lld (`VMA LMA Size Align Out In Symbol`)
+/// or mold (`VMA Size Align Out In Symbol`)
+fn llvm_columns(line: &str) -> Option<usize> {
+    let mut names = line.split_whitespace();
+
+    (names.next()? == "VMA").then_some(())?;
+    names.position(|name| name == "Out").map(|n| n + 1)
+}
+
+fn parse_llvm(text: &str, columns: usize) -> Vec<(u64, u64, Box<str>)> {
+    let mut ranges = Vec::new();
+
+    for line in text.lines() {
+        let mut rest = line;
+        let mut fields = Vec::with_capacity(columns);
+
+        for _ in 0..columns {
+            let trimmed = rest.trim_start();
+            let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+            fields.push(&trimmed[..end]);
+            rest = &trimmed[end..];
+        }
+
+        let Ok(vma) = u64::from_str_radix(fields[0], 16)
+            else { continue };
+        // size is the column before `Align`
+        let Some(Ok(size)) = fields.get(columns - 2).map(|size| u64::from_str_radix(size, 16))
+            else { continue };
+
+        // input section, `file.o:(.text)` or `lib.a(member.o):(.text)`
+        if let Some((object, _)) = rest.trim().rsplit_once(":(")
+            && size != 0
+        {
+            ranges.push((vma, vma + size, object.into()));
+        }
+    }
+
+    ranges
+}
+
+fn parse_gnu(text: &str) -> Vec<(u64, u64, Box<str>)> {
+    let mut ranges = Vec::new();
+    let mut lines = text.lines().peekable();
+
+    // skip archive member and discarded section lists
+    while lines.next_if(|line| *line != "Linker script and memory map").is_some() {}
+
+    while let Some(line) = lines.next() {
+        // input sections start with one space, ` .text 0x... 0x... file.o`
+        let Some(rest) = line.strip_prefix(' ')
+            else { continue };
+
+        if !rest.starts_with('.') && !rest.starts_with("COMMON") {
+            continue
+        }
+
+        let mut fields = rest.split_whitespace().collect::<Vec<_>>();
+
+        // long section names wrap, the rest is on the next line
+        if fields.len() == 1
+            && let Some(next) = lines.next_if(|next| next.trim_start().starts_with("0x"))
+        {
+            fields.extend(next.split_whitespace());
+        }
+
+        let [_, addr, size, object @ ..] = fields.as_slice()
+            else { continue };
+        let (Some(addr), Some(size)) = (parse_hex(addr), parse_hex(size))
+            else { continue };
+
+        if object.is_empty() || size == 0 || addr == 0 {
+            continue
+        }
+
+        ranges.push((addr, addr + size, object.join(" ").into()));
+    }
+
+    ranges
+}
+
+fn parse_hex(value: &str) -> Option<u64> {
+    u64::from_str_radix(value.strip_prefix("0x")?, 16).ok()
+}
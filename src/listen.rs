@@ -1,16 +1,26 @@
 mod server;
+pub mod logger;
 
 use std::{ io, fs, env };
-use std::path::PathBuf;
+use std::io::{ Read, Write, PipeWriter };
+use std::os::fd::AsRawFd;
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::time::{ Duration, SystemTime };
 use directories::ProjectDirs;
 
 use clap::Args;
 use serde::{ Serialize, Deserialize };
 
-use crate::util::{ hashpath, hashname };
-use crate::call::SESSION_ENVNAME;
+use crate::util::{ hashpath, hashname, parse_size };
+use crate::call::{ SESSION_ENVNAME, TOKEN_ENVNAME };
 use crate::explorer::Explorer;
+use crate::debuginfo::{ self, DebugFile, Debuginfod };
+use crate::sessions;
 use server::Server;
+use logger::{ Logger, Level };
 
 
 /// open file and listen ipc
@@ -19,11 +29,86 @@ use server::Server;
 #[command(args_conflicts_with_subcommands = true)]
 #[command(flatten_help = true)]
 pub struct Command {
-    pub path: PathBuf,
+    /// binaries to open, such as the executable and its shared libraries,
+    /// selected by `--bin` of client commands, the first one by default
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
 
-    /// set dwarf path
+    /// supplementary dwarf file of dwz of the first binary,
+    /// found by `.gnu_debugaltlink` if not set
     #[arg(long)]
     pub dwarf_path: Option<PathBuf>,    
+
+    /// separate debug file used for all DWARF lookups of the first binary
+    #[arg(long)]
+    pub dwarf: Option<PathBuf>,
+
+    /// linker map file (GNU ld, lld or mold) of the first binary, to attribute symbols to object files
+    #[arg(long)]
+    pub map: Option<PathBuf>,
+
+    /// Cargo.toml of the workspace that built the first binary, to attribute symbols to crates
+    #[arg(long)]
+    pub manifest_path: Option<PathBuf>,
+
+    /// remap source path prefix recorded in DWARF, such as `/build/src=./src`
+    #[arg(long, value_name = "FROM=TO", value_parser = parse_source_map)]
+    pub source_map: Vec<(PathBuf, PathBuf)>,
+
+    /// also accept clients of `--connect` on this address,
+    /// authenticated by the token printed at start, but not encrypted
+    #[arg(long, value_name = "ADDR:PORT")]
+    pub tcp: Option<SocketAddr>,
+
+    /// exit after no client connected for this long, such as `30m`, `2h` or `1d`
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub idle_timeout: Option<Duration>,
+
+    /// most commands to run at once, others wait for a slot
+    #[arg(long, value_name = "N")]
+    pub max_concurrent: Option<NonZeroUsize>,
+
+    /// stop commands running for longer than this, such as `30s` or `5m`
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub request_timeout: Option<Duration>,
+
+    /// evict decompressed section data, least recently used first, once
+    /// the caches of all binaries hold more than this, such as `512M`
+    ///
+    /// Only section data is evicted. Decompressed DWARF sections count against the limit
+    /// but are kept, and the parsed DWARF of line and unwind lookups is not counted.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_cache_memory: Option<u64>,
+
+    /// reopen the binaries when they are rebuilt, dropping all caches
+    #[arg(long)]
+    pub watch: bool,
+
+    /// do not build demangled names and the symbol name index in the background,
+    /// searches build them on first use instead
+    #[arg(long)]
+    pub no_index: bool,
+
+    /// build symbol indexes in the background right after start and reload
+    #[arg(long)]
+    pub prewarm: bool,
+
+    /// also load the addr2line context of DWARF in the background, implies `--prewarm`
+    #[arg(long)]
+    pub prewarm_dwarf: bool,
+
+    /// detach into the background once listening, logging into `logs/<session>.log` of the cache dir
+    #[arg(long)]
+    pub daemon: bool,
+
+    /// least severe messages to log, `debug` also logs the options of each request
+    #[arg(long, value_enum, default_value_t)]
+    pub log_level: Level,
+
+    /// append the log to this file instead of stdout,
+    /// by default the session log of `--daemon`
+    #[arg(long, value_name = "PATH", num_args = 0..=1)]
+    pub log_file: Option<Option<PathBuf>>,
 }
 
 impl Command {
@@ -31,46 +116,296 @@ impl Command {
         let ipc_path = if let Some(ipc_path) = env::var_os(SESSION_ENVNAME) {
             PathBuf::from(ipc_path)
         } else {
-            let dir = dir.runtime_dir()
-                .unwrap_or_else(|| dir.cache_dir());
-
-            fs::create_dir_all(dir)
-                .or_else(|err| match err.kind() {
-                    io::ErrorKind::AlreadyExists => Ok(()),
-                    _ => Err(err)
-                })?;
+            let dir = sessions::dir(dir);
+
+            sessions::create_dir(dir)?;
             let cwd = env::current_dir()?;
 
             let path = format!(
                 "{}-{}",
                 hashpath(&cwd),
-                hashname(&self.path)
+                hashname(&self.paths[0])
             );
 
             dir.join(path)
         };
 
-        let explorer = Explorer::open(self.path, self.dwarf_path)?;
+        // known to the parent of `--daemon`, which prints it
+        let token = server::token()?;
+        let session_log = sessions::log_path(dir, &ipc_path);
+        let mut ready = self.daemon
+            .then(|| daemonize(&ipc_path, &session_log, &token))
+            .transpose()?;
+        let info = sessions::Info::new(&self.paths)?;
+        let debuginfod_dir = dir.cache_dir().join("debuginfod");
+        let log_path = self.log_file.as_ref()
+            .map(|path| path.clone().unwrap_or_else(|| session_log.clone()));
+        let log = Arc::new(Logger::new(self.log_level, log_path.as_deref())?);
+        let explorers = self.open_all(&debuginfod_dir, &log)?;
+        let this = Arc::new(self);
 
         let rt = tokio::runtime::Builder::new_multi_thread()
             .enable_all()
             .build()?;
 
         rt.block_on(async move {
-            let server = Server::new(&ipc_path, explorer).await?;
+            let server = Server::new(&ipc_path, Arc::clone(&this), debuginfod_dir, log, explorers, token).await?;
 
             scopeguard::defer!{
                 fs::remove_file(&ipc_path).unwrap();
+                sessions::Info::remove(&ipc_path);
+            }
+
+            info.write(&ipc_path)?;
+
+            if let Some(ready) = ready.as_mut() {
+                fs::write(sessions::pid_path(&ipc_path), format!("{}\n", std::process::id()))?;
+                // the address of `--tcp`, bound to a port that may have been picked by the system
+                match server.tcp_addr() {
+                    Some(addr) => write!(ready, "ready {}", addr)?,
+                    None => ready.write_all(b"ready")?
+                }
             }
 
+            drop(ready);
+
             println!("set -x {} {}", SESSION_ENVNAME, ipc_path.display());
 
+            if let Some(addr) = server.tcp_addr() {
+                println!("set -x {} {}", TOKEN_ENVNAME, server.token());
+                println!("fi --connect {}", addr);
+            }
+
+            if !this.no_index {
+                server.build_index();
+            }
+
+            if this.prewarm || this.prewarm_dwarf {
+                server.prewarm();
+            }
+
             tokio::select!{
                 ret = tokio::signal::ctrl_c() => ret?,
-                ret = server.listen() => ret?
+                ret = server.listen() => ret?,
+                ret = watch(&server, &this.paths), if this.watch => ret?,
+                _ = server.idle(this.idle_timeout) => println!("idle timeout, exit"),
+                _ = server.shutdown() => {
+                    // no longer accepting, the running commands finish
+                    tokio::select!{
+                        ret = tokio::signal::ctrl_c() => ret?,
+                        _ = server.drain() => ()
+                    }
+                    println!("shutdown by client, exit")
+                }
             }
 
             Ok(())
         })
     }
+
+    fn open_all(&self, debuginfod_dir: &Path, log: &Arc<Logger>) -> anyhow::Result<Vec<Explorer>> {
+        let mut explorers = Vec::new();
+
+        for (i, path) in self.paths.iter().enumerate() {
+            // options of the first binary
+            let first = i == 0;
+            let explorer = Explorer::open(
+                path.clone(),
+                self.dwarf_path.clone().filter(|_| first),
+                self.map.clone().filter(|_| first),
+                self.manifest_path.clone().filter(|_| first),
+                self.source_map.clone()
+            )?;
+            let mut explorer = open(explorer, self.dwarf.clone().filter(|_| first), debuginfod_dir)?;
+            explorer.cache.log = Some(Arc::clone(log));
+
+            // also bound a single binary while a command runs, others are evicted after it
+            if let Some(max) = self.max_cache_memory {
+                explorer.cache.data.set_limit(max.try_into().unwrap_or(usize::MAX));
+            }
+
+            explorers.push(explorer);
+        }
+
+        Ok(explorers)
+    }
+}
+
+/// Detach from the terminal by forking twice, the returned pipe tells the
+/// waiting parent that the session is listening
+///
+/// Only the daemon returns, with its output redirected to the session log.
+/// The parent prints the `FI_SESSION` line, and with `--tcp` the `FI_TOKEN`
+/// and `--connect` lines, and exits once ready.
+fn daemonize(ipc_path: &Path, log_path: &Path, token: &str) -> anyhow::Result<PipeWriter> {
+    let log = sessions::create_log(log_path)?;
+    let null = fs::File::open("/dev/null")?;
+    let (mut rx, tx) = io::pipe()?;
+
+    match unsafe { libc::fork() } {
+        -1 => return Err(io::Error::last_os_error().into()),
+        0 => (),
+        pid => {
+            drop(tx);
+            unsafe {
+                libc::waitpid(pid, std::ptr::null_mut(), 0);
+            }
+
+            let mut buf = String::new();
+            let _ = rx.read_to_string(&mut buf);
+            let Some(tcp) = buf.strip_prefix("ready")
+                else { anyhow::bail!("session failed, see {}", log_path.display()) };
+
+            println!("set -x {} {}", SESSION_ENVNAME, ipc_path.display());
+
+            if let Some(addr) = tcp.strip_prefix(' ') {
+                println!("set -x {} {}", TOKEN_ENVNAME, token);
+                println!("fi --connect {}", addr);
+            }
+
+            std::process::exit(0);
+        }
+    }
+
+    drop(rx);
+
+    unsafe {
+        libc::setsid();
+
+        // the session leader exits, the daemon can not reacquire a terminal
+        match libc::fork() {
+            -1 => libc::_exit(1),
+            0 => (),
+            _ => libc::_exit(0)
+        }
+
+        libc::dup2(null.as_raw_fd(), libc::STDIN_FILENO);
+        libc::dup2(log.as_raw_fd(), libc::STDOUT_FILENO);
+        libc::dup2(log.as_raw_fd(), libc::STDERR_FILENO);
+    }
+
+    Ok(tx)
+}
+
+/// Modified time and size of each binary, None while being rewritten
+fn stamps(paths: &[PathBuf]) -> Vec<Option<(SystemTime, u64)>> {
+    paths.iter()
+        .map(|path| {
+            let metadata = fs::metadata(path).ok()?;
+            Some((metadata.modified().ok()?, metadata.len()))
+        })
+        .collect()
+}
+
+/// Poll the binaries and reload the server once they changed and settled
+async fn watch(server: &Server, paths: &[PathBuf]) -> anyhow::Result<()> {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    let mut loaded = stamps(paths);
+    let mut last = loaded.clone();
+
+    loop {
+        interval.tick().await;
+
+        let now = stamps(paths);
+
+        // wait one more tick for the linker to finish writing
+        if now != last || now == loaded || now.iter().any(Option::is_none) {
+            last = now;
+            continue
+        }
+
+        loaded = now;
+
+        match server.reload().await {
+            Ok(id) => println!("reloaded, generation {}", id),
+            Err(err) => eprintln!("reload failed: {:?}", err)
+        }
+    }
+}
+
+/// Find the debug file and dwz supplementary file of a binary
+fn open(mut explorer: Explorer, dwarf: Option<PathBuf>, debuginfod_dir: &Path) -> anyhow::Result<Explorer> {
+    explorer.debuginfod = Debuginfod::new(&explorer.obj, debuginfod_dir).map(Arc::new);
+
+    if let Some(path) = dwarf {
+        explorer.debug_file = Some(DebugFile::open(path, &explorer.cache.arena)?);
+    } else if !debuginfo::has_dwarf(&explorer.obj) {
+        let path = match debuginfo::locate(&explorer.path, &explorer.obj) {
+            Some(path) => Some(Ok(path)),
+            None => explorer.debuginfod.as_ref().map(|client| client.debuginfo())
+        };
+
+        match path.map(|path| path.and_then(|path| DebugFile::open(path, &explorer.cache.arena))) {
+            Some(Ok(file)) => explorer.debug_file = Some(file),
+            Some(Err(err)) => eprintln!("debug file failed: {:?}", err),
+            None => ()
+        }
+    }
+
+    if explorer.dwarf_path.is_none()
+        && let Some((path, id)) = debuginfo::find_altlink(explorer.dwarf_file(), explorer.dwarf_obj())
+    {
+        let path = match path {
+            Some(path) => Some(Ok(path)),
+            None => explorer.debuginfod.as_ref().map(|client| client.altlink(&id))
+        };
+
+        match path {
+            Some(Ok(path)) => explorer.dwarf_path = Some(path),
+            Some(Err(err)) => eprintln!("debugaltlink failed: {:?}", err),
+            None => eprintln!("debugaltlink not found: {}", id)
+        }
+    }
+
+    Ok(explorer)
+}
+
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (n, unit) = value.split_at(split);
+    let n: u64 = n.parse().map_err(|_| format!("expected number with unit: {}", value))?;
+    let scale = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => return Err(format!("unknown unit, expected s, m, h or d: {}", value))
+    };
+    let secs = n.checked_mul(scale)
+        .ok_or_else(|| format!("duration is too large: {}", value))?;
+    Ok(Duration::from_secs(secs))
+}
+
+fn parse_source_map(value: &str) -> Result<(PathBuf, PathBuf), String> {
+    let (from, to) = value.split_once('=')
+        .ok_or_else(|| format!("expected FROM=TO: {}", value))?;
+    Ok((PathBuf::from(from), PathBuf::from(to)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_units() {
+        assert_eq!(parse_duration("30").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(5 * 60));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(2 * 60 * 60));
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(24 * 60 * 60));
+        assert_eq!(parse_duration("0").unwrap(), Duration::ZERO);
+    }
+
+    #[test]
+    fn duration_errors() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5w").is_err());
+        assert!(parse_duration("5 m").is_err());
+        assert!(parse_duration("-5m").is_err());
+        assert!(parse_duration("99999999999999999999").is_err());
+
+        let err = parse_duration("999999999999999999d").unwrap_err();
+        assert!(err.contains("too large"), "{}", err);
+    }
 }
@@ -0,0 +1,119 @@
+use std::fmt;
+use std::fs::{ self, File };
+use std::io::{ self, Write };
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+use clap::ValueEnum;
+use serde::{ Serialize, Deserialize };
+
+
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Level {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    /// also the full options of each request
+    Debug
+}
+
+/// Log of the session as `key=value` lines, into a file or stdout and stderr
+pub struct Logger {
+    level: Level,
+    file: Option<Mutex<File>>
+}
+
+impl Level {
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug"
+        }
+    }
+}
+
+/// Value quoted if it would not read back as one field
+struct Value<'a>(&'a dyn fmt::Display);
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.0.to_string();
+
+        if value.is_empty() || value.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '=')) {
+            write!(f, "{:?}", value)
+        } else {
+            f.write_str(&value)
+        }
+    }
+}
+
+impl Logger {
+    pub fn new(level: Level, path: Option<&Path>) -> anyhow::Result<Logger> {
+        let file = match path {
+            Some(path) => {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+
+                let file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+                Some(Mutex::new(file))
+            },
+            None => None
+        };
+
+        Ok(Logger { level, file })
+    }
+
+    pub fn enabled(&self, level: Level) -> bool {
+        level <= self.level
+    }
+
+    /// Write one line of `event` and its fields, such as
+    /// `ts=1700000000.123 level=info event=request command=show`
+    pub fn log(&self, level: Level, event: &str, fields: &[(&str, &dyn fmt::Display)]) {
+        use std::fmt::Write as _;
+
+        if !self.enabled(level) {
+            return
+        }
+
+        let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut line = format!(
+            "ts={}.{:03} level={} event={}",
+            ts.as_secs(),
+            ts.subsec_millis(),
+            level.as_str(),
+            event
+        );
+
+        for (key, value) in fields {
+            let _ = write!(line, " {}={}", key, Value(*value));
+        }
+
+        line.push('\n');
+
+        // failing to log does not fail the request
+        let _ = match &self.file {
+            Some(file) => file.lock().unwrap().write_all(line.as_bytes()),
+            None if level <= Level::Warn => io::stderr().write_all(line.as_bytes()),
+            None => io::stdout().write_all(line.as_bytes())
+        };
+    }
+
+    pub fn info(&self, event: &str, fields: &[(&str, &dyn fmt::Display)]) {
+        self.log(Level::Info, event, fields)
+    }
+
+    pub fn warn(&self, event: &str, fields: &[(&str, &dyn fmt::Display)]) {
+        self.log(Level::Warn, event, fields)
+    }
+
+    pub fn error(&self, event: &str, fields: &[(&str, &dyn fmt::Display)]) {
+        self.log(Level::Error, event, fields)
+    }
+}
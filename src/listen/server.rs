@@ -1,90 +1,929 @@
-use std::fs::File;
-use std::io::Write;
-use std::sync::Arc;
-use std::path::Path;
-use std::os::fd::FromRawFd;
-use tokio::io::{ self, AsyncReadExt, AsyncWriteExt };
-use tokio::net::{ UnixListener, UnixStream };
-use crate::call::{ Start, Exit, ExitCode };
+use std::fmt;
+use std::fs::{ self, File };
+use std::io::{ Read, Write, PipeReader };
+use std::sync::{ Arc, Mutex, RwLock };
+use std::sync::atomic::{ AtomicUsize, AtomicU64, Ordering };
+use std::time::{ Duration, Instant };
+use std::path::{ Path, PathBuf };
+use std::net::SocketAddr;
+use std::os::fd::{ AsFd, AsRawFd, FromRawFd, OwnedFd };
+use std::os::unix::fs::PermissionsExt;
+use anyhow::Context;
+use object::Object;
+use indexmap::IndexMap;
+use tokio::io::{ self, AsyncRead, AsyncWrite, AsyncReadExt, AsyncWriteExt };
+use tokio::net::{ UnixListener, UnixStream, TcpListener, TcpStream };
+use tokio::sync::{ mpsc, Notify, Semaphore };
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use crate::call::{
+    PROTOCOL_VERSION, PROBE, FEATURES, FEATURE_FDS, FEATURE_FRAMES, FEATURE_HEARTBEAT, HEARTBEAT_INTERVAL,
+    Hello, Welcome, Start, Control, Exit, ExitCode,
+    FRAME_STDOUT, FRAME_STDERR, FRAME_EXIT, MAX_FRAME
+};
+use crate::Commands;
 use crate::explorer::Explorer;
-use crate::util::{ Stdio, recv_fd };
+use crate::status;
+use super::Command;
+use super::logger::{ Logger, Level };
+use crate::util::{ Stdio, Format, CountWriter, MaybePrinter, recv_fd };
+use crate::template::Template;
 
 
+/// Output chunks of a tcp request waiting for the client, each up to 64 KiB
+const FRAMES_QUEUED: usize = 16;
+
+/// Time for a tcp client to complete the handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Explorers of the session by file name, or by full path if file names collide
+type Explorers = IndexMap<String, Explorer>;
+
+/// Explorers opened together, replaced as a whole when the binaries are reloaded
+struct Generation {
+    id: u64,
+    explorers: Explorers
+}
+
+/// State shared by the server and the connections being served
+struct State {
+    current: RwLock<Arc<Generation>>,
+    activity: Arc<Activity>,
+    /// notified by `fi shutdown`
+    shutdown: Notify,
+    /// slots of commands running at once
+    limit: Option<Semaphore>,
+    /// options to reopen the binaries with
+    cmd: Arc<Command>,
+    /// required from tcp clients
+    token: String,
+    log: Arc<Logger>,
+    debuginfod_dir: PathBuf
+}
+
+/// Connections being served and when the last one ended
+struct Activity {
+    started: Instant,
+    active: AtomicUsize,
+    /// connections accepted since start
+    served: AtomicU64,
+    last: Mutex<Instant>,
+    /// notified when the last active connection ends
+    drained: Notify
+}
+
+/// A connection being served, counted until dropped
+struct Active(Arc<Activity>);
+
+impl Active {
+    fn new(activity: &Arc<Activity>) -> Active {
+        activity.active.fetch_add(1, Ordering::Relaxed);
+        activity.served.fetch_add(1, Ordering::Relaxed);
+        Active(Arc::clone(activity))
+    }
+}
+
+impl Drop for Active {
+    fn drop(&mut self) {
+        *self.0.last.lock().unwrap() = Instant::now();
+        if self.0.active.fetch_sub(1, Ordering::Relaxed) == 1 {
+            self.0.drained.notify_waiters();
+        }
+    }
+}
+
 pub struct Server {
-    explorer: Arc<Explorer>,
-    listener: UnixListener
+    state: Arc<State>,
+    listener: UnixListener,
+    tcp: Option<TcpListener>
 }
 
 impl Server {
-    pub async fn new(ipc_path: &Path, explorer: Explorer)
-        -> anyhow::Result<Self>
-    {
-        let explorer = Arc::new(explorer);
+    pub async fn new(
+        ipc_path: &Path,
+        cmd: Arc<Command>,
+        debuginfod_dir: PathBuf,
+        log: Arc<Logger>,
+        explorers: Vec<Explorer>,
+        token: String
+    ) -> anyhow::Result<Self> {
+        let generation = Generation { id: 0, explorers: index(explorers) };
         let listener = UnixListener::bind(ipc_path)?;
-        Ok(Server { explorer, listener })
+        // only the owner may connect, the session dir is private too
+        fs::set_permissions(ipc_path, fs::Permissions::from_mode(0o600))?;
+        let tcp = match cmd.tcp {
+            Some(addr) => Some(TcpListener::bind(addr).await?),
+            None => None
+        };
+        let activity = Arc::new(Activity {
+            started: Instant::now(),
+            active: AtomicUsize::new(0),
+            served: AtomicU64::new(0),
+            last: Mutex::new(Instant::now()),
+            drained: Notify::new()
+        });
+        let state = Arc::new(State {
+            current: RwLock::new(Arc::new(generation)),
+            activity,
+            shutdown: Notify::new(),
+            limit: cmd.max_concurrent.map(|n| Semaphore::new(n.get())),
+            cmd, log, debuginfod_dir, token
+        });
+        Ok(Server { state, listener, tcp })
+    }
+
+    pub fn tcp_addr(&self) -> Option<SocketAddr> {
+        self.tcp.as_ref()?.local_addr().ok()
+    }
+
+    pub fn token(&self) -> &str {
+        &self.state.token
+    }
+
+    /// Reopen the binaries, see `reload`
+    pub async fn reload(&self) -> anyhow::Result<u64> {
+        reload(&self.state).await
+    }
+
+    /// Build the name indexes in the background, see `build_index`
+    pub fn build_index(&self) {
+        build_index(&self.state)
+    }
+
+    /// Build the caches of `--prewarm` in the background, see `prewarm`
+    pub fn prewarm(&self) {
+        prewarm(&self.state)
+    }
+
+    /// Return once no client has been connected for `timeout`, never without one
+    pub async fn idle(&self, timeout: Option<Duration>) {
+        let Some(timeout) = timeout
+            else { return std::future::pending().await };
+
+        loop {
+            let elapsed = match self.state.activity.active.load(Ordering::Relaxed) {
+                0 => self.state.activity.last.lock().unwrap().elapsed(),
+                _ => Duration::ZERO
+            };
+
+            match timeout.checked_sub(elapsed) {
+                Some(rest) if !rest.is_zero() => tokio::time::sleep(rest).await,
+                _ => return
+            }
+        }
+    }
+
+    /// Return once `fi shutdown` was served, stop `listen` then wait for `drain`
+    pub async fn shutdown(&self) {
+        self.state.shutdown.notified().await;
+    }
+
+    /// Return once no connection is active
+    pub async fn drain(&self) {
+        loop {
+            let drained = self.state.activity.drained.notified();
+            tokio::pin!(drained);
+            // registered before checking, not to miss the last connection ending in between
+            drained.as_mut().enable();
+
+            if self.state.activity.active.load(Ordering::Relaxed) == 0 {
+                return
+            }
+
+            drained.await;
+        }
     }
 
     pub async fn listen(&self) -> anyhow::Result<()> {
         loop {
-            let (stream, _) = self.listener.accept().await?;
-            let explorer = Arc::clone(&self.explorer);
-            tokio::spawn(async move {
-                if let Err(err) = exec(&explorer, stream).await {
-                    eprintln!("ipc error: {:?}", err);
+            let state = Arc::clone(&self.state);
+
+            tokio::select!{
+                ret = self.listener.accept() => {
+                    let (stream, _) = ret?;
+                    tokio::spawn(async move {
+                        if let Err(err) = exec(&state, stream).await {
+                            state.log.error("ipc error", &[("error", &format_args!("{:#}", err))]);
+                        }
+                    });
+                },
+                Some(ret) = async { Some(self.tcp.as_ref()?.accept().await) } => {
+                    let (stream, _) = ret?;
+                    tokio::spawn(async move {
+                        if let Err(err) = exec_tcp(&state, stream).await {
+                            state.log.error("tcp error", &[("error", &format_args!("{:#}", err))]);
+                        }
+                    });
                 }
-            });
+            }
         }
     }
 }
 
-async fn exec(
-    explorer: &Explorer,
-    mut stream: UnixStream,
-) -> anyhow::Result<()> {
+/// Serve new commands from reopened binaries, running commands keep the previous ones,
+/// and build the indexes and prewarm the new caches as at start
+async fn reload(state: &Arc<State>) -> anyhow::Result<u64> {
+    let open = {
+        let state = Arc::clone(state);
+        move || state.cmd.open_all(&state.debuginfod_dir, &state.log)
+    };
+    let explorers = tokio::task::spawn_blocking(open).await??;
+
+    let id = {
+        let mut current = state.current.write().unwrap();
+        let id = current.id + 1;
+        *current = Arc::new(Generation { id, explorers: index(explorers) });
+        id
+    };
+
+    if !state.cmd.no_index {
+        build_index(state);
+    }
+
+    if state.cmd.prewarm || state.cmd.prewarm_dwarf {
+        prewarm(state);
+    }
+
+    Ok(id)
+}
+
+/// Build the demangled names and name indexes of each binary in background tasks,
+/// commands needing them wait for the build in progress instead of starting over
+fn build_index(state: &Arc<State>) {
+    let generation = Arc::clone(&state.current.read().unwrap());
+
+    for idx in 0..generation.explorers.len() {
+        let generation = Arc::clone(&generation);
+        let state = Arc::clone(state);
+
+        tokio::spawn(async move {
+            let (name, explorer) = generation.explorers.get_index(idx).unwrap();
+            let start = Instant::now();
+
+            explorer.cache.demangled(&explorer.obj).await;
+            explorer.cache.names(&explorer.obj, false).await;
+            explorer.cache.names(&explorer.obj, true).await;
+
+            state.log.info("index", &[
+                ("bin", name),
+                ("duration", &format_args!("{:.3}s", start.elapsed().as_secs_f64()))
+            ]);
+        });
+    }
+}
+
+/// Build the symbol indexes of each binary in background tasks,
+/// and with `--prewarm-dwarf` the addr2line context, logging the time of each
+fn prewarm(state: &Arc<State>) {
+    let generation = Arc::clone(&state.current.read().unwrap());
+    let dwarf = state.cmd.prewarm_dwarf;
+
+    for idx in 0..generation.explorers.len() {
+        let generation = Arc::clone(&generation);
+        let state = Arc::clone(state);
+
+        tokio::spawn(async move {
+            let (name, explorer) = generation.explorers.get_index(idx).unwrap();
+            let log = |step: &str, count: Option<usize>, start: Instant| state.log.info("prewarm", &[
+                ("bin", name),
+                ("step", &step),
+                ("count", &MaybePrinter(count, Some('-'))),
+                ("duration", &format_args!("{:.3}s", start.elapsed().as_secs_f64()))
+            ]);
+
+            let start = Instant::now();
+            let symlist = explorer.cache.symlist(&explorer.obj).await;
+            log("symlist", Some(symlist.len()), start);
+
+            let start = Instant::now();
+            explorer.cache.addr2sym(&explorer.obj).await;
+            log("addr2sym", None, start);
+
+            let start = Instant::now();
+            let dyn_rela = explorer.cache.dyn_rela(&explorer.obj).await;
+            log("dyn_rela", Some(dyn_rela.len()), start);
+
+            if dwarf {
+                let start = Instant::now();
+                let result = async {
+                    let loader = explorer.cache.addr2line(
+                        explorer.dwarf_file(),
+                        explorer.dwarf_path.as_deref()
+                    ).await?;
+                    // the first lookup parses the unit ranges
+                    loader.find_location(explorer.obj.entry())
+                        .map_err(|err| anyhow::format_err!("addr2line: {:?}", err))?;
+                    anyhow::Ok(())
+                }.await;
+
+                match result {
+                    Ok(()) => log("addr2line", None, start),
+                    Err(err) => state.log.error("prewarm", &[
+                        ("bin", name),
+                        ("error", &format_args!("{:#}", err))
+                    ])
+                }
+            }
+        });
+    }
+}
+
+/// Random token of the session, new on each start
+pub fn token() -> anyhow::Result<String> {
+    let mut buf = [0; 16];
+    File::open("/dev/urandom")?.read_exact(&mut buf)?;
+    Ok(data_encoding::HEXLOWER.encode(&buf))
+}
+
+/// Evict section data least recently used first,
+/// until the caches of all binaries hold at most `max` bytes
+async fn evict(explorers: &Explorers, max: usize, log: &Logger) {
+    let mut total: usize = explorers.values()
+        .map(|explorer| explorer.cache.memory())
+        .sum();
+
+    if total <= max {
+        return
+    }
+
+    let mut owned = Vec::new();
+
+    for explorer in explorers.values() {
+        for (used, idx, size) in explorer.cache.data.owned().await {
+            owned.push((used, explorer, idx, size));
+        }
+    }
+
+    owned.sort_by_key(|&(used, ..)| used);
+
+    let mut evicted = 0;
+
+    for (_, explorer, idx, size) in owned {
+        if total <= max {
+            break
+        }
+
+        explorer.cache.data.evict(idx).await;
+        total -= size;
+        evicted += size;
+    }
+
+    // other caches are kept, `total` may stay above `max`
+    if evicted != 0 {
+        log.info("evict", &[("bytes", &evicted), ("total", &total), ("max", &max)]);
+    }
+}
+
+fn index(explorers: Vec<Explorer>) -> Explorers {
+    let mut map = IndexMap::new();
+
+    for explorer in explorers {
+        let name = explorer.path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .filter(|name| !map.contains_key(name))
+            .unwrap_or_else(|| explorer.path.display().to_string());
+        map.insert(name, explorer);
+    }
+
+    map
+}
+
+/// Explorer named by `--bin`, the first one by default
+fn select<'a>(explorers: &'a Explorers, bin: Option<&str>) -> anyhow::Result<&'a Explorer> {
+    match bin {
+        Some(bin) => explorers.get(bin)
+            .or_else(|| explorers.values().find(|explorer| explorer.path.as_os_str() == bin
+                || fs::canonicalize(&explorer.path).is_ok_and(|path| path.as_os_str() == bin)
+            ))
+            .with_context(|| format!(
+                "not found binary: {}, expected one of: {}",
+                bin,
+                explorers.keys().map(String::as_str).collect::<Vec<_>>().join(", ")
+            )),
+        None => explorers.values().next().context("no binary opened")
+    }
+}
+
+/// Read a message of u16 le length and cbor
+async fn read_message<R: AsyncRead + Unpin, T: DeserializeOwned>(stream: &mut R) -> anyhow::Result<T> {
     let len = stream.read_u16_le().await?;
     let mut buf = vec![0; len.into()];
     stream.read_exact(&mut buf).await?;
 
-    let start: Start = cbor4ii::serde::from_slice(&buf)?;
-    let pid = stream.peer_cred()?.pid();
+    Ok(cbor4ii::serde::from_slice(&buf)?)
+}
+
+async fn write_message<W: AsyncWrite + Unpin, T: Serialize>(stream: &mut W, message: &T) -> anyhow::Result<()> {
+    let buf = cbor4ii::serde::to_vec(Vec::new(), message)?;
+    let len: u16 = buf.len().try_into()?;
+
+    stream.write_all(&len.to_le_bytes()).await?;
+    stream.write_all(&buf).await?;
+    stream.flush().await?;
+
+    Ok(())
+}
+
+/// Whether the peer is the liveness probe of `fi sessions`, peeking without consuming:
+/// it sends `PROBE`, probes of older builds close without a word
+async fn is_probe(stream: &UnixStream) -> io::Result<bool> {
+    use std::os::fd::AsRawFd;
+    use tokio::io::Interest;
+
+    stream.async_io(Interest::READABLE, || {
+        let mut buf = [0; 2];
+        let ret = unsafe {
+            libc::recv(stream.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), libc::MSG_PEEK | libc::MSG_DONTWAIT)
+        };
+
+        match ret {
+            0 => Ok(true),
+            // wait for the whole length
+            1 => Err(io::ErrorKind::WouldBlock.into()),
+            2 => Ok(buf == PROBE),
+            _ => Err(io::Error::last_os_error())
+        }
+    }).await
+}
+
+/// Answer the `Hello` of a client, rejecting other protocol versions
+/// and clients without the `required` transport
+async fn handshake<S>(stream: &mut S, required: &str, token: Option<&str>) -> anyhow::Result<Vec<String>>
+where
+    S: AsyncRead + AsyncWrite + Unpin
+{
+    // clients before the handshake start with `Start`
+    let hello = read_message::<_, Hello>(stream).await.ok();
+    let reject = match &hello {
+        None => Some("expected hello, the client is older than the session".to_owned()),
+        Some(hello) if hello.version != PROTOCOL_VERSION => Some(format!(
+            "client has protocol version {}", hello.version
+        )),
+        Some(hello) if !hello.features.iter().any(|feature| feature == required) => Some(format!(
+            "client does not support {}", required
+        )),
+        Some(hello) if !authorized(hello, token) => Some(
+            "invalid token, see `FI_TOKEN` printed by the session".to_owned()
+        ),
+        Some(_) => None
+    };
+
+    if let Some(reason) = reject {
+        let welcome = Welcome::Reject { version: PROTOCOL_VERSION, reason: reason.clone() };
+        write_message(stream, &welcome).await?;
+        anyhow::bail!("handshake rejected: {}", reason);
+    }
+
+    let features = hello.into_iter()
+        .flat_map(|hello| hello.features)
+        .filter(|feature| FEATURES.contains(&feature.as_str()))
+        .collect::<Vec<_>>();
+    write_message(stream, &Welcome::Accept { features: features.clone() }).await?;
+
+    Ok(features)
+}
+
+fn has_heartbeat(features: &[String]) -> bool {
+    features.iter().any(|feature| feature == FEATURE_HEARTBEAT)
+}
+
+/// Whether the client sent the `token` of the session, if one is required
+fn authorized(hello: &Hello, token: Option<&str>) -> bool {
+    let Some(token) = token
+        else { return true };
+    let Some(given) = hello.token.as_deref()
+        else { return false };
+
+    // compare without returning early, not to leak how much matched
+    given.len() == token.len()
+        && given.bytes().zip(token.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn stdio(start: &Start, stdin: File, stdout: File, stderr: File) -> Stdio {
+    Stdio {
+        colored: start.colored,
+        hyperlink: start.hyperlink,
+        format: start.options.format,
+        records: 0,
+        header: Vec::new(),
+        template: None,
+        sarif: Default::default(),
+        stdout: CountWriter::new(stdout),
+        cancel: Default::default(),
+        stdin, stderr
+    }
+}
+
+/// Run the command of `start`, errors of the command go to the client's stderr
+async fn run(state: &Arc<State>, start: Start, stdio: &mut Stdio) -> anyhow::Result<ExitCode> {
+    let rule = start.options.command.rule();
+    let reload_cmd = matches!(start.options.command, Commands::Reload(_));
+    let generation = Arc::clone(&state.current.read().unwrap());
+
+    let result = async {
+        anyhow::ensure!(
+            start.options.format != Format::Sarif || start.options.command.has_findings(),
+            "--format sarif is only for checksec and search --pattern"
+        );
+        anyhow::ensure!(
+            start.options.format != Format::Csv || !start.options.command.has_mixed_records(),
+            "--format csv needs records of one kind, show --all mixes code and data, use --format json"
+        );
+
+        // commands of the session itself
+        match start.options.command {
+            Commands::Status(cmd) => {
+                let session = status::Session {
+                    uptime: state.activity.started.elapsed(),
+                    served: state.activity.served.load(Ordering::Relaxed),
+                    active: state.activity.active.load(Ordering::Relaxed),
+                    generation: generation.id,
+                    max_cache_memory: state.cmd.max_cache_memory,
+                    explorers: &generation.explorers
+                };
+                return cmd.exec(&session, stdio).await;
+            },
+            Commands::Shutdown(cmd) => return cmd.exec(&state.shutdown, stdio).await,
+            Commands::Reload(cmd) => {
+                let id = reload(state).await?;
+                state.log.info("reload", &[("generation", &id)]);
+                return cmd.exec(id, stdio).await;
+            },
+            _ => ()
+        }
+
+        // waits for a slot of `--max-concurrent`
+        let _permit = match &state.limit {
+            Some(limit) => Some(limit.acquire().await?),
+            None => None
+        };
+
+        let explorer = select(&generation.explorers, start.options.bin.as_deref())?;
+        stdio.template = start.options.format_str.as_deref()
+            .map(Template::parse)
+            .transpose()?;
+
+        match start.options.command {
+            Commands::Similar(cmd) => {
+                let other = select(&generation.explorers, Some(&cmd.to))?;
+                cmd.exec(explorer, other, stdio).await?;
+            },
+            Commands::DiffSymbol(cmd) => {
+                let other = select(&generation.explorers, Some(&cmd.to))?;
+                cmd.exec(explorer, other, stdio).await?;
+            },
+            command => command.exec(explorer, stdio).await?
+        }
+
+        stdio.finish(&explorer.path, &rule)
+    }.await;
+
+    if let Some(max) = state.cmd.max_cache_memory {
+        evict(&generation.explorers, max.try_into().unwrap_or(usize::MAX), &state.log).await;
+    }
+
+    let code = match result {
+        Ok(()) => ExitCode::Ok,
+        // pager quit early
+        Err(err) if err.downcast_ref::<io::Error>()
+            .is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe) => ExitCode::Ok,
+        // stopped by `Cancel`, the outcome comes from `watch`
+        Err(_) if stdio.cancel.is_cancelled() => ExitCode::Failure,
+        Err(err) => {
+            writeln!(stdio.stderr, "exec failed: {:?}", err)?;
+            ExitCode::Failure
+        }
+    };
 
-    println!("{:?} {:?}", pid, &start.options);
+    let id = state.current.read().unwrap().id;
+
+    if id != generation.id && !reload_cmd {
+        writeln!(
+            stdio.stderr,
+            "note: session reloaded during the command, output is of the previous build (generation {})",
+            generation.id
+        )?;
+    }
+
+    Ok(code)
+}
+
+/// How a request ended
+enum Outcome {
+    Exit(ExitCode),
+    /// the client sent `Cancel`
+    Cancelled,
+    Disconnected,
+    /// the client stopped sending heartbeats
+    Unresponsive,
+    TimedOut
+}
+
+impl Outcome {
+    /// Exit code to send back, None if nobody waits for it
+    fn code(&self) -> Option<ExitCode> {
+        match self {
+            Outcome::Exit(code) => Some(*code),
+            Outcome::Cancelled => Some(ExitCode::Cancelled),
+            Outcome::Disconnected | Outcome::Unresponsive => None,
+            Outcome::TimedOut => Some(ExitCode::Failure)
+        }
+    }
+}
+
+/// Read the `Control` messages of the client until the request should stop:
+/// on `Cancel`, disconnect, missed heartbeats or `timeout`
+async fn watch<R: AsyncRead + Unpin>(mut reader: R, heartbeat: bool, timeout: Option<Duration>) -> Outcome {
+    let timeout = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await
+        }
+    };
+    tokio::pin!(timeout);
+
+    loop {
+        let missed = async {
+            match heartbeat {
+                true => tokio::time::sleep(HEARTBEAT_INTERVAL * 4).await,
+                false => std::future::pending().await
+            }
+        };
+
+        tokio::select! {
+            message = read_message(&mut reader) => match message {
+                Ok(Control::Heartbeat) => (),
+                Ok(Control::Cancel) => return Outcome::Cancelled,
+                // clients without heartbeat only close the stream
+                Err(_) => return Outcome::Disconnected
+            },
+            _ = missed => return Outcome::Unresponsive,
+            _ = &mut timeout => return Outcome::TimedOut
+        }
+    }
+}
+
+/// Run the command until it exits or `watch` stops it
+///
+/// `watch` runs in its own task, so it notices while the command blocks in a
+/// loop without await points; the command is dropped at its next await point,
+/// or returns early from loops checking `Stdio::cancel`.
+async fn serve<R>(state: &Arc<State>, start: Start, stdio: &mut Stdio, reader: R, heartbeat: bool)
+    -> anyhow::Result<Outcome>
+where
+    R: AsyncRead + Unpin + Send + 'static
+{
+    let cancel = stdio.cancel.clone();
+    let timeout = state.cmd.request_timeout;
+    let mut watcher = tokio::spawn(async move {
+        let outcome = watch(reader, heartbeat, timeout).await;
+        cancel.cancel();
+        outcome
+    });
+    // let `watch` start elsewhere, a task spawned last is not stolen by other workers
+    // while this one blocks
+    tokio::task::yield_now().await;
+
+    let code = tokio::select! {
+        code = run(state, start, stdio) => code,
+        outcome = &mut watcher => return stopped(state, outcome?, stdio)
+    };
+
+    // the command saw the cancel and returned early
+    if stdio.cancel.is_cancelled() {
+        return stopped(state, watcher.await?, stdio);
+    }
+
+    watcher.abort();
+    Ok(Outcome::Exit(code?))
+}
+
+/// Outcome of a request stopped by `watch`
+fn stopped(state: &State, outcome: Outcome, stdio: &mut Stdio) -> anyhow::Result<Outcome> {
+    if let (Outcome::TimedOut, Some(timeout)) = (&outcome, state.cmd.request_timeout) {
+        writeln!(stdio.stderr, "exec failed: request timed out after {}s", timeout.as_secs())?;
+    }
+
+    Ok(outcome)
+}
+
+async fn exec(
+    state: &Arc<State>,
+    mut stream: UnixStream,
+) -> anyhow::Result<()> {
+    // the liveness probe of `fi sessions` is not a client, it would reset the idle time
+    if is_probe(&stream).await? {
+        return Ok(());
+    }
+
+    let _active = Active::new(&state.activity);
+    let features = handshake(&mut stream, FEATURE_FDS, None).await?;
+    let start: Start = read_message(&mut stream).await?;
+    let cred = stream.peer_cred()?;
+    let client = vec![
+        ("pid", MaybePrinter(cred.pid(), Some('?')).to_string()),
+        ("uid", cred.uid().to_string())
+    ];
+    let request = Request::start(&state.log, client, &start);
 
     let stdin = recv_fd(&stream).await?;
     let stdout = recv_fd(&stream).await?;
     let stderr = recv_fd(&stream).await?;
     let mut stdio = unsafe {
-        Stdio {
-            colored: start.colored,
-            hyperlink: start.hyperlink,
-            stdin: File::from_raw_fd(stdin),
-            stdout: File::from_raw_fd(stdout),
-            stderr: File::from_raw_fd(stderr)
+        stdio(
+            &start,
+            File::from_raw_fd(stdin),
+            File::from_raw_fd(stdout),
+            File::from_raw_fd(stderr)
+        )
+    };
+
+    let (reader, mut writer) = stream.into_split();
+    let outcome = serve(state, start, &mut stdio, reader, has_heartbeat(&features)).await?;
+
+    request.finish(&outcome, stdio.stdout.count);
+
+    match outcome.code() {
+        Some(code) => write_message(&mut writer, &Exit { code }).await,
+        None => Ok(())
+    }
+}
+
+/// Serve a remote client, which cannot pass its fds
+///
+/// Output is drained from local pipes by blocking threads, so a command
+/// writing synchronously never waits on the network, and sent back as frames
+/// of a tag byte, an u32 le length and the data.
+async fn exec_tcp(
+    state: &Arc<State>,
+    mut stream: TcpStream,
+) -> anyhow::Result<()> {
+    let peer = stream.peer_addr()?;
+    // anyone reaching the port may connect, only an authorized client counts as active
+    let handshake = handshake(&mut stream, FEATURE_FRAMES, Some(&state.token));
+    let features = tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake).await
+        .map_err(|_| anyhow::format_err!("handshake timed out, peer {}", peer))??;
+    let _active = Active::new(&state.activity);
+    let (mut reader, mut writer) = stream.into_split();
+    let start: Start = read_message(&mut reader).await?;
+    let request = Request::start(&state.log, vec![("peer", peer.to_string())], &start);
+
+    let (stdout_rx, stdout_tx) = std::io::pipe()?;
+    let (stderr_rx, stderr_tx) = std::io::pipe()?;
+    let mut stdio = stdio(
+        &start,
+        File::open("/dev/null")?,
+        File::from(OwnedFd::from(stdout_tx)),
+        File::from(OwnedFd::from(stderr_tx))
+    );
+
+    // bounded, a slow client blocks the command on its pipes instead of buffering all output
+    let (tx, mut rx) = mpsc::channel(FRAMES_QUEUED);
+    for (tag, pipe) in [(FRAME_STDOUT, stdout_rx), (FRAME_STDERR, stderr_rx)] {
+        let tx = tx.clone();
+        tokio::task::spawn_blocking(move || drain(pipe, tag, tx));
+    }
+    drop(tx);
+
+    // frames are written off the runtime: a command blocked on its pipes may hold the
+    // only worker, which could then never run an async forwarding task
+    let socket = File::from(writer.as_ref().as_fd().try_clone_to_owned()?);
+    let forward = tokio::task::spawn_blocking(move || {
+        while let Some((tag, data)) = rx.blocking_recv() {
+            write_frame_blocking(&socket, tag, &data)?;
         }
+
+        anyhow::Ok(())
+    });
+
+    let outcome = serve(state, start, &mut stdio, reader, has_heartbeat(&features)).await?;
+    let bytes = stdio.stdout.count;
+
+    // pipes close with stdio, ending the forwarding
+    drop(stdio);
+
+    let Some(code) = outcome.code() else {
+        // wake a forwarding blocked on a client that stopped reading
+        unsafe { libc::shutdown(writer.as_ref().as_raw_fd(), libc::SHUT_RDWR) };
+        request.finish(&Outcome::Disconnected, bytes);
+        return Ok(())
     };
-    let mut sink = io::sink();
 
-    let code = tokio::select! {
-        result = start.options.command.exec(explorer, &mut stdio) => match result {
-            Ok(()) => ExitCode::Ok,
-            Err(err) => {
-                writeln!(stdio.stderr, "exec failed: {:?}", err)?;
-                ExitCode::Failure
+    if forward.await?.is_err() {
+        request.finish(&Outcome::Disconnected, bytes);
+        return Ok(())
+    }
+
+    request.finish(&outcome, bytes);
+
+    let exit = cbor4ii::serde::to_vec(Vec::new(), &Exit { code })?;
+    write_frame(&mut writer, FRAME_EXIT, &exit).await?;
+    writer.flush().await?;
+
+    Ok(())
+}
+
+/// A request being served, logged with its client once finished
+struct Request<'a> {
+    log: &'a Logger,
+    client: Vec<(&'static str, String)>,
+    command: String,
+    bin: Option<String>,
+    start: Instant
+}
+
+impl<'a> Request<'a> {
+    fn start(log: &'a Logger, client: Vec<(&'static str, String)>, start: &Start) -> Request<'a> {
+        if log.enabled(Level::Debug) {
+            let options = format!("{:?}", start.options);
+            let mut fields = fields(&client);
+            fields.push(("options", &options));
+            log.log(Level::Debug, "start", &fields);
+        }
+
+        Request {
+            log, client,
+            command: start.options.command.rule(),
+            bin: start.options.bin.clone(),
+            start: Instant::now()
+        }
+    }
+
+    fn finish(self, outcome: &Outcome, bytes: u64) {
+        let (level, outcome) = match outcome {
+            Outcome::Exit(ExitCode::Ok) => (Level::Info, "ok"),
+            Outcome::Exit(ExitCode::Failure) => (Level::Warn, "failed"),
+            Outcome::Cancelled | Outcome::Exit(ExitCode::Cancelled) => (Level::Info, "cancelled"),
+            Outcome::Disconnected => (Level::Info, "disconnected"),
+            Outcome::Unresponsive => (Level::Warn, "unresponsive"),
+            Outcome::TimedOut => (Level::Warn, "timeout")
+        };
+        let bin = MaybePrinter(self.bin.as_deref(), Some('-'));
+        let duration = format!("{:.3}s", self.start.elapsed().as_secs_f64());
+        let mut fields = fields(&self.client);
+        fields.extend([
+            ("command", &self.command as &dyn fmt::Display),
+            ("bin", &bin),
+            ("duration", &duration),
+            ("bytes", &bytes),
+            ("outcome", &outcome)
+        ]);
+
+        self.log.log(level, "request", &fields);
+    }
+}
+
+fn fields<'a>(client: &'a [(&'static str, String)]) -> Vec<(&'a str, &'a dyn fmt::Display)> {
+    client.iter()
+        .map(|(key, value)| (*key, value as &dyn fmt::Display))
+        .collect()
+}
+
+fn drain(mut pipe: PipeReader, tag: u8, tx: mpsc::Sender<(u8, Vec<u8>)>) {
+    let mut buf = vec![0; 64 * 1024];
+
+    loop {
+        match pipe.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => if tx.blocking_send((tag, buf[..n].to_vec())).is_err() {
+                // client gone, writers get a broken pipe
+                break
             }
-        },
-        _ = io::copy(&mut stream, &mut sink) => {
-            eprintln!("{:?} command cancel", pid);
-            return Ok(())
         }
-    };
+    }
+}
 
-    let exit = Exit { code };
-    let buf = cbor4ii::serde::to_vec(Vec::new(), &exit)?;
-    let len: u16 = buf.len().try_into()?;
+/// `write_frame` on a non-blocking socket, waiting in `poll` while it is full
+fn write_frame_blocking(mut socket: &File, tag: u8, data: &[u8]) -> anyhow::Result<()> {
+    anyhow::ensure!(data.len() <= MAX_FRAME, "frame too long: {} bytes", data.len());
+    let len: u32 = data.len().try_into()?;
+    let mut frame = Vec::with_capacity(5 + data.len());
+    frame.push(tag);
+    frame.extend_from_slice(&len.to_le_bytes());
+    frame.extend_from_slice(data);
 
-    stream.write_all(&len.to_le_bytes()).await?;
-    stream.write_all(&buf).await?;
-    stream.flush().await?;
+    let mut buf = frame.as_slice();
+    while !buf.is_empty() {
+        match socket.write(buf) {
+            Ok(0) => return Err(std::io::Error::from(std::io::ErrorKind::WriteZero).into()),
+            Ok(n) => buf = &buf[n..],
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                let mut pollfd = libc::pollfd { fd: socket.as_raw_fd(), events: libc::POLLOUT, revents: 0 };
+                unsafe { libc::poll(&mut pollfd, 1, -1) };
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err.into())
+        }
+    }
+
+    Ok(())
+}
 
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, tag: u8, data: &[u8]) -> anyhow::Result<()> {
+    anyhow::ensure!(data.len() <= MAX_FRAME, "frame too long: {} bytes", data.len());
+    let len: u32 = data.len().try_into()?;
+    writer.write_u8(tag).await?;
+    writer.write_all(&len.to_le_bytes()).await?;
+    writer.write_all(data).await?;
     Ok(())
 }
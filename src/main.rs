@@ -2,13 +2,47 @@
 
 mod listen;
 mod call;
+mod sessions;
+mod status;
+mod shutdown;
+mod reload;
 mod explorer;
 mod search;
 mod show;
 mod complete;
+mod export;
+mod types;
+mod lines;
+mod inlines;
+mod cu;
+mod frames;
+mod vars;
+mod enums;
+mod producers;
+mod reachable;
+mod padding;
+mod similar;
+mod diff;
+mod callees;
+mod calltree;
+mod vtables;
+mod cargo;
+mod debuginfo;
 mod disasm;
+mod dwarf;
+mod linkmap;
+mod names;
+mod refs;
+mod pdb;
+mod sarif;
+mod row;
+mod checksec;
+mod template;
 mod util;
+#[cfg(test)]
+mod testing;
 
+use std::path::PathBuf;
 use anyhow::Context;
 use clap::Parser;
 use directories::ProjectDirs;
@@ -27,6 +61,49 @@ use crate::util::Stdio;
 pub struct Options {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// write output to file (atomic replace on success)
+    #[arg(short, long, global = true)]
+    #[serde(skip)]
+    pub output: Option<PathBuf>,
+
+    /// do not pipe output into pager
+    #[arg(long, global = true)]
+    #[serde(skip)]
+    pub no_pager: bool,
+
+    /// output format
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub format: util::Format,
+
+    /// address of a session listening with `listen --tcp`, instead of the local socket
+    #[arg(long, global = true, value_name = "ADDR")]
+    #[serde(skip)]
+    pub connect: Option<String>,
+
+    /// token printed by `listen --tcp` for `--connect`, `$FI_TOKEN` by default
+    #[arg(long, global = true)]
+    #[serde(skip)]
+    pub token: Option<String>,
+
+    /// session to query, by a binary it serves, socket name, its prefix or pid,
+    /// instead of `$FI_SESSION` or the only one of the current dir
+    #[arg(long, global = true, value_name = "NAME")]
+    #[serde(skip)]
+    pub session: Option<String>,
+
+    /// binary to query, by the session serving it or a new one started in the background
+    #[arg(long, global = true, value_name = "PATH")]
+    #[serde(skip)]
+    pub file: Option<PathBuf>,
+
+    /// binary of the session to query, by file name or path, the first one by default
+    #[arg(long, global = true)]
+    pub bin: Option<String>,
+
+    /// template of symbol rows, eg `{addr:#x}\t{size}\t{name}`
+    #[arg(long, global = true)]
+    pub format_str: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -34,8 +111,29 @@ pub struct Options {
 pub enum Commands {
     Complete(complete::Command),
     Listen(listen::Command),
+    Sessions(sessions::Command),
+    Status(status::Command),
+    Shutdown(shutdown::Command),
+    Reload(reload::Command),
     Search(search::Command),
+    Checksec(checksec::Command),
     Show(show::Command),
+    Export(export::Command),
+    Types(types::Command),
+    Lines(lines::Command),
+    Inlines(inlines::Command),
+    Cu(cu::Command),
+    Frames(frames::Command),
+    Vars(vars::Command),
+    Enum(enums::Command),
+    Producers(producers::Command),
+    Reachable(reachable::Command),
+    Padding(padding::Command),
+    Similar(similar::Command),
+    DiffSymbol(diff::Command),
+    Callees(callees::Command),
+    Calltree(calltree::Command),
+    Vtables(vtables::Command),
 }
 
 
@@ -47,6 +145,8 @@ fn main() -> anyhow::Result<()> {
     match options.command {
         Commands::Complete(cmd) => cmd.exec(),
         Commands::Listen(cmd) => cmd.exec(&dir),
+        Commands::Sessions(cmd) => cmd.exec(&dir),
+        Commands::Shutdown(cmd) if cmd.all => cmd.exec_all(&dir),
         _ => call::call(&dir, Box::new(options))
     }
 }
@@ -56,9 +156,78 @@ impl Commands {
         -> anyhow::Result<()>
     {
         match self {
-            Commands::Complete(_) | Commands::Listen(_) => Ok(()),
+            Commands::Complete(_) | Commands::Listen(_) | Commands::Sessions(_) => Ok(()),
+            // served with the state of the session
+            Commands::Status(_) | Commands::Shutdown(_) | Commands::Reload(_) => Ok(()),
+            // served with two binaries of the session
+            Commands::Similar(_) | Commands::DiffSymbol(_) => Ok(()),
             Commands::Search(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Checksec(cmd) => cmd.exec(explorer, stdio).await,
             Commands::Show(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Export(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Types(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Lines(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Inlines(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Cu(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Frames(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Vars(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Enum(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Producers(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Reachable(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Padding(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Callees(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Calltree(cmd) => cmd.exec(explorer, stdio).await,
+            Commands::Vtables(cmd) => cmd.exec(explorer, stdio).await,
+        }
+    }
+
+    /// Whether results are findings, the only ones `--format sarif` reports
+    fn has_findings(&self) -> bool {
+        match self {
+            Commands::Checksec(_) => true,
+            // byte signatures
+            Commands::Search(cmd) => cmd.mode() == "pattern",
+            _ => false
+        }
+    }
+
+    /// Whether records of more than one struct are written, which `--format csv` can not share a header
+    fn has_mixed_records(&self) -> bool {
+        match self {
+            // instructions of text symbols, values of data symbols
+            Commands::Show(cmd) => cmd.all,
+            _ => false
+        }
+    }
+
+    /// Rule id of results, such as `search/pattern`
+    fn rule(&self) -> String {
+        match self {
+            Commands::Complete(_) => "complete".into(),
+            Commands::Listen(_) => "listen".into(),
+            Commands::Sessions(_) => "sessions".into(),
+            Commands::Status(_) => "status".into(),
+            Commands::Shutdown(_) => "shutdown".into(),
+            Commands::Reload(_) => "reload".into(),
+            Commands::Search(cmd) => format!("search/{}", cmd.mode()),
+            Commands::Checksec(_) => "checksec".into(),
+            Commands::Show(_) => "show".into(),
+            Commands::Export(_) => "export".into(),
+            Commands::Types(_) => "types".into(),
+            Commands::Lines(_) => "lines".into(),
+            Commands::Inlines(_) => "inlines".into(),
+            Commands::Cu(_) => "cu".into(),
+            Commands::Frames(_) => "frames".into(),
+            Commands::Vars(_) => "vars".into(),
+            Commands::Enum(_) => "enum".into(),
+            Commands::Producers(_) => "producers".into(),
+            Commands::Reachable(_) => "reachable".into(),
+            Commands::Padding(_) => "padding".into(),
+            Commands::Similar(_) => "similar".into(),
+            Commands::DiffSymbol(_) => "diff-symbol".into(),
+            Commands::Callees(_) => "callees".into(),
+            Commands::Calltree(_) => "calltree".into(),
+            Commands::Vtables(_) => "vtables".into(),
         }
     }
 }
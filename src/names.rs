@@ -0,0 +1,167 @@
+use aho_corasick::AhoCorasick;
+use regex_syntax::hir::literal::{ Extractor, ExtractKind };
+
+
+/// Symbol names in one buffer, to find the names containing a literal
+/// without running the regex on every symbol
+///
+/// Names are in symlist order and separated by `\0`, which no literal of a name contains.
+pub struct NameIndex {
+    buf: String,
+    /// start of each name in `buf`
+    starts: Box<[usize]>
+}
+
+impl NameIndex {
+    pub fn new<S: AsRef<str>>(names: impl Iterator<Item = S>) -> NameIndex {
+        let mut buf = String::new();
+        let mut starts = Vec::new();
+
+        for name in names {
+            starts.push(buf.len());
+            buf.push_str(name.as_ref());
+            buf.push('\0');
+        }
+
+        NameIndex { buf, starts: starts.into_boxed_slice() }
+    }
+
+    pub fn size(&self) -> usize {
+        self.buf.capacity() + size_of_val(&*self.starts)
+    }
+
+    /// Positions of the names containing any of `literals`, in order
+    fn containing(&self, literals: &[Vec<u8>]) -> anyhow::Result<Vec<usize>> {
+        let ac = AhoCorasick::new(literals)?;
+        let mut found = Vec::new();
+
+        for mat in ac.find_iter(&self.buf) {
+            let pos = self.starts.partition_point(|&start| start <= mat.start()) - 1;
+
+            if found.last() != Some(&pos) {
+                found.push(pos);
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Positions of the names that may match any, or with `all` every, pattern,
+    /// None if a pattern has no literal to look for and all names must be scanned
+    pub fn candidates<S: AsRef<str>>(&self, patterns: &[S], ignore_case: bool, all: bool)
+        -> anyhow::Result<Option<Vec<usize>>>
+    {
+        let mut candidates: Option<Vec<usize>> = None;
+
+        for pattern in patterns {
+            let found = match literals(pattern.as_ref(), ignore_case)? {
+                Some(literals) => self.containing(&literals)?,
+                // any name may match this one, the others still narrow with `all`
+                None if all => continue,
+                None => return Ok(None)
+            };
+
+            candidates = Some(match candidates {
+                None => found,
+                Some(prev) if all => intersect(&prev, &found),
+                Some(prev) => union(&prev, &found)
+            });
+        }
+
+        Ok(candidates)
+    }
+}
+
+/// Literals one of which starts every match of `pattern`, None if they may be empty
+/// or are too many to extract
+fn literals(pattern: &str, ignore_case: bool) -> anyhow::Result<Option<Vec<Vec<u8>>>> {
+    let hir = regex_syntax::ParserBuilder::new()
+        .case_insensitive(ignore_case)
+        .build()
+        .parse(pattern)?;
+    let mut seq = Extractor::new()
+        .kind(ExtractKind::Prefix)
+        .extract(&hir);
+    seq.optimize_for_prefix_by_preference();
+
+    let Some(literals) = seq.literals()
+        else { return Ok(None) };
+
+    if literals.is_empty() || literals.iter().any(|lit| lit.is_empty()) {
+        return Ok(None);
+    }
+
+    Ok(Some(literals.iter().map(|lit| lit.as_bytes().to_vec()).collect()))
+}
+
+fn union(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let mut out = Vec::with_capacity(a.len() + b.len());
+    out.extend_from_slice(a);
+    out.extend_from_slice(b);
+    out.sort_unstable();
+    out.dedup();
+    out
+}
+
+fn intersect(a: &[usize], b: &[usize]) -> Vec<usize> {
+    a.iter()
+        .copied()
+        .filter(|pos| b.binary_search(pos).is_ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> NameIndex {
+        NameIndex::new([
+            "main",
+            "core::fmt::write",
+            "alloc::fmt::format",
+            "std::io::Write::write_all",
+            "memcpy"
+        ].iter())
+    }
+
+    fn candidates(patterns: &[&str], ignore_case: bool, all: bool) -> Option<Vec<usize>> {
+        index().candidates(patterns, ignore_case, all).unwrap()
+    }
+
+    #[test]
+    fn literal_patterns() {
+        assert_eq!(candidates(&["write"], false, false), Some(vec![1, 3]));
+        assert_eq!(candidates(&["^main$"], false, false), Some(vec![0]));
+        assert_eq!(candidates(&["fmt::(write|format)"], false, false), Some(vec![1, 2]));
+        assert_eq!(candidates(&["nothing"], false, false), Some(vec![]));
+    }
+
+    #[test]
+    fn ignore_case() {
+        assert_eq!(candidates(&["WRITE"], false, false), Some(vec![]));
+        assert_eq!(candidates(&["WRITE"], true, false), Some(vec![1, 3]));
+    }
+
+    #[test]
+    fn any_or_all() {
+        assert_eq!(candidates(&["main", "memcpy"], false, false), Some(vec![0, 4]));
+        assert_eq!(candidates(&["fmt", "write"], false, true), Some(vec![1]));
+    }
+
+    #[test]
+    fn no_literal() {
+        // these may match any name, so all names are scanned
+        assert_eq!(candidates(&[".*"], false, false), None);
+        assert_eq!(candidates(&["[a-z]+"], false, false), None);
+        assert_eq!(candidates(&["write", "x?"], false, false), None);
+
+        // with `all`, the other patterns still narrow
+        assert_eq!(candidates(&["write", "x?"], false, true), Some(vec![1, 3]));
+        assert_eq!(candidates(&[".*"], false, true), None);
+    }
+
+    #[test]
+    fn bad_pattern() {
+        assert!(index().candidates(&["("], false, false).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use std::io::Write;
+use object::{ Object, ObjectSection, ObjectSymbol, SectionKind };
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, YieldPoint, MaybePrinter };
+
+
+/// report bytes lost to padding between symbols and to section alignment
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// list the symbols followed by the most padding instead of totals per section
+    #[arg(long)]
+    pub list: bool,
+
+    /// demangle symbol name (list)
+    #[arg(short, long)]
+    pub demangle: bool,
+
+    /// filter section by regex
+    #[arg(short, long)]
+    pub filter_section: Option<String>,
+
+    /// print at most N results
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Default)]
+struct Stats {
+    size: u64,
+    /// gaps between symbols and at the section end, up to the alignment of the next address
+    padding: u64,
+    /// gap before the section, up to its alignment
+    align: u64,
+    /// larger gaps, data without a symbol rather than padding
+    unnamed: u64
+}
+
+impl Command {
+    /// Symbol sizes come from the symbol table, so on Mach-O, where a symbol extends to
+    /// the next one, padding is counted as part of the symbol before it.
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            section: &'a str,
+            size: u64,
+            padding: u64,
+            align: u64,
+            unnamed: u64
+        }
+
+        #[derive(Serialize)]
+        struct GapRecord<'a> {
+            address: u64,
+            padding: u64,
+            section: &'a str,
+            after: Option<&'a str>
+        }
+
+        let filter = self.filter_section
+            .as_ref()
+            .map(|rule| regex::Regex::new(rule))
+            .transpose()?;
+        let symlist = explorer.cache.symlist(&explorer.obj).await;
+        let addr_of = |idx| explorer.sym_obj().symbol_by_index(idx).unwrap().address();
+        let mut point = YieldPoint::default();
+
+        let mut sections = explorer.obj.sections()
+            .filter(|section| section.address() != 0 && section.kind() != SectionKind::UninitializedTls)
+            .collect::<Vec<_>>();
+        sections.sort_by_key(|section| section.address());
+
+        let mut output = Vec::new();
+        let mut gaps = Vec::new();
+        let mut prev_end = None;
+
+        for section in &sections {
+            let start = section.address();
+            let end = start + section.size();
+            let name = section.name().unwrap_or("?");
+
+            // larger gaps are between segments
+            let align = prev_end.replace(end)
+                .and_then(|prev_end| start.checked_sub(prev_end))
+                .filter(|&gap| gap < section.align())
+                .unwrap_or_default();
+
+            if let Some(rule) = filter.as_ref()
+                && !rule.is_match(name)
+            {
+                continue
+            }
+
+            let mut stats = Stats { size: section.size(), align, ..Stats::default() };
+            let mut cursor = start;
+            let mut last = None;
+            let first = symlist.partition_point(|&idx| addr_of(idx) < start);
+
+            let syms = symlist[first..].iter()
+                .map(|&idx| explorer.sym_obj().symbol_by_index(idx).unwrap())
+                .take_while(|sym| sym.address() < end)
+                .filter(|sym| sym.section_index() == Some(section.index()))
+                .map(|sym| (Some(sym.index()), sym.address(), sym.size()))
+                // the section end closes the last gap
+                .chain([(None, end, 0)]);
+            // a symbol without size, such as a function of crt objects, may extend to the next one
+            let mut open_ended = false;
+
+            for (idx, addr, size) in syms {
+                point.yield_now().await;
+
+                if addr > cursor && !open_ended {
+                    let gap = addr - cursor;
+                    let next_align = 1u64.checked_shl(addr.trailing_zeros()).unwrap_or(u64::MAX);
+
+                    if gap < next_align.min(section.align()) {
+                        stats.padding += gap;
+
+                        if self.list {
+                            gaps.push((cursor, gap, section.index(), last));
+                        }
+                    } else {
+                        stats.unnamed += gap;
+                    }
+                }
+
+                if idx.is_some() {
+                    last = idx;
+                }
+                if addr >= cursor || addr + size > cursor {
+                    open_ended = size == 0;
+                }
+                cursor = cursor.max(addr + size);
+            }
+
+            output.push((name, stats));
+        }
+
+        if self.list {
+            gaps.sort_by(|(addr0, gap0, ..), (addr1, gap1, ..)| gap1.cmp(gap0).then(addr0.cmp(addr1)));
+
+            for (addr, gap, section_idx, last) in gaps.iter().take(self.limit.unwrap_or(usize::MAX)) {
+                point.yield_now().await;
+
+                let section_name = explorer.obj.section_by_index(*section_idx)?.name().unwrap_or("?");
+                let name = last
+                    .and_then(|idx| explorer.sym_obj().symbol_by_index(idx).ok())
+                    .and_then(|sym| sym.name().ok())
+                    .map(|name| if self.demangle {
+                        explorer.cache.demangle(name)
+                    } else {
+                        name.into()
+                    });
+
+                if !stdio.format.is_text() {
+                    stdio.record(&GapRecord {
+                        address: *addr,
+                        padding: *gap,
+                        section: section_name,
+                        after: name.as_deref()
+                    })?;
+                    continue
+                }
+
+                writeln!(
+                    stdio.stdout,
+                    "{:018p} {:6} {}\t{}",
+                    *addr as *const (),
+                    gap,
+                    section_name,
+                    MaybePrinter(name.as_deref(), Some('-'))
+                )?;
+            }
+
+            return Ok(());
+        }
+
+        // most padding first
+        output.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.padding + stats.align));
+
+        let mut total = Stats::default();
+
+        for (_, stats) in &output {
+            total.size += stats.size;
+            total.padding += stats.padding;
+            total.align += stats.align;
+            total.unnamed += stats.unnamed;
+        }
+
+        for (name, stats) in output.iter().take(self.limit.unwrap_or(usize::MAX)) {
+            if !stdio.format.is_text() {
+                stdio.record(&Record {
+                    section: name,
+                    size: stats.size,
+                    padding: stats.padding,
+                    align: stats.align,
+                    unnamed: stats.unnamed
+                })?;
+                continue
+            }
+
+            writeln!(
+                stdio.stdout,
+                "{:10} {:8} {:6} {:5.1}% {:10}\t{}",
+                stats.size,
+                stats.padding,
+                stats.align,
+                (stats.padding + stats.align) as f64 * 100.0 / stats.size.max(1) as f64,
+                stats.unnamed,
+                name
+            )?;
+        }
+
+        if stdio.format.is_text() {
+            writeln!(
+                stdio.stdout,
+                "total: {} bytes, {} padding, {} alignment, {} unnamed",
+                total.size,
+                total.padding,
+                total.align,
+                total.unnamed
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,297 @@
+use std::fs;
+use std::ops::Range;
+use std::path::{ Path, PathBuf };
+use std::collections::BTreeMap;
+use indexmap::IndexSet;
+use pdb::FallibleIterator;
+use object::{ Object, ObjectSection, ObjectSymbol };
+use crate::util::Arena;
+
+
+/// PDB referenced by the CodeView record of a PE, with the same GUID and age
+pub fn locate(path: &Path, obj: &object::File<'static>) -> Option<PathBuf> {
+    let info = obj.pdb_info().ok()??;
+    let recorded = std::str::from_utf8(info.path()).ok()?;
+    let name = recorded.rsplit(['/', '\\']).next()?;
+    let candidates = [
+        PathBuf::from(recorded),
+        path.with_file_name(name),
+        path.with_extension("pdb")
+    ];
+
+    candidates.into_iter()
+        .filter(|candidate| candidate.is_file())
+        .find(|candidate| signature_of(candidate)
+            .is_ok_and(|(guid, age)| guid == info.guid() && age == info.age())
+        )
+}
+
+/// GUID, as laid out in the CodeView record, and age of the PDB info stream
+fn signature_of(path: &Path) -> anyhow::Result<([u8; 16], u32)> {
+    let mut pdb = pdb::PDB::open(fs::File::open(path)?)?;
+    let info = pdb.pdb_information()?;
+
+    Ok((info.guid.to_bytes_le(), info.age))
+}
+
+/// Symbols and line info of a PDB
+pub struct Pdb {
+    /// symbol table of the PE symbols and those of the PDB, as an ELF object
+    /// without data whose sections have the addresses and indices of the PE ones
+    pub obj: object::File<'static>,
+    pub lines: Lines
+}
+
+/// Line records of a PDB, sorted by address
+pub struct Lines {
+    lines: Box<[Line]>,
+    files: Box<[Box<str>]>
+}
+
+struct Line {
+    address: u64,
+    len: u64,
+    file: usize,
+    line: u32
+}
+
+struct Found {
+    address: u64,
+    size: u64,
+    name: String,
+    function: bool
+}
+
+impl Lines {
+    /// File and line of `addr`
+    pub fn find(&self, addr: u64) -> Option<(&str, u32)> {
+        let idx = self.lines.partition_point(|line| line.address <= addr).checked_sub(1)?;
+        let line = &self.lines[idx];
+
+        (addr < line.address + line.len).then(|| (&*self.files[line.file], line.line))
+    }
+
+    /// Lines overlapping `range`, as (range, file, line)
+    pub fn range(&self, range: Range<u64>) -> impl Iterator<Item = (Range<u64>, &str, u32)> + '_ {
+        let start = self.lines.partition_point(|line| line.address + line.len <= range.start);
+
+        self.lines[start..].iter()
+            .take_while(move |line| line.address < range.end)
+            .map(|line| (line.address..line.address + line.len, &*self.files[line.file], line.line))
+    }
+}
+
+/// Public symbols, procedures with their sizes, global data and line records
+/// of the PDB at `path`, addressed by the image of `obj`
+///
+/// Procedures add the functions without a public symbol, such as static ones.
+pub fn load(path: &Path, obj: &object::File<'static>, arena: &Arena) -> anyhow::Result<Pdb> {
+    let mut pdb = pdb::PDB::open(fs::File::open(path)?)?;
+    let address_map = pdb.address_map()?;
+    let base = obj.relative_address_base();
+    let address_of = |offset: pdb::PdbInternalSectionOffset| offset.to_rva(&address_map)
+        .map(|rva| base + u64::from(rva.0));
+
+    // decorated public names are kept, procedures give the size
+    let mut found: BTreeMap<u64, Found> = BTreeMap::new();
+
+    let globals = pdb.global_symbols()?;
+    let mut iter = globals.iter();
+
+    while let Some(symbol) = iter.next()? {
+        let (offset, name, function) = match symbol.parse() {
+            Ok(pdb::SymbolData::Public(public)) => (public.offset, public.name, public.function),
+            Ok(pdb::SymbolData::Data(data)) => (data.offset, data.name, false),
+            _ => continue
+        };
+
+        if let Some(address) = address_of(offset) {
+            found.entry(address).or_insert_with(|| Found {
+                address,
+                size: 0,
+                name: name.to_string().into_owned(),
+                function
+            });
+        }
+    }
+
+    let dbi = pdb.debug_information()?;
+    let strings = pdb.string_table()?;
+    let mut modules = dbi.modules()?;
+    let mut files = IndexSet::new();
+    let mut lines = Vec::new();
+
+    while let Some(module) = modules.next()? {
+        let Some(info) = pdb.module_info(&module)?
+            else { continue };
+        let mut iter = info.symbols()?;
+
+        while let Some(symbol) = iter.next()? {
+            if let Ok(pdb::SymbolData::Procedure(procedure)) = symbol.parse()
+                && let Some(address) = address_of(procedure.offset)
+            {
+                let found = found.entry(address).or_insert_with(|| Found {
+                    address,
+                    size: 0,
+                    name: procedure.name.to_string().into_owned(),
+                    function: true
+                });
+                found.size = u64::from(procedure.len);
+                found.function = true;
+            }
+        }
+
+        let program = info.line_program()?;
+        let mut iter = program.lines();
+
+        while let Some(line) = iter.next()? {
+            let Some(address) = address_of(line.offset)
+                else { continue };
+            let file = program.get_file_info(line.file_index)?;
+            let name = file.name.to_string_lossy(&strings)?;
+            let file = files.get_index_of(&*name)
+                .unwrap_or_else(|| files.insert_full(name.into_owned()).0);
+
+            lines.push(Line {
+                address,
+                len: line.length.map(u64::from).unwrap_or_default(),
+                file,
+                line: line.line_start
+            });
+        }
+    }
+
+    // records without a length end at the next one
+    lines.sort_by_key(|line| line.address);
+    for idx in 1..lines.len() {
+        if lines[idx - 1].len == 0 {
+            lines[idx - 1].len = lines[idx].address - lines[idx - 1].address;
+        }
+    }
+
+    // symbols of the PE itself, such as those of mingw
+    for sym in obj.symbols() {
+        if matches!(sym.kind(), object::SymbolKind::Text | object::SymbolKind::Data)
+            && !sym.is_undefined()
+            && let Ok(name) = sym.name()
+        {
+            found.entry(sym.address()).or_insert_with(|| Found {
+                address: sym.address(),
+                size: sym.size(),
+                name: name.into(),
+                function: sym.kind() == object::SymbolKind::Text
+            });
+        }
+    }
+
+    let found = found.into_values().collect::<Vec<_>>();
+    let buf = write_symbols(obj, &found)
+        .map_err(|err| anyhow::format_err!("pdb symbols: {}", err))?;
+
+    Ok(Pdb {
+        obj: object::File::parse(arena.keep_heap(buf))?,
+        lines: Lines {
+            lines: lines.into_boxed_slice(),
+            files: files.into_iter().map(String::into_boxed_str).collect()
+        }
+    })
+}
+
+/// ELF object of `symbols`, with NOBITS sections laid out as those of `obj`
+fn write_symbols(obj: &object::File<'static>, symbols: &[Found]) -> object::write::Result<Vec<u8>> {
+    use object::elf;
+    use object::write::elf::{ Writer, FileHeader, SectionHeader, Sym };
+
+    let sections = obj.sections().collect::<Vec<_>>();
+    let mut buf = Vec::new();
+    let mut writer = Writer::new(object::Endianness::Little, obj.is_64(), &mut buf);
+
+    writer.reserve_file_header();
+    writer.reserve_null_section_index();
+    let names = sections.iter()
+        .map(|section| {
+            let name = writer.add_section_name(section.name_bytes().unwrap_or_default());
+            (name, writer.reserve_section_index())
+        })
+        .collect::<Vec<_>>();
+
+    writer.reserve_null_symbol_index();
+    let symbols = symbols.iter()
+        .map(|found| {
+            let name = writer.add_string(found.name.as_bytes());
+            let section = sections.iter()
+                .position(|section| (section.address()..section.address() + section.size())
+                    .contains(&found.address))
+                .map(|idx| names[idx].1);
+            writer.reserve_symbol_index(section);
+            (found, name, section)
+        })
+        .collect::<Vec<_>>();
+
+    writer.reserve_symtab_section_index();
+    writer.reserve_strtab_section_index();
+    writer.reserve_shstrtab_section_index();
+    writer.reserve_symtab();
+    writer.reserve_strtab();
+    writer.reserve_shstrtab();
+    writer.reserve_section_headers();
+
+    writer.write_file_header(&FileHeader {
+        os_abi: elf::ELFOSABI_NONE,
+        abi_version: 0,
+        e_type: elf::ET_EXEC,
+        e_machine: match obj.architecture() {
+            object::Architecture::X86_64 => elf::EM_X86_64,
+            object::Architecture::I386 => elf::EM_386,
+            object::Architecture::Aarch64 => elf::EM_AARCH64,
+            object::Architecture::Arm => elf::EM_ARM,
+            _ => elf::EM_NONE
+        },
+        e_entry: obj.entry(),
+        e_flags: 0
+    })?;
+
+    writer.write_null_symbol();
+    for (found, name, section) in symbols {
+        let kind = if found.function { elf::STT_FUNC } else { elf::STT_OBJECT };
+
+        writer.write_symbol(&Sym {
+            name: Some(name),
+            section,
+            st_info: (elf::STB_GLOBAL << 4) | kind,
+            st_other: elf::STV_DEFAULT,
+            st_shndx: if section.is_some() { 0 } else { elf::SHN_ABS },
+            st_value: found.address,
+            st_size: found.size
+        });
+    }
+    writer.write_strtab();
+    writer.write_shstrtab();
+
+    writer.write_null_section_header();
+    for (section, (name, _)) in sections.iter().zip(names) {
+        let flags = match section.kind() {
+            object::SectionKind::Text => elf::SHF_ALLOC | elf::SHF_EXECINSTR,
+            object::SectionKind::Data | object::SectionKind::UninitializedData => elf::SHF_ALLOC | elf::SHF_WRITE,
+            _ => elf::SHF_ALLOC
+        };
+
+        writer.write_section_header(&SectionHeader {
+            name: Some(name),
+            sh_type: elf::SHT_NOBITS,
+            sh_flags: u64::from(flags),
+            sh_addr: section.address(),
+            sh_offset: 0,
+            sh_size: section.size(),
+            sh_link: 0,
+            sh_info: 0,
+            sh_addralign: 1,
+            sh_entsize: 0
+        });
+    }
+    writer.write_symtab_section_header(1);
+    writer.write_strtab_section_header();
+    writer.write_shstrtab_section_header();
+
+    Ok(buf)
+}
@@ -0,0 +1,143 @@
+use std::io::Write;
+use indexmap::IndexMap;
+use object::{ Object, ObjectSection };
+use addr2line::gimli;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, YieldPoint, MaybePrinter };
+use crate::dwarf;
+
+
+/// report compilers of DWARF units and `.comment` entries
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// list units whose producer lacks the flag, such as `-fstack-protector-strong`
+    #[arg(long, allow_hyphen_values = true)]
+    pub missing: Option<String>,
+}
+
+impl Command {
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            source: &'a str,
+            count: usize,
+            producer: Option<&'a str>
+        }
+
+        #[derive(Serialize)]
+        struct MissingRecord<'a> {
+            unit: Option<&'a str>,
+            producer: Option<&'a str>
+        }
+
+        let dwarf = explorer.cache.dwarf(explorer.dwarf_obj(), explorer.dwarf_path.as_deref()).await?;
+        let mut point = YieldPoint::default();
+        let mut units = dwarf.units();
+        let mut producers: IndexMap<Option<String>, usize> = IndexMap::new();
+        let mut total = 0;
+
+        while let Some(header) = units.next()? {
+            point.yield_now().await;
+
+            let unit = dwarf.unit(header)?;
+            let mut entries = unit.entries();
+            let Some((_, root)) = entries.next_dfs()?
+                else { continue };
+            let producer = dwarf::attr_string(dwarf, &unit, root, gimli::DW_AT_producer)
+                .map(|producer| producer.into_owned());
+            total += 1;
+
+            if let Some(flag) = self.missing.as_deref() {
+                if producer.as_deref().is_some_and(|producer| producer.contains(flag)) {
+                    continue
+                }
+
+                let name = dwarf::attr_string(dwarf, &unit, root, gimli::DW_AT_name);
+
+                if !stdio.format.is_text() {
+                    stdio.record(&MissingRecord {
+                        unit: name.as_deref(),
+                        producer: producer.as_deref()
+                    })?;
+                    continue
+                }
+
+                writeln!(
+                    stdio.stdout,
+                    "{}\t{}",
+                    MaybePrinter(name.as_deref(), Some('?')),
+                    MaybePrinter(producer.as_deref(), Some('?'))
+                )?;
+                continue
+            }
+
+            *producers.entry(producer).or_default() += 1;
+        }
+
+        if self.missing.is_some() {
+            return Ok(());
+        }
+
+        producers.sort_by(|_, a, _, b| b.cmp(a));
+
+        for (producer, count) in &producers {
+            if !stdio.format.is_text() {
+                stdio.record(&Record {
+                    source: "dwarf",
+                    count: *count,
+                    producer: producer.as_deref()
+                })?;
+                continue
+            }
+
+            writeln!(stdio.stdout, "{:>6}\tdwarf\t{}", count, MaybePrinter(producer.as_deref(), Some('?')))?;
+        }
+
+        // NUL separated entries, one or more per linked object
+        let data = match explorer.obj.section_by_name(".comment") {
+            Some(section) => Some(explorer.cache.data(&explorer.obj, section.index()).await?),
+            None => None
+        };
+        let mut comments: IndexMap<&str, usize> = IndexMap::new();
+
+        for entry in data.iter().flat_map(|data| data.split(|&b| b == 0)) {
+            if let Ok(entry) = std::str::from_utf8(entry)
+                && !entry.is_empty()
+            {
+                *comments.entry(entry).or_default() += 1;
+            }
+        }
+
+        for (entry, count) in &comments {
+            if !stdio.format.is_text() {
+                stdio.record(&Record {
+                    source: "comment",
+                    count: *count,
+                    producer: Some(entry)
+                })?;
+                continue
+            }
+
+            writeln!(stdio.stdout, "{:>6}\tcomment\t{}", count, entry)?;
+        }
+
+        if stdio.format.is_text() {
+            writeln!(
+                stdio.stdout,
+                "total: {} units, {} producers, {} comments",
+                total,
+                producers.len(),
+                comments.len()
+            )?;
+        }
+
+        Ok(())
+    }
+}
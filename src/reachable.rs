@@ -0,0 +1,224 @@
+use std::borrow::Cow;
+use std::io::Write;
+use indexmap::IndexMap;
+use object::{ Object, ObjectSection, ObjectSymbol };
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, YieldPoint, MaybePrinter };
+use crate::{ cargo, refs };
+
+
+/// report text bytes reachable from the entry point, init/fini arrays and exports, per crate
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// list unreachable functions, largest first, instead of totals per crate
+    #[arg(long)]
+    pub list: bool,
+
+    /// demangle symbol name (list)
+    #[arg(short, long)]
+    pub demangle: bool,
+
+    /// print at most N results
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+/// Symbol, or section for the parts of it outside of any symbol
+struct Node {
+    range: std::ops::Range<u64>,
+    symbol: Option<object::SymbolIndex>
+}
+
+impl Command {
+    /// Walk the references of `refs::collect` from the roots
+    ///
+    /// Data without a symbol, such as the GOT or anonymous vtables, is a node per section,
+    /// so a function stored there is reachable once any reachable code uses the section.
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            #[serde(rename = "crate")]
+            krate: Option<&'a str>,
+            reachable: u64,
+            unreachable: u64
+        }
+
+        #[derive(Serialize)]
+        struct SymbolRecord<'a> {
+            address: u64,
+            size: u64,
+            name: &'a str
+        }
+
+        let symlist = explorer.cache.symlist(&explorer.obj).await;
+        let refs = refs::collect(explorer, stdio).await?;
+        let roots = refs::roots(explorer, &refs)?;
+        let mut point = YieldPoint::default();
+
+        let mut nodes = Vec::new();
+        for &idx in symlist {
+            let sym = explorer.sym_obj().symbol_by_index(idx)?;
+
+            if sym.is_undefined()
+                || !matches!(sym.kind(), object::SymbolKind::Text | object::SymbolKind::Data)
+                || nodes.last().is_some_and(|node: &Node| node.range.start == sym.address())
+            {
+                continue
+            }
+
+            let size = explorer.symbol_size(symlist, idx)?;
+            if size != 0 {
+                nodes.push(Node { range: sym.address()..sym.address() + size, symbol: Some(idx) });
+            }
+        }
+
+        let symbols = nodes.len();
+        let mut sections = explorer.obj.sections()
+            .filter(|section| section.address() != 0 && section.size() != 0)
+            .map(|section| Node { range: section.address()..section.address() + section.size(), symbol: None })
+            .collect::<Vec<_>>();
+        sections.sort_by_key(|node| node.range.start);
+        nodes.extend(sections);
+
+        let node_of = |addr: u64| {
+            let find = |nodes: &[Node]| nodes.partition_point(|node| node.range.start <= addr)
+                .checked_sub(1)
+                .filter(|&idx| nodes[idx].range.contains(&addr));
+
+            find(&nodes[..symbols])
+                .or_else(|| find(&nodes[symbols..]).map(|idx| symbols + idx))
+        };
+
+        let mut edges = Vec::with_capacity(refs.len());
+        for &(from, to) in &refs {
+            point.yield_now().await;
+
+            if let Some(from) = node_of(from)
+                && let Some(to) = node_of(to)
+                && from != to
+            {
+                edges.push((from, to));
+            }
+        }
+        edges.sort_unstable();
+        edges.dedup();
+
+        let mut reachable = vec![false; nodes.len()];
+        let mut stack = roots.iter()
+            .filter_map(|&addr| node_of(addr))
+            .collect::<Vec<_>>();
+
+        while let Some(node) = stack.pop() {
+            if std::mem::replace(&mut reachable[node], true) {
+                continue
+            }
+
+            let start = edges.partition_point(|&(from, _)| from < node);
+            stack.extend(edges[start..].iter()
+                .take_while(|&&(from, _)| from == node)
+                .map(|&(_, to)| to)
+                .filter(|&to| !reachable[to]));
+        }
+
+        let text = nodes[..symbols].iter()
+            .zip(&reachable)
+            .filter_map(|(node, &reachable)| {
+                let sym = explorer.sym_obj().symbol_by_index(node.symbol?).ok()?;
+                (sym.kind() == object::SymbolKind::Text).then_some((node, sym, reachable))
+            });
+
+        if self.list {
+            let mut output = text
+                .filter(|(.., reachable)| !reachable)
+                .map(|(node, sym, _)| (node.range.start, node.range.end - node.range.start, sym))
+                .collect::<Vec<_>>();
+            output.sort_by(|(addr0, size0, _), (addr1, size1, _)| size1.cmp(size0).then(addr0.cmp(addr1)));
+
+            let mut sum = 0;
+
+            for (addr, size, sym) in output.iter().take(self.limit.unwrap_or(usize::MAX)) {
+                point.yield_now().await;
+
+                let mangled_name = sym.name().unwrap_or_default();
+                let name = if self.demangle {
+                    explorer.cache.demangle(mangled_name)
+                } else {
+                    mangled_name.into()
+                };
+                sum += size;
+
+                if !stdio.format.is_text() {
+                    stdio.record(&SymbolRecord { address: *addr, size: *size, name: &name })?;
+                    continue
+                }
+
+                writeln!(stdio.stdout, "{:018p} {:10} {}", *addr as *const (), size, name)?;
+            }
+
+            if stdio.format.is_text() {
+                writeln!(stdio.stdout, "sum: {}", sum)?;
+            }
+
+            return Ok(());
+        }
+
+        let mut crates: IndexMap<Option<Cow<'_, str>>, (u64, u64)> = IndexMap::new();
+        let mut total = (0, 0);
+
+        for (node, sym, reachable) in text {
+            point.yield_now().await;
+
+            let name = explorer.cache.demangle(sym.name().unwrap_or_default());
+            let label = match explorer.crates.as_ref() {
+                Some(crates) => Some(crates.label_of(&name).to_owned().into()),
+                None => cargo::crate_of(&name).map(|name| name.to_owned().into())
+            };
+            let size = node.range.end - node.range.start;
+            let (yes, no) = crates.entry(label).or_default();
+
+            if reachable {
+                *yes += size;
+                total.0 += size;
+            } else {
+                *no += size;
+                total.1 += size;
+            }
+        }
+
+        // most unreachable code first
+        crates.sort_by(|_, (_, no0), _, (_, no1)| no1.cmp(no0));
+
+        for (label, (yes, no)) in crates.iter().take(self.limit.unwrap_or(usize::MAX)) {
+            if !stdio.format.is_text() {
+                stdio.record(&Record { krate: label.as_deref(), reachable: *yes, unreachable: *no })?;
+                continue
+            }
+
+            writeln!(
+                stdio.stdout,
+                "{:10} {:10} {:5.1}% {}",
+                yes, no,
+                *no as f64 * 100.0 / (yes + no).max(1) as f64,
+                MaybePrinter(label.as_deref(), Some('?'))
+            )?;
+        }
+
+        if stdio.format.is_text() {
+            writeln!(
+                stdio.stdout,
+                "total: {} crates, {} reachable, {} unreachable bytes of text",
+                crates.len(),
+                total.0, total.1
+            )?;
+        }
+
+        Ok(())
+    }
+}
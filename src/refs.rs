@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+use object::{ Object, ObjectSection, ObjectSymbol };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, YieldPoint, is_data_section, read_pointer };
+use crate::disasm::DataRefs;
+use crate::search::par_disasm;
+
+
+/// Names of symbols used without a reference in the binary, by the loader or the C runtime
+const KNOWN_ROOTS: &[&str] = &["_start", "main", "_init", "_fini", "_IO_stdin_used"];
+
+/// Sections of pointers called by the loader at startup and exit
+const INIT_SECTIONS: &[&str] = &[".preinit_array", ".init_array", ".fini_array", ".ctors", ".dtors"];
+
+/// References between addresses of the binary as `(from, to)`, sorted by `to`
+///
+/// `from` is the instruction, relocated word or data word that refers to `to`. Instructions give
+/// direct calls and jumps, rip-relative or `adrp` operands and immediates that may be addresses
+/// of non-PIC code, a symbol target of a relocation counts even if the addend points before it.
+/// References of a function to itself are left out, and only targets within the loaded sections
+/// are kept.
+pub async fn collect(explorer: &Explorer, stdio: &Stdio) -> anyhow::Result<Vec<(u64, u64)>> {
+    let arch = explorer.obj.architecture();
+    if !matches!(arch, object::Architecture::X86_64 | object::Architecture::Aarch64) {
+        anyhow::bail!("unsupported arch: {:?}", arch);
+    }
+
+    let dyn_rela = explorer.cache.dyn_rela(&explorer.obj).await;
+
+    let bounds = explorer.obj.sections()
+        .filter(|section| section.address() != 0)
+        .map(|section| section.address()..section.address() + section.size())
+        .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+        .unwrap_or(0..0);
+
+    let mut refs = par_disasm(explorer, None, stdio, |disasm, symidx, size, mut insts| {
+        let start = explorer.sym_obj().symbol_by_index(symidx)?.address();
+        let own = start..start + size;
+        let mut data_refs = DataRefs::default();
+        let mut output = Vec::new();
+
+        while let Some(Ok(inst)) = insts.next() {
+            let targets = disasm.operand2addr(&inst)?
+                .into_iter()
+                .chain(disasm.operand2data(&inst, &mut data_refs))
+                .chain(disasm.immediates(&inst).into_iter().filter_map(|imm| u64::try_from(imm).ok()));
+
+            for addr in targets {
+                if bounds.contains(&addr) && !own.contains(&addr) {
+                    output.push((inst.address(), addr));
+                }
+            }
+        }
+
+        Ok(output)
+    }).await?;
+
+    let static_rela = explorer.obj.sections()
+        .flat_map(|section| section.relocations()
+            .map(move |(offset, rela)| (section.address() + offset, rela.target(), rela.addend())));
+    let relas = dyn_rela.iter()
+        .map(|(addr, rela)| (*addr, rela.target(), rela.addend()))
+        .chain(static_rela);
+
+    for (from, target, addend) in relas {
+        match target {
+            object::RelocationTarget::Symbol(symidx) => {
+                let Ok(sym) = explorer.obj.symbol_by_index(symidx)
+                    else { continue };
+
+                refs.push((from, sym.address()));
+                refs.push((from, sym.address().wrapping_add_signed(addend)));
+            },
+            object::RelocationTarget::Absolute => if let Ok(addr) = u64::try_from(addend) {
+                refs.push((from, addr));
+            },
+            _ => ()
+        }
+    }
+
+    // pointers stored in data by value
+    let ptr_size = if explorer.obj.is_64() { 8 } else { 4 };
+    let endian = explorer.obj.endianness();
+    let mut point = YieldPoint::default();
+
+    for section in explorer.obj.sections()
+        .filter(|section| is_data_section(section.kind()))
+    {
+        let Ok(data) = explorer.cache.data(&explorer.obj, section.index()).await
+            else { continue };
+        let base = section.address();
+
+        for (i, word) in data.chunks_exact(ptr_size).enumerate() {
+            let addr = read_pointer(word, endian);
+
+            if bounds.contains(&addr) {
+                refs.push((base + (i * ptr_size) as u64, addr));
+            }
+        }
+
+        point.yield_now().await;
+    }
+
+    refs.retain(|(_, to)| bounds.contains(to));
+    refs.sort_unstable_by_key(|&(from, to)| (to, from));
+    refs.dedup();
+
+    Ok(refs)
+}
+
+/// Addresses used from outside the code of the binary: exports, the entry point,
+/// `KNOWN_ROOTS` and the pointers of init and fini arrays
+pub fn roots(explorer: &Explorer, refs: &[(u64, u64)]) -> anyhow::Result<HashSet<u64>> {
+    let mut roots = explorer.obj.exports()?
+        .iter()
+        .map(|export| export.address())
+        .collect::<HashSet<_>>();
+    roots.insert(explorer.obj.entry());
+
+    roots.extend(explorer.obj.symbols()
+        .filter(|sym| !sym.is_undefined())
+        .filter(|sym| sym.name().is_ok_and(|name| KNOWN_ROOTS.contains(&name)))
+        .map(|sym| sym.address()));
+
+    let arrays = explorer.obj.sections()
+        .filter(|section| section.name().is_ok_and(|name| INIT_SECTIONS.contains(&name)))
+        .map(|section| section.address()..section.address() + section.size())
+        .collect::<Vec<_>>();
+    roots.extend(refs.iter()
+        .filter(|(from, _)| arrays.iter().any(|range| range.contains(from)))
+        .map(|&(_, to)| to));
+
+    Ok(roots)
+}
@@ -0,0 +1,22 @@
+use std::io::Write;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::util::Stdio;
+
+
+/// reopen the binaries of the session, dropping all caches
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {}
+
+impl Command {
+    pub async fn exec(self, generation: u64, stdio: &mut Stdio) -> anyhow::Result<()> {
+        writeln!(stdio.stdout, "reloaded, generation {}", generation)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+use serde::Serialize;
+use serde::ser::{ self, Impossible, SerializeStruct };
+use serde_json::Value;
+
+
+/// Columns and fields of one record, for `--format csv`
+///
+/// A field skipped by `skip_serializing_if` keeps its column, left empty,
+/// so every record of a struct has the same header.
+#[derive(Default)]
+pub struct Row {
+    pub header: Vec<&'static str>,
+    pub fields: Vec<String>
+}
+
+#[derive(Debug)]
+pub struct Error(String);
+
+impl Row {
+    pub fn of<T: Serialize>(record: &T) -> anyhow::Result<Row> {
+        let mut row = Row::default();
+        record.serialize(&mut row)?;
+        Ok(row)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+macro_rules! not_struct {
+    ( $( $name:ident ( $( $ty:ty ),* ) -> $ret:ty ; )* ) => {
+        $(
+            fn $name(self, $( _: $ty ),*) -> Result<$ret, Error> {
+                Err(Error("csv record is not a struct".into()))
+            }
+        )*
+    }
+}
+
+impl ser::Serializer for &mut Row {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<(), Error> {
+        Err(Error("csv record is not a struct".into()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, _value: &T)
+        -> Result<(), Error>
+    {
+        Err(Error("csv record is not a struct".into()))
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T
+    ) -> Result<(), Error> {
+        Err(Error("csv record is not a struct".into()))
+    }
+
+    not_struct! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+}
+
+impl SerializeStruct for &mut Row {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T)
+        -> Result<(), Error>
+    {
+        // nested values are written as JSON
+        let field = match serde_json::to_value(value).map_err(ser::Error::custom)? {
+            Value::Null => String::new(),
+            Value::String(value) => value,
+            value => value.to_string()
+        };
+
+        self.header.push(key);
+        self.fields.push(field);
+        Ok(())
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Error> {
+        self.header.push(key);
+        self.fields.push(String::new());
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use std::path::Path;
+use serde::Serialize;
+use serde_json::{ json, Value };
+
+
+/// Results of `--format sarif`, written as one log when the command ends
+#[derive(Default)]
+pub struct Log {
+    results: Vec<Value>
+}
+
+impl Log {
+    pub fn push<T: Serialize>(&mut self, record: &T) -> anyhow::Result<()> {
+        self.results.push(serde_json::to_value(record)?);
+        Ok(())
+    }
+
+    /// SARIF 2.1.0 log located in `artifact` by address
+    ///
+    /// A record gives its `rule`, `level` and `message`, or else is a match
+    /// of `rule` at warning level.
+    pub fn finish(self, artifact: &Path, rule: &str) -> Value {
+        let uri = if artifact.is_absolute() {
+            format!("file://{}", artifact.display())
+        } else {
+            artifact.display().to_string()
+        };
+        let mut rules = Vec::new();
+        let results = self.results.into_iter()
+            .map(|record| {
+                let address = ["address", "start"].iter()
+                    .find_map(|key| record.get(key)?.as_u64());
+                let rule = record.get("rule")
+                    .and_then(Value::as_str)
+                    .unwrap_or(rule)
+                    .to_owned();
+                let level = record.get("level")
+                    .and_then(Value::as_str)
+                    .unwrap_or("warning")
+                    .to_owned();
+                let text = match record.get("message").and_then(Value::as_str) {
+                    Some(message) => message.to_owned(),
+                    None => {
+                        let name = ["demangled", "name", "symbol"].iter()
+                            .find_map(|key| record.get(key)?.as_str());
+                        match (name, address) {
+                            (Some(name), Some(address)) => format!("{} at {:#x}", name, address),
+                            (Some(name), None) => name.to_owned(),
+                            (None, Some(address)) => format!("match at {:#x}", address),
+                            (None, None) => record.to_string()
+                        }
+                    }
+                };
+
+                let mut location = json!({
+                    "artifactLocation": { "uri": uri }
+                });
+
+                if let Some(address) = address {
+                    location["address"] = json!({ "absoluteAddress": address });
+                }
+
+                if !rules.contains(&rule) {
+                    rules.push(rule.clone());
+                }
+
+                json!({
+                    "ruleId": rule,
+                    "level": level,
+                    "message": { "text": text },
+                    "locations": [{ "physicalLocation": location }],
+                    "properties": record
+                })
+            })
+            .collect::<Vec<_>>();
+        let rules = rules.iter()
+            .map(|id| json!({ "id": id }))
+            .collect::<Vec<_>>();
+
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules
+                    }
+                },
+                "artifacts": [{ "location": { "uri": uri } }],
+                "results": results
+            }]
+        })
+    }
+}
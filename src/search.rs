@@ -1,17 +1,23 @@
-use std::{ fs, cmp };
-use std::collections::HashSet;
+use std::cmp;
+use std::ops::Range;
+use std::borrow::Cow;
+use std::collections::{ HashSet, HashMap };
 use std::io::Write;
 use anyhow::Context;
 use bstr::ByteSlice;
-use object::{ Object, ObjectSection, ObjectSymbol };
-use symbolic_demangle::demangle;
+use object::{ Object, ObjectSection, ObjectSymbol, ObjectSymbolTable, SectionKind };
 
 use clap::Args;
 use serde::{ Serialize, Deserialize };
 
-use crate::explorer::Explorer;
-use crate::util::{ Stdio, YieldPoint, MaybePrinter, is_data_section, u64ptr };
-use crate::disasm::Disassembler;
+use crate::explorer::{ Explorer, SectionData };
+use crate::{ cargo, refs, vtables };
+use crate::util::{
+    Stdio, YieldPoint, Progress,
+    MaybePrinter, HexPrinter, AsciiPrinter, Hyperlink,
+    is_data_section, read_pointer, u64ptr, parse_size, wait_for
+};
+use crate::disasm::{ self, Disassembler, DataRefs, Inst, InstIter };
 
 
 /// search symbol name and data
@@ -20,21 +26,128 @@ use crate::disasm::Disassembler;
 #[command(args_conflicts_with_subcommands = true)]
 #[command(flatten_help = true)]
 pub struct Command {
-    /// search keyword (regex, symbol address)
+    /// search keyword (regex, symbol address or name)
     pub keyword: String,
 
+    /// more keywords, combined by `--any` or `--all` (regex)
+    pub more_keywords: Vec<String>,
+
+    /// match any of the keywords (default)
+    #[arg(long, conflicts_with = "all")]
+    pub any: bool,
+
+    /// match all of the keywords
+    #[arg(long)]
+    pub all: bool,
+
     /// demangle symbol name
     #[arg(short, long, default_value_t = false)]
     pub demangle: bool,
 
+    /// print source file and line of each symbol (symbol)
+    #[arg(long)]
+    pub source: bool,
+
     /// search by data instead of symbol name
     #[arg(long)]
     pub data: bool,
 
-    /// search for direct calls by symbol address
+    /// treat keyword as a literal string instead of regex
+    #[arg(short = 'F', long)]
+    pub fixed_strings: bool,
+
+    /// case insensitive keyword match
+    #[arg(short, long)]
+    pub ignore_case: bool,
+
+    /// drop matches whose name (or data) matches regex, repeatable
+    #[arg(short = 'x', long)]
+    pub exclude: Vec<String>,
+
+    /// search for calls by symbol address or name, direct or through a vtable the caller refers to
     #[arg(long)]
     pub callsite: bool,
 
+    /// leave out tail calls, jumps to the target from another function (callsite)
+    #[arg(long, conflicts_with = "only_tail")]
+    pub no_tail: bool,
+
+    /// only tail calls, jumps to the target from another function (callsite)
+    #[arg(long)]
+    pub only_tail: bool,
+
+    /// also walk callers of callers up to N levels, printed as a reverse tree (callsite)
+    #[arg(long, value_name = "N")]
+    pub depth: Option<usize>,
+
+    /// search disassembly text by regex
+    #[arg(long)]
+    pub asm: bool,
+
+    /// search by byte pattern, eg `48 8b ?? ?? c3`
+    #[arg(long)]
+    pub pattern: bool,
+
+    /// search for code and data references to a data address or name
+    #[arg(long)]
+    pub xref: bool,
+
+    /// search for instructions with immediate operand equal to keyword
+    #[arg(long)]
+    pub imm: bool,
+
+    /// compare only the low bits of immediate (imm)
+    #[arg(long)]
+    pub width: Option<u32>,
+
+    /// accept immediate within percent of keyword (imm)
+    #[arg(long)]
+    pub tolerance: Option<f64>,
+
+    /// search struct, enum and typedef names in debug info
+    #[arg(long)]
+    pub dwarf_types: bool,
+
+    /// search global and static variable names in debug info
+    #[arg(long)]
+    pub dwarf_vars: bool,
+
+    /// search code generated from source `file:line`
+    #[arg(long)]
+    pub line: bool,
+
+    /// report the stack frame size of functions by name regex, from CFI or the prologue
+    #[arg(long)]
+    pub stack: bool,
+
+    /// list symbols by name regex that no instruction, relocation or data pointer refers to
+    #[arg(long)]
+    pub unreferenced: bool,
+
+    /// group text symbols by name regex whose bytes are identical, the copies the linker did not fold
+    #[arg(long)]
+    pub identical: bool,
+
+    /// compare call, jump and rip-relative targets by the address they resolve to (identical)
+    #[arg(long)]
+    pub normalize: bool,
+
+    /// print N bytes before each match as hexdump (data)
+    #[arg(long, value_name = "N")]
+    pub before: Option<usize>,
+
+    /// print N bytes after each match as hexdump (data)
+    #[arg(long, value_name = "N")]
+    pub after: Option<usize>,
+
+    /// truncate displayed match to N bytes (data)
+    #[arg(long, value_name = "N")]
+    pub max_match_len: Option<usize>,
+
+    /// only match at aligned address (pattern)
+    #[arg(long)]
+    pub align: Option<u64>,
+
     /// filter section by regex
     #[arg(short, long)]
     pub filter_section: Option<String>,
@@ -43,49 +156,305 @@ pub struct Command {
     #[arg(short, long)]
     pub size: bool,
 
-    /// sort by size (symbol)
+    /// only symbols of at least this size, eg `16K` (symbol, stack, unreferenced)
+    #[arg(long, value_parser = parse_size)]
+    pub min_size: Option<u64>,
+
+    /// only symbols of at most this size (symbol, stack, unreferenced)
+    #[arg(long, value_parser = parse_size)]
+    pub max_size: Option<u64>,
+
+    /// sort by size (symbol, stack, unreferenced)
     #[arg(long)]
     pub sort_size: bool,
     
-    /// sort by name (symbol)
+    /// sort by name (symbol, stack, unreferenced)
     #[arg(long)]
     pub sort_name: bool,
 
+    /// sort by address (symbol, stack, unreferenced)
+    #[arg(long)]
+    pub sort_addr: bool,
+
+    /// reverse sort order (symbol, stack, unreferenced)
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// print at most N results, after sorting, `sum:` still counts all matches
+    #[arg(long)]
+    pub limit: Option<usize>,
+
+    /// search dynamic symbol table (symbol)
+    #[arg(long)]
+    pub dynamic: bool,
+
+    /// only undefined (imported) symbols (symbol)
+    #[arg(long)]
+    pub undefined: bool,
+
+    /// group by section with subtotals (symbol)
+    #[arg(long)]
+    pub group_section: bool,
+
+    /// group by object file of `listen --map` with subtotals (symbol)
+    #[arg(long, conflicts_with = "group_section")]
+    pub group_object: bool,
+
+    /// group by crate, named by `listen --manifest-path` or guessed from the path (symbol)
+    #[arg(long, conflicts_with_all = ["group_section", "group_object"])]
+    pub group_crate: bool,
+
     /// only print duplicate (symbol)
     #[arg(long)]
     pub only_duplicate: bool,
+
+    /// collapse duplicates into count and size stats, sorted by total size (symbol)
+    #[arg(long)]
+    pub dedup_stats: bool,
 }
 
 impl Command {
     pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
-        match (self.callsite, self.data) {
-            (false, false) => by_symbol(&self, explorer, stdio).await,
-            (true, false) => by_call(&self, explorer, stdio).await,
-            (false, true) => by_data(&self, explorer, stdio).await,
-            (true, true) => anyhow::bail!("cannot use `--callsite` and `--data` at the same time")
+        let dwarf = self.dwarf_types || self.dwarf_vars;
+        let modes = [self.callsite, self.data, self.asm, self.pattern, self.xref, self.imm, dwarf, self.line, self.stack, self.unreferenced, self.identical];
+
+        if modes.iter().filter(|&&mode| mode).count() > 1 {
+            anyhow::bail!("cannot use more than one of `--callsite`, `--data`, `--asm`, `--pattern`, `--xref`, `--imm`, `--dwarf-*`, `--line`, `--stack`, `--unreferenced` and `--identical`");
         }
+
+        if !self.more_keywords.is_empty()
+            && (self.callsite || self.pattern || self.xref || self.imm || self.line)
+        {
+            anyhow::bail!("multiple keywords are only supported by regex search");
+        }
+
+        if self.callsite {
+            by_call(&self, explorer, stdio).await
+        } else if self.data {
+            by_data(&self, explorer, stdio).await
+        } else if self.asm {
+            by_asm(&self, explorer, stdio).await
+        } else if self.pattern {
+            by_pattern(&self, explorer, stdio).await
+        } else if self.xref {
+            by_xref(&self, explorer, stdio).await
+        } else if self.imm {
+            by_imm(&self, explorer, stdio).await
+        } else if dwarf {
+            by_dwarf(&self, explorer, stdio).await
+        } else if self.line {
+            by_line(&self, explorer, stdio).await
+        } else if self.stack {
+            by_stack(&self, explorer, stdio).await
+        } else if self.unreferenced {
+            by_unreferenced(&self, explorer, stdio).await
+        } else if self.identical {
+            by_identical(&self, explorer, stdio).await
+        } else if self.dynamic || self.undefined {
+            by_dynamic(&self, explorer, stdio).await
+        } else {
+            by_symbol(&self, explorer, stdio).await
+        }
+    }
+
+    /// Name of the search mode
+    pub fn mode(&self) -> &'static str {
+        if self.callsite {
+            "callsite"
+        } else if self.data {
+            "data"
+        } else if self.asm {
+            "asm"
+        } else if self.pattern {
+            "pattern"
+        } else if self.xref {
+            "xref"
+        } else if self.imm {
+            "imm"
+        } else if self.dwarf_types || self.dwarf_vars {
+            "dwarf"
+        } else if self.line {
+            "line"
+        } else if self.stack {
+            "stack"
+        } else if self.unreferenced {
+            "unreferenced"
+        } else if self.identical {
+            "identical"
+        } else if self.dynamic || self.undefined {
+            "dynamic"
+        } else {
+            "symbol"
+        }
+    }
+
+    fn size_in_range(&self, size: u64) -> bool {
+        self.min_size.is_none_or(|min| size >= min)
+            && self.max_size.is_none_or(|max| size <= max)
+    }
+
+    /// Exclude patterns honoring `--fixed-strings` and `--ignore-case`
+    fn exclude_regex(&self) -> anyhow::Result<regex::RegexSet> {
+        let patterns = self.exclude.iter()
+            .map(|pat| if self.fixed_strings { regex::escape(pat) } else { pat.clone() });
+        regex::RegexSetBuilder::new(patterns)
+            .case_insensitive(self.ignore_case)
+            .build()
+            .map_err(Into::into)
+    }
+
+    fn exclude_bytes_regex(&self) -> anyhow::Result<regex::bytes::RegexSet> {
+        let patterns = self.exclude.iter()
+            .map(|pat| if self.fixed_strings { regex::escape(pat) } else { pat.clone() });
+        regex::bytes::RegexSetBuilder::new(patterns)
+            .case_insensitive(self.ignore_case)
+            .build()
+            .map_err(Into::into)
+    }
+
+    fn keyword_patterns(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        std::iter::once(&self.keyword)
+            .chain(&self.more_keywords)
+            .map(|keyword| if self.fixed_strings {
+                regex::escape(keyword).into()
+            } else {
+                keyword.as_str().into()
+            })
+    }
+
+    /// Keyword regexes honoring `--fixed-strings`, `--ignore-case` and `--all`
+    fn keyword_regex(&self) -> anyhow::Result<Keywords> {
+        let regexes = self.keyword_patterns()
+            .map(|pat| regex::RegexBuilder::new(&pat)
+                .case_insensitive(self.ignore_case)
+                .build()
+            )
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Keywords { regexes, all: self.all })
+    }
+
+    /// Data matches have no name to match all keywords against,
+    /// so multiple keywords are only allowed with `--any`.
+    fn keyword_bytes_matcher(&self) -> anyhow::Result<DataMatcher> {
+        let re = self.keyword_bytes_regex()?;
+
+        if self.more_keywords.is_empty()
+            && let Some(pattern) = self.keyword_patterns().next()
+            && let Some(literal) = literal_bytes(&pattern, self.ignore_case)
+        {
+            let finder = memchr::memmem::Finder::new(&literal).into_owned();
+            return Ok(DataMatcher::Literal(Box::new(finder)));
+        }
+
+        Ok(DataMatcher::Regex(re))
+    }
+
+    fn keyword_bytes_regex(&self) -> anyhow::Result<regex::bytes::Regex> {
+        if self.all && !self.more_keywords.is_empty() {
+            anyhow::bail!("`--all` is not supported by data search");
+        }
+
+        let pattern = self.keyword_patterns()
+            .map(|pat| format!("(?:{})", pat))
+            .collect::<Vec<_>>()
+            .join("|");
+        regex::bytes::RegexBuilder::new(&pattern)
+            .case_insensitive(self.ignore_case)
+            .build()
+            .map_err(Into::into)
+    }
+}
+
+struct Keywords {
+    regexes: Vec<regex::Regex>,
+    all: bool
+}
+
+impl Keywords {
+    fn is_match(&self, haystack: &str) -> bool {
+        if self.all {
+            self.regexes.iter().all(|re| re.is_match(haystack))
+        } else {
+            self.regexes.iter().any(|re| re.is_match(haystack))
+        }
+    }
+}
+
+/// Data keyword, searched with `memmem` instead of the regex engine if it is one literal
+enum DataMatcher {
+    Literal(Box<memchr::memmem::Finder<'static>>),
+    Regex(regex::bytes::Regex)
+}
+
+impl DataMatcher {
+    /// Non-overlapping matches, leftmost first
+    fn find_iter<'a>(&'a self, data: &'a [u8]) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        match self {
+            DataMatcher::Literal(finder) => {
+                let len = finder.needle().len();
+                Box::new(finder.find_iter(data).map(move |start| start..start + len))
+            },
+            DataMatcher::Regex(re) => Box::new(re.find_iter(data).map(|mat| mat.range()))
+        }
+    }
+}
+
+/// Bytes matched by `pattern` if it is a plain non-empty literal
+fn literal_bytes(pattern: &str, ignore_case: bool) -> Option<Vec<u8>> {
+    use regex_syntax::hir::HirKind;
+
+    // as `regex::bytes` parses it, which allows `(?-u)` escapes of any byte
+    let hir = regex_syntax::ParserBuilder::new()
+        .case_insensitive(ignore_case)
+        .utf8(false)
+        .build()
+        .parse(pattern)
+        .ok()?;
+
+    match hir.kind() {
+        HirKind::Literal(literal) => Some(literal.0.to_vec()),
+        _ => None
     }
 }
 
 async fn by_symbol(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
     -> anyhow::Result<()>
 {
-    let re = regex::Regex::new(&cmd.keyword)?;
+    let re = cmd.keyword_regex()?;
+    let exclude = cmd.exclude_regex()?;
     let filter = cmd.filter_section
         .as_ref()
         .map(|rule| regex::Regex::new(rule))
         .transpose()?;
+    if cmd.group_object && explorer.link_map.is_none() {
+        anyhow::bail!("--group-object needs a map file, see `listen --map`");
+    }
+
     let symlist = explorer.cache.symlist(&explorer.obj).await;
-    
+    let source = Source::new(cmd, explorer).await?;
+
+    // only names containing a literal of the keywords can match
+    let index = wait_for(
+        &stdio.stderr,
+        "the symbol index",
+        explorer.cache.names(&explorer.obj, cmd.demangle)
+    ).await;
+    let patterns = cmd.keyword_patterns().collect::<Vec<_>>();
+    let candidates: Cow<'_, [_]> = match index.candidates(&patterns, cmd.ignore_case, cmd.all)? {
+        Some(found) => found.into_iter().map(|pos| symlist[pos]).collect(),
+        None => symlist.into()
+    };
+
     let mut outbuf = Vec::new();
     let mut point = YieldPoint::default();
     let mut output = Vec::new();
     let mut sum = 0;
+    let mut count = 0;
 
-    for &idx in symlist {
+    for &idx in candidates.iter() {
         point.yield_now().await;
 
-        let sym = explorer.obj.symbol_by_index(idx).unwrap();
+        let sym = explorer.sym_obj().symbol_by_index(idx).unwrap();
         let mangled_name = match sym.name() {
             Ok(name) => name,
             Err(err) => {
@@ -96,7 +465,7 @@ async fn by_symbol(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
         
         // filter section by regex
         if let Some(rule) = filter.as_ref() {
-            let sym = explorer.obj.symbol_by_index(idx)?;
+            let sym = explorer.sym_obj().symbol_by_index(idx)?;
             let Some(section_idx) = sym.section_index()
                 else { continue };
             let section = explorer.obj.section_by_index(section_idx)?;
@@ -109,26 +478,49 @@ async fn by_symbol(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
         }
         
         let name = if cmd.demangle {
-            demangle(mangled_name)
+            explorer.cache.demangle(mangled_name)
         } else {
             (*mangled_name).into()
         };
 
-        if re.is_match(&name) {
+        if re.is_match(&name) && !exclude.is_match(&name) {
             let mut sym_size = 0;
 
-            if cmd.size || cmd.sort_size {
+            if cmd.size || cmd.sort_size || cmd.group_section || cmd.group_object || cmd.group_crate || cmd.dedup_stats
+                || cmd.min_size.is_some() || cmd.max_size.is_some()
+                || !stdio.format.is_text()
+                || stdio.template.as_ref().is_some_and(|template| template.uses("size"))
+            {
                 sym_size = explorer.symbol_size(symlist, idx)?;
             }
 
-            if !cmd.sort_size && !cmd.sort_name && !cmd.only_duplicate {
+            if !cmd.size_in_range(sym_size) {
+                continue
+            }
+
+            if !cmd.sort_size && !cmd.sort_name && !cmd.sort_addr && !cmd.reverse
+                && !cmd.only_duplicate && !cmd.group_section && !cmd.group_object && !cmd.group_crate
+                && !cmd.dedup_stats
+            {
+                // past the limit, keep going only to sum the sizes of all matches
+                if cmd.limit.is_some_and(|limit| count >= limit) {
+                    if !cmd.size {
+                        break
+                    }
+
+                    sum += sym_size;
+                    continue
+                }
+
+                count += 1;
                 sum += sym_size;
                 print_symbol(
                     explorer,
                     idx, &name, sym_size,
                     cmd.size,
+                    source.as_ref(),
                     &mut outbuf,
-                    &mut stdio.stdout
+                    stdio
                 )?;
             } else {
                 output.push((idx, name, sym_size));
@@ -136,16 +528,16 @@ async fn by_symbol(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
         }
     }
 
-    output.sort_unstable_by(|(_, name0, size0), (_, name1, size1)| match (cmd.sort_size, cmd.sort_name) {
-        (false, false) => cmp::Ordering::Equal,
-        (true, false) => size0.cmp(size1),
-        (false, true) => name0.cmp(name1),
-        (true, true) => (name0, size0).cmp(&(name1, size1))
-    });
+    if cmd.dedup_stats {
+        return print_dedup_stats(cmd, explorer, &output, stdio);
+    }
+
+    sort_symbols(cmd, &mut output, |&idx| symbol_addr(explorer, idx));
 
     let mut dup = HashSet::new();
+    let mut selected = Vec::new();
 
-    for (idx, name, size) in &output {
+    for item @ (_, name, _) in &output {
         if cmd.only_duplicate {
             let dupname = name.split('.').next().unwrap_or(name);
             if dup.insert(dupname) {
@@ -153,17 +545,255 @@ async fn by_symbol(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
             }
         }
 
-        sum += size;
+        sum += item.2;
+
+        if cmd.limit.is_none_or(|limit| selected.len() < limit) {
+            selected.push(item);
+        }
+    }
+
+    let grouped = cmd.group_section || cmd.group_object || cmd.group_crate;
+
+    if grouped {
+        // stable, so each group keeps the sort order
+        selected.sort_by_cached_key(|(idx, name, _)| {
+            let (section_idx, label) = group_of(cmd, explorer, *idx, name);
+            (section_idx.map(|section_idx| section_idx.0), label)
+        });
+    }
+
+    let mut group: Option<(_, usize, u64)> = None;
+
+    for (idx, name, size) in selected {
+        if grouped {
+            let key = group_of(cmd, explorer, *idx, name);
+
+            if group.as_ref().is_none_or(|(last, ..)| *last != key)
+                && stdio.format.is_text()
+            {
+                if let Some((_, count, sum)) = group.take() {
+                    writeln!(stdio.stdout, "subtotal: {} symbols, {} bytes", count, sum)?;
+                }
+
+                if cmd.group_object {
+                    writeln!(stdio.stdout, "object: {}", MaybePrinter(key.1.as_ref(), Some('?')))?;
+                } else if cmd.group_crate {
+                    writeln!(stdio.stdout, "crate: {}", MaybePrinter(key.1.as_ref(), Some('?')))?;
+                } else {
+                    let section_name = key.0
+                        .and_then(|section_idx| explorer.obj.section_by_index(section_idx).ok())
+                        .and_then(|section| section.name().ok().map(str::to_owned));
+                    writeln!(stdio.stdout, "section: {}", MaybePrinter(section_name, Some('?')))?;
+                }
+                group = Some((key, 0, 0));
+            }
+
+            if let Some((_, count, sum)) = group.as_mut() {
+                *count += 1;
+                *sum += size;
+            }
+        }
+
         print_symbol(
             explorer,
             *idx, name, *size,
             cmd.size,
+            source.as_ref(),
             &mut outbuf,
-            &mut stdio.stdout
+            stdio
+        )?;
+    }
+
+    if let Some((_, count, sum)) = group {
+        writeln!(stdio.stdout, "subtotal: {} symbols, {} bytes", count, sum)?;
+    }
+
+    if (cmd.size || grouped) && stdio.format.is_text() {
+        writeln!(stdio.stdout, "sum: {}", sum)?;
+    }
+
+    Ok(())
+}
+
+/// Section index, object file or crate a symbol is grouped by
+fn group_of<'a>(cmd: &Command, explorer: &'a Explorer, idx: object::SymbolIndex, name: &'a str)
+    -> (Option<object::SectionIndex>, Option<Cow<'a, str>>)
+{
+    let Ok(sym) = explorer.sym_obj().symbol_by_index(idx)
+        else { return (None, None) };
+
+    if cmd.group_object {
+        let object = explorer.link_map.as_ref()
+            .filter(|_| sym.address() != 0)
+            .and_then(|map| map.object_of(sym.address()));
+        (None, object.map(Cow::Borrowed))
+    } else if cmd.group_crate {
+        let label = match (cmd.demangle, explorer.crates.as_ref()) {
+            (true, Some(crates)) => Some(crates.label_of(name).into()),
+            (false, Some(crates)) => Some(crates.label_of(&explorer.cache.demangle(name)).to_owned().into()),
+            (true, None) => cargo::crate_of(name).map(Cow::Borrowed),
+            (false, None) => cargo::crate_of(&explorer.cache.demangle(name)).map(|name| name.to_owned().into())
+        };
+        (None, label)
+    } else {
+        (sym.section_index(), None)
+    }
+}
+
+fn print_dedup_stats(
+    cmd: &Command,
+    explorer: &Explorer,
+    output: &[(object::SymbolIndex, Cow<'_, str>, u64)],
+    stdio: &mut Stdio
+) -> anyhow::Result<()> {
+    #[derive(Default)]
+    struct Stats {
+        count: usize,
+        total: u64,
+        min: u64,
+        max: u64
+    }
+
+    let mut groups: HashMap<String, Stats> = HashMap::new();
+
+    for (_, name, size) in output {
+        let name = if cmd.demangle {
+            dedup_name(name).into()
+        } else {
+            dedup_name(&explorer.cache.demangle(name)).to_owned()
+        };
+        let stats = groups.entry(name).or_default();
+
+        stats.min = if stats.count == 0 { *size } else { stats.min.min(*size) };
+        stats.max = stats.max.max(*size);
+        stats.count += 1;
+        stats.total += size;
+    }
+
+    let mut groups = groups.into_iter()
+        .filter(|(_, stats)| stats.count > 1)
+        .collect::<Vec<_>>();
+
+    if cmd.sort_name {
+        groups.sort_by(|(name0, _), (name1, _)| name0.cmp(name1));
+    } else {
+        groups.sort_by(|(name0, stats0), (name1, stats1)| stats1.total.cmp(&stats0.total)
+            .then_with(|| name0.cmp(name1))
+        );
+    }
+
+    if cmd.reverse {
+        groups.reverse();
+    }
+
+    #[derive(Serialize)]
+    struct Record<'a> {
+        name: &'a str,
+        count: usize,
+        total: u64,
+        min: u64,
+        max: u64
+    }
+
+    let sum: u64 = groups.iter().map(|(_, stats)| stats.total).sum();
+
+    for (name, stats) in groups.iter().take(cmd.limit.unwrap_or(usize::MAX)) {
+        if !stdio.format.is_text() {
+            stdio.record(&Record {
+                name,
+                count: stats.count,
+                total: stats.total,
+                min: stats.min,
+                max: stats.max
+            })?;
+            continue
+        }
+
+        writeln!(
+            stdio.stdout,
+            "{:6} {:10} {:10} {:10} {}",
+            stats.count,
+            stats.total,
+            stats.min,
+            stats.max,
+            name
         )?;
     }
 
-    if cmd.size {
+    if stdio.format.is_text() {
+        writeln!(stdio.stdout, "sum: {}", sum)?;
+    }
+
+    Ok(())
+}
+
+/// Strip `.llvm.N`-style suffixes and the rust legacy hash from a demangled name
+pub(crate) fn dedup_name(name: &str) -> &str {
+    let name = name.split_once(".llvm.").map_or(name, |(name, _)| name);
+
+    match name.rsplit_once("::h") {
+        Some((base, hash)) if hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()) => base,
+        _ => name
+    }
+}
+
+/// Search dynamic and undefined symbols
+///
+/// These are not part of `symlist`, so rows carry address and kind directly.
+async fn by_dynamic(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
+    -> anyhow::Result<()>
+{
+    let re = cmd.keyword_regex()?;
+    let exclude = cmd.exclude_regex()?;
+    let mut point = YieldPoint::default();
+    let mut seen = HashSet::new();
+    let mut output = Vec::new();
+
+    let symbols = if cmd.dynamic {
+        None
+    } else {
+        explorer.obj.symbol_table()
+    };
+    let symbols = symbols.into_iter()
+        .flat_map(|symtab| symtab.symbols())
+        .chain(explorer.obj.dynamic_symbols())
+        .collect::<Vec<_>>();
+
+    for sym in symbols {
+        point.yield_now().await;
+
+        if cmd.undefined && !sym.is_undefined() {
+            continue
+        }
+
+        let Ok(mangled_name) = sym.name()
+            else { continue };
+
+        if mangled_name.is_empty() || !seen.insert((mangled_name, sym.address())) {
+            continue
+        }
+
+        let name = if cmd.demangle {
+            explorer.cache.demangle(mangled_name)
+        } else {
+            mangled_name.into()
+        };
+
+        if re.is_match(&name) && !exclude.is_match(&name) && cmd.size_in_range(sym.size()) {
+            output.push(((sym.address(), explorer.symbol_kind_of(&sym), mangled_name), name, sym.size()));
+        }
+    }
+
+    sort_symbols(cmd, &mut output, |&(addr, ..)| addr);
+
+    let mut outbuf = Vec::new();
+    let sum: u64 = output.iter().map(|(_, _, size)| size).sum();
+
+    for ((addr, kind, mangled_name), name, size) in output.iter().take(cmd.limit.unwrap_or(usize::MAX)) {
+        print_symbol_line(explorer, *addr, *kind, mangled_name, name, *size, cmd.size, None, &mut outbuf, stdio)?;
+    }
+
+    if cmd.size && stdio.format.is_text() {
         writeln!(stdio.stdout, "sum: {}", sum)?;
     }
 
@@ -173,13 +803,25 @@ async fn by_symbol(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
 async fn by_data(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
     -> anyhow::Result<()>
 {
-    let re = regex::bytes::Regex::new(&cmd.keyword)?;
+    #[derive(Serialize)]
+    struct Record<'a> {
+        address: u64,
+        section: Option<&'a str>,
+        text: Cow<'a, str>,
+        bytes: String,
+        length: usize
+    }
+
+    use rayon::prelude::*;
+
+    let matcher = cmd.keyword_bytes_matcher()?;
+    let exclude = cmd.exclude_bytes_regex()?;
     let filter = cmd.filter_section
         .as_ref()
         .map(|rule| regex::Regex::new(rule))
         .transpose()?;
-    let mut point = YieldPoint::default();
-    
+    let mut sections = Vec::new();
+
     for section in explorer.obj.sections()
         .filter(|section| is_data_section(section.kind()))
     {
@@ -192,19 +834,64 @@ async fn by_data(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
         }
 
         if let Ok(data) = explorer.cache.data(&explorer.obj, section.index()).await {
-            let base = section.address();
-            
-            for mat in re.find_iter(&data) {
-                let addr = base + mat.start() as u64;
-                point.yield_now().await;
-
-                writeln!(
-                    &mut stdio.stdout,
-                    "{:018p}\t{:?}\t{}",
-                    addr as *const (),
-                    section.name(),
-                    data[mat.range()].as_bstr()
-                )?;
+            sections.push((section, data));
+        }
+    }
+
+    // search sections in parallel, then print in section order
+    let found = sections.par_iter()
+        .map(|(_, data)| matcher.find_iter(data)
+            .filter(|range| !exclude.is_match(&data[range.clone()]))
+            .take(cmd.limit.unwrap_or(usize::MAX))
+            .collect::<Vec<_>>()
+        )
+        .collect::<Vec<_>>();
+    let mut point = YieldPoint::default();
+    let mut count = 0;
+
+    for ((section, data), found) in sections.iter().zip(found) {
+        let base = section.address();
+
+        for range in found {
+            if cmd.limit.is_some_and(|limit| count >= limit) {
+                return Ok(())
+            }
+
+            let addr = base + range.start as u64;
+            count += 1;
+            point.yield_now().await;
+
+            let matched = &data[range.clone()];
+            let shown = &matched[..matched.len().min(cmd.max_match_len.unwrap_or(usize::MAX))];
+
+            if !stdio.format.is_text() {
+                stdio.record(&Record {
+                    address: addr,
+                    section: section.name().ok(),
+                    text: shown.to_str_lossy(),
+                    bytes: data_encoding::HEXLOWER.encode(shown),
+                    length: matched.len()
+                })?;
+                continue
+            }
+
+            if let Some(before) = cmd.before {
+                let start = range.start.saturating_sub(before);
+                print_context(&mut stdio.stdout, base + start as u64, &data[start..range.start])?;
+            }
+
+            writeln!(
+                &mut stdio.stdout,
+                "{:018p}\t{:?}\t{}{}",
+                addr as *const (),
+                section.name(),
+                shown.as_bstr(),
+                if shown.len() < matched.len() { "…" } else { "" }
+            )?;
+
+            if let Some(after) = cmd.after {
+                let end = range.end.saturating_add(after).min(data.len());
+                print_context(&mut stdio.stdout, base + range.end as u64, &data[range.end..end])?;
             }
         }
     }
@@ -212,49 +899,1489 @@ async fn by_data(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
     Ok(())    
 }
 
-async fn by_call(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
-    -> anyhow::Result<()>
-{
+/// Hexdump rows around a data match
+fn print_context<W: Write>(stdout: &mut W, addr: u64, data: &[u8]) -> anyhow::Result<()> {
+    let width = 16;
+
+    for (offset, chunk) in data.chunks(width).enumerate() {
+        writeln!(
+            stdout,
+            "  {:018p}  {} {}",
+            addr.wrapping_add((offset * width) as u64) as *const u8,
+            HexPrinter(chunk, width),
+            AsciiPrinter(chunk)
+        )?;
+    }
+
+    Ok(())
+}
+
+async fn by_pattern(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
+    -> anyhow::Result<()>
+{
+    #[derive(Serialize)]
+    struct Record<'a> {
+        address: u64,
+        section: Option<&'a str>,
+        symbol: Option<String>,
+        bytes: String
+    }
+
+    let pattern = BytePattern::parse(&cmd.keyword)?;
+    let align = cmd.align.unwrap_or(1);
+    let filter = cmd.filter_section
+        .as_ref()
+        .map(|rule| regex::Regex::new(rule))
+        .transpose()?;
+    let addr2sym = explorer.cache.addr2sym(&explorer.obj).await;
+    let mut point = YieldPoint::default();
+    let mut count = 0;
+
+    if align == 0 {
+        anyhow::bail!("alignment must be greater than zero");
+    }
+    
+    for section in explorer.obj.sections()
+        .filter(|section| section.kind() == SectionKind::Text || is_data_section(section.kind()))
+    {
+        // filter section by regex
+        if let Some(rule) = filter.as_ref()
+            && let Ok(section_name) = section.name()
+            && !rule.is_match(section_name)
+        {
+            continue
+        }
+
+        let Ok(data) = explorer.cache.data(&explorer.obj, section.index()).await
+            else { continue };
+        let base = section.address();
+
+        for offset in pattern.find_iter(&data) {
+            let addr = base + offset as u64;
+
+            if !addr.is_multiple_of(align) {
+                continue
+            }
+
+            if cmd.limit.is_some_and(|limit| count >= limit) {
+                return Ok(())
+            }
+
+            count += 1;
+            point.yield_now().await;
+
+            let sym = addr2sym.get(addr).map(|sym| {
+                let name = if cmd.demangle {
+                    explorer.cache.demangle(sym.name())
+                } else {
+                    sym.name().into()
+                };
+                format!("{}+{:#x}", name, addr - sym.address())
+            });
+
+            if !stdio.format.is_text() {
+                stdio.record(&Record {
+                    address: addr,
+                    section: section.name().ok(),
+                    symbol: sym,
+                    bytes: data_encoding::HEXLOWER.encode(&data[offset..][..pattern.len()])
+                })?;
+                continue
+            }
+
+            writeln!(
+                &mut stdio.stdout,
+                "{:018p}\t{:?}\t{}\t{}",
+                addr as *const (),
+                section.name(),
+                MaybePrinter(sym, Some('?')),
+                HexPrinter(&data[offset..][..pattern.len()], pattern.len()),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn by_call(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
+    -> anyhow::Result<()>
+{
+    let exclude = cmd.exclude_regex()?;
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let plt = explorer.cache.plt(&explorer.obj).await;
+    let address = match u64ptr(&cmd.keyword) {
+        Ok(address) => address,
+        Err(_) => resolve_symbol(explorer, &cmd.keyword).await?
+    };
+
+    // a call to the plt stub is a call to the target
+    let stubs = plt.iter()
+        .filter(|entry| entry.addr == address || (entry.target != 0 && entry.target == address))
+        .map(|entry| entry.addr)
+        .collect::<Vec<_>>();
+
+    if stubs.is_empty() {
+        let symidx = symlist
+            .binary_search_by_key(&address, |&idx| explorer.sym_obj().symbol_by_index(idx).unwrap().address())
+            .ok()
+            .context("not found symbol by address")?;
+        let symidx = symlist[symidx];
+        let sym = explorer.sym_obj().symbol_by_index(symidx).unwrap();
+
+        if !matches!(sym.kind(), object::SymbolKind::Text) {
+            anyhow::bail!("symbol kind is not text");
+        }
+    }
+
+    if let Some(depth) = cmd.depth {
+        return by_call_tree(cmd, explorer, address, &stubs, depth, stdio).await;
+    }
+
+    let index = call_index(explorer, stdio).await?;
+    let mut callees = stubs.clone();
+    callees.push(address);
+
+    let callers = callers_of(index, &callees)
+        .into_iter()
+        .map(|(caller, kinds)| (caller, kinds.into_iter().filter(|&kind| kind.shown(cmd)).collect::<Vec<_>>()))
+        .filter(|(_, kinds)| !kinds.is_empty())
+        .collect::<Vec<_>>();
+
+    let vtables = if callers.iter().flat_map(|(_, kinds)| kinds).any(|kind| matches!(kind, CallKind::Vtable(_))) {
+        vtables::find(explorer).await?
+    } else {
+        Vec::new()
+    };
+
+    let mut output = Vec::new();
+
+    for (symidx, kinds) in callers {
+        let sym = explorer.sym_obj().symbol_by_index(symidx)?;
+        let mangled_name = sym.name()?;
+        let name = if cmd.demangle {
+            explorer.cache.demangle(mangled_name)
+        } else {
+            mangled_name.into()
+        };
+
+        if exclude.is_match(&name) {
+            continue
+        }
+
+        let labels = kinds.iter()
+            .map(|&kind| match kind {
+                CallKind::Call => Cow::Borrowed("call"),
+                CallKind::TailJump => Cow::Borrowed("tail-jump"),
+                CallKind::Vtable(addr) => match vtables.iter()
+                    .find(|vtable| vtable.address == addr)
+                    .and_then(|vtable| vtable.type_name.as_deref())
+                {
+                    Some(type_name) => format!("indirect via vtable of {}", type_name).into(),
+                    None => format!("indirect via vtable {:#x}", addr).into()
+                }
+            })
+            .collect::<Vec<_>>();
+        let name = format!("{} ({})", name, labels.join(", "));
+        let size = explorer.symbol_size(symlist, symidx)?;
+
+        output.push((symidx, name, size));
+    }
+
+    sort_symbols(cmd, &mut output, |&idx| symbol_addr(explorer, idx));
+
+    let source = Source::new(cmd, explorer).await?;
+    let mut outbuf = Vec::new();
+
+    for (idx, name, size) in output.iter().take(cmd.limit.unwrap_or(usize::MAX)) {
+        print_symbol(
+            explorer,
+            *idx, name, *size,
+            cmd.size,
+            source.as_ref(),
+            &mut outbuf,
+            stdio
+        )?;
+    }
+
+    Ok(())    
+}
+
+/// How a caller reaches a callee in the call index
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum CallKind {
+    Call,
+    /// jump to another function
+    TailJump,
+    /// call through a slot of the vtable at this address, in a function that refers to it
+    Vtable(u64)
+}
+
+impl CallKind {
+    /// Whether `--no-tail` and `--only-tail` keep it
+    fn shown(self, cmd: &Command) -> bool {
+        match self {
+            CallKind::TailJump => !cmd.no_tail,
+            CallKind::Call | CallKind::Vtable(_) => !cmd.only_tail
+        }
+    }
+}
+
+/// Callers of any of `callees` in the call index, in symbol index order, with how they call
+fn callers_of(index: &[(u64, object::SymbolIndex, CallKind)], callees: &[u64])
+    -> Vec<(object::SymbolIndex, Vec<CallKind>)>
+{
+    let mut calls = callees.iter()
+        .flat_map(|&callee| {
+            let start = index.partition_point(|&(addr, ..)| addr < callee);
+            index[start..].iter().take_while(move |&&(addr, ..)| addr == callee)
+        })
+        .map(|&(_, caller, kind)| (caller, kind))
+        .collect::<Vec<_>>();
+    calls.sort_unstable_by_key(|&(caller, kind)| (caller.0, kind));
+    calls.dedup();
+
+    let mut callers: Vec<(object::SymbolIndex, Vec<CallKind>)> = Vec::new();
+
+    for (caller, kind) in calls {
+        match callers.last_mut() {
+            Some((last, kinds)) if *last == caller => kinds.push(kind),
+            _ => callers.push((caller, vec![kind]))
+        }
+    }
+
+    callers
+}
+
+/// Callers of `address` and their callers in turn, one line per path
+///
+/// A caller already on the path is printed with `(recursive)` and not walked again.
+async fn by_call_tree(
+    cmd: &Command,
+    explorer: &Explorer,
+    address: u64,
+    stubs: &[u64],
+    depth: usize,
+    stdio: &mut Stdio
+)
+    -> anyhow::Result<()>
+{
+    #[derive(Serialize)]
+    struct Record<'a> {
+        depth: usize,
+        address: u64,
+        name: &'a str,
+        recursive: bool
+    }
+
+    struct Walk<'a> {
+        cmd: &'a Command,
+        explorer: &'a Explorer,
+        index: &'a [(u64, object::SymbolIndex, CallKind)],
+        exclude: regex::RegexSet,
+        path: Vec<object::SymbolIndex>,
+        limit: usize
+    }
+
+    impl Walk<'_> {
+        fn print(&self, depth: usize, address: u64, name: &str, recursive: bool, stdio: &mut Stdio)
+            -> anyhow::Result<()>
+        {
+            if !stdio.format.is_text() {
+                return stdio.record(&Record { depth, address, name, recursive });
+            }
+
+            writeln!(
+                stdio.stdout,
+                "{:018p} {:indent$}{}{}",
+                address as *const (),
+                "",
+                name,
+                if recursive { " (recursive)" } else { "" },
+                indent = depth * 2
+            )?;
+
+            Ok(())
+        }
+
+        fn walk(&mut self, callees: &[u64], depth: usize, max_depth: usize, stdio: &mut Stdio)
+            -> anyhow::Result<()>
+        {
+            let mut callers = callers_of(self.index, callees)
+                .into_iter()
+                .filter(|(_, kinds)| kinds.iter().any(|kind| kind.shown(self.cmd)))
+                .map(|(caller, _)| caller)
+                .collect::<Vec<_>>();
+            callers.sort_unstable_by_key(|&idx| (
+                self.explorer.sym_obj().symbol_by_index(idx).map(|sym| sym.address()).unwrap_or_default(),
+                idx.0
+            ));
+            callers.dedup();
+
+            for caller in callers {
+                if self.limit == 0 {
+                    break
+                }
+
+                let sym = self.explorer.sym_obj().symbol_by_index(caller)?;
+                let mangled_name = sym.name().unwrap_or_default();
+                let name = if self.cmd.demangle {
+                    self.explorer.cache.demangle(mangled_name)
+                } else {
+                    mangled_name.into()
+                };
+
+                if self.exclude.is_match(&name) {
+                    continue
+                }
+
+                let recursive = self.path.contains(&caller);
+                self.limit -= 1;
+                self.print(depth, sym.address(), &name, recursive, stdio)?;
+
+                if !recursive && depth < max_depth {
+                    self.path.push(caller);
+                    self.walk(&[sym.address()], depth + 1, max_depth, stdio)?;
+                    self.path.pop();
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let plt = explorer.cache.plt(&explorer.obj).await;
+    let index = call_index(explorer, stdio).await?;
+
+    let root = symlist
+        .binary_search_by_key(&address, |&idx| explorer.sym_obj().symbol_by_index(idx).unwrap().address())
+        .ok()
+        .map(|idx| symlist[idx]);
+    let name = match root {
+        Some(idx) => explorer.sym_obj().symbol_by_index(idx)?.name()?,
+        None => plt.iter()
+            .find(|entry| stubs.contains(&entry.addr))
+            .map(|entry| entry.name)
+            .unwrap_or_default()
+    };
+    let name = if cmd.demangle {
+        explorer.cache.demangle(name)
+    } else {
+        name.into()
+    };
+
+    let mut callees = stubs.to_vec();
+    callees.push(address);
+
+    let mut walk = Walk {
+        cmd, explorer, index,
+        exclude: cmd.exclude_regex()?,
+        path: root.into_iter().collect(),
+        limit: cmd.limit.unwrap_or(usize::MAX)
+    };
+
+    walk.print(0, address, &name, false, stdio)?;
+    walk.walk(&callees, 1, depth, stdio)
+}
+
+/// Calls of every text symbol as (callee, caller, kind), sorted by callee, built on first use
+///
+/// A call to a plt stub is a call to its target if that is defined here, else to the stub.
+pub(crate) async fn call_index<'a>(explorer: &'a Explorer, stdio: &Stdio)
+    -> anyhow::Result<&'a [(u64, object::SymbolIndex, CallKind)]>
+{
+    use super::show;
+
+    let addr2sym = explorer.cache.addr2sym(&explorer.obj).await;
+    let dyn_rela = explorer.cache.dyn_rela(&explorer.obj).await;
+    let plt = explorer.cache.plt(&explorer.obj).await;
+
+    explorer.cache.calls.get_or_try_init(async || {
+        let vtables = vtables::find(explorer).await?;
+        let resolve = |addr| match show::query_plt(plt, addr) {
+            Some(entry) if entry.target != 0 => Some(entry.target),
+            Some(entry) => Some(entry.addr),
+            None => show::query_symbol_by_addr(explorer, addr2sym, dyn_rela, addr)
+                .map(|(_name, callee)| callee)
+        };
+
+        let mut index = par_disasm(explorer, None, stdio, |disasm, symidx, size, insts| {
+            let sym = explorer.sym_obj().symbol_by_index(symidx)?;
+            let own = sym.address()..sym.address() + size;
+            let calls = calls_of(disasm, insts, own, &resolve, &vtables)?;
+
+            Ok(calls.into_iter().map(|(callee, kind)| (callee, symidx, kind)).collect())
+        }).await?;
+
+        index.sort_unstable_by_key(|&(callee, caller, kind)| (callee, caller.0, kind));
+        index.dedup();
+        Ok(index.into_boxed_slice())
+    }).await.map(|index| &**index)
+}
+
+/// Callees of the function at `own` and how it reaches them
+///
+/// A jump out of the function is a tail jump. A load from a vtable marks it as used, a call
+/// through a displacement then counts for the method at that offset from its address point,
+/// as does a load of a method slot itself.
+fn calls_of(
+    disasm: &Disassembler,
+    mut insts: InstIter<'_>,
+    own: std::ops::Range<u64>,
+    resolve: &impl Fn(u64) -> Option<u64>,
+    vtables: &[vtables::Vtable]
+) -> anyhow::Result<Vec<(u64, CallKind)>> {
+    let mut calls = Vec::new();
+    let mut data_refs = DataRefs::default();
+    // vtables used by the function and the displacement of the last load
+    let mut used = Vec::new();
+    let mut last_disp = None;
+
+    while let Some(Ok(inst)) = insts.next() {
+        let Some(addr) = disasm.operand2addr(&inst)?
+            else {
+                if vtables.is_empty() {
+                    continue
+                }
+
+                if let Some(addr) = disasm.operand2data(&inst, &mut data_refs)
+                    && let Some(vtable) = vtable_at(vtables, addr)
+                {
+                    if let Some(callee) = vtable.method_at(addr) {
+                        calls.push((callee, CallKind::Vtable(vtable.address)));
+                    }
+
+                    if !used.iter().any(|used: &&vtables::Vtable| used.address == vtable.address) {
+                        used.push(vtable);
+                    }
+                }
+
+                let disp = disasm.mem_displacement(&inst);
+
+                if disasm.inst_kind(&inst) == disasm::InstKind::Call
+                    && let Some(disp) = disp.or(last_disp)
+                {
+                    calls.extend(used.iter()
+                        .filter_map(|vtable| Some((vtable.method_at(vtable.point.wrapping_add_signed(disp))?, vtable.address)))
+                        .map(|(callee, vtable)| (callee, CallKind::Vtable(vtable))));
+                } else if disp.is_some() {
+                    last_disp = disp;
+                }
+
+                continue
+            };
+
+        let Some(callee) = resolve(addr)
+            else { continue };
+
+        match disasm.inst_kind(&inst) {
+            disasm::InstKind::Call => calls.push((callee, CallKind::Call)),
+            // a jump within the function, such as back to its start, is not a call
+            _ if own.contains(&addr) => (),
+            _ => calls.push((callee, CallKind::TailJump))
+        }
+    }
+
+    Ok(calls)
+}
+
+/// Vtable whose header or method slots cover `addr`, of vtables in address order
+fn vtable_at(vtables: &[vtables::Vtable], addr: u64) -> Option<&vtables::Vtable> {
+    let idx = vtables.partition_point(|vtable| vtable.address <= addr).checked_sub(1)?;
+    let vtable = &vtables[idx];
+    let end = vtable.methods.last().map_or(vtable.point, |&(slot, _)| slot + 1);
+
+    (vtable.address..end).contains(&addr).then_some(vtable)
+}
+
+/// Stack frame size of each function, from its CFI if that tracks the stack pointer
+/// throughout, else from its prologue
+async fn by_stack(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
+    -> anyhow::Result<()>
+{
+    let re = cmd.keyword_regex()?;
+    let exclude = cmd.exclude_regex()?;
+    let filter = cmd.filter_section
+        .as_ref()
+        .map(|rule| regex::Regex::new(rule))
+        .transpose()?;
+    let frames = explorer.cache.frames(&explorer.obj).await?;
+    let sizes = crate::frames::frame_sizes(frames, explorer.obj.architecture())?;
+
+    let mut output = par_disasm(explorer, filter.as_ref(), stdio, |disasm, symidx, _size, mut insts| {
+        let sym = explorer.sym_obj().symbol_by_index(symidx)?;
+
+        if !matches!(sym.kind(), object::SymbolKind::Text) {
+            return Ok(Vec::new());
+        }
+
+        let mangled_name = sym.name()?;
+        let name = if cmd.demangle {
+            explorer.cache.demangle(mangled_name)
+        } else {
+            mangled_name.into()
+        };
+
+        if !re.is_match(&name) || exclude.is_match(&name) {
+            return Ok(Vec::new());
+        }
+
+        let addr = sym.address();
+        let cfi = sizes.partition_point(|&(start, ..)| start <= addr)
+            .checked_sub(1)
+            .map(|idx| sizes[idx])
+            .filter(|&(_, end, _)| addr < end)
+            .and_then(|(.., size)| size);
+        let size = match cfi {
+            Some(size) => size,
+            None => disasm::stack::prologue_size(disasm, &mut insts)?
+        };
+
+        if !cmd.size_in_range(size) {
+            return Ok(Vec::new());
+        }
+
+        Ok(vec![(symidx, name, size)])
+    }).await?;
+
+    output.sort_unstable_by_key(|(idx, ..)| idx.0);
+    sort_symbols(cmd, &mut output, |&idx| symbol_addr(explorer, idx));
+
+    let source = Source::new(cmd, explorer).await?;
+    let mut outbuf = Vec::new();
+
+    for (idx, name, size) in output.iter().take(cmd.limit.unwrap_or(usize::MAX)) {
+        print_symbol(
+            explorer,
+            *idx, name, *size,
+            true,
+            source.as_ref(),
+            &mut outbuf,
+            stdio
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Defined code and data symbols that nothing else refers to, see `refs::collect`
+///
+/// Exported symbols, the entry point and other roots are left out as they are used from outside.
+/// References from a symbol to itself do not count, so a recursive function can still be unreferenced.
+async fn by_unreferenced(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
+    -> anyhow::Result<()>
+{
+    let re = cmd.keyword_regex()?;
+    let exclude = cmd.exclude_regex()?;
+    let filter = cmd.filter_section
+        .as_ref()
+        .map(|rule| regex::Regex::new(rule))
+        .transpose()?;
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let refs = refs::collect(explorer, stdio).await?;
+    let roots = refs::roots(explorer, &refs)?;
+    let mut point = YieldPoint::default();
+    let mut output = Vec::new();
+
+    for &idx in symlist {
+        point.yield_now().await;
+
+        let sym = explorer.sym_obj().symbol_by_index(idx)?;
+
+        if sym.is_undefined()
+            || !matches!(sym.kind(), object::SymbolKind::Text | object::SymbolKind::Data)
+            || roots.contains(&sym.address())
+        {
+            continue
+        }
+
+        let Some(section_idx) = sym.section_index()
+            else { continue };
+
+        // filter section by regex
+        if let Some(rule) = filter.as_ref()
+            && let Ok(section_name) = explorer.obj.section_by_index(section_idx)?.name()
+            && !rule.is_match(section_name)
+        {
+            continue
+        }
+
+        // markers defined by the linker have no size
+        let size = explorer.symbol_size(symlist, idx)?;
+        if size == 0 {
+            continue
+        }
+
+        // a reference to any field of the symbol counts
+        let range = sym.address()..sym.address() + size;
+        let pos = refs.partition_point(|&(_, to)| to < range.start);
+        let referenced = refs[pos..].iter()
+            .take_while(|(_, to)| range.contains(to))
+            .any(|(from, _)| !range.contains(from));
+
+        if referenced || !cmd.size_in_range(size) {
+            continue
+        }
+
+        let mangled_name = sym.name()?;
+        let name = if cmd.demangle {
+            explorer.cache.demangle(mangled_name)
+        } else {
+            mangled_name.into()
+        };
+
+        if re.is_match(&name) && !exclude.is_match(&name) {
+            output.push((idx, name, size));
+        }
+    }
+
+    sort_symbols(cmd, &mut output, |&idx| symbol_addr(explorer, idx));
+
+    let source = Source::new(cmd, explorer).await?;
+    let mut outbuf = Vec::new();
+    let sum: u64 = output.iter().map(|(_, _, size)| size).sum();
+
+    for (idx, name, size) in output.iter().take(cmd.limit.unwrap_or(usize::MAX)) {
+        print_symbol(
+            explorer,
+            *idx, name, *size,
+            true,
+            source.as_ref(),
+            &mut outbuf,
+            stdio
+        )?;
+    }
+
+    if stdio.format.is_text() {
+        writeln!(stdio.stdout, "sum: {}", sum)?;
+    }
+
+    Ok(())
+}
+
+/// Text symbols with the same bytes, which identical code folding could have merged
+///
+/// With `--normalize` the targets of calls, jumps and rip-relative operands are compared by
+/// the address they resolve to, or their offset if within the symbol, instead of by encoding,
+/// so copies at different addresses that use the same callees and data still match.
+/// Aliases, symbols at the same address, are already folded and count once.
+async fn by_identical(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
+    -> anyhow::Result<()>
+{
+    use std::hash::{ Hash, Hasher };
+    use std::collections::hash_map::DefaultHasher;
+
+    let re = cmd.keyword_regex()?;
+    let exclude = cmd.exclude_regex()?;
+    let filter = cmd.filter_section
+        .as_ref()
+        .map(|rule| regex::Regex::new(rule))
+        .transpose()?;
+
+    let mut sections = HashMap::new();
+    for section in explorer.obj.sections()
+        .filter(|section| section.kind() == SectionKind::Text)
+    {
+        let data = explorer.cache.data(&explorer.obj, section.index()).await?;
+        sections.insert(section.index(), (section.address(), data));
+    }
+
+    let mut output = par_disasm(explorer, filter.as_ref(), stdio, |disasm, symidx, size, insts| {
+        let sym = explorer.sym_obj().symbol_by_index(symidx)?;
+
+        if !matches!(sym.kind(), object::SymbolKind::Text) || size == 0 || !cmd.size_in_range(size) {
+            return Ok(Vec::new());
+        }
+
+        let mangled_name = sym.name()?;
+        let name = if cmd.demangle {
+            explorer.cache.demangle(mangled_name)
+        } else {
+            mangled_name.into()
+        };
+
+        if !re.is_match(&name) || exclude.is_match(&name) {
+            return Ok(Vec::new());
+        }
+
+        let Some(data) = symbol_data(&sections, &sym, size)
+            else { return Ok(Vec::new()) };
+        let key = identical_key(disasm, insts, sym.address(), data, cmd.normalize)?;
+
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+
+        Ok(vec![(symidx, name, size, hasher.finish())])
+    }).await?;
+
+    output.dedup_by_key(|(idx, ..)| symbol_addr(explorer, *idx));
+    output.sort_by_cached_key(|&(idx, _, size, hash)| (size, hash, symbol_addr(explorer, idx)));
+
+    let source = Source::new(cmd, explorer).await?;
+    let disasm = Disassembler::new(&explorer.obj)?;
+    let mut groups = Vec::new();
+
+    // equal hashes only narrow, the members of a group are compared by their bytes
+    for group in output.chunk_by(|(_, _, size0, hash0), (_, _, size1, hash1)| (size0, hash0) == (size1, hash1))
+        .filter(|group| group.len() > 1)
+    {
+        let mut keyed: Vec<(Cow<'_, [u8]>, Vec<_>)> = Vec::new();
+
+        for item in group {
+            let (idx, _, size, _) = item;
+            let sym = explorer.sym_obj().symbol_by_index(*idx)?;
+            let Some(data) = symbol_data(&sections, &sym, *size)
+                else { continue };
+            let insts = disasm.disasm_iter(data, sym.address())?;
+            let key = identical_key(&disasm, insts, sym.address(), data, cmd.normalize)?;
+
+            match keyed.iter_mut().find(|(key0, _)| *key0 == key) {
+                Some((_, members)) => members.push(item),
+                None => keyed.push((key, vec![item]))
+            }
+        }
+
+        groups.extend(keyed.into_iter()
+            .map(|(_, members)| members)
+            .filter(|members| members.len() > 1));
+    }
+
+    // most wasted bytes first
+    groups.sort_by_key(|group| cmp::Reverse((group.len() as u64 - 1) * group[0].2));
+
+    let mut outbuf = Vec::new();
+    let sum: u64 = groups.iter()
+        .map(|group| (group.len() as u64 - 1) * group[0].2)
+        .sum();
+
+    for group in groups.iter().take(cmd.limit.unwrap_or(usize::MAX)) {
+        let wasted = (group.len() as u64 - 1) * group[0].2;
+
+        for (idx, name, size, _) in group.iter() {
+            print_symbol(
+                explorer,
+                *idx, name, *size,
+                true,
+                source.as_ref(),
+                &mut outbuf,
+                stdio
+            )?;
+        }
+
+        if stdio.format.is_text() {
+            writeln!(stdio.stdout, "identical: {} symbols, {} bytes wasted", group.len(), wasted)?;
+        }
+    }
+
+    if stdio.format.is_text() {
+        writeln!(stdio.stdout, "sum: {}", sum)?;
+    }
+
+    Ok(())
+}
+
+/// Bytes of a text symbol of `size`, from the sections read by `by_identical`
+fn symbol_data<'a>(
+    sections: &'a HashMap<object::SectionIndex, (u64, SectionData)>,
+    sym: &object::Symbol<'_, '_>,
+    size: u64
+) -> Option<&'a [u8]> {
+    let (section_addr, section_data) = sections.get(&sym.section_index()?)?;
+    section_data.get((sym.address() - section_addr) as usize..)?.get(..size as usize)
+}
+
+/// What `by_identical` compares of a symbol at `start`: its bytes, or with `normalize`
+/// each instruction as its encoding or as its mnemonic and resolved target
+fn identical_key<'a>(
+    disasm: &Disassembler,
+    mut insts: InstIter<'_>,
+    start: u64,
+    data: &'a [u8],
+    normalize: bool
+) -> anyhow::Result<Cow<'a, [u8]>> {
+    if !normalize {
+        return Ok(Cow::Borrowed(data));
+    }
+
+    let size = data.len() as u64;
+    let mut refs = DataRefs::default();
+    let mut key = Vec::with_capacity(data.len());
+    let mut decoded = 0;
+
+    // each item is tagged and self-delimiting, so distinct sequences never encode alike
+    while let Some(Ok(inst)) = insts.next() {
+        let target = match disasm.operand2addr(&inst)? {
+            Some(addr) => Some(addr),
+            None => disasm.operand2data(&inst, &mut refs)
+        };
+
+        let (tag, value) = match target {
+            Some(addr) if (start..start + size).contains(&addr) => (0, addr - start),
+            Some(addr) => (1, addr),
+            None => {
+                key.push(2);
+                key.extend_from_slice(&(inst.bytes().len() as u32).to_le_bytes());
+                key.extend_from_slice(inst.bytes());
+                decoded += inst.bytes().len();
+                continue
+            }
+        };
+
+        key.push(tag);
+        key.extend_from_slice(inst.mnemonic().unwrap_or_default().as_bytes());
+        key.push(0xff);
+        key.extend_from_slice(&value.to_le_bytes());
+        decoded += inst.bytes().len();
+    }
+
+    // bytes that did not decode are compared as they are
+    key.push(3);
+    key.extend_from_slice(data.get(decoded..).unwrap_or_default());
+
+    Ok(Cow::Owned(key))
+}
+
+async fn by_xref(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
+    -> anyhow::Result<()>
+{
+    use super::show;
+
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let addr2sym = explorer.cache.addr2sym(&explorer.obj).await;
+    let dyn_rela = explorer.cache.dyn_rela(&explorer.obj).await;
+    let address = match u64ptr(&cmd.keyword) {
+        Ok(address) => address,
+        Err(_) => resolve_symbol(explorer, &cmd.keyword).await?
+    };
+
+    // a reference to any field of the symbol counts
+    let range = match symlist
+        .binary_search_by_key(&address, |&idx| explorer.sym_obj().symbol_by_index(idx).unwrap().address())
+    {
+        Ok(idx) => {
+            let size = explorer.symbol_size(symlist, symlist[idx])?;
+            address..address + size.max(1)
+        },
+        Err(_) => address..address + 1
+    };
+    let symbol_name = |addr: u64| addr2sym.get(addr).map(|sym| {
+        let name = if cmd.demangle {
+            explorer.cache.demangle(sym.name())
+        } else {
+            sym.name().into()
+        };
+        format!("{}+{:#x}", name, addr - sym.address())
+    });
+
+    // code references
+    let output = par_disasm(explorer, None, stdio, |disasm, _symidx, _size, mut insts| {
+        let mut hits = Vec::new();
+        let mut refs = DataRefs::default();
+
+        while let Some(Ok(inst)) = insts.next() {
+            let Some(addr) = disasm.operand2data(&inst, &mut refs)
+                else { continue };
+
+            // GOT-indirect access
+            let hit = range.contains(&addr)
+                || show::query_symbol_by_addr(explorer, addr2sym, dyn_rela, addr)
+                    .is_some_and(|(_, target)| target != addr && range.contains(&target));
+
+            if hit {
+                hits.push((inst.address(), access_kind(&inst), inst.to_string()));
+            }
+        }
+
+        Ok(hits)
+    }).await?;
+
+    #[derive(Serialize)]
+    struct Record<'a> {
+        address: u64,
+        kind: &'a str,
+        symbol: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        inst: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        section: Option<&'a str>
+    }
+
+    let mut point = YieldPoint::default();
+
+    for (addr, kind, text) in &output {
+        point.yield_now().await;
+
+        if !stdio.format.is_text() {
+            stdio.record(&Record {
+                address: *addr,
+                kind,
+                symbol: symbol_name(*addr),
+                inst: Some(text),
+                section: None
+            })?;
+            continue
+        }
+
+        writeln!(
+            &mut stdio.stdout,
+            "{:018p}\t{}\t{}\t{}",
+            *addr as *const (),
+            kind,
+            MaybePrinter(symbol_name(*addr), Some('?')),
+            text
+        )?;
+    }
+
+    // pointers stored in data, by relocation or by value
+    let mut pointers = dyn_rela.iter()
+        .filter(|(_, rela)| match rela.target() {
+            object::RelocationTarget::Symbol(symidx) => explorer.obj.symbol_by_index(symidx)
+                .is_ok_and(|sym| range.contains(&sym.address().wrapping_add_signed(rela.addend()))),
+            object::RelocationTarget::Absolute => u64::try_from(rela.addend())
+                .is_ok_and(|addr| range.contains(&addr)),
+            _ => false
+        })
+        .map(|(addr, _)| *addr)
+        .collect::<Vec<_>>();
+
+    let ptr_size = if explorer.obj.is_64() { 8 } else { 4 };
+    let endian = explorer.obj.endianness();
+
+    for section in explorer.obj.sections()
+        .filter(|section| is_data_section(section.kind()))
+    {
+        let Ok(data) = explorer.cache.data(&explorer.obj, section.index()).await
+            else { continue };
+        let base = section.address();
+
+        for (i, word) in data.chunks_exact(ptr_size).enumerate() {
+            if range.contains(&read_pointer(word, endian)) {
+                pointers.push(base + (i * ptr_size) as u64);
+            }
+        }
+
+        point.yield_now().await;
+    }
+
+    pointers.sort_unstable();
+    pointers.dedup();
+
+    for addr in pointers {
+        let section = explorer.obj.sections()
+            .find(|section| (section.address()..section.address() + section.size()).contains(&addr));
+
+        if !stdio.format.is_text() {
+            stdio.record(&Record {
+                address: addr,
+                kind: "ptr",
+                symbol: symbol_name(addr),
+                inst: None,
+                section: section.as_ref().and_then(|section| section.name().ok())
+            })?;
+            continue
+        }
+
+        writeln!(
+            &mut stdio.stdout,
+            "{:018p}\tptr\t{}\t{}",
+            addr as *const (),
+            MaybePrinter(symbol_name(addr), Some('?')),
+            MaybePrinter(section.as_ref().and_then(|section| section.name().ok()), Some('?'))
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Guess whether an instruction reads, writes or takes the address of its data operand
+fn access_kind(inst: &Inst<'_>) -> &'static str {
+    let mnemonic = inst.mnemonic().unwrap_or_default();
+    let dst = inst.op_str().unwrap_or_default().split(',').next().unwrap_or_default();
+
+    match mnemonic {
+        "lea" | "adr" | "adrp" => "addr",
+        "add" if matches!(inst, Inst::Aarch64(_)) => "addr",
+        "cmp" | "test" | "ucomiss" | "ucomisd" => "read",
+        mnemonic if mnemonic.starts_with("st") => "write",
+        mnemonic if mnemonic.starts_with("ld") => "read",
+        _ if dst.contains('[') => "write",
+        _ => "read"
+    }
+}
+
+async fn by_dwarf(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
+    -> anyhow::Result<()>
+{
+    use addr2line::gimli;
+
+    #[derive(Serialize)]
+    struct Record<'a> {
+        kind: &'a str,
+        name: &'a str,
+        address: Option<u64>,
+        file: Option<String>,
+        line: Option<u64>
+    }
+
+    let re = cmd.keyword_regex()?;
+    let exclude = cmd.exclude_regex()?;
+    let dwarf = explorer.cache.dwarf(explorer.dwarf_obj(), explorer.dwarf_path.as_deref()).await?;
+    let mut point = YieldPoint::default();
+    let mut units = dwarf.units();
+    let mut seen = HashSet::new();
+    let mut count = 0;
+
+    while let Some(header) = units.next()? {
+        point.yield_now().await;
+
+        let unit = dwarf.unit(header)?;
+        let mut entries = unit.entries();
+        let mut depth = 0;
+        let mut scope = crate::dwarf::Scope::default();
+
+        while let Some((delta, entry)) = entries.next_dfs()? {
+            stdio.cancel.check()?;
+            depth += delta;
+
+            let tag = entry.tag();
+            let name = crate::dwarf::attr_string(dwarf, &unit, entry, gimli::DW_AT_name);
+            let is_declaration = crate::dwarf::attr_flag(entry, gimli::DW_AT_declaration);
+            scope.enter(depth, tag, name.clone());
+
+            let kind = match tag {
+                gimli::DW_TAG_structure_type if cmd.dwarf_types => "struct",
+                gimli::DW_TAG_class_type if cmd.dwarf_types => "class",
+                gimli::DW_TAG_union_type if cmd.dwarf_types => "union",
+                gimli::DW_TAG_enumeration_type if cmd.dwarf_types => "enum",
+                gimli::DW_TAG_typedef if cmd.dwarf_types => "typedef",
+                gimli::DW_TAG_variable if cmd.dwarf_vars => "var",
+                _ => continue
+            };
+
+            if is_declaration {
+                continue
+            }
+
+            // out-of-line definition of a member variable
+            let (name, spec) = match (name, entry.attr_value(gimli::DW_AT_specification)) {
+                (Some(name), _) => (name, None),
+                (None, Ok(Some(gimli::AttributeValue::UnitRef(offset)))) => {
+                    let Ok(spec) = unit.entry(offset)
+                        else { continue };
+                    let Some(name) = crate::dwarf::attr_string(dwarf, &unit, &spec, gimli::DW_AT_name)
+                        else { continue };
+                    (name, Some(spec))
+                },
+                _ => continue
+            };
+
+            let address = if tag == gimli::DW_TAG_variable {
+                // locals without static storage have no address
+                match crate::dwarf::location_address(dwarf, &unit, entry) {
+                    Some(address) => Some(address),
+                    None => continue
+                }
+            } else {
+                None
+            };
+
+            let qualified_name = scope.qualify(&name);
+
+            if !re.is_match(&qualified_name) || exclude.is_match(&qualified_name) {
+                continue
+            }
+
+            let location = crate::dwarf::decl_location(dwarf, &unit, entry)
+                .or_else(|| crate::dwarf::decl_location(dwarf, &unit, spec.as_ref()?));
+
+            if !seen.insert((kind, qualified_name.clone(), location.clone(), address)) {
+                continue
+            }
+
+            if cmd.limit.is_some_and(|limit| count >= limit) {
+                return Ok(())
+            }
+
+            count += 1;
+
+            if !stdio.format.is_text() {
+                let (file, line) = location.unzip();
+                stdio.record(&Record {
+                    kind,
+                    name: &qualified_name,
+                    address,
+                    file,
+                    line: line.flatten()
+                })?;
+                continue
+            }
+
+            let location = location.map(|(file, line)| format!("{}:{}", file, MaybePrinter(line, Some('?'))));
+
+            match address {
+                Some(address) => writeln!(
+                    stdio.stdout,
+                    "{:018p}\t{}\t{}",
+                    address as *const (),
+                    qualified_name,
+                    MaybePrinter(location, Some('?'))
+                )?,
+                None => writeln!(
+                    stdio.stdout,
+                    "{}\t{}\t{}",
+                    kind,
+                    qualified_name,
+                    MaybePrinter(location, Some('?'))
+                )?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Address ranges generated from a source line, inlined copies included
+async fn by_line(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
+    -> anyhow::Result<()>
+{
+    #[derive(Serialize)]
+    struct Record<'a> {
+        start: u64,
+        end: u64,
+        symbol: Option<String>,
+        file: &'a str,
+        line: u64
+    }
+
+    let Some((file, line)) = cmd.keyword.rsplit_once(':')
+        else {
+            anyhow::bail!("expect `file:line`, got `{}`", cmd.keyword)
+        };
+    let file = std::path::Path::new(file);
+    let line: u64 = line.parse().context("bad line number")?;
+
+    let dwarf = explorer.cache.dwarf(explorer.dwarf_obj(), explorer.dwarf_path.as_deref()).await?;
+    let addr2sym = explorer.cache.addr2sym(&explorer.obj).await;
+    let mut point = YieldPoint::default();
+    let mut units = dwarf.units();
+    let mut ranges = Vec::new();
+
+    while let Some(header) = units.next()? {
+        point.yield_now().await;
+
+        let unit = dwarf.unit(header)?;
+        let Some(program) = unit.line_program.clone()
+            else { continue };
+        // file index to matched path
+        let mut files: HashMap<u64, Option<String>> = HashMap::new();
+        let mut rows = program.rows();
+        let mut open: Option<(u64, String)> = None;
+
+        while let Some((_, row)) = rows.next_row()? {
+            stdio.cancel.check()?;
+
+            if let Some((start, path)) = open.take() {
+                ranges.push((start, row.address(), path));
+            }
+
+            if row.end_sequence() || row.line().map(|n| n.get()) != Some(line) {
+                continue
+            }
+
+            let path = files.entry(row.file_index())
+                .or_insert_with(|| crate::dwarf::file_path(dwarf, &unit, row.file_index())
+                    .filter(|path| std::path::Path::new(path).ends_with(file)));
+
+            // skip code discarded by the linker
+            if let Some(path) = path
+                && row.address() != 0
+            {
+                open = Some((row.address(), path.clone()));
+            }
+        }
+    }
+
+    ranges.sort();
+
+    // join adjacent rows
+    let mut merged: Vec<(u64, u64, String)> = Vec::new();
+    for (start, end, path) in ranges {
+        match merged.last_mut() {
+            Some(last) if last.1 == start && last.2 == path => last.1 = end,
+            _ => merged.push((start, end, path))
+        }
+    }
+
+    for (start, end, path) in merged.iter().take(cmd.limit.unwrap_or(usize::MAX)) {
+        let symbol = addr2sym.get(*start).map(|sym| {
+            let name = if cmd.demangle {
+                explorer.cache.demangle(sym.name())
+            } else {
+                sym.name().into()
+            };
+            format!("{}+{:#x}", name, start - sym.address())
+        });
+
+        if !stdio.format.is_text() {
+            stdio.record(&Record { start: *start, end: *end, symbol, file: path, line })?;
+            continue
+        }
+
+        writeln!(
+            stdio.stdout,
+            "{:018p}..{:018p}\t{}\t{}:{}",
+            *start as *const (),
+            *end as *const (),
+            MaybePrinter(symbol, Some('?')),
+            path,
+            line
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Resolve a mangled or demangled symbol name to its address
+///
+/// Falls back to plt stubs for imported functions.
+async fn resolve_symbol(explorer: &Explorer, name: &str) -> anyhow::Result<u64> {
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let plt = explorer.cache.plt(&explorer.obj).await;
+    let mut point = YieldPoint::default();
+    let mut found = Vec::new();
+
+    for &idx in symlist {
+        point.yield_now().await;
+
+        let sym = explorer.sym_obj().symbol_by_index(idx)?;
+        let Ok(mangled_name) = sym.name()
+            else { continue };
+
+        if sym.is_undefined() || sym.address() == 0 {
+            continue
+        }
+
+        // demangled again if the background index is not built yet
+        if mangled_name == name || explorer.cache.demangle(mangled_name) == name {
+            found.push((sym.address(), idx, mangled_name));
+        }
+    }
+
+    found.dedup_by_key(|(addr, ..)| *addr);
+
+    match found.as_slice() {
+        [] => plt.iter()
+            .find(|entry| entry.name == name || explorer.cache.demangle(entry.name) == name)
+            .map(|entry| entry.addr)
+            .with_context(|| format!("not found symbol by name: {:?}", name)),
+        [(addr, ..)] => Ok(*addr),
+        found => {
+            let mut msg = format!("ambiguous symbol name: {:?}, candidates:", name);
+            for (addr, idx, mangled_name) in found {
+                use std::fmt::Write;
+
+                write!(
+                    msg,
+                    "\n{:018p} {} {}",
+                    *addr as *const (),
+                    explorer.symbol_kind(*idx),
+                    mangled_name
+                )?;
+            }
+            anyhow::bail!(msg)
+        }
+    }
+}
+
+async fn by_asm(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
+    -> anyhow::Result<()>
+{
+    let re = cmd.keyword_regex()?;
+    let exclude = cmd.exclude_regex()?;
+    let filter = cmd.filter_section
+        .as_ref()
+        .map(|rule| regex::Regex::new(rule))
+        .transpose()?;
+
+    let output = par_disasm(explorer, filter.as_ref(), stdio, |_disasm, symidx, _size, mut insts| {
+        let mut hits = Vec::new();
+        let mut text = String::new();
+
+        while let Some(Ok(inst)) = insts.next() {
+            use std::fmt::Write;
+
+            text.clear();
+            write!(text, "{}", inst)?;
+
+            if re.is_match(&text) && !exclude.is_match(&text) {
+                hits.push((symidx, inst.address(), text.clone()));
+            }
+        }
+
+        Ok(hits)
+    }).await?;
+
+    print_insts(cmd, explorer, &output, stdio).await
+}
+
+async fn by_imm(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
+    -> anyhow::Result<()>
+{
+    let value = match cmd.keyword.strip_prefix('-') {
+        Some(value) => u64ptr(value)?.wrapping_neg(),
+        None => u64ptr(&cmd.keyword)?
+    };
+    let mask = match cmd.width {
+        Some(8) => 0xff,
+        Some(16) => 0xffff,
+        Some(32) => 0xffff_ffff,
+        Some(64) | None => u64::MAX,
+        Some(width) => anyhow::bail!("unsupported immediate width: {}", width)
+    };
+    let value = value & mask;
+    let filter = cmd.filter_section
+        .as_ref()
+        .map(|rule| regex::Regex::new(rule))
+        .transpose()?;
+
+    let is_match = |imm: i64| {
+        let imm = imm as u64 & mask;
+
+        match cmd.tolerance {
+            Some(percent) => imm.abs_diff(value) as f64 <= value as f64 * percent / 100.0,
+            None => imm == value
+        }
+    };
+
+    let output = par_disasm(explorer, filter.as_ref(), stdio, |disasm, symidx, _size, mut insts| {
+        let mut hits = Vec::new();
+
+        while let Some(Ok(inst)) = insts.next() {
+            if disasm.immediates(&inst).into_iter().any(is_match) {
+                hits.push((symidx, inst.address(), inst.to_string()));
+            }
+        }
+
+        Ok(hits)
+    }).await?;
+
+    print_insts(cmd, explorer, &output, stdio).await
+}
+
+async fn print_insts(
+    cmd: &Command,
+    explorer: &Explorer,
+    output: &[(object::SymbolIndex, u64, String)],
+    stdio: &mut Stdio
+) -> anyhow::Result<()> {
+    #[derive(Serialize)]
+    struct Record<'a> {
+        address: u64,
+        name: &'a str,
+        demangled: Cow<'a, str>,
+        inst: &'a str
+    }
+
+    let mut point = YieldPoint::default();
+
+    for (symidx, addr, text) in output.iter().take(cmd.limit.unwrap_or(usize::MAX)) {
+        point.yield_now().await;
+
+        let sym = explorer.sym_obj().symbol_by_index(*symidx)?;
+        let mangled_name = sym.name().unwrap_or_default();
+        if !stdio.format.is_text() {
+            stdio.record(&Record {
+                address: *addr,
+                name: mangled_name,
+                demangled: explorer.cache.demangle(mangled_name),
+                inst: text
+            })?;
+            continue
+        }
+
+        let name = if cmd.demangle {
+            explorer.cache.demangle(mangled_name)
+        } else {
+            mangled_name.into()
+        };
+
+        writeln!(
+            &mut stdio.stdout,
+            "{:018p}\t{}\t{}",
+            *addr as *const (),
+            name,
+            text
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Disassemble every text symbol in parallel
+///
+/// `f` gets the instructions of each symbol as they decode, together with its index and size,
+/// hits are collected in symbol order. Stops early once the request is cancelled.
+pub(crate) async fn par_disasm<T, F>(
+    explorer: &Explorer,
+    filter: Option<&regex::Regex>,
+    stdio: &Stdio,
+    f: F
+)
+    -> anyhow::Result<Vec<T>>
+where
+    T: Send,
+    F: Fn(&Disassembler, object::SymbolIndex, u64, InstIter<'_>) -> anyhow::Result<Vec<T>> + Sync
+{
     use std::rc::Rc;
     use std::cell::RefCell;
     use rayon::prelude::*;
-    use super::show;
 
     thread_local! {
         static DISASM_CACHE: RefCell<Option<Rc<Disassembler>>> =
             const { RefCell::new(None) };
     }
-    
-    let address = u64ptr(&cmd.keyword)?;
+
     let symlist = explorer.cache.symlist(&explorer.obj).await;
-    let addr2sym = explorer.cache.addr2sym(&explorer.obj).await;
-    let dyn_rela = explorer.cache.dyn_rela(&explorer.obj).await;
 
-    let symidx = symlist
-        .binary_search_by_key(&address, |&idx| explorer.obj.symbol_by_index(idx).unwrap().address())
-        .ok()
-        .context("not found symbol by address")?;
-    let symidx = symlist[symidx];
-    let sym = explorer.obj.symbol_by_index(symidx).unwrap();
+    let mut sections = HashMap::new();
+    for section in explorer.obj.sections()
+        .filter(|section| section.kind() == SectionKind::Text)
+    {
+        // filter section by regex
+        if let Some(rule) = filter
+            && let Ok(section_name) = section.name()
+            && !rule.is_match(section_name)
+        {
+            continue
+        }
 
-    if !matches!(sym.kind(), object::SymbolKind::Text) {
-        anyhow::bail!("symbol kind is not text");
+        let data = explorer.cache.data(&explorer.obj, section.index()).await?;
+        sections.insert(section.index(), (section.address(), data));
     }
 
-    let section_idx = sym.section_index().unwrap();
-    let section = explorer.obj.section_by_index(section_idx)?;
-    let section_data = explorer.cache.data(&explorer.obj, section_idx).await?;
+    let total = symlist.iter()
+        .filter(|&&symidx| explorer.sym_obj().symbol_by_index(symidx).unwrap()
+            .section_index()
+            .is_some_and(|idx| sections.contains_key(&idx)))
+        .count();
+    let progress = Progress::new(&stdio.stderr, total);
 
-    let mut output = symlist
+    let output = symlist
         .par_iter()
         .filter_map(|&symidx| {
-            let sym = explorer.obj.symbol_by_index(symidx).unwrap();
+            let sym = explorer.sym_obj().symbol_by_index(symidx).unwrap();
+            let (section_addr, section_data) = sections.get(&sym.section_index()?)?;
+
+            // the collect stops at the first error
+            if let Err(err) = stdio.cancel.check() {
+                return Some(Err(err));
+            }
 
-            if sym.section_index() != Some(section_idx) {
-                return None;
+            if let Some(progress) = progress.as_ref() {
+                progress.inc();
             }
 
-            let offset = (sym.address() - section.address()) as usize;
+            let offset = (sym.address() - section_addr) as usize;
             let size = match explorer.symbol_size(symlist, symidx) {
                 Ok(size) => size,
                 Err(err) => return Some(Err(err))
@@ -275,81 +2402,362 @@ async fn by_call(cmd: &Command, explorer: &Explorer, stdio: &mut Stdio)
             };
             let disasm = &*disasm;
 
-            let insts = match disasm.disasm_all(data, sym.address()) {
+            let insts = match disasm.disasm_iter(data, sym.address()) {
                 Ok(insts) => insts,
                 Err(err) => return Some(Err(err))
             };
-            for inst in insts.iter()
-                .ok()?
-                .filter_map(|inst| inst.ok())
-            {
-                let addr = match disasm.operand2addr(&inst) {
-                    Ok(Some(addr)) => addr,
-                    Ok(None) => continue,
-                    Err(err) => return Some(Err(err))
-                };
-                
-                if let Some((_name, addr)) = show::query_symbol_by_addr(explorer, addr2sym, dyn_rela, addr)
-                    && addr == address
-                {
-                    let mangled_name = sym.name().unwrap();
-                    let name = if cmd.demangle {
-                        demangle(mangled_name)
-                    } else {
-                        (*mangled_name).into()
-                    };
-                    return Some(Ok((symidx, name, size)));
-                }
-            }
 
-            None
+            Some(f(disasm, symidx, size, insts))
         })
         .collect::<anyhow::Result<Vec<_>>>()?;
 
-    output.sort_unstable_by(|(idx0, name0, size0), (idx1, name1, size1)| match (cmd.sort_size, cmd.sort_name) {
-        (false, false) => idx0.0.cmp(&idx1.0),
+    drop(progress);
+
+    Ok(output.into_iter().flatten().collect())
+}
+
+/// Byte signature with `??` wildcards
+struct BytePattern {
+    bytes: Vec<Option<u8>>,
+    /// longest run of fixed bytes, used to find candidates
+    anchor: (usize, Vec<u8>)
+}
+
+impl BytePattern {
+    fn parse(pattern: &str) -> anyhow::Result<BytePattern> {
+        let bytes = pattern.split_whitespace()
+            .map(|byte| match byte {
+                "?" | "??" => Ok(None),
+                byte if byte.len() == 2 => u8::from_str_radix(byte, 16)
+                    .map(Some)
+                    .with_context(|| format!("bad pattern byte: {:?}", byte)),
+                byte => anyhow::bail!("bad pattern byte: {:?}", byte)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut anchor = (0, Vec::new());
+        let mut start = 0;
+        for (i, byte) in bytes.iter().enumerate() {
+            if byte.is_none() {
+                start = i + 1;
+            } else if i + 1 - start > anchor.1.len() {
+                anchor = (start, bytes[start..=i].iter().flatten().copied().collect());
+            }
+        }
+
+        if anchor.1.is_empty() {
+            anyhow::bail!("pattern need at least one fixed byte");
+        }
+
+        Ok(BytePattern { bytes, anchor })
+    }
+
+    fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn is_match(&self, data: &[u8]) -> bool {
+        data.len() >= self.bytes.len()
+            && self.bytes.iter().zip(data).all(|(pat, b)| pat.is_none_or(|pat| pat == *b))
+    }
+
+    /// Offsets of all (possibly overlapping) matches
+    fn find_iter<'a>(&'a self, data: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        let (anchor_offset, anchor) = (self.anchor.0, &self.anchor.1);
+        let mut pos = 0;
+
+        std::iter::from_fn(move || loop {
+            let found = pos + data.get(pos..)?.find(anchor)?;
+            pos = found + 1;
+
+            if let Some(offset) = found.checked_sub(anchor_offset)
+                && self.is_match(&data[offset..])
+            {
+                return Some(offset)
+            }
+        })
+    }
+}
+
+/// Sort symbol results by the `--sort-*` flags, then apply `--reverse`
+fn sort_symbols<T, N: Ord>(cmd: &Command, output: &mut [(T, N, u64)], addr: impl Fn(&T) -> u64) {
+    if cmd.sort_addr {
+        output.sort_by_key(|(sym, ..)| addr(sym));
+    }
+
+    // stable, so ties keep the order above
+    output.sort_by(|(_, name0, size0), (_, name1, size1)| match (cmd.sort_size, cmd.sort_name) {
+        (false, false) => cmp::Ordering::Equal,
         (true, false) => size0.cmp(size1),
         (false, true) => name0.cmp(name1),
         (true, true) => (name0, size0).cmp(&(name1, size1))
     });
 
-    let mut outbuf = Vec::new();
-
-    for (idx, name, size) in &output {
-        print_symbol(
-            explorer,
-            *idx, name, *size,
-            cmd.size,
-            &mut outbuf,
-            &mut stdio.stdout
-        )?;
+    if cmd.reverse {
+        output.reverse();
     }
-
-    Ok(())    
 }
 
+#[allow(clippy::too_many_arguments)]
 fn print_symbol(
     explorer: &Explorer,
     idx: object::SymbolIndex,
     name: &str,
     size: u64,
     show_size: bool,
+    source: Option<&Source<'_>>,
     outbuf: &mut Vec<u8>,
-    stdout: &mut fs::File,
+    stdio: &mut Stdio,
 ) -> anyhow::Result<()> {
-    let sym = explorer.obj.symbol_by_index(idx)?;
+    let sym = explorer.sym_obj().symbol_by_index(idx)?;
     let kind = explorer.symbol_kind(idx);
-    
+    let mangled_name = sym.name().unwrap_or_default();
+
+    print_symbol_line(explorer, sym.address(), kind, mangled_name, name, size, show_size, source, outbuf, stdio)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn print_symbol_line(
+    explorer: &Explorer,
+    addr: u64,
+    kind: char,
+    mangled_name: &str,
+    name: &str,
+    size: u64,
+    show_size: bool,
+    source: Option<&Source<'_>>,
+    outbuf: &mut Vec<u8>,
+    stdio: &mut Stdio,
+) -> anyhow::Result<()> {
+    #[derive(Serialize)]
+    struct Record<'a> {
+        address: u64,
+        kind: char,
+        size: u64,
+        name: &'a str,
+        demangled: Cow<'a, str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        line: Option<u32>
+    }
+
+    let location = match source {
+        Some(source) => source.location(addr)?,
+        None => None
+    };
+
+    if let Some(template) = stdio.template.as_ref()
+        && stdio.format.is_text()
+    {
+        use crate::template::Value;
+
+        let (file, line) = location.unzip();
+        let mut text = String::new();
+        template.render(&mut text, |field| Some(match field {
+            "addr" => Value::Int(addr),
+            "size" => Value::Int(size),
+            "kind" => Value::Str(kind.to_string().into()),
+            "name" => Value::Str(name.into()),
+            "mangled" => Value::Str(mangled_name.into()),
+            "demangled" => Value::Str(explorer.cache.demangle(mangled_name)),
+            "file" => file.map_or(Value::None, |file| Value::Str(file.into())),
+            "line" => line.flatten().map_or(Value::None, |line| Value::Int(line.into())),
+            _ => return None
+        }))?;
+        stdio.stdout.write_all(text.as_bytes())?;
+
+        return Ok(());
+    }
+
+    if !stdio.format.is_text() {
+        let (file, line) = location.unzip();
+        return stdio.record(&Record {
+            address: addr,
+            kind, size,
+            name: mangled_name,
+            demangled: explorer.cache.demangle(mangled_name),
+            file,
+            line: line.flatten()
+        });
+    }
+
     outbuf.clear();
-    writeln!(
+    write!(
         outbuf,
         "{:018p}{} {} {}",
-        sym.address() as *const (),
+        addr as *const (),
         MaybePrinter(show_size.then_some(format_args!(" {:10}", size)), None),
         kind,
         name,
     )?;
-    stdout.write_all(outbuf)?;
+
+    if let Some((file, line)) = location {
+        let text = format_args!("{}:{}", file, MaybePrinter(line, Some('?')));
+
+        if stdio.hyperlink {
+            let link = source.map_or(Cow::Borrowed(file), |source| source.explorer.source_path(file));
+            write!(outbuf, "\t{}", Hyperlink::new(text, link))?;
+        } else {
+            write!(outbuf, "\t{}", text)?;
+        }
+    }
+
+    writeln!(outbuf)?;
+    stdio.stdout.write_all(outbuf)?;
     
     Ok(())
 }
+
+/// `--source` column, resolved by the addr2line loader or the line records of a PDB
+struct Source<'a> {
+    explorer: &'a Explorer,
+    lines: SourceLines<'a>
+}
+
+enum SourceLines<'a> {
+    Dwarf(tokio::sync::OwnedMutexGuard<addr2line::Loader>),
+    Pdb(&'a crate::pdb::Lines)
+}
+
+impl Source<'_> {
+    async fn new<'a>(cmd: &Command, explorer: &'a Explorer)
+        -> anyhow::Result<Option<Source<'a>>>
+    {
+        if !cmd.source {
+            return Ok(None);
+        }
+
+        let lines = match explorer.cache.pdb(&explorer.obj).await {
+            Some(pdb) => SourceLines::Pdb(&pdb.lines),
+            None => SourceLines::Dwarf(
+                explorer.cache.addr2line(explorer.dwarf_file(), explorer.dwarf_path.as_deref()).await?
+            )
+        };
+        Ok(Some(Source {
+            explorer,
+            lines
+        }))
+    }
+
+    /// File and line of `addr`
+    fn location(&self, addr: u64) -> anyhow::Result<Option<(&str, Option<u32>)>> {
+        // undefined symbol
+        if addr == 0 {
+            return Ok(None);
+        }
+
+        let loader = match &self.lines {
+            SourceLines::Dwarf(loader) => loader,
+            SourceLines::Pdb(lines) => return Ok(lines.find(addr).map(|(file, line)| (file, Some(line))))
+        };
+        let location = loader.find_location(addr)
+            .map_err(|err| anyhow::format_err!("addr2line: {:?}", err))?;
+
+        Ok(location.and_then(|location| Some((location.file?, location.line))))
+    }
+}
+
+fn symbol_addr(explorer: &Explorer, idx: object::SymbolIndex) -> u64 {
+    explorer.sym_obj().symbol_by_index(idx)
+        .map(|sym| sym.address())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object::SymbolIndex;
+
+    fn command(args: &[&str]) -> Command {
+        use clap::Parser;
+
+        #[derive(Parser)]
+        struct Cli {
+            #[command(flatten)]
+            cmd: Command
+        }
+
+        Cli::parse_from(["search"].iter().chain(args)).cmd
+    }
+
+    #[test]
+    fn callers_by_kind() {
+        let index = [
+            (0x10, SymbolIndex(1), CallKind::Call),
+            (0x10, SymbolIndex(2), CallKind::TailJump),
+            (0x20, SymbolIndex(1), CallKind::Vtable(0x100)),
+            (0x20, SymbolIndex(1), CallKind::Call),
+            (0x30, SymbolIndex(3), CallKind::Call)
+        ];
+
+        // a caller of several callees is listed once, each kind once
+        assert_eq!(callers_of(&index, &[0x20, 0x10]), vec![
+            (SymbolIndex(1), vec![CallKind::Call, CallKind::Vtable(0x100)]),
+            (SymbolIndex(2), vec![CallKind::TailJump])
+        ]);
+        assert_eq!(callers_of(&index, &[0x30]), vec![(SymbolIndex(3), vec![CallKind::Call])]);
+        assert!(callers_of(&index, &[0x18, 0x40]).is_empty());
+    }
+
+    #[test]
+    fn call_kind_tail_flags() {
+        let kinds = [CallKind::Call, CallKind::TailJump, CallKind::Vtable(0x100)];
+        let shown = |args: &[&str]| {
+            let cmd = command(args);
+            kinds.map(|kind| kind.shown(&cmd))
+        };
+
+        assert_eq!(shown(&["foo"]), [true, true, true]);
+        assert_eq!(shown(&["foo", "--no-tail"]), [true, false, true]);
+        assert_eq!(shown(&["foo", "--only-tail"]), [false, true, false]);
+    }
+
+    #[test]
+    fn identical_normalized() {
+        let disasm = Disassembler::x86_64();
+        let key = |start: u64, data: &'static [u8], normalize: bool| {
+            let insts = disasm.disasm_iter(data, start).unwrap();
+            identical_key(&disasm, insts, start, data, normalize).unwrap().into_owned()
+        };
+
+        // call 0x2000; jmp to its own start; ret
+        let f1: &[u8] = &[0xe8, 0xfb, 0x0f, 0x00, 0x00, 0xeb, 0xf9, 0xc3];
+        let f2: &[u8] = &[0xe8, 0xfb, 0xef, 0xff, 0xff, 0xeb, 0xf9, 0xc3];
+
+        assert_eq!(key(0x1000, f1, false), f1);
+        assert_ne!(key(0x1000, f1, false), key(0x3000, f2, false));
+        assert_eq!(key(0x1000, f1, true), key(0x3000, f2, true));
+
+        // same bytes calling another function
+        assert_ne!(key(0x1000, f1, true), key(0x1100, f1, true));
+
+        // bytes that do not decode are kept
+        let f3: &[u8] = &[0xe8, 0xfb, 0x0f, 0x00, 0x00, 0xeb, 0xf9, 0xc3, 0x0f];
+        assert_ne!(key(0x1000, f1, true), key(0x1000, f3, true));
+    }
+
+    #[test]
+    fn byte_pattern() {
+        let pattern = BytePattern::parse("48 ?? 89 e5 ?").unwrap();
+        assert_eq!(pattern.len(), 5);
+        assert_eq!(pattern.anchor, (2, vec![0x89, 0xe5]));
+
+        // the first anchor has no room for the leading bytes
+        let data = [0x89, 0xe5, 0x48, 0x00, 0x89, 0xe5, 0x89, 0xe5, 0x00, 0xff];
+        assert_eq!(pattern.find_iter(&data).collect::<Vec<_>>(), vec![2]);
+
+        // matches may overlap
+        let pattern = BytePattern::parse("aa ?? aa").unwrap();
+        let data = [0xaa, 0x01, 0xaa, 0x02, 0xaa, 0xaa];
+        assert_eq!(pattern.find_iter(&data).collect::<Vec<_>>(), vec![0, 2]);
+
+        // a match cut off at the end is not one
+        assert_eq!(pattern.find_iter(&[0xaa, 0x01]).count(), 0);
+
+        assert!(BytePattern::parse("?? ??").is_err());
+        assert!(BytePattern::parse("4").is_err());
+        assert!(BytePattern::parse("zz").is_err());
+        assert!(BytePattern::parse("488b").is_err());
+    }
+}
@@ -0,0 +1,483 @@
+use std::{ io, fs, env };
+use std::path::{ Path, PathBuf };
+use std::{ process, thread };
+use std::time::{ Duration, Instant, SystemTime, UNIX_EPOCH };
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::process::CommandExt;
+use std::os::unix::net::UnixStream;
+use anyhow::Context;
+use directories::ProjectDirs;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::call::{ SESSION_ENVNAME, PROBE };
+use crate::util::{ hashpath, hashname, peer_pid };
+
+
+/// list, attach or kill sessions of the runtime dir
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// print the `FI_SESSION` line of a session, by socket name, its prefix or pid
+    #[arg(long, value_name = "SESSION", conflicts_with = "kill")]
+    #[serde(skip)]
+    attach: Option<String>,
+
+    /// stop a session, by socket name, its prefix or pid
+    #[arg(long, value_name = "SESSION")]
+    #[serde(skip)]
+    kill: Option<String>,
+}
+
+/// Sidecar of a session socket, written by `listen`
+#[derive(Serialize, Deserialize)]
+pub struct Info {
+    pub pid: u32,
+    pub cwd: PathBuf,
+    pub paths: Vec<PathBuf>,
+    /// unix time in seconds
+    pub started: u64
+}
+
+pub struct Session {
+    pub path: PathBuf,
+    pub info: Option<Info>,
+    pub alive: bool
+}
+
+/// Directory of session sockets
+pub fn dir(dir: &ProjectDirs) -> &Path {
+    dir.runtime_dir().unwrap_or_else(|| dir.cache_dir())
+}
+
+/// Create the session dir only accessible by the current user,
+/// refusing to use one owned by another user
+pub fn create_dir(dir: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::{ DirBuilderExt, MetadataExt, PermissionsExt };
+
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+
+    let metadata = fs::metadata(dir)?;
+    let uid = unsafe { libc::getuid() };
+
+    anyhow::ensure!(
+        metadata.uid() == uid,
+        "session dir {} belongs to uid {}, not the current uid {}",
+        dir.display(), metadata.uid(), uid
+    );
+
+    // created by an older fi
+    if metadata.mode() & 0o077 != 0 {
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+
+    Ok(())
+}
+
+fn sidecar_path(ipc_path: &Path, ext: &str) -> PathBuf {
+    let mut path = ipc_path.as_os_str().to_owned();
+    path.push(ext);
+    PathBuf::from(path)
+}
+
+fn info_path(ipc_path: &Path) -> PathBuf {
+    sidecar_path(ipc_path, ".json")
+}
+
+/// Log of a session in `logs` of the cache dir, kept once it exits
+///
+/// Output of a session started by `spawn` or `listen --daemon`, and the default of `--log-file`.
+pub fn log_path(dir: &ProjectDirs, ipc_path: &Path) -> PathBuf {
+    dir.cache_dir()
+        .join("logs")
+        .join(ipc_path.file_name().unwrap_or_default())
+        .with_extension("log")
+}
+
+/// Open a session log to append to, removing the oldest logs beyond `LOGS_KEPT`
+pub fn create_log(path: &Path) -> io::Result<fs::File> {
+    const LOGS_KEPT: usize = 32;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+
+        let mut logs = fs::read_dir(dir)?
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "log"))
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .collect::<Vec<_>>();
+
+        if logs.len() >= LOGS_KEPT {
+            logs.sort();
+
+            for (_, old) in &logs[..logs.len() + 1 - LOGS_KEPT] {
+                let _ = fs::remove_file(old);
+            }
+        }
+    }
+
+    fs::OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Pid of a session started by `listen --daemon`
+pub fn pid_path(ipc_path: &Path) -> PathBuf {
+    sidecar_path(ipc_path, ".pid")
+}
+
+impl Info {
+    pub fn new(paths: &[PathBuf]) -> anyhow::Result<Info> {
+        Ok(Info {
+            pid: std::process::id(),
+            cwd: env::current_dir()?,
+            paths: paths.iter()
+                .map(|path| fs::canonicalize(path).unwrap_or_else(|_| path.clone()))
+                .collect(),
+            started: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()
+        })
+    }
+
+    pub fn write(&self, ipc_path: &Path) -> anyhow::Result<()> {
+        fs::write(info_path(ipc_path), serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    /// Remove the sidecars of a session, its log is kept
+    pub fn remove(ipc_path: &Path) {
+        let _ = fs::remove_file(info_path(ipc_path));
+        let _ = fs::remove_file(pid_path(ipc_path));
+    }
+
+    fn read(ipc_path: &Path) -> Option<Info> {
+        let buf = fs::read(info_path(ipc_path)).ok()?;
+        serde_json::from_slice(&buf).ok()
+    }
+
+    /// Whether one of the binaries is `bin`, by file name or path
+    fn serves(&self, bin: &str) -> bool {
+        let path = fs::canonicalize(bin).ok();
+
+        self.paths.iter().any(|path2| path2.file_name().is_some_and(|name| name == bin)
+            || path2.as_os_str() == bin
+            || path.as_ref() == Some(path2)
+        )
+    }
+}
+
+/// Sockets of the session dir, with whether a server still listens on them
+pub fn list(dir: &Path) -> anyhow::Result<Vec<Session>> {
+    let mut sessions = Vec::new();
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(sessions),
+        Err(err) => return Err(err.into())
+    };
+
+    for entry in entries {
+        let entry = entry?;
+
+        if !entry.file_type()?.is_socket() {
+            continue
+        }
+
+        let path = entry.path();
+        let info = Info::read(&path);
+        let alive = probe(&path, info.as_ref());
+
+        sessions.push(Session { path, info, alive });
+    }
+
+    sessions.sort_by_key(|session| session.info.as_ref().map(|info| info.started));
+
+    Ok(sessions)
+}
+
+/// Whether a server accepts on the socket, and is the process of its sidecar if there is one
+///
+/// The pid alone may belong to another process once a crashed session's pid is reused.
+fn probe(path: &Path, info: Option<&Info>) -> bool {
+    use std::io::Write;
+
+    let Ok(mut stream) = UnixStream::connect(path)
+        else { return false };
+
+    // not counted as a client, probes would otherwise keep every session from idling
+    let _ = stream.write_all(PROBE);
+
+    match (info, peer_pid(&stream)) {
+        (Some(info), Some(pid)) => info.pid == pid,
+        _ => true
+    }
+}
+
+/// Live session serving the binary at `path`
+pub fn find_serving(dir: &Path, path: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let path = fs::canonicalize(path)?;
+
+    Ok(list(dir)?
+        .into_iter()
+        .filter(|session| session.alive)
+        .find(|session| session.info.as_ref().is_some_and(|info| info.paths.contains(&path)))
+        .map(|session| session.path))
+}
+
+/// Live session of `--session`, by the binary it serves, socket name, its prefix or pid
+pub fn select(dir: &Path, id: &str) -> anyhow::Result<PathBuf> {
+    let sessions = list(dir)?;
+    let serving = sessions.iter()
+        .filter(|session| session.alive)
+        .filter(|session| session.info.as_ref().is_some_and(|info| info.serves(id)))
+        .collect::<Vec<_>>();
+
+    let session = match serving.as_slice() {
+        [] => find(&sessions, id)?,
+        [session] => session,
+        _ => return Err(ambiguous(&serving, &format!("several sessions serve {}", id)))
+    };
+
+    anyhow::ensure!(session.alive, "session is stale: {}", session.path.display());
+
+    Ok(session.path.clone())
+}
+
+/// Live session without `--session` or `--file`: the only one serving `bin` if given,
+/// preferring those started from the current directory
+pub fn select_default(dir: &Path, bin: Option<&str>) -> anyhow::Result<PathBuf> {
+    let sessions = list(dir)?;
+    let prefix = hashpath(&env::current_dir()?);
+    let live = sessions.iter()
+        .filter(|session| session.alive)
+        .filter(|session| bin.is_none_or(|bin| session.info.as_ref().is_some_and(|info| info.serves(bin))))
+        .collect::<Vec<_>>();
+    let here = live.iter()
+        .copied()
+        .filter(|session| session.name().starts_with(&prefix))
+        .collect::<Vec<_>>();
+
+    let found = if here.is_empty() { live } else { here };
+
+    match (found.as_slice(), bin) {
+        ([session], _) => Ok(session.path.clone()),
+        ([], Some(bin)) => anyhow::bail!("not found any session serving {}, start one with `--file`", bin),
+        ([], None) => anyhow::bail!("not found any session, start one with `fi listen` or `--file`"),
+        (found, _) => Err(ambiguous(found, "several sessions found"))
+    }
+}
+
+/// Error listing the sessions to choose from
+fn ambiguous(sessions: &[&Session], reason: &str) -> anyhow::Error {
+    use std::fmt::Write;
+
+    let mut msg = format!("{}, select one with `--session`:", reason);
+
+    for session in sessions {
+        let _ = match &session.info {
+            Some(info) => write!(
+                msg,
+                "\n  {}\t{}\t{}\t{}",
+                session.name(),
+                info.pid,
+                info.cwd.display(),
+                info.paths.iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            None => write!(msg, "\n  {}\t?\t?\t?", session.name())
+        };
+    }
+
+    anyhow::Error::msg(msg)
+}
+
+/// Start `fi listen path` detached in the background and wait for its socket
+pub fn spawn(dirs: &ProjectDirs, path: &Path) -> anyhow::Result<PathBuf> {
+    const WAIT: Duration = Duration::from_secs(60);
+
+    let dir = self::dir(dirs);
+    create_dir(dir)?;
+
+    let ipc_path = dir.join(format!(
+        "{}-{}",
+        hashpath(&env::current_dir()?),
+        hashname(path)
+    ));
+
+    // left by a session that crashed, listen would fail to bind it
+    if !probe(&ipc_path, None) {
+        match fs::remove_file(&ipc_path) {
+            Ok(()) => Info::remove(&ipc_path),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err.into())
+        }
+    }
+
+    let log_path = log_path(dirs, &ipc_path);
+    let log = create_log(&log_path)?;
+
+    let mut cmd = process::Command::new(env::current_exe()?);
+    cmd.arg("listen")
+        .arg(path)
+        .env(SESSION_ENVNAME, &ipc_path)
+        .stdin(process::Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+
+    // own session, not interrupted with the terminal of the client
+    unsafe {
+        cmd.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+
+    let mut child = cmd.spawn().context("spawn session failed")?;
+    let start = Instant::now();
+
+    // the socket accepts once the binary is opened
+    while !probe(&ipc_path, None) {
+        if let Some(status) = child.try_wait()? {
+            anyhow::bail!("session exited with {}, see {}", status, log_path.display());
+        }
+
+        anyhow::ensure!(start.elapsed() < WAIT, "session not ready, see {}", log_path.display());
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    Ok(ipc_path)
+}
+
+impl Session {
+    fn name(&self) -> &str {
+        self.path.file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+    }
+
+    /// Interrupt a live session, or remove the socket and sidecars of a stale one
+    ///
+    /// Liveness is probed again right before signalling, a stale pid is never signalled.
+    pub fn kill(&self) -> anyhow::Result<()> {
+        let info = self.info.as_ref();
+
+        match info.filter(|info| probe(&self.path, Some(info))) {
+            // listen removes its socket on interrupt
+            Some(info) => {
+                let ret = unsafe { libc::kill(info.pid as libc::pid_t, libc::SIGINT) };
+                if ret != 0 {
+                    return Err(io::Error::last_os_error()).context("kill session failed");
+                }
+            },
+            None => {
+                anyhow::ensure!(
+                    info.is_some() || !probe(&self.path, None),
+                    "session pid unknown: {}", self.path.display()
+                );
+                fs::remove_file(&self.path)?;
+                Info::remove(&self.path);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn find<'a>(sessions: &'a [Session], id: &str) -> anyhow::Result<&'a Session> {
+    let pid = id.parse::<u32>().ok();
+    let found = sessions.iter()
+        .filter(|session| session.name() == id
+            || session.path.as_os_str() == id
+            || session.info.as_ref().is_some_and(|info| Some(info.pid) == pid)
+        )
+        .collect::<Vec<_>>();
+    let found = if found.is_empty() {
+        sessions.iter()
+            .filter(|session| session.name().starts_with(id))
+            .collect()
+    } else {
+        found
+    };
+
+    match found.as_slice() {
+        [session] => Ok(session),
+        [] => anyhow::bail!("not found session: {}", id),
+        _ => anyhow::bail!("ambiguous session: {}", id)
+    }
+}
+
+/// Elapsed time in the largest two units, such as `3h05m`
+pub struct Uptime(pub Duration);
+
+impl std::fmt::Display for Uptime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = self.0.as_secs();
+
+        match secs {
+            0..60 => write!(f, "{}s", secs),
+            60..3600 => write!(f, "{}m{:02}s", secs / 60, secs % 60),
+            3600..86400 => write!(f, "{}h{:02}m", secs / 3600, secs % 3600 / 60),
+            _ => write!(f, "{}d{:02}h", secs / 86400, secs % 86400 / 3600)
+        }
+    }
+}
+
+impl Command {
+    pub fn exec(self, dir: &ProjectDirs) -> anyhow::Result<()> {
+        let sessions = list(self::dir(dir))?;
+
+        if let Some(id) = self.attach.as_deref() {
+            let session = find(&sessions, id)?;
+            anyhow::ensure!(session.alive, "session is stale: {}", session.path.display());
+            println!("set -x {} {}", SESSION_ENVNAME, session.path.display());
+            return Ok(());
+        }
+
+        if let Some(id) = self.kill.as_deref() {
+            let session = find(&sessions, id)?;
+
+            session.kill()?;
+            println!("{}", session.path.display());
+            return Ok(());
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut stale = 0;
+
+        for session in &sessions {
+            stale += usize::from(!session.alive);
+
+            match &session.info {
+                Some(info) => println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    session.path.display(),
+                    info.pid,
+                    if session.alive {
+                        Uptime(Duration::from_secs(now.saturating_sub(info.started))).to_string()
+                    } else {
+                        "stale".into()
+                    },
+                    info.cwd.display(),
+                    info.paths.iter()
+                        .map(|path| path.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                ),
+                None => println!(
+                    "{}\t?\t{}\t?\t?",
+                    session.path.display(),
+                    if session.alive { "?" } else { "stale" }
+                )
+            }
+        }
+
+        println!("total: {} sessions, {} stale", sessions.len(), stale);
+
+        Ok(())
+    }
+}
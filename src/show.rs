@@ -1,11 +1,16 @@
+mod arrows;
+
 use std::fs;
 use std::io::Write;
 use std::ops::Range;
+use std::borrow::Cow;
 use std::path::Path;
+use std::sync::Arc;
 use std::collections::hash_map;
-use std::collections::HashMap;
+use std::collections::{ HashMap, HashSet };
 use anyhow::Context;
-use symbolic_demangle::demangle;
+use tokio::sync::OwnedMutexGuard;
+use addr2line::Loader;
 use object::{
     Object, ObjectSection, ObjectSymbol,
     SectionIndex, SectionKind,
@@ -17,13 +22,17 @@ use owo_colors::OwoColorize;
 use clap::Args;
 use serde::{ Serialize, Deserialize };
 
-use crate::explorer::Explorer;
+use crate::explorer::{ Explorer, PltEntry };
 use crate::util::{
     u64ptr, Stdio, YieldPoint,
     HexPrinter, AsciiPrinter, MaybePrinter, EitherPrinter,
     IfSupported, Hyperlink
 };
 use crate::disasm::{ self, Disassembler };
+use crate::dwarf::{ self, value::Decoder };
+use crate::disasm::syscall::SyscallTracker;
+use crate::disasm::jumptable::{ JumpTable, JumpTableFinder, JumpTableRef };
+use arrows::JumpArrows;
 
 
 /// show text or data
@@ -32,9 +41,21 @@ use crate::disasm::{ self, Disassembler };
 #[command(args_conflicts_with_subcommands = true)]
 #[command(flatten_help = true)]
 pub struct Command {
-    /// show address
+    /// show address (section name with `--section`, regex with `--all`)
     pub address: String,
 
+    /// disassemble every symbol of the named section
+    #[arg(long, default_value_t = false)]
+    pub section: bool,
+
+    /// show every symbol matching the regex
+    #[arg(long, default_value_t = false)]
+    pub all: bool,
+
+    /// filter section by regex (with `--all`)
+    #[arg(short, long)]
+    pub filter_section: Option<String>,
+
     /// show length
     #[arg(short, long)]
     pub length: Option<u64>,
@@ -61,13 +82,37 @@ pub struct Command {
 
     /// show instr top usage by dwarf (bytes)
     #[arg(long)]
-    pub dwarf_top: bool
+    pub dwarf_top: bool,
+
+    /// no draw jump arrows
+    #[arg(long, default_value_t = false)]
+    pub no_arrows: bool,
+
+    /// also show direct call targets up to depth
+    #[arg(long)]
+    pub follow: Option<usize>,
+
+    /// render data as the named DWARF type, one line per field
+    #[arg(long = "type", value_name = "TYPE", conflicts_with_all = ["section", "all", "dump"])]
+    pub type_name: Option<String>
 }
 
 impl Command {
     pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        if self.section {
+            return by_section_name(&self, explorer, stdio).await;
+        }
+
+        if self.all {
+            return by_pattern(&self, explorer, stdio).await;
+        }
+
         let addr = u64ptr(&self.address)?;
 
+        if let Some(name) = self.type_name.as_deref() {
+            return by_type(&self, explorer, addr, name, stdio).await;
+        }
+
         if !self.no_symbol {
             by_symbol(&self, explorer, addr, stdio).await
         } else {
@@ -95,12 +140,12 @@ async fn by_symbol(
             let sym = map.get(idx).context("no available symbols found")?;
             let symlist_idx = symlist.binary_search_by_key(
                 &sym.address(),
-                |&symidx| explorer.obj.symbol_by_index(symidx).unwrap().address()
+                |&symidx| explorer.sym_obj().symbol_by_index(symidx).unwrap().address()
             )
                 .ok()
                 .context("not found symbol")?;
             let sym_idx = symlist[symlist_idx];
-            let sym = explorer.obj.symbol_by_index(sym_idx)?;
+            let sym = explorer.sym_obj().symbol_by_index(sym_idx)?;
             let start = sym.address();
             let end = start + sym.size();
 
@@ -117,13 +162,129 @@ async fn by_symbol(
     } else {
         let symlist_idx = symlist.binary_search_by_key(
             &map[idx].address(),
-            |&symidx| explorer.obj.symbol_by_index(symidx).unwrap().address()
+            |&symidx| explorer.sym_obj().symbol_by_index(symidx).unwrap().address()
         )
             .ok()
             .context("not found symbol")?;
         symlist[symlist_idx]
     };
-    let sym = explorer.obj.symbol_by_index(sym_idx)?;
+
+    show_symbol(cmd, explorer, sym_idx, Some(map[idx].name()), stdio).await?;
+
+    if let Some(depth) = cmd.follow {
+        follow_calls(cmd, explorer, sym_idx, depth, stdio).await?;
+    }
+
+    Ok(())
+}
+
+async fn follow_calls(
+    cmd: &Command,
+    explorer: &Explorer,
+    root: SymbolIndex,
+    depth: usize,
+    stdio: &mut Stdio
+)
+    -> anyhow::Result<()>
+{
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let mut visited = HashSet::from([root]);
+    let mut queue = vec![root];
+
+    for level in 1..=depth {
+        let mut next = Vec::new();
+
+        for sym_idx in queue {
+            for addr in call_targets(explorer, sym_idx).await? {
+                let Ok(pos) = symlist.binary_search_by_key(
+                    &addr,
+                    |&symidx| explorer.sym_obj().symbol_by_index(symidx).unwrap().address()
+                )
+                    else { continue };
+                let target = symlist[pos];
+
+                if visited.insert(target) {
+                    next.push(target);
+                }
+            }
+        }
+
+        for &sym_idx in &next {
+            let sym = explorer.sym_obj().symbol_by_index(sym_idx)?;
+            let name = sym.name().ok();
+
+            if stdio.format.is_text() {
+                writeln!(stdio.stdout)?;
+                writeln!(
+                    stdio.stdout,
+                    "{} {} @ {:018p}",
+                    format_args!("==== follow {}:", level).if_supported(stdio.colored, |a| a.cyan()),
+                    MaybePrinter(name.as_ref().map(|name| name.if_supported(cmd.demangle, |name| explorer.cache.demangle(name))), Some('?')),
+                    sym.address() as *const ()
+                )?;
+            }
+            show_symbol(cmd, explorer, sym_idx, name, stdio).await?;
+        }
+
+        if next.is_empty() {
+            break
+        }
+
+        queue = next;
+    }
+
+    Ok(())
+}
+
+/// Addresses of symbols directly called by a text symbol
+pub(crate) async fn call_targets(explorer: &Explorer, sym_idx: SymbolIndex) -> anyhow::Result<Vec<u64>> {
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let addr2sym = explorer.cache.addr2sym(&explorer.obj).await;
+    let dyn_rela = explorer.cache.dyn_rela(&explorer.obj).await;
+    let sym = explorer.sym_obj().symbol_by_index(sym_idx)?;
+
+    if !matches!(sym.kind(), SymbolKind::Text) {
+        return Ok(Vec::new());
+    }
+
+    let section_idx = sym.section_index().context("not found section index")?;
+    let section = explorer.obj.section_by_index(section_idx)?;
+    let data = explorer.cache.data(&explorer.obj, section_idx).await?;
+    let offset = (sym.address() - section.address()) as usize;
+    let size = explorer.symbol_size(symlist, sym_idx)? as usize;
+    let data = data.get(offset..).unwrap_or_default();
+    let data = &data[..size.min(data.len())];
+
+    let disasm = Disassembler::new(&explorer.obj)?;
+    let mut insts = disasm.disasm_iter(data, sym.address())?;
+    let mut targets = Vec::new();
+
+    while let Some(inst) = insts.next() {
+        let inst = inst?;
+
+        if disasm.inst_kind(&inst) == disasm::InstKind::Call
+            && let Ok(Some(addr)) = disasm.operand2addr(&inst)
+            && let Some((_name, addr)) = query_symbol_by_addr(explorer, addr2sym, dyn_rela, addr)
+            && !targets.contains(&addr)
+        {
+            targets.push(addr);
+        }
+    }
+
+    Ok(targets)
+}
+
+async fn show_symbol(
+    cmd: &Command,
+    explorer: &Explorer,
+    sym_idx: SymbolIndex,
+    name: Option<&str>,
+    stdio: &mut Stdio
+)
+    -> anyhow::Result<()>
+{
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let sym = explorer.sym_obj().symbol_by_index(sym_idx)?;
     let section_idx = sym.section_index().context("not found section index")?;
     let section = explorer.obj.section_by_index(section_idx)?;
 
@@ -152,8 +313,9 @@ async fn by_symbol(
     } else {
         show_data(
             cmd,
+            explorer,
             section.name().ok(),
-            Some(map[idx].name()),
+            name,
             sym.address(),
             data,
             stdio
@@ -199,6 +361,7 @@ async fn by_section(
     } else {
         show_data(
             cmd,
+            explorer,
             section.name().ok(),
             None,
             addr,
@@ -210,6 +373,157 @@ async fn by_section(
     Ok(())
 }
 
+async fn by_pattern(
+    cmd: &Command,
+    explorer: &Explorer,
+    stdio: &mut Stdio
+)
+    -> anyhow::Result<()>
+{
+    let re = regex::Regex::new(&cmd.address)?;
+    let filter = cmd.filter_section
+        .as_ref()
+        .map(|rule| regex::Regex::new(rule))
+        .transpose()?;
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let mut point = YieldPoint::default();
+    let mut count = 0;
+
+    for &sym_idx in symlist {
+        point.yield_now().await;
+
+        let sym = explorer.sym_obj().symbol_by_index(sym_idx)?;
+        let Some(section_idx) = sym.section_index()
+            else { continue };
+        let Ok(mangled_name) = sym.name()
+            else { continue };
+
+        // filter section by regex
+        if let Some(rule) = filter.as_ref()
+            && let Ok(section_name) = explorer.obj.section_by_index(section_idx)?.name()
+            && !rule.is_match(section_name)
+        {
+            continue
+        }
+
+        let name = if cmd.demangle {
+            explorer.cache.demangle(mangled_name)
+        } else {
+            mangled_name.into()
+        };
+
+        if !re.is_match(&name) {
+            continue
+        }
+
+        if stdio.format.is_text() {
+            if count > 0 {
+                writeln!(stdio.stdout)?;
+            }
+
+            writeln!(
+                stdio.stdout,
+                "{} {} @ {:018p}",
+                "====".if_supported(stdio.colored, |a| a.cyan()),
+                name,
+                sym.address() as *const ()
+            )?;
+        }
+        show_symbol(cmd, explorer, sym_idx, Some(mangled_name), stdio).await?;
+        count += 1;
+    }
+
+    if count == 0 {
+        anyhow::bail!("not found symbol by pattern");
+    }
+
+    Ok(())
+}
+
+async fn by_section_name(
+    cmd: &Command,
+    explorer: &Explorer,
+    stdio: &mut Stdio
+)
+    -> anyhow::Result<()>
+{
+    let section = explorer.obj.section_by_name(&cmd.address)
+        .context("not found section by name")?;
+
+    if section.kind() != SectionKind::Text {
+        anyhow::bail!("section kind is not text");
+    }
+
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let data = explorer.cache.data(&explorer.obj, section.index()).await?;
+    let end = section.address() + data.len() as u64;
+    let mut cursor = section.address();
+
+    for &sym_idx in symlist {
+        let sym = explorer.sym_obj().symbol_by_index(sym_idx)?;
+
+        // skip aliases and symbols covered by previous one
+        if sym.section_index() != Some(section.index())
+            || !matches!(sym.kind(), SymbolKind::Text)
+            || sym.address() < cursor
+        {
+            continue
+        }
+
+        let size = explorer.symbol_size(symlist, sym_idx)?;
+
+        if size == 0 {
+            continue
+        }
+
+        if sym.address() > cursor && stdio.format.is_text() {
+            writeln!(
+                stdio.stdout,
+                "{} {:018p}..{:018p} ({} bytes)",
+                "gap:".if_supported(stdio.colored, |a| a.cyan()),
+                cursor as *const (),
+                sym.address() as *const (),
+                sym.address() - cursor
+            )?;
+        }
+
+        let offset = (sym.address() - section.address()) as usize;
+        let size = std::cmp::min(size as usize, data.len() - offset);
+
+        if stdio.format.is_text() {
+            writeln!(stdio.stdout)?;
+        }
+
+        show_text(
+            cmd,
+            explorer,
+            section.index(),
+            sym_idx,
+            &data[offset..][..size],
+            stdio
+        ).await?;
+
+        if stdio.format.is_text() {
+            writeln!(stdio.stdout)?;
+        }
+
+        cursor = sym.address() + size as u64;
+    }
+
+    if end > cursor && stdio.format.is_text() {
+        writeln!(
+            stdio.stdout,
+            "{} {:018p}..{:018p} ({} bytes)",
+            "gap:".if_supported(stdio.colored, |a| a.cyan()),
+            cursor as *const (),
+            end as *const (),
+            end - cursor
+        )?;
+    }
+
+    Ok(())
+}
+
 async fn show_text(
     cmd: &Command,
     explorer: &Explorer,
@@ -226,24 +540,36 @@ async fn show_text(
         disasm: &'a Disassembler,
         addr2sym: &'a SymbolMap<SymbolMapName<'static>>,
         dyn_rela: &'a [(u64, object::read::Relocation)],
+        plt: &'a [PltEntry],
         inst: &'a disasm::Inst<'a>,
     }
 
     impl fmt::Display for RelaPrinter<'_> {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            if let Ok(Some(addr)) = self.disasm.operand2addr(self.inst)
-                && let Some((name, addr)) = query_symbol_by_addr(
-                    self.explorer,
-                    self.addr2sym,
-                    self.dyn_rela,
-                    addr
+            let Ok(Some(addr)) = self.disasm.operand2addr(self.inst)
+                else {
+                    return Ok(())
+                };
+
+            if let Some((name, addr)) = query_symbol_by_addr(
+                self.explorer,
+                self.addr2sym,
+                self.dyn_rela,
+                addr
             ) {
                 write!(
                     f,
                     "\t# {} @ {:018p}",
-                    name.if_supported(self.demangle, |name| demangle(name)),
+                    name.if_supported(self.demangle, |name| self.explorer.cache.demangle(name)),
                     addr as *const ()
                 )?;
+            } else if let Some(entry) = query_plt(self.plt, addr) {
+                write!(
+                    f,
+                    "\t# {}@plt @ {:018p}",
+                    entry.name.if_supported(self.demangle, |name| self.explorer.cache.demangle(name)),
+                    entry.addr as *const ()
+                )?;
             }
 
             Ok(())            
@@ -258,28 +584,28 @@ async fn show_text(
         column: Option<u32>
     }
 
-    let addr2line = if cmd.dwarf {
-        let path = &explorer.path;
-        let addr2line = explorer.cache.addr2line.get_or_try_init(|| async {
-            if let Some(dwarf_path) = explorer.dwarf_path.as_ref() {
-                addr2line::Loader::new_with_sup(path, Some(dwarf_path)).map(Into::into)
-            } else {
-                addr2line::Loader::new(path).map(Into::into)
-            }
-        })
-            .await
-            .map_err(|err| anyhow::format_err!("addr2line: {:?}", err))?;
+    // line records of a PDB, without inline frames
+    let pdb = if cmd.dwarf {
+        explorer.cache.pdb(&explorer.obj).await
+    } else {
+        None
+    };
+    let addr2line = if cmd.dwarf && pdb.is_none() {
+        let addr2line = explorer.cache.addr2line(explorer.dwarf_file(), explorer.dwarf_path.as_deref()).await?;
         Some(addr2line)
     } else {
         None
     };
     
     let section = explorer.obj.section_by_index(section_idx)?;
-    let symbol = explorer.obj.symbol_by_index(symbol_idx)?;
+    let symbol = explorer.sym_obj().symbol_by_index(symbol_idx)?;
     let addr2sym = explorer.cache.addr2sym(&explorer.obj).await;
     let dyn_rela = explorer.cache.dyn_rela(&explorer.obj).await;
+    let plt = explorer.cache.plt(&explorer.obj).await;
 
-    if let Ok(name) = section.name() {
+    if let Ok(name) = section.name()
+        && stdio.format.is_text()
+    {
         writeln!(
             stdio.stdout,
             "{} {}",
@@ -288,20 +614,31 @@ async fn show_text(
         )?;        
     }
 
-    if let Ok(name) = symbol.name() {
+    if let Ok(name) = symbol.name()
+        && stdio.format.is_text()
+    {
         writeln!(
             stdio.stdout,
             "{} {}",
             "symbol:".if_supported(stdio.colored, |a| a.cyan()),
-            name.if_supported(cmd.demangle, |name| demangle(name))
+            name.if_supported(cmd.demangle, |name| explorer.cache.demangle(name))
         )?;        
     }    
 
+    if let Some(object) = explorer.link_map.as_ref()
+        .and_then(|map| map.object_of(symbol.address()))
+        && stdio.format.is_text()
+    {
+        writeln!(
+            stdio.stdout,
+            "{} {}",
+            "object:".if_supported(stdio.colored, |a| a.cyan()),
+            object
+        )?;
+    }
+
     let mut files = IndexSet::new();
-    let mut texts = HashMap::new();
     let lines = if let Some(addr2line) = addr2line.as_ref() {
-        let addr2line = addr2line.lock().await;
-
         let mut lines = addr2line.find_location_range(
             symbol.address(),
             symbol.address() + symbol.size()
@@ -318,97 +655,293 @@ async fn show_text(
             .collect::<Vec<_>>();
         lines.sort_by_key(|line| line.range.start);
         lines
+    } else if let Some(pdb) = pdb {
+        pdb.lines.range(symbol.address()..symbol.address() + symbol.size())
+            .map(|(range, file, line)| Line {
+                range,
+                file: Some(files.insert_full(file.to_owned()).0),
+                line: Some(line),
+                column: None
+            })
+            .collect()
     } else {
         Vec::new()
     };
     let mut last_fileid = None;
+    let mut last_inlines = Vec::new();
     let mut cursor = 0;
 
     // print top
     if cmd.dwarf_top {
-        use addr2line::fallible_iterator::FallibleIterator;
-
-        let addr2line = addr2line.as_ref().context("need --dward")?;
-        let addr2line = addr2line.lock().await;
-        let mut map: IndexMap<_, u64> = IndexMap::new();
+        let addr2line = addr2line.context("need --dwarf")?;
+        let starts = lines.iter().map(|line| line.range.start).collect::<Vec<_>>();
+        let frames = top_frames(explorer, addr2line, &starts).await?;
+        let mut map: IndexMap<&str, u64> = IndexMap::new();
 
         for line in &lines {
             let len = line.range.end - line.range.start;
-
-            let mut iter = addr2line.find_frames(line.range.start)
-                .map_err(|err| anyhow::format_err!("addr2line: {:?}", err))?
-                .filter_map(|frame| Ok(frame.function))
-                .filter_map(|name| Ok(name.raw_name().ok().map(|name| name.into_owned())))
-                .peekable();
-            let mut last = None;
-            while let Some(next) = iter.next()? {
-                if iter.peek()?.is_some() || last.is_none() {
-                    last = Some(next);
-                }
-            }
-
-            if let Some(frame) = last {
-                *map.entry(frame).or_default() += len;
-            } else {
-                *map.entry("<unknown>".into()).or_default() += len;
-            }
+            let frame = frames.get(&line.range.start)
+                .and_then(Option::as_deref)
+                .unwrap_or("<unknown>");
+            *map.entry(frame).or_default() += len;
         }
 
         let mut map: Vec<_> = map.into_iter().collect();
         map.sort_by_key(|(_, count)| *count);
 
+        #[derive(Serialize)]
+        struct Record<'a> {
+            bytes: u64,
+            function: &'a str,
+            demangled: Cow<'a, str>
+        }
+
         for (symbol, count) in map {
+            if !stdio.format.is_text() {
+                stdio.record(&Record {
+                    bytes: count,
+                    function: symbol,
+                    demangled: explorer.cache.demangle(symbol)
+                })?;
+                continue
+            }
+
             writeln!(
                 stdio.stdout,
                 "{:10 }\t{}",
                 count,
-                symbol.if_supported(cmd.demangle, |s| demangle(s)),
+                symbol.if_supported(cmd.demangle, |s| explorer.cache.demangle(s)),
             )?;
         }
 
         return Ok(());
     }    
 
+    if !stdio.format.is_text() {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            address: u64,
+            symbol: &'a str,
+            demangled: Cow<'a, str>,
+            bytes: String,
+            mnemonic: Option<&'a str>,
+            operands: Option<&'a str>,
+            target: Option<u64>,
+            target_symbol: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            file: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            line: Option<u32>
+        }
+
+        let disasm = Disassembler::new(&explorer.obj)?;
+        let mut insts = disasm.disasm_iter(data, symbol.address())?;
+        let symbol_name = symbol.name().unwrap_or_default();
+
+        // no await in this loop, capstone is not `Send`
+        while let Some(inst) = insts.next() {
+            let inst = inst?;
+
+            let target = disasm.operand2addr(&inst).ok().flatten();
+            let target_symbol = target.and_then(|addr| {
+                query_symbol_by_addr(explorer, addr2sym, dyn_rela, addr)
+                    .map(|(name, _)| name)
+                    .or_else(|| query_plt(plt, addr).map(|entry| entry.name))
+            });
+
+            while lines.get(cursor).is_some_and(|line| line.range.end <= inst.address()) {
+                cursor += 1;
+            }
+
+            let line = lines.get(cursor).filter(|line| line.range.contains(&inst.address()));
+
+            stdio.record(&Record {
+                address: inst.address(),
+                symbol: symbol_name,
+                demangled: explorer.cache.demangle(symbol_name),
+                bytes: data_encoding::HEXLOWER.encode(inst.bytes()),
+                mnemonic: inst.mnemonic(),
+                operands: inst.op_str(),
+                target, target_symbol,
+                file: line
+                    .and_then(|line| line.file)
+                    .and_then(|fileid| files.get_index(fileid))
+                    .map(String::as_str),
+                line: line.and_then(|line| line.line)
+            })?;
+        }
+
+        return Ok(());
+    }
+
     // print asm
     {
+        // collect intra-function branch targets and jump tables, the instructions
+        // are decoded again to print them, capstone is not `Send` so it is dropped
+        // before awaiting the table data
+        let (addrs, jumps, finder, datas) = {
+            let disasm = Disassembler::new(&explorer.obj)?;
+            let mut insts = disasm.disasm_iter(data, symbol.address())?;
+            let mut addrs = Vec::new();
+            let mut jumps = Vec::new();
+            let mut data_refs = disasm::DataRefs::default();
+            let mut datas = Vec::new();
+            let mut ptr_arg = None;
+            let mut finder = JumpTableFinder::default();
+
+            while let Some(inst) = insts.next() {
+                let inst = inst?;
+
+                if disasm.inst_kind(&inst) == disasm::InstKind::Jump
+                    && let Ok(Some(target)) = disasm.operand2addr(&inst)
+                {
+                    jumps.push((addrs.len(), target));
+                }
+
+                // a rust `&str` argument is a pointer then its length in the next register
+                let pending = ptr_arg.take();
+
+                if let Some(addr) = disasm.operand2data(&inst, &mut data_refs) {
+                    ptr_arg = disasm.arg_reg(&inst).map(|arg| (datas.len(), arg));
+                    datas.push((inst.address(), addr, None));
+                } else if let Some((idx, arg)) = pending
+                    && inst.mnemonic() == Some("mov")
+                    && disasm.arg_reg(&inst) == Some(arg + 1)
+                    && let [len] = disasm.immediates(&inst)[..]
+                {
+                    datas[idx].2 = u64::try_from(len).ok();
+                }
+
+                finder.step(&disasm, &inst);
+                addrs.push(inst.address());
+            }
+
+            (addrs, jumps, finder, datas)
+        };
+
+        // read the referenced literals ahead, for the same reason, by instruction
+        let mut literals = HashMap::new();
+
+        for (inst_addr, addr, len) in datas {
+            if let Some(literal) = read_literal(explorer, addr, len).await {
+                literals.insert(inst_addr, literal);
+            }
+        }
+
+        let jumps = jumps.into_iter()
+            .filter_map(|(from, target)| Some((from, addrs.binary_search(&target).ok()?)))
+            .collect::<Vec<_>>();
+        let mut labels = jumps.iter().map(|&(_, to)| to).collect::<Vec<_>>();
+
+        // decode jump tables
+        let mut tables = HashMap::new();
+        let range = symbol.address()..symbol.address() + data.len() as u64;
+
+        for table in finder.finish() {
+            let Some(table) = decode_jump_table(explorer, &table, range.clone()).await
+                else { continue };
+
+            labels.extend(table.targets.iter().filter_map(|target| addrs.binary_search(target).ok()));
+            tables.insert(table.jump, table);
+        }
+
+        labels.sort_unstable();
+        labels.dedup();
+
+        let arrows = if !cmd.no_arrows {
+            let arrows = JumpArrows::new(jumps.iter().copied());
+            (!arrows.is_empty()).then_some(arrows)
+        } else {
+            None
+        };
+        let mut jumps = jumps.into_iter().peekable();
+
+        // read the sources ahead, a debuginfod download must not stall the loop below
+        let mut texts = HashMap::new();
+
+        for fileid in lines.iter().filter_map(|line| line.file) {
+            let hash_map::Entry::Vacant(e) = texts.entry(fileid)
+                else { continue };
+            let path = explorer.source_path(files.get_index(fileid).unwrap());
+            let text = match (fs::read_to_string(&*path), explorer.debuginfod.clone()) {
+                (Ok(text), _) => Some(text),
+                (Err(_), Some(client)) => {
+                    let path = path.into_owned();
+                    tokio::task::spawn_blocking(move || client.source(&path)).await?
+                        .and_then(|path| fs::read_to_string(path).ok())
+                },
+                (Err(_), None) => None
+            };
+            e.insert(text);
+        }
+
         let disasm = Disassembler::new(&explorer.obj)?;
         let disasm = &disasm;
+        let mut syscalls = SyscallTracker::default();
+        
+        let mut insts = disasm.disasm_iter(data, symbol.address())?;
 
-        let insts = disasm.disasm_all(data, symbol.address())?;
-        for inst in insts.iter()? {
+        for idx in 0.. {
+            let Some(inst) = insts.next()
+                else { break };
             let inst = inst?;
             let inst = &inst;
+            let label = jumps.next_if(|&(from, _)| from == idx)
+                .and_then(|(_, to)| Some((labels.binary_search(&to).ok()?, addrs[to])));
 
             if let Some(line) = lines.get(cursor)
                 && line.range.contains(&inst.address())
             {
                 cursor += 1;
 
+                if let Some(addr2line) = addr2line.as_ref() {
+                    let inlines = inline_frames(addr2line, line.range.start)?;
+
+                    if inlines != last_inlines {
+                        let common = inlines.iter()
+                            .zip(&last_inlines)
+                            .take_while(|(a, b)| a == b)
+                            .count();
+
+                        for (depth, frame) in inlines.iter().enumerate().skip(common) {
+                            writeln!(
+                                stdio.stdout,
+                                "{}{:width$}{} {}{}",
+                                MaybePrinter(arrows.as_ref().map(|arrows| arrows.gap(idx)), None)
+                                    .if_supported(stdio.colored, |a| a.dimmed()),
+                                "",
+                                "inlined:".if_supported(stdio.colored, |a| a.cyan()),
+                                frame.name.if_supported(cmd.demangle, |name| explorer.cache.demangle(name)),
+                                MaybePrinter(frame.call_site.as_ref().map(|site| format!(" at {}", site)), None)
+                                    .if_supported(stdio.colored, |a| a.dimmed()),
+                                width = depth * 2
+                            )?;
+                        }
+
+                        last_inlines = inlines;
+                    }
+                }
+
                 if let Some(fileid) = line.file {
-                    let path = files.get_index(fileid).unwrap();
-                    let text = match texts.entry(fileid) {
-                        hash_map::Entry::Occupied(e) => Some(e.into_mut()),
-                        hash_map::Entry::Vacant(e) => {
-                            fs::read_to_string(path)
-                                .ok()
-                                .map(|text| e.insert(text))
-                        },
-                    };
+                    let path = explorer.source_path(files.get_index(fileid).unwrap());
+                    let text = texts.get(&fileid).and_then(Option::as_ref);
 
                     if last_fileid.replace(fileid) != Some(fileid) {
-                        let path_ref = Path::new(path);
+                        let path_ref = Path::new(&*path);
                     
                         writeln!(
                             stdio.stdout,
-                            "{} {}{}",
+                            "{}{} {}{}",
+                            MaybePrinter(arrows.as_ref().map(|arrows| arrows.gap(idx)), None)
+                                .if_supported(stdio.colored, |a| a.dimmed()),
                             "file:".if_supported(stdio.colored, |a| a.cyan()),
                             if stdio.hyperlink {
                                 EitherPrinter::Left(Hyperlink::new(
                                     MaybePrinter(path_ref.file_name().map(|name| name.display()), None),
-                                    path
+                                    &*path
                                 ))
                             } else {
-                                EitherPrinter::Right(path)
+                                EitherPrinter::Right(&*path)
                             }.if_supported(stdio.colored, |a| a.dimmed()),
                             format_args!(
                                 ":{},{}",
@@ -427,7 +960,9 @@ async fn show_text(
 
                         writeln!(
                             stdio.stdout,
-                            "{}{}",
+                            "{}{}{}",
+                            MaybePrinter(arrows.as_ref().map(|arrows| arrows.gap(idx)), None)
+                                .if_supported(stdio.colored, |a| a.dimmed()),
                             text0.if_supported(stdio.colored, |a| a.dimmed()),
                             text1
                         )?;
@@ -435,19 +970,72 @@ async fn show_text(
                 }
             }
         
+            if let Ok(id) = labels.binary_search(&idx) {
+                syscalls.reset();
+
+                writeln!(
+                    stdio.stdout,
+                    "{}{}",
+                    MaybePrinter(arrows.as_ref().map(|arrows| arrows.gap(idx)), None)
+                        .if_supported(stdio.colored, |a| a.dimmed()),
+                    format_args!(".LBB{}:", id).if_supported(stdio.colored, |a| a.magenta())
+                )?;
+            }
+        
             let rela = RelaPrinter {
                 demangle: cmd.demangle,
-                explorer, disasm, addr2sym, dyn_rela, inst
+                explorer, disasm, addr2sym, dyn_rela, plt, inst
             };
+            let literal = literals.get(&inst.address());
+            let syscall = syscalls.step(disasm, inst).map(|syscall| match (syscall.name, syscall.nr) {
+                (Some(name), Some(nr)) => format!("\t# syscall {} ({})", name, nr),
+                (None, Some(nr)) => format!("\t# syscall {}", nr),
+                (_, None) => "\t# syscall ?".into()
+            });
         
             writeln!(
                 stdio.stdout,
-                "{:018p}  {}  {}{}",
+                "{}{:018p}  {}  {}{}{}{}",
+                MaybePrinter(arrows.as_ref().map(|arrows| arrows.line(idx)), None)
+                    .if_supported(stdio.colored, |a| a.dimmed()),
                 (inst.address() as *const ()),
                 HexPrinter(inst.bytes(), 8).if_supported(stdio.colored, |a| a.dimmed()),
-                inst,
-                rela.if_supported(stdio.colored, |a| a.dimmed())
+                disasm::Highlight::new(disasm, inst, stdio.colored).label(label),
+                rela.if_supported(stdio.colored, |a| a.dimmed()),
+                MaybePrinter(literal.map(|literal| format!("\t# {}", literal)), None)
+                    .if_supported(stdio.colored, |a| a.dimmed()),
+                MaybePrinter(syscall, None).if_supported(stdio.colored, |a| a.dimmed())
             )?;
+
+            if let Some(table) = tables.get(&inst.address()) {
+                let gap = MaybePrinter(arrows.as_ref().map(|arrows| arrows.gap(idx + 1)), None);
+
+                writeln!(
+                    stdio.stdout,
+                    "{}{}",
+                    gap.if_supported(stdio.colored, |a| a.dimmed()),
+                    format_args!(
+                        "\t# jump table @ {:018p}, {} cases",
+                        table.table as *const (),
+                        table.targets.len()
+                    ).if_supported(stdio.colored, |a| a.dimmed())
+                )?;
+
+                for (case, &target) in table.targets.iter().enumerate() {
+                    let label = addrs.binary_search(&target).ok()
+                        .and_then(|idx| labels.binary_search(&idx).ok());
+
+                    writeln!(
+                        stdio.stdout,
+                        "{}\t#   case {}: {}{}",
+                        gap.if_supported(stdio.colored, |a| a.dimmed()),
+                        case,
+                        MaybePrinter(label.map(|id| format!(".LBB{} ", id)), None)
+                            .if_supported(stdio.colored, |a| a.magenta()),
+                        format_args!("({:#x})", target).if_supported(stdio.colored, |a| a.dimmed())
+                    )?;
+                }
+            }
         }
     }
     
@@ -456,12 +1044,39 @@ async fn show_text(
 
 async fn show_data(
     cmd: &Command,
+    explorer: &Explorer,
     section_name: Option<&str>,
     symbol_name: Option<&str>,
     start: u64,
     data: &[u8],
     stdio: &mut Stdio    
 ) -> anyhow::Result<()> {
+    if !stdio.format.is_text() {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            address: u64,
+            section: Option<&'a str>,
+            symbol: Option<&'a str>,
+            bytes: String
+        }
+
+        let width = 16;
+        let mut point = YieldPoint::default();
+
+        for (offset, chunk) in data.chunks(width).enumerate() {
+            point.yield_now().await;
+
+            stdio.record(&Record {
+                address: start.wrapping_add((offset * width) as u64),
+                section: section_name,
+                symbol: symbol_name,
+                bytes: data_encoding::HEXLOWER.encode(chunk)
+            })?;
+        }
+
+        return Ok(());
+    }
+
     if let Some(name) = section_name {
         writeln!(
             stdio.stdout,
@@ -476,10 +1091,54 @@ async fn show_data(
             stdio.stdout,
             "{} {}",
             "symbol:".if_supported(stdio.colored, |a| a.cyan()),
-            name.if_supported(cmd.demangle, |name| demangle(name))
+            name.if_supported(cmd.demangle, |name| explorer.cache.demangle(name))
+        )?;
+    }
+
+    if let Some(object) = explorer.link_map.as_ref()
+        .and_then(|map| map.object_of(start))
+    {
+        writeln!(
+            stdio.stdout,
+            "{} {}",
+            "object:".if_supported(stdio.colored, |a| a.cyan()),
+            object
         )?;
     }
 
+    // annotate jump tables overlapping the data, also one starting before it
+    {
+        let addr2sym = explorer.cache.addr2sym(&explorer.obj).await;
+        let tables = jump_tables(explorer, stdio).await?;
+        let end = start.saturating_add(data.len() as u64);
+        let tables = tables[..tables.partition_point(|table| table.table < end)]
+            .iter()
+            .filter(|table| table.table + u64::from(table.size) * table.targets.len() as u64 > start);
+
+        for table in tables {
+            writeln!(
+                stdio.stdout,
+                "{} {:018p} ({} x {} bytes) for jump at {:018p}{}",
+                "jump table:".if_supported(stdio.colored, |a| a.cyan()),
+                table.table as *const (),
+                table.targets.len(),
+                table.size,
+                table.jump as *const (),
+                MaybePrinter(
+                    addr2sym.get(table.jump).map(|sym| format!(
+                        " in {}",
+                        sym.name().if_supported(cmd.demangle, |name| explorer.cache.demangle(name))
+                    )),
+                    None
+                )
+            )?;
+
+            for (case, target) in table.targets.iter().enumerate() {
+                writeln!(stdio.stdout, "\t# case {}: {:018p}", case, *target as *const ())?;
+            }
+        }
+    }
+
     let addr = start;
     let width = 16;
     let mut point = YieldPoint::default();
@@ -500,6 +1159,390 @@ async fn show_data(
     Ok(())
 }
 
+async fn by_type(
+    cmd: &Command,
+    explorer: &Explorer,
+    addr: u64,
+    name: &str,
+    stdio: &mut Stdio
+)
+    -> anyhow::Result<()>
+{
+    #[derive(Serialize)]
+    struct Record<'a> {
+        address: u64,
+        path: &'a str,
+        #[serde(rename = "type")]
+        type_name: &'a str,
+        value: &'a str,
+        symbol: Option<&'a str>,
+        string: Option<&'a str>
+    }
+
+    let dwarf = explorer.cache.dwarf(explorer.dwarf_obj(), explorer.dwarf_path.as_deref()).await?;
+    let addr2sym = explorer.cache.addr2sym(&explorer.obj).await;
+    let dyn_rela = explorer.cache.dyn_rela(&explorer.obj).await;
+    let (unit, type_offset) = find_type(dwarf, name).await?
+        .with_context(|| format!("not found type: {}", name))?;
+    let size = dwarf::type_size(&unit, type_offset)
+        .with_context(|| format!("unknown size of type: {}", name))?;
+    let data = crate::vars::read_static(explorer, addr, size).await?;
+
+    let mut decoder = Decoder::new(dwarf, &unit, explorer.obj.is_little_endian());
+    decoder.decode(type_offset, &data, 0, String::new());
+
+    if stdio.format.is_text() {
+        writeln!(
+            stdio.stdout,
+            "{} {}\t{} bytes",
+            "type:".if_supported(stdio.colored, |a| a.cyan()),
+            dwarf::type_name(dwarf, &unit, type_offset),
+            size
+        )?;
+
+        if let Some(sym) = addr2sym.get(addr) {
+            writeln!(
+                stdio.stdout,
+                "{} {}+{:#x}",
+                "symbol:".if_supported(stdio.colored, |a| a.cyan()),
+                sym.name().if_supported(cmd.demangle, |name| explorer.cache.demangle(name)),
+                addr - sym.address()
+            )?;
+        }
+    }
+
+    for item in &decoder.items {
+        let field_addr = addr + item.offset;
+
+        // pointers of position independent code are filled by relocations
+        let target = match dyn_rela.binary_search_by_key(&field_addr, |(addr, _)| *addr) {
+            Ok(idx) => match dyn_rela[idx].1.target() {
+                object::RelocationTarget::Absolute => u64::try_from(dyn_rela[idx].1.addend()).ok(),
+                object::RelocationTarget::Symbol(symidx) => explorer.obj.symbol_by_index(symidx).ok()
+                    .map(|sym| sym.address().wrapping_add_signed(dyn_rela[idx].1.addend())),
+                _ => None
+            },
+            Err(_) => item.pointer
+        };
+        let target = target.filter(|&target| item.pointer.is_some() && target != 0);
+        let symbol = target
+            .and_then(|target| addr2sym.get(target).map(|sym| (sym, target)))
+            .map(|(sym, target)| {
+                let name = sym.name();
+                let name = name.if_supported(cmd.demangle, |name| explorer.cache.demangle(name));
+
+                match target - sym.address() {
+                    0 => name.to_string(),
+                    offset => format!("{}+{:#x}", name, offset)
+                }
+            });
+        let string = match target {
+            Some(target) => read_literal(explorer, target, None).await,
+            None => None
+        };
+        let value = match (item.pointer, target) {
+            (Some(0), Some(target)) => format!("{:#x}", target),
+            _ => item.value.clone()
+        };
+        let path = if item.path.is_empty() { "." } else { &item.path };
+
+        if !stdio.format.is_text() {
+            stdio.record(&Record {
+                address: field_addr,
+                path,
+                type_name: &item.type_name,
+                value: &value,
+                symbol: symbol.as_deref(),
+                string: string.as_deref()
+            })?;
+            continue
+        }
+
+        writeln!(
+            stdio.stdout,
+            "  {:#06x}\t{}\t{}\t= {}{}{}",
+            item.offset,
+            path,
+            item.type_name,
+            value,
+            MaybePrinter(symbol.map(|symbol| format!(" <{}>", symbol)), None),
+            MaybePrinter(string.map(|string| format!(" {}", string)), None)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Defined type by qualified name or last component, generic parameters optional
+async fn find_type(dwarf: &dwarf::Dwarf, name: &str)
+    -> anyhow::Result<Option<(dwarf::Unit, addr2line::gimli::UnitOffset)>>
+{
+    use addr2line::gimli;
+
+    let mut point = YieldPoint::default();
+    let mut units = dwarf.units();
+
+    while let Some(header) = units.next()? {
+        point.yield_now().await;
+
+        let unit = dwarf.unit(header)?;
+        let mut entries = unit.entries();
+        let mut depth = 0;
+        let mut scope = dwarf::Scope::default();
+        let mut found = None;
+
+        while let Some((delta, entry)) = entries.next_dfs()? {
+            depth += delta;
+
+            let entry_name = dwarf::attr_string(dwarf, &unit, entry, gimli::DW_AT_name);
+            scope.enter(depth, entry.tag(), entry_name.clone());
+
+            if !matches!(
+                entry.tag(),
+                gimli::DW_TAG_structure_type
+                    | gimli::DW_TAG_class_type
+                    | gimli::DW_TAG_union_type
+                    | gimli::DW_TAG_enumeration_type
+                    | gimli::DW_TAG_typedef
+                    | gimli::DW_TAG_base_type
+            ) || dwarf::attr_flag(entry, gimli::DW_AT_declaration) {
+                continue
+            }
+
+            let Some(entry_name) = entry_name
+                else { continue };
+            let qualified = scope.qualify(&entry_name);
+            let base = qualified.split('<').next().unwrap_or(&qualified);
+            let last = base.rsplit("::").next().unwrap_or(base);
+
+            if qualified == name || base == name || last == name {
+                found = Some(entry.offset());
+                break
+            }
+        }
+
+        if let Some(offset) = found {
+            return Ok(Some((unit, offset)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Line ranges looked up per loader at least, fewer are not worth opening another
+const TOP_FRAMES_CHUNK: usize = 256;
+
+/// Outermost inlined function at each of `addrs`, None if there is no frame
+///
+/// Results are cached across requests. Missing ones are split across `loader`
+/// and the idle loaders of the pool, and looked up in parallel.
+async fn top_frames(explorer: &Explorer, loader: OwnedMutexGuard<Loader>, addrs: &[u64])
+    -> anyhow::Result<HashMap<u64, Option<Arc<str>>>>
+{
+    use rayon::prelude::*;
+
+    let mut frames = HashMap::new();
+    let mut missing = Vec::new();
+
+    {
+        let cache = explorer.cache.top_frames.read().await;
+
+        for &addr in addrs {
+            match cache.get(&addr) {
+                Some(frame) => {
+                    frames.insert(addr, frame.clone());
+                },
+                None => missing.push(addr)
+            }
+        }
+    }
+
+    if missing.is_empty() {
+        return Ok(frames);
+    }
+
+    missing.sort_unstable();
+    missing.dedup();
+
+    let mut loaders = vec![loader];
+    let wanted = missing.len().div_ceil(TOP_FRAMES_CHUNK);
+
+    if let Some(pool) = explorer.cache.addr2line.get() {
+        while loaders.len() < wanted
+            && let Some(loader) = pool.try_get().await?
+        {
+            loaders.push(loader);
+        }
+    }
+
+    let chunk = missing.len().div_ceil(loaders.len());
+    let found = loaders.into_par_iter()
+        .zip(missing.par_chunks(chunk))
+        .map(|(loader, addrs)| addrs.iter()
+            .map(|&addr| Ok((addr, top_frame(&loader, addr)?)))
+            .collect::<anyhow::Result<Vec<_>>>()
+        )
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut cache = explorer.cache.top_frames.write().await;
+
+    for (addr, frame) in found.into_iter().flatten() {
+        cache.insert(addr, frame.clone());
+        frames.insert(addr, frame);
+    }
+
+    Ok(frames)
+}
+
+/// Outermost function inlined into the symbol at `addr`, the symbol itself if none is
+fn top_frame(addr2line: &Loader, addr: u64) -> anyhow::Result<Option<Arc<str>>> {
+    use addr2line::fallible_iterator::FallibleIterator;
+
+    let mut iter = addr2line.find_frames(addr)
+        .map_err(|err| anyhow::format_err!("addr2line: {:?}", err))?
+        .filter_map(|frame| Ok(frame.function))
+        .filter_map(|name| Ok(name.raw_name().ok().map(Arc::from)))
+        .peekable();
+    let mut last = None;
+
+    while let Some(next) = iter.next()? {
+        if iter.peek()?.is_some() || last.is_none() {
+            last = Some(next);
+        }
+    }
+
+    Ok(last)
+}
+
+#[derive(PartialEq, Eq)]
+struct InlineFrame {
+    name: String,
+    call_site: Option<String>
+}
+
+/// Inlined function chain at `addr`, outermost first
+fn inline_frames(addr2line: &addr2line::Loader, addr: u64) -> anyhow::Result<Vec<InlineFrame>> {
+    use addr2line::fallible_iterator::FallibleIterator;
+
+    let frames = addr2line.find_frames(addr)
+        .map_err(|err| anyhow::format_err!("addr2line: {:?}", err))?
+        .collect::<Vec<_>>()
+        .map_err(|err| anyhow::format_err!("addr2line: {:?}", err))?;
+
+    // innermost first, the last frame is the symbol itself
+    let mut inlines = frames.windows(2)
+        .map(|pair| {
+            let name = pair[0].function.as_ref()
+                .and_then(|name| name.raw_name().ok())
+                .map(|name| name.into_owned())
+                .unwrap_or_else(|| "<unknown>".into());
+            let call_site = pair[1].location.as_ref().map(|location| format!(
+                "{}:{}",
+                location.file
+                    .and_then(|file| Path::new(file).file_name().map(|name| name.display().to_string()))
+                    .unwrap_or_else(|| "?".into()),
+                MaybePrinter(location.line, Some('?'))
+            ));
+            InlineFrame { name, call_site }
+        })
+        .collect::<Vec<_>>();
+    inlines.reverse();
+
+    Ok(inlines)
+}
+
+/// Jump tables of all functions, found by disassembling them on first use
+async fn jump_tables<'a>(explorer: &'a Explorer, stdio: &Stdio) -> anyhow::Result<&'a [JumpTable]> {
+    explorer.cache.jump_tables.get_or_try_init(async || {
+        let found = crate::search::par_disasm(explorer, None, stdio, |disasm, symidx, size, mut insts| {
+            let start = explorer.sym_obj().symbol_by_index(symidx)?.address();
+            let mut finder = JumpTableFinder::default();
+
+            while let Some(Ok(inst)) = insts.next() {
+                finder.step(disasm, &inst);
+            }
+
+            Ok(finder.finish()
+                .into_iter()
+                .map(|table| (table, start..start + size))
+                .collect())
+        }).await?;
+
+        let mut tables = Vec::new();
+
+        for (table, range) in found {
+            if let Some(table) = decode_jump_table(explorer, &table, range).await {
+                tables.push(table);
+            }
+        }
+
+        tables.sort_unstable_by_key(|table| (table.table, table.jump));
+        tables.dedup_by_key(|table| table.table);
+        Ok(tables.into_boxed_slice())
+    })
+        .await
+        .map(|tables| &**tables)
+}
+
+/// Jump table found in the function at `range`, decoded from the section holding it
+pub(crate) async fn decode_jump_table(explorer: &Explorer, table: &JumpTableRef, range: Range<u64>)
+    -> Option<JumpTable>
+{
+    let section = explorer.obj.sections()
+        .find(|section| (section.address()..section.address() + section.size()).contains(&table.table))?;
+    let data = explorer.cache.data(&explorer.obj, section.index()).await.ok()?;
+    Some(table.decode(&data, section.address(), range))
+}
+
+/// String literal at `addr`, of `len` bytes if known, else up to a NUL
+///
+/// Rust literals are packed without a NUL, so without the length the text runs
+/// into the next one. It is cut at `MAX_LEN` bytes and marked with `...`.
+pub(crate) async fn read_literal(explorer: &Explorer, addr: u64, len: Option<u64>) -> Option<String> {
+    const MAX_LEN: usize = 64;
+    const MIN_LEN: usize = 3;
+
+    let section = explorer.obj.sections()
+        .filter(|section| matches!(
+            section.kind(),
+            SectionKind::ReadOnlyData | SectionKind::ReadOnlyString | SectionKind::ReadOnlyDataWithRel
+        ))
+        .find(|section| {
+            let start = section.address();
+            let end = start + section.size();
+            (start..end).contains(&addr)
+        })?;
+    let data = explorer.cache.data(&explorer.obj, section.index()).await.ok()?;
+    let data = data.get((addr - section.address()) as usize..)?;
+
+    let len = match len {
+        Some(len) => usize::try_from(len).ok().filter(|&len| len > 0 && len <= data.len())?,
+        None => data.iter()
+            .take(MAX_LEN + 1)
+            .position(|&b| b == 0)
+            .unwrap_or_else(|| data.len().min(MAX_LEN + 1))
+    };
+
+    if len < MIN_LEN {
+        return None;
+    }
+
+    let truncated = len > MAX_LEN;
+    let bytes = &data[..len.min(MAX_LEN)];
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        // a character cut by the limit
+        Err(err) if truncated && err.error_len().is_none() => std::str::from_utf8(&bytes[..err.valid_up_to()]).ok()?,
+        Err(_) => return None
+    };
+
+    if text.chars().any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r')) {
+        return None;
+    }
+
+    Some(format!("{:?}{}", text, if truncated { "..." } else { "" }))
+}
+
 async fn dump_data(data: &[u8], stdio: &mut Stdio) -> anyhow::Result<()> {
     let mut point = YieldPoint::default();
     
@@ -511,6 +1554,12 @@ async fn dump_data(data: &[u8], stdio: &mut Stdio) -> anyhow::Result<()> {
     Ok(())
 }
 
+pub(crate) fn query_plt(plt: &[PltEntry], addr: u64) -> Option<&PltEntry> {
+    plt.binary_search_by_key(&addr, |entry| entry.addr)
+        .ok()
+        .map(|idx| &plt[idx])
+}
+
 pub(crate) fn query_symbol_by_addr(
     explorer: &Explorer,
     addr2sym: &SymbolMap<SymbolMapName<'static>>,
@@ -0,0 +1,124 @@
+use std::fmt;
+
+
+const MAX_LANES: usize = 8;
+
+pub struct JumpArrows {
+    arrows: Vec<Arrow>,
+    lanes: usize
+}
+
+struct Arrow {
+    from: usize,
+    to: usize,
+    lane: usize
+}
+
+pub struct Margin<'a> {
+    arrows: &'a JumpArrows,
+    idx: usize,
+    gap: bool
+}
+
+impl Arrow {
+    fn lo(&self) -> usize {
+        self.from.min(self.to)
+    }
+
+    fn hi(&self) -> usize {
+        self.from.max(self.to)
+    }
+}
+
+impl JumpArrows {
+    /// Layout arrows from `(from, to)` instruction index pairs
+    ///
+    /// Shorter jumps get the inner lanes, jumps that do not fit into
+    /// `MAX_LANES` are dropped.
+    pub fn new(jumps: impl IntoIterator<Item = (usize, usize)>) -> JumpArrows {
+        let mut jumps = jumps.into_iter()
+            .filter(|(from, to)| from != to)
+            .collect::<Vec<_>>();
+        jumps.sort_by_key(|(from, to)| from.abs_diff(*to));
+
+        let mut lanes: Vec<Vec<(usize, usize)>> = Vec::new();
+        let mut arrows = Vec::with_capacity(jumps.len());
+
+        for (from, to) in jumps {
+            let (lo, hi) = (from.min(to), from.max(to));
+            let lane = match lanes.iter()
+                .position(|lane| lane.iter().all(|&(lo0, hi0)| hi0 < lo || hi < lo0))
+            {
+                Some(lane) => lane,
+                None if lanes.len() < MAX_LANES => {
+                    lanes.push(Vec::new());
+                    lanes.len() - 1
+                },
+                None => continue
+            };
+
+            lanes[lane].push((lo, hi));
+            arrows.push(Arrow { from, to, lane });
+        }
+
+        JumpArrows { arrows, lanes: lanes.len() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.arrows.is_empty()
+    }
+
+    /// Margin for the instruction line at `idx`
+    pub fn line(&self, idx: usize) -> Margin<'_> {
+        Margin { arrows: self, idx, gap: false }
+    }
+
+    /// Margin for extra lines printed before the instruction at `idx`
+    pub fn gap(&self, idx: usize) -> Margin<'_> {
+        Margin { arrows: self, idx, gap: true }
+    }
+}
+
+impl fmt::Display for Margin<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use std::fmt::Write;
+
+        let idx = self.idx;
+        let mut horizontal = false;
+
+        for lane in (0..self.arrows.lanes).rev() {
+            let arrow = self.arrows.arrows.iter()
+                .find(|arrow| arrow.lane == lane && (arrow.lo()..=arrow.hi()).contains(&idx));
+
+            let c = match arrow {
+                Some(arrow) if self.gap && arrow.lo() < idx => '│',
+                _ if self.gap => ' ',
+                Some(arrow) if arrow.lo() == idx => {
+                    horizontal = true;
+                    '╭'
+                },
+                Some(arrow) if arrow.hi() == idx => {
+                    horizontal = true;
+                    '╰'
+                },
+                Some(_) if horizontal => '┼',
+                Some(_) => '│',
+                None if horizontal => '─',
+                None => ' '
+            };
+            f.write_char(c)?;
+        }
+
+        let tail = if self.gap {
+            ' '
+        } else if self.arrows.arrows.iter().any(|arrow| arrow.to == idx) {
+            '>'
+        } else if horizontal {
+            '─'
+        } else {
+            ' '
+        };
+        f.write_char(tail)?;
+        f.write_char(' ')
+    }
+}
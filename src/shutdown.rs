@@ -0,0 +1,54 @@
+use std::env;
+use std::io::Write;
+use directories::ProjectDirs;
+use tokio::sync::Notify;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::util::Stdio;
+use crate::sessions;
+
+
+/// stop the session once its running commands finish
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// stop every live session started from the current directory instead
+    #[arg(long)]
+    #[serde(skip)]
+    pub all: bool,
+}
+
+impl Command {
+    pub async fn exec(self, shutdown: &Notify, stdio: &mut Stdio) -> anyhow::Result<()> {
+        // the listen loop waits for this connection to end
+        shutdown.notify_one();
+        writeln!(stdio.stdout, "shutdown session {}", std::process::id())?;
+
+        Ok(())
+    }
+
+    /// Interrupt the sessions of the current directory, without connecting to them
+    pub fn exec_all(self, dir: &ProjectDirs) -> anyhow::Result<()> {
+        let cwd = env::current_dir()?;
+        let mut count = 0;
+
+        let sessions = sessions::list(sessions::dir(dir))?
+            .into_iter()
+            .filter(|session| session.alive)
+            .filter(|session| session.info.as_ref().is_some_and(|info| info.cwd == cwd));
+
+        for session in sessions {
+            session.kill()?;
+            println!("{}", session.path.display());
+            count += 1;
+        }
+
+        println!("total: {} sessions", count);
+
+        Ok(())
+    }
+}
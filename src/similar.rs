@@ -0,0 +1,375 @@
+use std::io::Write;
+use std::hash::{ Hash, Hasher };
+use std::collections::{ HashMap, HashSet };
+use std::collections::hash_map::DefaultHasher;
+use object::{ Object, ObjectSection, ObjectSymbol, SectionKind };
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, Progress, MaybePrinter };
+use crate::disasm::{ Disassembler, InstKind };
+
+
+/// match functions with another binary of the session by their basic blocks, for patch diffing
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// binary of the session to match against, by file name or path as `--bin`
+    #[arg(long, value_name = "BIN")]
+    pub to: String,
+
+    /// only pairs scoring at least this, from 0 to 1
+    #[arg(long, default_value_t = 0.5)]
+    pub min_score: f64,
+
+    /// only pairs that changed, scoring below 1
+    #[arg(long)]
+    pub changed: bool,
+
+    /// demangle symbol name
+    #[arg(short, long)]
+    pub demangle: bool,
+
+    /// print at most N results
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+/// Blocks found in more functions than this are too common to tell them apart
+const MAX_BLOCK_FUNCS: usize = 64;
+
+/// Basic block hashes of a function
+struct Fingerprint {
+    start: u64,
+    size: u64,
+    symbol: Option<object::SymbolIndex>,
+    /// hash of all blocks in order
+    whole: u64,
+    /// block hash and its instruction count, one entry per distinct hash, sorted
+    blocks: Vec<(u64, u32)>,
+    insts: u32
+}
+
+impl Command {
+    /// Pairs functions one to one, best score first
+    ///
+    /// A block hashes the mnemonics and operands of its instructions with numbers masked,
+    /// so moved code and changed addresses still match. The score of a pair is the
+    /// instructions in the blocks they share, relative to the instructions of both.
+    pub async fn exec(self, explorer: &Explorer, other: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            address: u64,
+            name: Option<&'a str>,
+            to_address: u64,
+            to_name: Option<&'a str>,
+            score: f64
+        }
+
+        if explorer.obj.architecture() != other.obj.architecture() {
+            anyhow::bail!(
+                "arch mismatch: {:?} and {:?}",
+                explorer.obj.architecture(),
+                other.obj.architecture()
+            );
+        }
+
+        let funcs = fingerprints(explorer, stdio).await?;
+        let other_funcs = fingerprints(other, stdio).await?;
+
+        let mut index: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (idx, func) in other_funcs.iter().enumerate() {
+            for &(hash, _) in &func.blocks {
+                index.entry(hash).or_default().push(idx);
+            }
+        }
+        index.retain(|_, funcs| funcs.len() <= MAX_BLOCK_FUNCS);
+
+        let mut wholes: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (idx, func) in other_funcs.iter().enumerate() {
+            wholes.entry(func.whole).or_default().push(idx);
+        }
+
+        let mut candidates = {
+            use rayon::prelude::*;
+
+            let other_funcs = &other_funcs;
+            let min_score = self.min_score;
+
+            funcs.par_iter()
+                .enumerate()
+                .flat_map_iter(|(idx, func)| {
+                    let mut shared: HashMap<usize, u32> = HashMap::new();
+
+                    for &(hash, count) in &func.blocks {
+                        for &other_idx in index.get(&hash).into_iter().flatten() {
+                            let other_func = &other_funcs[other_idx];
+                            let pos = other_func.blocks.binary_search_by_key(&hash, |&(hash, _)| hash).unwrap();
+                            *shared.entry(other_idx).or_default() += count.min(other_func.blocks[pos].1);
+                        }
+                    }
+
+                    // identical, even if all its blocks are common
+                    for &other_idx in wholes.get(&func.whole).into_iter().flatten() {
+                        if other_funcs[other_idx].size == func.size {
+                            shared.insert(other_idx, func.insts);
+                        }
+                    }
+
+                    shared.into_iter()
+                        .map(move |(other_idx, shared)| {
+                            let other_func = &other_funcs[other_idx];
+                            let score = 2.0 * shared as f64 / (func.insts + other_func.insts).max(1) as f64;
+                            (score, idx, other_idx)
+                        })
+                        .filter(move |&(score, ..)| score >= min_score)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // best first, closest size on ties
+        candidates.sort_by(|(score0, idx0, other0), (score1, idx1, other1)| score1.total_cmp(score0)
+            .then_with(|| funcs[*idx0].size.abs_diff(other_funcs[*other0].size)
+                .cmp(&funcs[*idx1].size.abs_diff(other_funcs[*other1].size)))
+            .then((idx0, other0).cmp(&(idx1, other1)))
+        );
+
+        let mut used = HashSet::new();
+        let mut used_other = HashSet::new();
+        let mut pairs = Vec::new();
+
+        for (score, idx, other_idx) in candidates {
+            if !used.contains(&idx) && !used_other.contains(&other_idx) {
+                used.insert(idx);
+                used_other.insert(other_idx);
+                pairs.push((idx, other_idx, score));
+            }
+        }
+
+        pairs.sort_by_key(|&(idx, ..)| funcs[idx].start);
+
+        let name_of = |explorer: &'_ Explorer, func: &Fingerprint| {
+            let name = explorer.sym_obj().symbol_by_index(func.symbol?).ok()?.name().ok()?;
+            Some(if self.demangle {
+                explorer.cache.demangle(name).into_owned()
+            } else {
+                name.to_owned()
+            })
+        };
+
+        let changed = pairs.iter().filter(|(.., score)| *score < 1.0).count();
+        let mut count = 0;
+
+        for &(idx, other_idx, score) in &pairs {
+            if self.changed && score >= 1.0 {
+                continue
+            }
+
+            if self.limit.is_some_and(|limit| count >= limit) {
+                break
+            }
+            count += 1;
+
+            let (func, other_func) = (&funcs[idx], &other_funcs[other_idx]);
+            let name = name_of(explorer, func);
+            let other_name = name_of(other, other_func);
+
+            if !stdio.format.is_text() {
+                stdio.record(&Record {
+                    address: func.start,
+                    name: name.as_deref(),
+                    to_address: other_func.start,
+                    to_name: other_name.as_deref(),
+                    score
+                })?;
+                continue
+            }
+
+            writeln!(
+                stdio.stdout,
+                "{:018p} {:018p} {:.3}\t{}\t{}",
+                func.start as *const (),
+                other_func.start as *const (),
+                score,
+                MaybePrinter(name.as_deref(), Some('?')),
+                MaybePrinter(other_name.as_deref(), Some('?'))
+            )?;
+        }
+
+        if stdio.format.is_text() {
+            writeln!(
+                stdio.stdout,
+                "total: {} matched, {} changed, {} unmatched of {}, {} unmatched of {}",
+                pairs.len(),
+                changed,
+                funcs.len() - pairs.len(),
+                funcs.len(),
+                other_funcs.len() - pairs.len(),
+                other_funcs.len()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Fingerprints of the text symbols, or of the FDEs of the unwind info if stripped
+async fn fingerprints(explorer: &Explorer, stdio: &Stdio) -> anyhow::Result<Vec<Fingerprint>> {
+    use rayon::prelude::*;
+
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+
+    let mut sections = Vec::new();
+    for section in explorer.obj.sections()
+        .filter(|section| section.kind() == SectionKind::Text)
+    {
+        let data = explorer.cache.data(&explorer.obj, section.index()).await?;
+        sections.push((section.address(), data));
+    }
+
+    let mut funcs: Vec<(u64, u64, Option<object::SymbolIndex>)> = Vec::new();
+    for &idx in symlist {
+        let sym = explorer.sym_obj().symbol_by_index(idx)?;
+
+        if sym.kind() == object::SymbolKind::Text
+            && !sym.is_undefined()
+            && funcs.last().is_none_or(|&(start, ..)| start != sym.address())
+        {
+            let size = explorer.symbol_size(symlist, idx)?;
+            funcs.push((sym.address(), size, Some(idx)));
+        }
+    }
+
+    if funcs.is_empty() {
+        let frames = explorer.cache.frames(&explorer.obj).await?;
+        funcs = crate::frames::frame_sizes(frames, explorer.obj.architecture())?
+            .into_iter()
+            .map(|(start, end, _)| (start, end - start, None))
+            .collect();
+    }
+
+    let progress = Progress::new(&stdio.stderr, funcs.len());
+
+    let output = funcs.par_iter()
+        .map_init(
+            || Disassembler::new(&explorer.obj),
+            |disasm, &(start, size, symbol)| {
+                stdio.cancel.check()?;
+
+                if let Some(progress) = progress.as_ref() {
+                    progress.inc();
+                }
+
+                let disasm = disasm.as_ref().map_err(|err| anyhow::format_err!("{:?}", err))?;
+                let Some(data) = sections.iter()
+                    .find(|(addr, data)| (*addr..*addr + data.len() as u64).contains(&start))
+                    .and_then(|(addr, data)| data.get((start - addr) as usize..)?.get(..size as usize))
+                    else { return Ok(None) };
+
+                fingerprint(disasm, data, start, symbol).map(Some)
+            }
+        )
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    drop(progress);
+
+    Ok(output.into_iter().flatten().collect())
+}
+
+fn fingerprint(disasm: &Disassembler, data: &[u8], start: u64, symbol: Option<object::SymbolIndex>)
+    -> anyhow::Result<Fingerprint>
+{
+    let mut insts = disasm.disasm_iter(data, start)?;
+    let mut hashes = Vec::new();
+    let mut leaders = HashSet::new();
+    let mut cut = true;
+
+    while let Some(Ok(inst)) = insts.next() {
+        let kind = disasm.inst_kind(&inst);
+
+        if cut {
+            leaders.insert(inst.address());
+        }
+
+        // a jump target within the function starts a block
+        if kind == InstKind::Jump
+            && let Some(target) = disasm.operand2addr(&inst)?
+            && (start..start + data.len() as u64).contains(&target)
+        {
+            leaders.insert(target);
+        }
+
+        let mut hasher = DefaultHasher::new();
+        inst.mnemonic().hash(&mut hasher);
+        hash_operands(inst.op_str().unwrap_or_default(), &mut hasher);
+        hashes.push((inst.address(), hasher.finish()));
+
+        cut = matches!(kind, InstKind::Jump | InstKind::Return);
+    }
+
+    let mut whole = DefaultHasher::new();
+    let mut blocks: Vec<(u64, u32)> = Vec::new();
+    let mut block: Option<(DefaultHasher, u32)> = None;
+
+    for (addr, hash) in &hashes {
+        if leaders.contains(addr)
+            && let Some((hasher, count)) = block.take()
+        {
+            blocks.push((hasher.finish(), count));
+        }
+
+        let (hasher, count) = block.get_or_insert_with(|| (DefaultHasher::new(), 0));
+        hash.hash(hasher);
+        hash.hash(&mut whole);
+        *count += 1;
+    }
+
+    blocks.extend(block.map(|(hasher, count)| (hasher.finish(), count)));
+    blocks.sort_unstable();
+    blocks.dedup_by(|(hash, count), (prev_hash, prev_count)| {
+        let same = hash == prev_hash;
+        if same {
+            *prev_count += *count;
+        }
+        same
+    });
+
+    Ok(Fingerprint {
+        start,
+        size: data.len() as u64,
+        symbol,
+        whole: whole.finish(),
+        blocks,
+        insts: hashes.len() as u32
+    })
+}
+
+/// Hash operands with each number, such as an address, displacement or immediate, as `N`
+fn hash_operands(op_str: &str, hasher: &mut DefaultHasher) {
+    let mut in_number = false;
+    let mut in_word = false;
+
+    for c in op_str.chars() {
+        let alnum = c.is_ascii_alphanumeric();
+
+        if alnum && !in_word {
+            in_number = c.is_ascii_digit();
+            in_word = true;
+
+            if in_number {
+                'N'.hash(hasher);
+            }
+        } else if !alnum {
+            in_word = false;
+            in_number = false;
+        }
+
+        if !in_number {
+            c.hash(hasher);
+        }
+    }
+}
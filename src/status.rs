@@ -0,0 +1,134 @@
+use std::io::Write;
+use std::time::Duration;
+use indexmap::IndexMap;
+use object::Object;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, MaybePrinter };
+use crate::sessions::Uptime;
+use crate::debuginfo;
+
+
+/// show the state of the session: binaries, built caches and served requests
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {}
+
+/// State of the session serving the command
+pub struct Session<'a> {
+    pub uptime: Duration,
+    /// requests accepted since start, including this one
+    pub served: u64,
+    pub active: usize,
+    pub generation: u64,
+    /// `--max-cache-memory` of the session
+    pub max_cache_memory: Option<u64>,
+    pub explorers: &'a IndexMap<String, Explorer>
+}
+
+impl Command {
+    pub async fn exec(self, session: &Session<'_>, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            uptime: u64,
+            served: u64,
+            generation: u64,
+            max_cache_memory: Option<u64>,
+            name: &'a str,
+            path: String,
+            format: String,
+            arch: String,
+            build_id: Option<&'a str>,
+            debug_file: Option<String>,
+            cache: &'a str,
+            built: bool,
+            bytes: Option<usize>
+        }
+
+        if stdio.format.is_text() {
+            writeln!(
+                stdio.stdout,
+                "pid {}\tuptime {}\tgeneration {}\t{} served\t{} active\tcache limit {}",
+                std::process::id(),
+                Uptime(session.uptime),
+                session.generation,
+                session.served,
+                session.active,
+                MaybePrinter(session.max_cache_memory, Some('-'))
+            )?;
+        }
+
+        let mut total = 0;
+        let mut memory = 0;
+
+        for (name, explorer) in session.explorers {
+            let build_id = debuginfo::build_id(&explorer.obj);
+            let debug_file = explorer.debug_file.as_ref()
+                .map(|file| file.path.display().to_string());
+            let format = format!("{:?}", explorer.obj.format()).to_lowercase();
+            let arch = format!("{:?}", explorer.obj.architecture()).to_lowercase();
+            let usage = explorer.cache.usage();
+            memory += explorer.cache.memory();
+
+            if !stdio.format.is_text() {
+                for usage in &usage {
+                    stdio.record(&Record {
+                        uptime: session.uptime.as_secs(),
+                        served: session.served,
+                        generation: session.generation,
+                        max_cache_memory: session.max_cache_memory,
+                        name,
+                        path: explorer.path.display().to_string(),
+                        format: format.clone(),
+                        arch: arch.clone(),
+                        build_id: build_id.as_deref(),
+                        debug_file: debug_file.clone(),
+                        cache: usage.name,
+                        built: usage.built,
+                        bytes: usage.bytes
+                    })?;
+                }
+                continue
+            }
+
+            writeln!(
+                stdio.stdout,
+                "{}\t{}\t{}/{}\t{}\t{}",
+                name,
+                explorer.path.display(),
+                format, arch,
+                MaybePrinter(build_id.as_deref(), Some('?')),
+                MaybePrinter(debug_file.as_deref(), Some('-'))
+            )?;
+
+            for usage in &usage {
+                let bytes = usage.bytes.filter(|_| usage.built);
+                total += bytes.unwrap_or_default();
+
+                writeln!(
+                    stdio.stdout,
+                    "\t{}\t{}\t{}",
+                    usage.name,
+                    if usage.built { "built" } else { "-" },
+                    MaybePrinter(bytes, Some(if usage.built { '?' } else { '-' }))
+                )?;
+            }
+        }
+
+        if stdio.format.is_text() {
+            writeln!(
+                stdio.stdout,
+                "total: {} binaries, {} bytes of known caches and mappings, {} bytes of caches",
+                session.explorers.len(),
+                total, memory
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+use std::fmt::Write;
+use std::borrow::Cow;
+
+
+/// Output template of `--format-str`, such as `{addr:#x}\t{size}\t{name}`
+///
+/// Fields take a spec like rust format strings: `[<>^][#][0][width][xXob]`.
+/// `{{`, `}}`, `\t`, `\n` and `\\` are escapes.
+#[derive(Debug)]
+pub struct Template {
+    parts: Vec<Part>
+}
+
+#[derive(Debug)]
+enum Part {
+    Literal(String),
+    Field {
+        name: String,
+        spec: Spec
+    }
+}
+
+#[derive(Debug, Default)]
+struct Spec {
+    align: Option<char>,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    radix: Option<char>
+}
+
+/// Field value passed to `Template::render`
+pub enum Value<'a> {
+    Int(u64),
+    Str(Cow<'a, str>),
+    None
+}
+
+impl Template {
+    pub fn parse(template: &str) -> anyhow::Result<Template> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.next_if_eq(&'{').is_some() => literal.push('{'),
+                '}' if chars.next_if_eq(&'}').is_some() => literal.push('}'),
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('\\') => literal.push('\\'),
+                    Some(c) => {
+                        literal.push('\\');
+                        literal.push(c);
+                    },
+                    None => literal.push('\\')
+                },
+                '{' => {
+                    let mut field = String::new();
+
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => anyhow::bail!("unclosed `{{` in format string")
+                        }
+                    }
+
+                    let (name, spec) = field.split_once(':').unwrap_or((&field, ""));
+
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    parts.push(Part::Field {
+                        name: name.trim().to_owned(),
+                        spec: Spec::parse(spec)?
+                    });
+                },
+                '}' => anyhow::bail!("unmatched `}}` in format string"),
+                c => literal.push(c)
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Template { parts })
+    }
+
+    /// Whether the field `name` is rendered, for values only computed on demand
+    pub fn uses(&self, name: &str) -> bool {
+        self.parts.iter().any(|part| matches!(part, Part::Field { name: field, .. } if field == name))
+    }
+
+    /// Render one line, looking up fields by name
+    pub fn render<'a>(&self, out: &mut String, field: impl Fn(&str) -> Option<Value<'a>>)
+        -> anyhow::Result<()>
+    {
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Field { name, spec } => {
+                    let value = field(name)
+                        .ok_or_else(|| anyhow::format_err!("unknown field `{}` in format string", name))?;
+                    spec.write(out, value)?;
+                }
+            }
+        }
+
+        out.push('\n');
+
+        Ok(())
+    }
+}
+
+impl Spec {
+    fn parse(spec: &str) -> anyhow::Result<Spec> {
+        let mut out = Spec::default();
+        let mut spec = spec;
+
+        if let Some(c) = spec.chars().next()
+            && matches!(c, '<' | '>' | '^')
+        {
+            out.align = Some(c);
+            spec = &spec[1..];
+        }
+
+        if let Some(rest) = spec.strip_prefix('#') {
+            out.alternate = true;
+            spec = rest;
+        }
+
+        if let Some(rest) = spec.strip_prefix('0') {
+            out.zero = true;
+            spec = rest;
+        }
+
+        let digits = spec.bytes().take_while(u8::is_ascii_digit).count();
+
+        if digits > 0 {
+            out.width = spec[..digits].parse()?;
+            spec = &spec[digits..];
+        }
+
+        match spec {
+            "" => (),
+            "x" | "X" | "o" | "b" => out.radix = spec.chars().next(),
+            _ => anyhow::bail!("bad field spec: {:?}", spec)
+        }
+
+        Ok(out)
+    }
+
+    fn write(&self, out: &mut String, value: Value<'_>) -> anyhow::Result<()> {
+        let is_int = matches!(value, Value::Int(_));
+        let text: Cow<'_, str> = match (value, self.radix) {
+            (Value::Int(n), Some('x')) if self.alternate => format!("{:#x}", n).into(),
+            (Value::Int(n), Some('x')) => format!("{:x}", n).into(),
+            (Value::Int(n), Some('X')) if self.alternate => format!("{:#X}", n).into(),
+            (Value::Int(n), Some('X')) => format!("{:X}", n).into(),
+            (Value::Int(n), Some('o')) if self.alternate => format!("{:#o}", n).into(),
+            (Value::Int(n), Some('o')) => format!("{:o}", n).into(),
+            (Value::Int(n), Some('b')) if self.alternate => format!("{:#b}", n).into(),
+            (Value::Int(n), Some('b')) => format!("{:b}", n).into(),
+            (Value::Int(n), _) => n.to_string().into(),
+            (Value::Str(s), _) => s,
+            (Value::None, _) => "?".into()
+        };
+        let pad = self.width.saturating_sub(text.chars().count());
+
+        if self.zero && self.align.is_none() {
+            // zeros go after the radix prefix
+            let (prefix, digits) = match text.get(..2) {
+                Some("0x" | "0X" | "0o" | "0b") if self.alternate => text.split_at(2),
+                _ => ("", &*text)
+            };
+            write!(out, "{}{:0>pad$}{}", prefix, "", digits, pad = pad)?;
+            return Ok(());
+        }
+
+        let (left, right) = match self.align {
+            Some('<') => (0, pad),
+            Some('^') => (pad / 2, pad - pad / 2),
+            Some('>') => (pad, 0),
+            // numbers align right like rust
+            _ if is_int => (pad, 0),
+            _ => (0, pad)
+        };
+        write!(out, "{:left$}{}{:right$}", "", text, "", left = left, right = right)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, field: impl Fn(&str) -> Option<Value<'static>>) -> String {
+        let mut out = String::new();
+        Template::parse(template).unwrap().render(&mut out, field).unwrap();
+        out
+    }
+
+    fn fields(name: &str) -> Option<Value<'static>> {
+        match name {
+            "addr" => Some(Value::Int(0x1a2b)),
+            "size" => Some(Value::Int(42)),
+            "name" => Some(Value::Str("main".into())),
+            "file" => Some(Value::None),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn fields_and_specs() {
+        assert_eq!(render("{addr:#x}\\t{size}\\t{name}", fields), "0x1a2b\t42\tmain\n");
+        assert_eq!(render("{addr:x} {addr:X} {size:o} {size:#b}", fields), "1a2b 1A2B 52 0b101010\n");
+        assert_eq!(render("[{name:6}] [{size:6}]", fields), "[main  ] [    42]\n");
+        assert_eq!(render("[{name:>6}] [{size:<6}] [{name:^7}]", fields), "[  main] [42    ] [ main  ]\n");
+        assert_eq!(render("{addr:#010x} {size:05}", fields), "0x00001a2b 00042\n");
+        assert_eq!(render("{ name }:{file}", fields), "main:?\n");
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(render("{{{name}}}", fields), "{main}\n");
+        assert_eq!(render("a\\\\b\\nc\\q", fields), "a\\b\nc\\q\n");
+        assert_eq!(render("end\\", fields), "end\\\n");
+    }
+
+    #[test]
+    fn uses() {
+        let template = Template::parse("{addr:#x} {{size}} {name}").unwrap();
+
+        assert!(template.uses("addr"));
+        assert!(template.uses("name"));
+        assert!(!template.uses("size"));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Template::parse("{name").is_err());
+        assert!(Template::parse("name}").is_err());
+        assert!(Template::parse("{size:q}").is_err());
+        assert!(Template::parse("{size:#x8}").is_err());
+
+        let template = Template::parse("{nope}").unwrap();
+        assert!(template.render(&mut String::new(), fields).is_err());
+    }
+}
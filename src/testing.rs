@@ -0,0 +1,73 @@
+//! Fixtures shared by the unit tests
+
+use object::elf;
+use object::write::elf::{ Writer, FileHeader, SectionHeader };
+
+
+/// `SHT_PROGBITS` section of `write_elf`
+pub struct Section<'a> {
+    pub name: &'a [u8],
+    pub flags: u32,
+    /// address if allocated, else zero
+    pub addr: u64,
+    pub data: &'a [u8],
+    pub align: u64,
+    pub entsize: u64
+}
+
+/// Little endian x86-64 ELF of `e_type` with `sections` and no symbols
+pub fn write_elf(e_type: u16, sections: &[Section<'_>]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let mut writer = Writer::new(object::Endianness::Little, true, &mut buf);
+    writer.reserve_file_header();
+    writer.reserve_null_section_index();
+    let names = sections.iter()
+        .map(|section| {
+            let name = writer.add_section_name(section.name);
+            writer.reserve_section_index();
+            name
+        })
+        .collect::<Vec<_>>();
+    writer.reserve_shstrtab_section_index();
+    let offsets = sections.iter()
+        .map(|section| writer.reserve(section.data.len(), section.align as usize))
+        .collect::<Vec<_>>();
+    writer.reserve_shstrtab();
+    writer.reserve_section_headers();
+
+    writer.write_file_header(&FileHeader {
+        os_abi: elf::ELFOSABI_NONE,
+        abi_version: 0,
+        e_type,
+        e_machine: elf::EM_X86_64,
+        e_entry: 0,
+        e_flags: 0
+    }).unwrap();
+
+    for section in sections {
+        writer.write_align(section.align as usize);
+        writer.write(section.data);
+    }
+
+    writer.write_shstrtab();
+    writer.write_null_section_header();
+
+    for ((section, name), offset) in sections.iter().zip(names).zip(offsets) {
+        writer.write_section_header(&SectionHeader {
+            name: Some(name),
+            sh_type: elf::SHT_PROGBITS,
+            sh_flags: section.flags.into(),
+            sh_addr: section.addr,
+            sh_offset: offset as u64,
+            sh_size: section.data.len() as u64,
+            sh_link: 0,
+            sh_info: 0,
+            sh_addralign: section.align,
+            sh_entsize: section.entsize
+        });
+    }
+
+    writer.write_shstrtab_section_header();
+
+    buf
+}
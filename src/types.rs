@@ -0,0 +1,473 @@
+use std::io::Write;
+use std::collections::HashSet;
+use addr2line::gimli;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, YieldPoint, MaybePrinter };
+use crate::dwarf::{ self, Dwarf, Unit };
+
+
+/// print struct, union and enum layout from DWARF
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// type name, full path or last component
+    #[arg(required_unless_present = "holes_only")]
+    pub name: Option<String>,
+
+    /// list all types with padding holes, most wasted bytes first
+    #[arg(long)]
+    pub holes_only: bool,
+
+    /// only print the first N types
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+pub struct Layout {
+    pub kind: &'static str,
+    pub name: String,
+    pub size: u64,
+    pub location: Option<(String, Option<u64>)>,
+    /// sorted by offset
+    pub fields: Vec<Field>,
+    /// (value, name) of enumerators
+    pub enumerators: Vec<(i64, String)>,
+}
+
+pub struct Field {
+    pub name: String,
+    pub type_name: String,
+    /// offset and size in bits
+    pub bit_offset: u64,
+    pub bit_size: Option<u64>,
+    /// variant of a rust enum this field belongs to
+    pub variant: Option<String>,
+}
+
+/// Gap before the field at `index`, or after the last field
+pub enum Gap {
+    Hole(u64, u64),
+    Padding(u64, u64),
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    #[serde(rename = "type")]
+    type_name: &'a str,
+    kind: &'a str,
+    offset: Option<u64>,
+    size: Option<u64>,
+    bit_offset: Option<u64>,
+    bit_size: Option<u64>,
+    name: Option<&'a str>,
+    field_type: Option<&'a str>,
+    value: Option<i64>,
+}
+
+impl Command {
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        let dwarf = explorer.cache.dwarf(explorer.dwarf_obj(), explorer.dwarf_path.as_deref()).await?;
+        let mut layouts = Vec::new();
+
+        walk(dwarf, |layout| {
+            if self.holes_only {
+                let (holes, hole_bytes, _) = layout.waste();
+
+                if holes == 0 || hole_bytes == 0 {
+                    return false
+                }
+            }
+
+            if let Some(name) = self.name.as_deref() {
+                let last = layout.name.rsplit("::").next().unwrap_or(&layout.name);
+
+                if layout.name != name && last != name {
+                    return false
+                }
+            }
+
+            layouts.push(layout);
+            !self.holes_only && self.limit.is_some_and(|limit| layouts.len() >= limit)
+        }).await?;
+
+        if self.holes_only {
+            layouts.sort_by(|a, b| b.waste().1.cmp(&a.waste().1)
+                .then_with(|| a.name.cmp(&b.name))
+            );
+            layouts.truncate(self.limit.unwrap_or(usize::MAX));
+
+            return print_holes(&layouts, stdio);
+        }
+
+        if layouts.is_empty() {
+            anyhow::bail!("not found type: {}", MaybePrinter(self.name.as_deref(), None));
+        }
+
+        for layout in &layouts {
+            print_layout(layout, stdio)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Visit the layout of every defined struct, union and enum, once per name and size,
+/// stop when `f` returns true
+pub async fn walk<F>(dwarf: &Dwarf, mut f: F) -> anyhow::Result<()>
+where
+    F: FnMut(Layout) -> bool
+{
+    let mut point = YieldPoint::default();
+    let mut units = dwarf.units();
+    let mut seen = HashSet::new();
+
+    while let Some(header) = units.next()? {
+        point.yield_now().await;
+
+        let unit = dwarf.unit(header)?;
+        let mut entries = unit.entries();
+        let mut depth = 0;
+        let mut scope = dwarf::Scope::default();
+
+        while let Some((delta, entry)) = entries.next_dfs()? {
+            depth += delta;
+
+            let tag = entry.tag();
+            let name = dwarf::attr_string(dwarf, &unit, entry, gimli::DW_AT_name);
+            scope.enter(depth, tag, name.clone());
+
+            let kind = match tag {
+                gimli::DW_TAG_structure_type => "struct",
+                gimli::DW_TAG_class_type => "class",
+                gimli::DW_TAG_union_type => "union",
+                gimli::DW_TAG_enumeration_type => "enum",
+                _ => continue
+            };
+
+            let Some(name) = name
+                else { continue };
+            let Some(size) = entry.attr_value(gimli::DW_AT_byte_size)?
+                .and_then(|value| value.udata_value())
+                else { continue };
+
+            if dwarf::attr_flag(entry, gimli::DW_AT_declaration) {
+                continue
+            }
+
+            let name = scope.qualify(&name);
+            let location = dwarf::decl_location(dwarf, &unit, entry);
+
+            if !seen.insert((name.clone(), size, location.clone())) {
+                continue
+            }
+
+            let mut layout = Layout {
+                kind, name, size, location,
+                fields: Vec::new(),
+                enumerators: Vec::new()
+            };
+
+            let mut tree = unit.entries_tree(Some(entry.offset()))?;
+            collect(dwarf, &unit, tree.root()?, None, &mut layout)?;
+            layout.fields.sort_by_key(|field| field.bit_offset);
+
+            // rust enums are structs with a variant part
+            if layout.fields.iter().any(|field| field.variant.is_some()) {
+                layout.kind = "enum";
+            }
+
+            if f(layout) {
+                return Ok(())
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn collect(
+    dwarf: &Dwarf,
+    unit: &Unit,
+    node: gimli::EntriesTreeNode<'_, '_, '_, dwarf::Reader>,
+    variant: Option<&str>,
+    layout: &mut Layout
+) -> anyhow::Result<()> {
+    let mut children = node.children();
+
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+
+        match entry.tag() {
+            gimli::DW_TAG_member => {
+                // static members have no offset
+                if dwarf::attr_flag(entry, gimli::DW_AT_external)
+                    || dwarf::attr_flag(entry, gimli::DW_AT_declaration)
+                {
+                    continue
+                }
+
+                let type_offset = dwarf::type_offset(entry);
+                let bit_size = entry.attr_value(gimli::DW_AT_bit_size)?
+                    .and_then(|value| value.udata_value());
+                let bit_offset = match entry.attr_value(gimli::DW_AT_data_bit_offset)?
+                    .and_then(|value| value.udata_value())
+                {
+                    Some(bit_offset) => bit_offset,
+                    None => dwarf::member_offset(unit, entry).unwrap_or(0) * 8
+                };
+                let bit_size = bit_size.or_else(|| type_offset
+                    .and_then(|offset| dwarf::type_size(unit, offset))
+                    .map(|size| size * 8)
+                );
+
+                layout.fields.push(Field {
+                    name: match dwarf::attr_string(dwarf, unit, entry, gimli::DW_AT_name) {
+                        Some(name) => name.into_owned(),
+                        None if dwarf::attr_flag(entry, gimli::DW_AT_artificial) => "<discriminant>".into(),
+                        None => "?".into()
+                    },
+                    type_name: type_offset
+                        .map(|offset| dwarf::type_name(dwarf, unit, offset))
+                        .unwrap_or_else(|| "?".into()),
+                    bit_offset, bit_size,
+                    variant: variant.map(str::to_owned)
+                });
+            },
+            gimli::DW_TAG_variant_part => collect(dwarf, unit, child, None, layout)?,
+            gimli::DW_TAG_variant => {
+                let discr = entry.attr_value(gimli::DW_AT_discr_value)?
+                    .and_then(|value| value.udata_value());
+                let discr = match discr {
+                    Some(discr) => discr.to_string(),
+                    None => "_".into()
+                };
+                collect(dwarf, unit, child, Some(&discr), layout)?;
+            },
+            gimli::DW_TAG_enumerator => {
+                let Some(name) = dwarf::attr_string(dwarf, unit, entry, gimli::DW_AT_name)
+                    else { continue };
+                let Some(value) = entry.attr_value(gimli::DW_AT_const_value)?
+                    .and_then(|value| value.sdata_value().or_else(|| value.udata_value().map(|n| n as i64)))
+                    else { continue };
+
+                layout.enumerators.push((value, name.into_owned()));
+            },
+            _ => ()
+        }
+    }
+
+    Ok(())
+}
+
+impl Layout {
+    /// Holes between fields and tail padding, only for structs
+    pub fn gaps(&self) -> Vec<(usize, Gap)> {
+        let mut gaps = Vec::new();
+
+        if !matches!(self.kind, "struct" | "class") {
+            return gaps;
+        }
+
+        let mut end = 0;
+
+        for (i, field) in self.fields.iter().enumerate() {
+            let start = field.bit_offset / 8;
+
+            if start > end {
+                gaps.push((i, Gap::Hole(end, start - end)));
+            }
+
+            end = end.max((field.bit_offset + field.bit_size.unwrap_or(0)).div_ceil(8));
+        }
+
+        if self.size > end && !self.fields.is_empty() {
+            gaps.push((self.fields.len(), Gap::Padding(end, self.size - end)));
+        }
+
+        gaps
+    }
+
+    /// (holes, hole bytes, padding bytes)
+    pub fn waste(&self) -> (usize, u64, u64) {
+        let mut waste = (0, 0, 0);
+
+        for (_, gap) in self.gaps() {
+            match gap {
+                Gap::Hole(_, size) => {
+                    waste.0 += 1;
+                    waste.1 += size;
+                },
+                Gap::Padding(_, size) => waste.2 += size
+            }
+        }
+
+        waste
+    }
+}
+
+fn print_layout(layout: &Layout, stdio: &mut Stdio) -> anyhow::Result<()> {
+    let gaps = layout.gaps();
+    let mut gaps = gaps.iter().peekable();
+
+    if stdio.format.is_text() {
+        let location = layout.location.as_ref()
+            .map(|(file, line)| format!("{}:{}", file, MaybePrinter(*line, Some('?'))));
+        writeln!(
+            stdio.stdout,
+            "{} {}\t{} bytes\t{}",
+            layout.kind,
+            layout.name,
+            layout.size,
+            MaybePrinter(location, Some('?'))
+        )?;
+    }
+
+    for i in 0..=layout.fields.len() {
+        while let Some((_, gap)) = gaps.next_if(|(idx, _)| *idx == i) {
+            let (kind, offset, size) = match gap {
+                Gap::Hole(offset, size) => ("hole", offset, size),
+                Gap::Padding(offset, size) => ("padding", offset, size)
+            };
+
+            if !stdio.format.is_text() {
+                stdio.record(&Record {
+                    type_name: &layout.name,
+                    kind,
+                    offset: Some(*offset),
+                    size: Some(*size),
+                    bit_offset: None,
+                    bit_size: None,
+                    name: None,
+                    field_type: None,
+                    value: None
+                })?;
+                continue
+            }
+
+            writeln!(stdio.stdout, "  {:#06x}\t{}\t/* {} */", offset, size, kind)?;
+        }
+
+        let Some(field) = layout.fields.get(i)
+            else { break };
+
+        if !stdio.format.is_text() {
+            stdio.record(&Record {
+                type_name: &layout.name,
+                kind: "field",
+                offset: Some(field.bit_offset / 8),
+                size: field.bit_size.map(|size| size.div_ceil(8)),
+                bit_offset: Some(field.bit_offset),
+                bit_size: field.bit_size,
+                name: Some(&field.name),
+                field_type: Some(&field.type_name),
+                value: None
+            })?;
+            continue
+        }
+
+        let variant = field.variant.as_deref()
+            .map(|discr| format!("[{}] ", discr))
+            .unwrap_or_default();
+
+        if field.bit_offset % 8 != 0 || field.bit_size.is_some_and(|size| size % 8 != 0) {
+            writeln!(
+                stdio.stdout,
+                "  {:#06x}:{}\t{}b\t{}{}\t{}",
+                field.bit_offset / 8,
+                field.bit_offset % 8,
+                MaybePrinter(field.bit_size, Some('?')),
+                variant,
+                field.name,
+                field.type_name
+            )?;
+        } else {
+            writeln!(
+                stdio.stdout,
+                "  {:#06x}\t{}\t{}{}\t{}",
+                field.bit_offset / 8,
+                MaybePrinter(field.bit_size.map(|size| size / 8), Some('?')),
+                variant,
+                field.name,
+                field.type_name
+            )?;
+        }
+    }
+
+    for (value, name) in &layout.enumerators {
+        if !stdio.format.is_text() {
+            stdio.record(&Record {
+                type_name: &layout.name,
+                kind: "enumerator",
+                offset: None,
+                size: None,
+                bit_offset: None,
+                bit_size: None,
+                name: Some(name),
+                field_type: None,
+                value: Some(*value)
+            })?;
+            continue
+        }
+
+        writeln!(stdio.stdout, "  {}\t{}", value, name)?;
+    }
+
+    if stdio.format.is_text() {
+        let (holes, hole_bytes, padding) = layout.waste();
+
+        if !layout.fields.is_empty() {
+            writeln!(
+                stdio.stdout,
+                "  total: {} fields, {} holes, {} hole bytes, {} padding bytes",
+                layout.fields.len(), holes, hole_bytes, padding
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_holes(layouts: &[Layout], stdio: &mut Stdio) -> anyhow::Result<()> {
+    #[derive(Serialize)]
+    struct Record<'a> {
+        #[serde(rename = "type")]
+        type_name: &'a str,
+        kind: &'a str,
+        size: u64,
+        holes: usize,
+        hole_bytes: u64,
+        padding: u64
+    }
+
+    for layout in layouts {
+        let (holes, hole_bytes, padding) = layout.waste();
+
+        if !stdio.format.is_text() {
+            stdio.record(&Record {
+                type_name: &layout.name,
+                kind: layout.kind,
+                size: layout.size,
+                holes, hole_bytes, padding
+            })?;
+            continue
+        }
+
+        writeln!(
+            stdio.stdout,
+            "{}\t{}\t{}\t{} {}",
+            hole_bytes,
+            holes,
+            layout.size,
+            layout.kind,
+            layout.name
+        )?;
+    }
+
+    Ok(())
+}
@@ -4,7 +4,14 @@ use std::path::Path;
 use std::os::fd::RawFd;
 use std::hash::{ Hash, Hasher };
 use std::collections::hash_map::DefaultHasher;
+use std::sync::{ Arc, Mutex };
+use std::sync::atomic::{ AtomicBool, AtomicU64, AtomicUsize, Ordering };
+use std::time::{ Duration, Instant };
 use tokio::net::UnixStream;
+use serde::{ Serialize, Deserialize };
+use crate::template::Template;
+use crate::sarif;
+use crate::row::Row;
 
 
 pub fn hashpath(path: &Path) -> String {
@@ -26,13 +33,132 @@ pub fn hashname(path: &Path) -> String {
     data_encoding::HEXLOWER.encode(&out.to_le_bytes())
 }
 
+/// Output format of `search` and `show`
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Format {
+    /// aligned columns for humans
+    #[default]
+    Text,
+    /// one JSON object per line
+    Json,
+    /// comma separated, with a header row
+    Csv,
+    /// CBOR records, each prefixed by its length as u32 little endian
+    Cbor,
+    /// SARIF log of all results, for code scanning
+    Sarif,
+}
+
+impl Format {
+    pub fn is_text(self) -> bool {
+        self == Format::Text
+    }
+}
+
 pub struct Stdio {
     pub colored: bool,
     pub hyperlink: bool,
+    pub format: Format,
+    /// records written so far
+    pub records: usize,
+    /// columns of `--format csv`, those of the first record
+    pub header: Vec<&'static str>,
+    /// `--format-str` of symbol rows
+    pub template: Option<Template>,
+    /// results collected for `--format sarif`
+    pub sarif: sarif::Log,
     #[allow(dead_code)]
     pub stdin: File,
-    pub stdout: File,
-    pub stderr: File
+    pub stdout: CountWriter,
+    pub stderr: File,
+    /// set once the request is cancelled
+    pub cancel: Cancel
+}
+
+/// Output file counting the bytes written into it
+pub struct CountWriter {
+    file: File,
+    pub count: u64
+}
+
+impl CountWriter {
+    pub fn new(file: File) -> CountWriter {
+        CountWriter { file, count: 0 }
+    }
+}
+
+impl io::Write for CountWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.file.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Stdio {
+    /// Write one record in the structured `--format`
+    pub fn record<T: Serialize>(&mut self, record: &T) -> anyhow::Result<()> {
+        use std::io::Write;
+
+        let buf = match self.format {
+            Format::Json => {
+                let mut buf = serde_json::to_vec(record)?;
+                buf.push(b'\n');
+                buf
+            },
+            Format::Csv => {
+                let row = Row::of(record)?;
+                let mut writer = csv::Writer::from_writer(Vec::new());
+
+                if self.records == 0 {
+                    writer.write_record(&row.header)?;
+                    self.header = row.header;
+                } else {
+                    anyhow::ensure!(
+                        row.header == self.header,
+                        "--format csv: records have different columns, use --format json"
+                    );
+                }
+
+                writer.write_record(&row.fields)?;
+                writer.into_inner()?
+            },
+            Format::Cbor => {
+                // reserve the length prefix
+                let mut buf = cbor4ii::serde::to_vec(vec![0; 4], record)?;
+                let len: u32 = (buf.len() - 4).try_into()?;
+                buf[..4].copy_from_slice(&len.to_le_bytes());
+                buf
+            },
+            Format::Sarif => {
+                self.records += 1;
+                return self.sarif.push(record);
+            },
+            Format::Text => anyhow::bail!("text format has no records")
+        };
+        self.stdout.write_all(&buf)?;
+        self.records += 1;
+
+        Ok(())
+    }
+
+    /// Write output collected over the whole command
+    pub fn finish(&mut self, artifact: &Path, rule: &str) -> anyhow::Result<()> {
+        use std::io::Write;
+
+        if self.format == Format::Sarif {
+            let log = std::mem::take(&mut self.sarif).finish(artifact, rule);
+            serde_json::to_writer_pretty(&mut self.stdout, &log)?;
+            writeln!(self.stdout)?;
+        }
+
+        Ok(())
+    }
 }
 
 pub async fn recv_fd(stream: &UnixStream) -> io::Result<RawFd> {
@@ -50,6 +176,82 @@ pub async fn recv_fd(stream: &UnixStream) -> io::Result<RawFd> {
     }
 }
 
+/// Credentials of the process on the other side of a unix socket
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_cred<T: std::os::fd::AsRawFd>(stream: &T) -> io::Result<libc::ucred> {
+    let mut cred: libc::ucred = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&raw mut cred).cast(),
+            &mut len
+        )
+    };
+
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(cred)
+}
+
+/// Uid of the process on the other side of a unix socket
+pub fn peer_uid<T: std::os::fd::AsRawFd>(stream: &T) -> io::Result<u32> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        peer_cred(stream).map(|cred| cred.uid)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let mut uid = 0;
+        let mut gid = 0;
+
+        if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(uid)
+    }
+}
+
+/// Pid of the process on the other side of a unix socket, where the platform tells it
+pub fn peer_pid<T: std::os::fd::AsRawFd>(stream: &T) -> Option<u32> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        peer_cred(stream).ok().map(|cred| cred.pid as u32)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = stream;
+        None
+    }
+}
+
+/// Parse a byte size with optional `K`/`M`/`G` (or `KiB`...) suffix
+pub fn parse_size(value: &str) -> anyhow::Result<u64> {
+    use anyhow::Context;
+
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (num, unit) = value.split_at(split);
+    let num: u64 = num.parse().context("size parse failed")?;
+
+    let shift = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 0,
+        "k" | "kb" | "kib" => 10,
+        "m" | "mb" | "mib" => 20,
+        "g" | "gb" | "gib" => 30,
+        unit => anyhow::bail!("unknown size unit: {:?}", unit)
+    };
+
+    num.checked_mul(1 << shift).context("size is too large")
+}
+
 pub fn u64ptr(value: &str) -> anyhow::Result<u64> {
     use anyhow::Context;
 
@@ -85,6 +287,24 @@ pub fn is_data_section(kind: object::read::SectionKind) -> bool {
     )    
 }
 
+/// Pointer stored in a 4 or 8 byte `word`
+pub fn read_pointer(word: &[u8], endian: object::Endianness) -> u64 {
+    let mut buf = [0; 8];
+
+    match (word.len(), endian) {
+        (8, object::Endianness::Little) => u64::from_le_bytes(word.try_into().unwrap()),
+        (8, object::Endianness::Big) => u64::from_be_bytes(word.try_into().unwrap()),
+        (_, object::Endianness::Little) => {
+            buf[..4].copy_from_slice(word);
+            u64::from_le_bytes(buf)
+        },
+        (_, object::Endianness::Big) => {
+            buf[4..].copy_from_slice(word);
+            u64::from_be_bytes(buf)
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct YieldPoint(u8);
 
@@ -99,6 +319,172 @@ impl YieldPoint {
     }
 }
 
+/// Flag of a cancelled request, set from outside the command
+///
+/// Commands are dropped at their next await point, loops that run
+/// without awaiting, such as the parallel disassembly, check it instead.
+#[derive(Clone, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn check(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(!self.is_cancelled(), "cancelled");
+        Ok(())
+    }
+}
+
+/// Buffers lent out as `'static` and freed with the arena
+///
+/// Holds the mappings and decompressed sections of an explorer,
+/// so that reopening a binary does not leak the previous one.
+/// Owners must drop everything borrowed from it first.
+#[derive(Default)]
+pub struct Arena {
+    bufs: Mutex<Vec<Box<dyn AsRef<[u8]> + Send + Sync>>>,
+    /// bytes of the buffers kept by `keep_heap`
+    heap: AtomicUsize
+}
+
+impl Arena {
+    pub fn keep<T>(&self, buf: T) -> &'static [u8]
+    where
+        T: AsRef<[u8]> + Send + Sync + 'static
+    {
+        let buf = Box::new(buf);
+        let data = (*buf).as_ref();
+        // SAFETY: heap buffers and mappings do not move with the box,
+        // they live until the arena is dropped.
+        let data: &'static [u8] = unsafe {
+            std::slice::from_raw_parts(data.as_ptr(), data.len())
+        };
+        self.bufs.lock().unwrap().push(buf);
+        data
+    }
+
+    /// Keep an allocated buffer, such as a decompressed section, counted by `heap_size`
+    pub fn keep_heap(&self, buf: Vec<u8>) -> &'static [u8] {
+        self.heap.fetch_add(buf.len(), Ordering::Relaxed);
+        self.keep(buf)
+    }
+
+    /// Bytes of all kept buffers, mapped or allocated
+    pub fn size(&self) -> usize {
+        self.bufs.lock().unwrap()
+            .iter()
+            .map(|buf| (**buf).as_ref().len())
+            .sum()
+    }
+
+    /// Bytes of the allocated buffers, the mappings left out
+    pub fn heap_size(&self) -> usize {
+        self.heap.load(Ordering::Relaxed)
+    }
+}
+
+/// Throttled `scanned/total` line on the client's stderr
+///
+/// Only shown on a terminal, cleared on drop.
+pub struct Progress<'a> {
+    stderr: &'a File,
+    total: usize,
+    done: AtomicUsize,
+    start: Instant,
+    /// milliseconds since start of last print
+    last: AtomicU64,
+    shown: AtomicBool
+}
+
+impl Progress<'_> {
+    const INTERVAL: u64 = 200;
+
+    pub fn new(stderr: &File, total: usize) -> Option<Progress<'_>> {
+        use std::io::IsTerminal;
+
+        stderr.is_terminal().then(|| Progress {
+            stderr, total,
+            done: AtomicUsize::new(0),
+            start: Instant::now(),
+            last: AtomicU64::new(0),
+            shown: AtomicBool::new(false)
+        })
+    }
+
+    pub fn inc(&self) {
+        use std::io::Write;
+
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        let elapsed = self.start.elapsed();
+        let now = elapsed.as_millis() as u64;
+        let last = self.last.load(Ordering::Relaxed);
+
+        if now >= last + Self::INTERVAL
+            && self.last.compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed).is_ok()
+        {
+            let mut stderr = self.stderr;
+            self.shown.store(true, Ordering::Relaxed);
+            let _ = write!(
+                stderr,
+                "\r\x1b[K{}/{} symbols, {:.1}s",
+                done, self.total,
+                elapsed.as_secs_f64()
+            );
+        }
+    }
+}
+
+impl Drop for Progress<'_> {
+    fn drop(&mut self) {
+        use std::io::Write;
+
+        if *self.shown.get_mut() {
+            let _ = self.stderr.write_all(b"\r\x1b[K");
+        }
+    }
+}
+
+/// Wait for `fut`, such as a cache built by a background task, noting on the
+/// client's stderr what it waits for if that takes a while
+///
+/// Only shown on a terminal, cleared once done.
+pub async fn wait_for<F: Future>(stderr: &File, what: &str, fut: F) -> F::Output {
+    use std::io::{ IsTerminal, Write };
+
+    const INTERVAL: Duration = Duration::from_millis(200);
+
+    if !stderr.is_terminal() {
+        return fut.await;
+    }
+
+    let mut stderr = stderr;
+    let start = Instant::now();
+    let mut shown = false;
+    tokio::pin!(fut);
+
+    let output = loop {
+        tokio::select! {
+            output = &mut fut => break output,
+            _ = tokio::time::sleep(INTERVAL) => {
+                shown = true;
+                let _ = write!(stderr, "\r\x1b[Kwaiting for {}, {:.1}s", what, start.elapsed().as_secs_f64());
+            }
+        }
+    };
+
+    if shown {
+        let _ = stderr.write_all(b"\r\x1b[K");
+    }
+
+    output
+}
+
 pub struct HexPrinter<'a>(pub &'a [u8], pub usize);
 pub struct AsciiPrinter<'a>(pub &'a [u8]);
 pub struct MaybePrinter<T>(pub Option<T>, pub Option<char>);
@@ -204,3 +590,142 @@ impl<T: fmt::Display, L: AsRef<str>> fmt::Display for Hyperlink<T, L> {
         write!(f, "\x1B]8;;\x1B\\")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Record {
+        addr: u64,
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(default)]
+        file: Option<String>
+    }
+
+    #[derive(Serialize)]
+    struct Other {
+        addr: u64
+    }
+
+    fn record(addr: u64, name: &str, file: Option<&str>) -> Record {
+        Record { addr, name: name.into(), file: file.map(Into::into) }
+    }
+
+    /// Run `f` on a Stdio of `format`, returning what it wrote to stdout
+    fn output(format: Format, f: impl FnOnce(&mut Stdio) -> anyhow::Result<()>) -> anyhow::Result<Vec<u8>> {
+        use std::io::Read;
+
+        let (mut reader, writer) = io::pipe()?;
+        let mut stdio = Stdio {
+            colored: false,
+            hyperlink: false,
+            format,
+            records: 0,
+            header: Vec::new(),
+            template: None,
+            sarif: Default::default(),
+            stdin: File::open("/dev/null")?,
+            stdout: CountWriter::new(File::from(std::os::fd::OwnedFd::from(writer))),
+            stderr: File::options().write(true).open("/dev/null")?,
+            cancel: Default::default()
+        };
+
+        let ret = f(&mut stdio);
+        drop(stdio);
+
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        ret.map(|()| buf)
+    }
+
+    #[test]
+    fn size_units() {
+        assert_eq!(parse_size("0").unwrap(), 0);
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("512b").unwrap(), 512);
+        assert_eq!(parse_size("4K").unwrap(), 4 << 10);
+        assert_eq!(parse_size("4kb").unwrap(), 4 << 10);
+        assert_eq!(parse_size("4KiB").unwrap(), 4 << 10);
+        assert_eq!(parse_size("3m").unwrap(), 3 << 20);
+        assert_eq!(parse_size(" 2 GiB ").unwrap(), 2 << 30);
+    }
+
+    #[test]
+    fn size_errors() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("k").is_err());
+        assert!(parse_size("4t").is_err());
+        assert!(parse_size("-4k").is_err());
+        assert!(parse_size("1.5m").is_err());
+        assert!(parse_size("99999999999999999999").is_err());
+        assert!(parse_size("17179869184g").is_err());
+    }
+
+    #[test]
+    fn json_records() {
+        let buf = output(Format::Json, |stdio| {
+            stdio.record(&record(0x10, "main", Some("main.c")))?;
+            stdio.record(&record(0x20, "foo", None))
+        }).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "{\"addr\":16,\"name\":\"main\",\"file\":\"main.c\"}\n\
+             {\"addr\":32,\"name\":\"foo\"}\n"
+        );
+    }
+
+    #[test]
+    fn csv_records() {
+        let buf = output(Format::Csv, |stdio| {
+            stdio.record(&record(0x10, "main", Some("main.c")))?;
+            // a skipped field keeps its column
+            stdio.record(&record(0x20, "a, b", None))?;
+            assert_eq!(stdio.records, 2);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "addr,name,file\n16,main,main.c\n32,\"a, b\",\n"
+        );
+    }
+
+    #[test]
+    fn csv_mixed_records() {
+        let err = output(Format::Csv, |stdio| {
+            stdio.record(&record(0x10, "main", None))?;
+            stdio.record(&Other { addr: 0x20 })
+        }).unwrap_err();
+        assert!(err.to_string().contains("different columns"), "{}", err);
+
+        assert!(output(Format::Csv, |stdio| stdio.record(&0u64)).is_err());
+    }
+
+    #[test]
+    fn cbor_records() {
+        let records = [record(0x10, "main", Some("main.c")), record(0x20, "foo", None)];
+        let buf = output(Format::Cbor, |stdio| {
+            records.iter().try_for_each(|record| stdio.record(record))
+        }).unwrap();
+
+        let mut rest = &buf[..];
+
+        for expected in &records {
+            let (len, tail) = rest.split_first_chunk::<4>().unwrap();
+            let (item, tail) = tail.split_at(u32::from_le_bytes(*len) as usize);
+            let item: Record = cbor4ii::serde::from_slice(item).unwrap();
+            assert_eq!(&item, expected);
+            rest = tail;
+        }
+
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn text_has_no_records() {
+        assert!(output(Format::Text, |stdio| stdio.record(&record(0, "main", None))).is_err());
+    }
+}
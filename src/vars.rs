@@ -0,0 +1,192 @@
+use std::io::Write;
+use std::collections::HashSet;
+use object::{ Object, ObjectSection, SectionKind };
+use addr2line::gimli;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, YieldPoint, MaybePrinter };
+use crate::dwarf::{ self, value::Decoder };
+
+
+/// search global and static variables in DWARF
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// variable name regex
+    pub keyword: String,
+
+    /// decode the initial value with the variable type
+    #[arg(long)]
+    pub value: bool,
+
+    /// only print the first N variables
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+impl Command {
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            address: u64,
+            size: Option<u64>,
+            name: &'a str,
+            #[serde(rename = "type")]
+            type_name: Option<&'a str>,
+            file: Option<&'a str>,
+            line: Option<u64>
+        }
+
+        #[derive(Serialize)]
+        struct ValueRecord<'a> {
+            variable: &'a str,
+            address: u64,
+            path: &'a str,
+            #[serde(rename = "type")]
+            type_name: &'a str,
+            value: &'a str
+        }
+
+        let re = regex::Regex::new(&self.keyword)?;
+        let dwarf = explorer.cache.dwarf(explorer.dwarf_obj(), explorer.dwarf_path.as_deref()).await?;
+        let mut point = YieldPoint::default();
+        let mut units = dwarf.units();
+        let mut seen = HashSet::new();
+        let mut count = 0;
+
+        while let Some(header) = units.next()? {
+            point.yield_now().await;
+
+            let unit = dwarf.unit(header)?;
+            let mut entries = unit.entries();
+            let mut depth = 0;
+            let mut scope = dwarf::Scope::default();
+
+            while let Some((delta, entry)) = entries.next_dfs()? {
+                depth += delta;
+
+                let name = dwarf::attr_string(dwarf, &unit, entry, gimli::DW_AT_name);
+                scope.enter(depth, entry.tag(), name.clone());
+
+                if entry.tag() != gimli::DW_TAG_variable
+                    || dwarf::attr_flag(entry, gimli::DW_AT_declaration)
+                {
+                    continue
+                }
+
+                // locals without static storage have no address
+                let Some(address) = dwarf::location_address(dwarf, &unit, entry)
+                    else { continue };
+
+                // out-of-line definition of a member variable
+                let spec = dwarf::attr_ref(&unit, entry, gimli::DW_AT_specification)
+                    .and_then(|offset| unit.entry(offset).ok());
+                let Some(name) = name.or_else(|| dwarf::attr_string(dwarf, &unit, spec.as_ref()?, gimli::DW_AT_name))
+                    else { continue };
+                let name = scope.qualify(&name);
+
+                if !re.is_match(&name) || !seen.insert((name.clone(), address)) {
+                    continue
+                }
+
+                if self.limit.is_some_and(|limit| count >= limit) {
+                    return Ok(())
+                }
+
+                count += 1;
+
+                let type_offset = dwarf::type_offset(entry)
+                    .or_else(|| dwarf::type_offset(spec.as_ref()?));
+                let type_name = type_offset.map(|offset| dwarf::type_name(dwarf, &unit, offset));
+                let size = type_offset.and_then(|offset| dwarf::type_size(&unit, offset));
+                let location = dwarf::decl_location(dwarf, &unit, entry)
+                    .or_else(|| dwarf::decl_location(dwarf, &unit, spec.as_ref()?));
+
+                if !stdio.format.is_text() && !self.value {
+                    stdio.record(&Record {
+                        address,
+                        size,
+                        name: &name,
+                        type_name: type_name.as_deref(),
+                        file: location.as_ref().map(|(file, _)| file.as_str()),
+                        line: location.as_ref().and_then(|(_, line)| *line)
+                    })?;
+                    continue
+                }
+
+                if stdio.format.is_text() {
+                    let location = location.as_ref()
+                        .map(|(file, line)| format!("{}:{}", file, MaybePrinter(*line, Some('?'))));
+
+                    writeln!(
+                        stdio.stdout,
+                        "{:018p}\t{}\t{}\t{}\t{}",
+                        address as *const (),
+                        MaybePrinter(size, Some('?')),
+                        name,
+                        MaybePrinter(type_name.as_deref(), Some('?')),
+                        MaybePrinter(location, Some('?'))
+                    )?;
+                }
+
+                if !self.value {
+                    continue
+                }
+
+                let (Some(type_offset), Some(size)) = (type_offset, size)
+                    else { continue };
+                let data = read_static(explorer, address, size).await?;
+                let mut decoder = Decoder::new(dwarf, &unit, explorer.obj.is_little_endian());
+                decoder.decode(type_offset, &data, 0, String::new());
+
+                for item in &decoder.items {
+                    if !stdio.format.is_text() {
+                        stdio.record(&ValueRecord {
+                            variable: &name,
+                            address: address + item.offset,
+                            path: &item.path,
+                            type_name: &item.type_name,
+                            value: &item.value
+                        })?;
+                        continue
+                    }
+
+                    writeln!(
+                        stdio.stdout,
+                        "  {:#06x}\t{}\t{}\t= {}",
+                        item.offset,
+                        if item.path.is_empty() { "." } else { &item.path },
+                        item.type_name,
+                        item.value
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Initial bytes at `addr`, zero for `.bss`
+pub(crate) async fn read_static(explorer: &Explorer, addr: u64, size: u64) -> anyhow::Result<Vec<u8>> {
+    let section = explorer.obj.sections()
+        .find(|section| (section.address()..section.address() + section.size()).contains(&addr))
+        .ok_or_else(|| anyhow::format_err!("not found section of {:#x}", addr))?;
+    let size = size.min(section.address() + section.size() - addr) as usize;
+
+    if matches!(section.kind(), SectionKind::UninitializedData | SectionKind::UninitializedTls) {
+        return Ok(vec![0; size]);
+    }
+
+    let data = explorer.cache.data(&explorer.obj, section.index()).await?;
+    let start = (addr - section.address()) as usize;
+
+    Ok(data.get(start..)
+        .map(|data| data[..size.min(data.len())].to_vec())
+        .unwrap_or_default())
+}
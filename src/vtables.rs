@@ -0,0 +1,444 @@
+use std::io::Write;
+use std::borrow::Cow;
+use std::collections::{ HashMap, HashSet };
+use object::{ Object, ObjectSection, ObjectSymbol, SectionKind };
+use owo_colors::OwoColorize;
+
+use clap::Args;
+use serde::{ Serialize, Deserialize };
+
+use crate::explorer::Explorer;
+use crate::util::{ Stdio, YieldPoint, MaybePrinter, IfSupported, read_pointer };
+use crate::search::dedup_name;
+
+
+/// list vtables of rust trait objects and c++ classes with their methods
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(flatten_help = true)]
+pub struct Command {
+    /// filter by type name regex
+    pub filter: Option<String>,
+
+    /// only print the vtables, not their methods
+    #[arg(long)]
+    pub no_methods: bool,
+
+    /// demangle symbol name
+    #[arg(short, long)]
+    pub demangle: bool,
+
+    /// print at most N results
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+/// Largest alignment taken for the align word of a rust vtable
+const MAX_ALIGN: u64 = 1 << 16;
+
+/// Word of a data section, with relocations applied
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Word {
+    Value(u64),
+    /// symbol of another module, such as `__cxa_pure_virtual`, and whether it is a function
+    Import(&'static str, bool)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Kind {
+    Rust,
+    #[serde(rename = "c++")]
+    Cxx
+}
+
+pub(crate) struct Vtable {
+    /// first word, the drop glue or offset to top
+    pub address: u64,
+    /// address stored in the object or fat pointer, slots are loaded at an offset from it
+    pub point: u64,
+    pub kind: Kind,
+    pub type_name: Option<String>,
+    /// size and align of the rust type
+    pub layout: Option<(u64, u64)>,
+    /// method slot address and the word in it
+    pub methods: Vec<(u64, Word)>
+}
+
+impl Vtable {
+    /// Function in the method slot at `slot`
+    pub fn method_at(&self, slot: u64) -> Option<u64> {
+        let idx = self.methods.binary_search_by_key(&slot, |&(slot, _)| slot).ok()?;
+
+        match self.methods[idx].1 {
+            Word::Value(addr) => Some(addr),
+            Word::Import(..) => None
+        }
+    }
+}
+
+impl Command {
+    /// A rust vtable is the drop glue, or null, then the size and align of the type followed
+    /// by the methods, its type is named by the drop glue. A c++ vtable is the offset to top
+    /// and a typeinfo followed by the methods, found by the typeinfo symbol or the vtable symbol,
+    /// so only with symbols. Methods run up to the first word that is not a function pointer.
+    pub async fn exec(self, explorer: &Explorer, stdio: &mut Stdio) -> anyhow::Result<()> {
+        #[derive(Serialize)]
+        struct Method<'a> {
+            slot: u64,
+            address: Option<u64>,
+            name: Option<Cow<'a, str>>
+        }
+
+        #[derive(Serialize)]
+        struct Record<'a> {
+            address: u64,
+            kind: Kind,
+            type_name: Option<&'a str>,
+            size: Option<u64>,
+            align: Option<u64>,
+            methods: Vec<Method<'a>>
+        }
+
+        let filter = self.filter
+            .as_ref()
+            .map(|rule| regex::Regex::new(rule))
+            .transpose()?;
+        let addr2sym = explorer.cache.addr2sym(&explorer.obj).await;
+
+        let vtables = find(explorer).await?;
+
+        let name_of = |word: Word| -> (Option<u64>, Option<Cow<'_, str>>) {
+            let (addr, name) = match word {
+                Word::Value(addr) => match addr2sym.get(addr) {
+                    Some(sym) if sym.address() == addr => (Some(addr), Some(Cow::Borrowed(sym.name()))),
+                    Some(sym) => (Some(addr), Some(format!("{}+{:#x}", sym.name(), addr - sym.address()).into())),
+                    None => (Some(addr), None)
+                },
+                Word::Import(name, _) => (None, Some(Cow::Borrowed(name)))
+            };
+            let name = name.map(|name| if self.demangle {
+                Cow::Owned(explorer.cache.demangle(&name).into_owned())
+            } else {
+                name
+            });
+            (addr, name)
+        };
+
+        let mut count = 0;
+        let mut rust = 0;
+
+        for vtable in &vtables {
+            if let Some(rule) = filter.as_ref()
+                && !vtable.type_name.as_deref().is_some_and(|name| rule.is_match(name))
+            {
+                continue
+            }
+
+            if self.limit.is_some_and(|limit| count >= limit) {
+                break
+            }
+            count += 1;
+
+            if vtable.kind == Kind::Rust {
+                rust += 1;
+            }
+
+            if !stdio.format.is_text() {
+                let methods = if self.no_methods {
+                    Vec::new()
+                } else {
+                    vtable.methods.iter()
+                        .map(|&(slot, word)| {
+                            let (address, name) = name_of(word);
+                            Method { slot, address, name }
+                        })
+                        .collect()
+                };
+
+                stdio.record(&Record {
+                    address: vtable.address,
+                    kind: vtable.kind,
+                    type_name: vtable.type_name.as_deref(),
+                    size: vtable.layout.map(|(size, _)| size),
+                    align: vtable.layout.map(|(_, align)| align),
+                    methods
+                })?;
+                continue
+            }
+
+            writeln!(
+                stdio.stdout,
+                "{:018p} {:4} {:4}\t{}",
+                vtable.address as *const (),
+                vtable.methods.len(),
+                match vtable.kind {
+                    Kind::Rust => "rust",
+                    Kind::Cxx => "c++"
+                },
+                MaybePrinter(vtable.type_name.as_deref(), Some('?'))
+                    .if_supported(stdio.colored, |name| name.bold())
+            )?;
+
+            if self.no_methods {
+                continue
+            }
+
+            for &(slot, word) in &vtable.methods {
+                let (_, name) = name_of(word);
+
+                writeln!(
+                    stdio.stdout,
+                    "  {:018p} {}",
+                    slot as *const (),
+                    MaybePrinter(name.as_deref(), Some('?'))
+                )?;
+            }
+        }
+
+        if stdio.format.is_text() {
+            writeln!(
+                stdio.stdout,
+                "total: {} vtables, {} rust, {} c++",
+                count,
+                rust,
+                count - rust
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Vtables of the data sections, `.data.rel.ro` being writable before relocation, in address order
+pub(crate) async fn find(explorer: &Explorer) -> anyhow::Result<Vec<Vtable>> {
+    use object::RelocationTarget;
+
+    let symlist = explorer.cache.symlist(&explorer.obj).await;
+    let addr2sym = explorer.cache.addr2sym(&explorer.obj).await;
+    let dyn_rela = explorer.cache.dyn_rela(&explorer.obj).await;
+    let ptr_size = if explorer.obj.is_64() { 8 } else { 4 };
+    let endian = explorer.obj.endianness();
+    let mut point = YieldPoint::default();
+
+    let text = explorer.obj.sections()
+        .filter(|section| section.kind() == SectionKind::Text && section.address() != 0)
+        .map(|section| section.address()..section.address() + section.size())
+        .collect::<Vec<_>>();
+    let is_func = |word: Word| match word {
+        Word::Value(addr) => text.iter().any(|range| range.contains(&addr)),
+        Word::Import(_, func) => func
+    };
+
+    // typeinfo and vtable symbols of c++
+    let name_at = |word: Word| match word {
+        Word::Value(addr) => addr2sym.get(addr)
+            .filter(|sym| sym.address() == addr)
+            .map(|sym| sym.name()),
+        Word::Import(name, _) => Some(name)
+    };
+    let cxx_vtables = symlist.iter()
+        .filter_map(|&idx| explorer.sym_obj().symbol_by_index(idx).ok())
+        .filter(|sym| !sym.is_undefined() && sym.name().is_ok_and(|name| name.starts_with("_ZTV")))
+        .map(|sym| (sym.address(), sym.name().unwrap_or_default()))
+        .collect::<HashMap<_, _>>();
+
+    let mut output = Vec::new();
+
+    for section in explorer.obj.sections()
+        .filter(|section| matches!(section.kind(), SectionKind::ReadOnlyData | SectionKind::ReadOnlyDataWithRel | SectionKind::Data))
+        .filter(|section| section.address() != 0)
+    {
+        let Ok(data) = explorer.cache.data(&explorer.obj, section.index()).await
+            else { continue };
+        let base = section.address();
+        let rela_start = dyn_rela.partition_point(|(addr, _)| *addr < base);
+
+        let mut words = data.chunks_exact(ptr_size)
+            .map(|word| Word::Value(read_pointer(word, endian)))
+            .collect::<Vec<_>>();
+
+        for (addr, rela) in dyn_rela[rela_start..].iter()
+            .take_while(|(addr, _)| *addr < base + data.len() as u64)
+        {
+            let offset = addr - base;
+            if offset % ptr_size as u64 != 0 {
+                continue
+            }
+
+            let word = match rela.target() {
+                RelocationTarget::Symbol(idx) => match explorer.obj.symbol_by_index(idx) {
+                    Ok(sym) if sym.is_undefined() => Word::Import(
+                        sym.name().unwrap_or_default(),
+                        sym.kind() == object::SymbolKind::Text
+                    ),
+                    Ok(sym) => Word::Value(sym.address().wrapping_add_signed(rela.addend())),
+                    Err(_) => continue
+                },
+                RelocationTarget::Absolute => Word::Value(rela.addend() as u64),
+                _ => continue
+            };
+            // a trailing partial word is not read
+            if let Some(slot) = words.get_mut((offset / ptr_size as u64) as usize) {
+                *slot = word;
+            }
+        }
+
+        point.yield_now().await;
+
+        let value = |i: usize| match words.get(i) {
+            Some(&Word::Value(value)) => Some(value),
+            _ => None
+        };
+
+        // headers first, the methods of a vtable stop at the header of the next one
+        let mut headers = Vec::new();
+
+        for i in 0..words.len() {
+            let addr = base + (i * ptr_size) as u64;
+            let vtable_symbol = cxx_vtables.get(&addr);
+            let typeinfo = words.get(i + 1)
+                .and_then(|&word| name_at(word))
+                .filter(|name| name.starts_with("_ZTI"));
+
+            if (vtable_symbol.is_some() || typeinfo.is_some())
+                && words.get(i + 2).is_some_and(|&word| is_func(word))
+            {
+                let type_name = typeinfo
+                    .map(|name| (name, "typeinfo for "))
+                    .or(vtable_symbol.map(|name| (*name, "vtable for ")))
+                    .and_then(|(name, prefix)| explorer.cache.demangle(name).strip_prefix(prefix).map(str::to_owned));
+
+                headers.push((i, i + 2, Kind::Cxx, type_name, None));
+                continue
+            }
+
+            let (Some(size), Some(align)) = (value(i + 1), value(i + 2))
+                else { continue };
+            let drop = words[i];
+
+            if !words.get(i + 3).is_some_and(|&word| is_func(word))
+                || !(drop == Word::Value(0) || is_func(drop))
+                || !align.is_power_of_two()
+                || align > MAX_ALIGN
+                || size % align != 0
+                || is_func(Word::Value(size))
+            {
+                continue
+            }
+
+            let type_name = name_at(drop)
+                .map(|name| explorer.cache.demangle(name))
+                .and_then(|name| {
+                    let name = dedup_name(&name);
+                    let name = name.strip_prefix("core::ptr::drop_in_place<")?.strip_suffix('>')?;
+                    Some(name.to_owned())
+                });
+
+            headers.push((i, i + 3, Kind::Rust, type_name, Some((size, align))));
+        }
+
+        let starts = headers.iter()
+            .map(|&(start, ..)| start)
+            .collect::<HashSet<_>>();
+
+        for (start, first, kind, type_name, layout) in headers {
+            let methods = (first..words.len())
+                .take_while(|&i| is_func(words[i]) && !starts.contains(&i))
+                .map(|i| (base + (i * ptr_size) as u64, words[i]))
+                .collect();
+
+            let address = base + (start * ptr_size) as u64;
+
+            output.push(Vtable {
+                address,
+                point: match kind {
+                    Kind::Rust => address,
+                    Kind::Cxx => address + 2 * ptr_size as u64
+                },
+                kind,
+                type_name,
+                layout,
+                methods
+            });
+        }
+    }
+
+    output.sort_by_key(|vtable| vtable.address);
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object::elf;
+    use crate::explorer::Cache;
+    use crate::testing::{ write_elf, Section };
+
+    /// Executable of `.text` at 0x1000 and `.rodata` at 0x2000, without symbols
+    fn exec_elf(text: &[u8], rodata: &[u8]) -> Vec<u8> {
+        write_elf(elf::ET_EXEC, &[
+            Section {
+                name: b".text",
+                flags: elf::SHF_ALLOC | elf::SHF_EXECINSTR,
+                addr: 0x1000,
+                data: text,
+                align: 16,
+                entsize: 0
+            },
+            Section {
+                name: b".rodata",
+                flags: elf::SHF_ALLOC,
+                addr: 0x2000,
+                data: rodata,
+                align: 8,
+                entsize: 0
+            }
+        ])
+    }
+
+    #[tokio::test]
+    async fn rust_vtables() {
+        let words: [u64; 10] = [
+            // drop glue, size, align and two methods
+            0x1000, 16, 8, 0x1008, 0x1010,
+            // align not a power of two
+            0, 3, 3, 0x1000,
+            0
+        ];
+        let mut rodata = words.iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect::<Vec<_>>();
+        // a trailing partial word is not read
+        rodata.extend_from_slice(&[0x10, 0x10, 0, 0]);
+
+        let cache = Cache::default();
+        let obj = object::File::parse(cache.arena.keep(exec_elf(&[0xc3; 0x20], &rodata))).unwrap();
+        let explorer = Explorer {
+            path: "vtables".into(),
+            dwarf_path: None,
+            link_map: None,
+            crates: None,
+            source_map: Vec::new(),
+            debug_file: None,
+            debuginfod: None,
+            obj, cache
+        };
+
+        let vtables = find(&explorer).await.unwrap();
+        assert_eq!(vtables.len(), 1);
+
+        let vtable = &vtables[0];
+        assert_eq!((vtable.address, vtable.point), (0x2000, 0x2000));
+        assert!(vtable.kind == Kind::Rust);
+        assert_eq!(vtable.layout, Some((16, 8)));
+        assert!(vtable.methods == [(0x2018, Word::Value(0x1008)), (0x2020, Word::Value(0x1010))]);
+
+        assert_eq!(vtable.method_at(0x2018), Some(0x1008));
+        assert_eq!(vtable.method_at(0x2020), Some(0x1010));
+        assert_eq!(vtable.method_at(0x2028), None);
+    }
+}